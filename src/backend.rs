@@ -0,0 +1,421 @@
+use std::{
+    cell::RefCell,
+    io::{stdout, Write},
+    rc::Rc,
+    time::Duration,
+};
+
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    queue,
+    style::Print,
+    terminal::{self, BeginSynchronizedUpdate, EndSynchronizedUpdate, ScrollDown, ScrollUp},
+};
+use unicode_width::UnicodeWidthChar;
+
+/// 画面を出力する先の端末
+/// # Notes
+/// * 表示するものは端末を直接操作せずに、`screen`を通してこのトレイトで出力する
+/// * テスト用の出力先や、ほかの端末のライブラリに差し替えられるようにする
+pub trait Backend {
+    /// カーソルを移動する
+    /// # Arguments
+    /// * `x` - 移動する位置(X座標)
+    /// * `y` - 移動する位置(Y座標)
+    fn move_to(&mut self, x: u16, y: u16) -> std::io::Result<()>;
+
+    /// カーソルの位置から文字列を出力する
+    /// # Arguments
+    /// * `text` - 出力する文字列(装飾のエスケープシーケンスを含む場合もある)
+    fn print(&mut self, text: &str) -> std::io::Result<()>;
+
+    /// 端末の大きさ
+    /// # Returns
+    /// * `std::io::Result<(u16, u16)>` - 横幅と高さ
+    fn size(&self) -> std::io::Result<(u16, u16)>;
+
+    /// 行の範囲を上下にずらす
+    /// # Arguments
+    /// * `y_start` - ずらす範囲の一番上の行(Y座標)
+    /// * `height` - ずらす範囲の行数
+    /// * `delta` - ずらす行数(正の場合は上に、負の場合は下にずらす)
+    /// # Notes
+    /// * 範囲の行は、端末の横幅すべてがずれる
+    /// * 新しく見えるようになった行は空になる
+    fn scroll(&mut self, y_start: u16, height: u16, delta: i32) -> std::io::Result<()>;
+
+    /// カーソルを表示する位置に移すか、隠す
+    /// # Arguments
+    /// * `position` - 表示する位置(隠す場合はNone)
+    fn set_cursor(&mut self, _position: Option<(u16, u16)>) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    /// 画面の更新を始める
+    /// # Notes
+    /// * `end_update`までの出力をまとめて表示できる場合は、まとめて表示する
+    fn begin_update(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    /// 画面の更新を終える
+    fn end_update(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    /// 出力したものを表示させる
+    fn flush(&mut self) -> std::io::Result<()>;
+}
+
+/// 端末に問い合わせた答えを待つ最長の時間
+const QUERY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// crosstermで標準出力の端末に出力する
+#[derive(Default)]
+pub struct CrosstermBackend {
+    /// 端末が同期出力(mode 2026)に対応しているか
+    /// `query_synchronized_update`で問い合わせた結果を設定する
+    pub synchronized_update: bool,
+}
+
+impl Backend for CrosstermBackend {
+    fn move_to(&mut self, x: u16, y: u16) -> std::io::Result<()> {
+        queue!(stdout(), MoveTo(x, y))
+    }
+
+    fn print(&mut self, text: &str) -> std::io::Result<()> {
+        queue!(stdout(), Print(text))
+    }
+
+    fn size(&self) -> std::io::Result<(u16, u16)> {
+        terminal::size()
+    }
+
+    fn set_cursor(&mut self, position: Option<(u16, u16)>) -> std::io::Result<()> {
+        match position {
+            Some((x, y)) => queue!(stdout(), MoveTo(x, y), Show),
+            None => queue!(stdout(), Hide),
+        }
+    }
+
+    fn scroll(&mut self, y_start: u16, height: u16, delta: i32) -> std::io::Result<()> {
+        let count = delta.unsigned_abs() as u16;
+
+        // スクロール領域を設定して(DECSTBM、行は1から数える)ずらし、元に戻す
+        queue!(
+            stdout(),
+            Print(format!("\x1b[{};{}r", y_start + 1, y_start + height))
+        )?;
+        if delta > 0 {
+            queue!(stdout(), ScrollUp(count))?;
+        } else {
+            queue!(stdout(), ScrollDown(count))?;
+        }
+        queue!(stdout(), Print("\x1b[r"))
+    }
+
+    /// 同期出力(mode 2026)に対応している端末は、`end_update`までの出力をまとめて表示するので、書きかけの画面が見えない
+    /// 対応していない端末には、知らないエスケープシーケンスを表示してしまう場合もあるので送らない
+    fn begin_update(&mut self) -> std::io::Result<()> {
+        if self.synchronized_update {
+            queue!(stdout(), BeginSynchronizedUpdate)?;
+        }
+        Ok(())
+    }
+
+    fn end_update(&mut self) -> std::io::Result<()> {
+        if self.synchronized_update {
+            queue!(stdout(), EndSynchronizedUpdate)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        stdout().flush()
+    }
+}
+
+/// 端末が同期出力(mode 2026)に対応しているかを問い合わせる
+/// # Returns
+/// * `bool` - 対応している場合はtrue
+/// # Notes
+/// * 端末をRawモードにしてから、入力を読むスレッドを始める前に呼び出す
+/// * DECRQM(`CSI ? 2026 $ p`)の答えが1(設定している)か2(設定していない)の場合だけ、対応しているとみなす
+/// * DECRQMに答えない端末もDA1(`CSI c`)には答えるので、DA1の答えが届いたら待つのをやめる
+/// * 答えが届かない場合は、`QUERY_TIMEOUT`だけ待ってから対応していないとみなす
+#[cfg(unix)]
+pub fn query_synchronized_update() -> bool {
+    use std::{io::Read, os::fd::AsRawFd, time::Instant};
+
+    // 標準入力はファイルの内容を読むために使う場合もあるので、端末を直接開く
+    let Ok(mut tty) = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+    else {
+        return false;
+    };
+    if tty
+        .write_all(b"\x1b[?2026$p\x1b[c")
+        .and_then(|_| tty.flush())
+        .is_err()
+    {
+        return false;
+    }
+
+    let deadline = Instant::now() + QUERY_TIMEOUT;
+    let mut reply = Vec::new();
+    let mut buf = [0; 64];
+    while !received_device_attributes(&reply) {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        let mut fds = libc::pollfd {
+            fd: tty.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: 1つのpollfdを指す有効なポインタと、その数を渡している
+        let ready = unsafe { libc::poll(&mut fds, 1, remaining.as_millis() as libc::c_int) };
+        if ready < 0 && std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted {
+            continue;
+        }
+        if ready <= 0 {
+            break;
+        }
+        match tty.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => reply.extend_from_slice(&buf[..n]),
+        }
+    }
+    supports_synchronized_update(&reply)
+}
+
+/// 端末が同期出力(mode 2026)に対応しているかを問い合わせる
+/// # Notes
+/// * Unix以外では問い合わせずに、対応していないとみなす
+#[cfg(not(unix))]
+pub fn query_synchronized_update() -> bool {
+    false
+}
+
+/// 端末から読んだ内容に、DA1の答え(`CSI ? ... c`)まで含まれているか
+fn received_device_attributes(reply: &[u8]) -> bool {
+    String::from_utf8_lossy(reply)
+        .split("\x1b[?")
+        .skip(1)
+        .any(|sequence| {
+            sequence
+                .trim_start_matches(|c: char| c.is_ascii_digit() || c == ';')
+                .starts_with('c')
+        })
+}
+
+/// 端末から読んだ内容のうち、DECRQMの答え(`CSI ? 2026 ; 状態 $ y`)から、同期出力に対応しているかを求める
+/// # Notes
+/// * 状態は0が知らないモード、1が設定している、2が設定していない、3と4が変えられないことを表す
+fn supports_synchronized_update(reply: &[u8]) -> bool {
+    let reply = String::from_utf8_lossy(reply);
+    ["1", "2"]
+        .iter()
+        .any(|state| reply.contains(&format!("\x1b[?2026;{}$y", state)))
+}
+
+/// 端末の代わりに、メモリ上の文字の格子に出力する
+/// # Notes
+/// * 端末がなくても表示した内容を確かめられるように、テストなどで使う
+/// * 装飾などのエスケープシーケンスは無視して、文字だけを残す
+/// * 複製したものは同じ格子を共有するので、出力する先に渡した後も内容を取得できる
+#[derive(Clone)]
+pub struct MemoryBackend {
+    grid: Rc<RefCell<Grid>>,
+}
+
+/// 文字の格子と、カーソルの位置
+struct Grid {
+    /// 行ごとの、1文字分の幅ごとの文字(全角文字の右半分は空文字列)
+    cells: Vec<Vec<String>>,
+    /// 横幅
+    width: u16,
+    /// カーソルの位置(X座標)
+    x: u16,
+    /// カーソルの位置(Y座標)
+    y: u16,
+}
+
+impl Grid {
+    /// 空白だけの1行
+    fn blank_row(&self) -> Vec<String> {
+        vec![" ".to_string(); self.width as usize]
+    }
+
+    /// カーソルの位置に1文字出力して、カーソルを進める
+    /// # Notes
+    /// * 幅のない文字(結合文字など)は、直前の文字に加える
+    /// * 行の右端からはみ出す部分は出力しない
+    fn put(&mut self, c: char) {
+        let Some(row) = self.cells.get_mut(self.y as usize) else {
+            return;
+        };
+        let x = self.x as usize;
+        match c.width().unwrap_or(0) {
+            0 => {
+                if let Some(cell) = x.checked_sub(1).and_then(|x| row.get_mut(x)) {
+                    cell.push(c);
+                }
+            }
+            width => {
+                if x + width <= row.len() {
+                    row[x] = c.to_string();
+                    for cell in &mut row[x + 1..x + width] {
+                        cell.clear();
+                    }
+                }
+                self.x = self.x.saturating_add(width as u16);
+            }
+        }
+    }
+}
+
+impl MemoryBackend {
+    /// MemoryBackendを作成する
+    /// # Arguments
+    /// * `width` - 横幅
+    /// * `height` - 高さ
+    pub fn new(width: u16, height: u16) -> Self {
+        let grid = Grid {
+            cells: vec![vec![" ".to_string(); width as usize]; height as usize],
+            width,
+            x: 0,
+            y: 0,
+        };
+        Self {
+            grid: Rc::new(RefCell::new(grid)),
+        }
+    }
+}
+
+impl std::fmt::Display for MemoryBackend {
+    /// 出力した文字を、行ごとに改行で区切って表示する
+    /// # Notes
+    /// * 見比べやすいように、行末の空白は取り除く
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let grid = self.grid.borrow();
+        let rows: Vec<String> = grid
+            .cells
+            .iter()
+            .map(|row| row.concat().trim_end().to_string())
+            .collect();
+        write!(f, "{}", rows.join("\n"))
+    }
+}
+
+impl Backend for MemoryBackend {
+    fn move_to(&mut self, x: u16, y: u16) -> std::io::Result<()> {
+        let mut grid = self.grid.borrow_mut();
+        grid.x = x;
+        grid.y = y;
+        Ok(())
+    }
+
+    fn print(&mut self, text: &str) -> std::io::Result<()> {
+        let mut grid = self.grid.borrow_mut();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                // CSI(装飾など)は、終わりの文字まで読み飛ばす
+                '\x1b' if chars.peek() == Some(&'[') => {
+                    chars.next();
+                    for c in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+                // OSC(リンクなど)は、BELかESC \まで読み飛ばす
+                '\x1b' if chars.peek() == Some(&']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                '\x1b' => {
+                    chars.next();
+                }
+                c if c.is_control() => {}
+                c => grid.put(c),
+            }
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> std::io::Result<(u16, u16)> {
+        let grid = self.grid.borrow();
+        Ok((grid.width, grid.cells.len() as u16))
+    }
+
+    fn scroll(&mut self, y_start: u16, height: u16, delta: i32) -> std::io::Result<()> {
+        let mut grid = self.grid.borrow_mut();
+        let start = (y_start as usize).min(grid.cells.len());
+        let end = (y_start as usize + height as usize).min(grid.cells.len());
+        let count = (delta.unsigned_abs() as usize).min(end - start);
+        let blank = grid.blank_row();
+        let region = &mut grid.cells[start..end];
+        if delta > 0 {
+            region.rotate_left(count);
+            let len = region.len();
+            region[len - count..].fill(blank);
+        } else {
+            region.rotate_right(count);
+            region[..count].fill(blank);
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_backend() {
+        let mut backend = MemoryBackend::new(6, 3);
+        backend.move_to(1, 0).unwrap();
+        backend.print("\x1b[7mab\x1b[0m日本語").unwrap();
+        backend.move_to(0, 1).unwrap();
+        backend
+            .print("\x1b]8;;https://example.com\x1b\\e\u{301}\x1b]8;;\x1b\\")
+            .unwrap();
+        assert_eq!(backend.to_string(), " ab日\ne\u{301}\n");
+
+        // 上にずらすと、新しく見えるようになった行は空になる
+        backend.scroll(0, 3, 1).unwrap();
+        assert_eq!(backend.to_string(), "e\u{301}\n\n");
+        backend.scroll(0, 3, -2).unwrap();
+        assert_eq!(backend.to_string(), "\n\ne\u{301}");
+        assert_eq!(backend.size().unwrap(), (6, 3));
+    }
+
+    #[test]
+    fn test_synchronized_update_reply() {
+        // DA1の答えが届くまでは読み続ける
+        assert!(!received_device_attributes(b"\x1b[?2026;2$y"));
+        assert!(!received_device_attributes(b"\x1b[?2026;2$y\x1b[?62;2"));
+        assert!(received_device_attributes(b"\x1b[?2026;2$y\x1b[?62;22c"));
+        assert!(received_device_attributes(b"\x1b[?1;2c"));
+
+        // 設定しているか、設定していない場合だけ対応している
+        assert!(supports_synchronized_update(b"\x1b[?2026;1$y\x1b[?62c"));
+        assert!(supports_synchronized_update(b"\x1b[?2026;2$y\x1b[?62c"));
+        assert!(!supports_synchronized_update(b"\x1b[?2026;0$y\x1b[?62c"));
+        assert!(!supports_synchronized_update(b"\x1b[?2026;4$y\x1b[?62c"));
+        // DECRQMに答えない端末
+        assert!(!supports_synchronized_update(b"\x1b[?1;2c"));
+    }
+}
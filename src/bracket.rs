@@ -0,0 +1,116 @@
+use ropey::Rope;
+
+/// 対応を探す括弧の組
+const PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+/// 開き括弧か閉じ括弧かを判定する
+/// # Arguments
+/// * `c` - 判定する文字
+/// # Returns
+/// * `Option<(char, char, bool)>` - 開き括弧、閉じ括弧と、開き括弧の場合はtrue(括弧ではない場合はNone)
+fn classify(c: char) -> Option<(char, char, bool)> {
+    PAIRS.iter().find_map(|&(open, close)| {
+        if c == open {
+            Some((open, close, true))
+        } else if c == close {
+            Some((open, close, false))
+        } else {
+            None
+        }
+    })
+}
+
+/// 範囲の中で最初にある括弧の位置を探す
+/// # Arguments
+/// * `s` - 文字列
+/// * `start` - 探し始める位置(バイト単位)
+/// * `end` - 探し終える位置(バイト単位、この位置は含まない)
+/// # Returns
+/// * `Option<usize>` - 括弧の位置(バイト単位、括弧がない場合はNone)
+pub fn first_bracket(s: &Rope, start: usize, end: usize) -> Option<usize> {
+    let mut position = start;
+    s.byte_slice(start..end).chars().find_map(|c| {
+        let found = classify(c).map(|_| position);
+        position += c.len_utf8();
+        found
+    })
+}
+
+/// 括弧に対応する括弧の位置を探す
+/// # Arguments
+/// * `s` - 文字列
+/// * `position` - 括弧の位置(バイト単位)
+/// # Returns
+/// * `Option<usize>` - 対応する括弧の位置(バイト単位、括弧ではないか対応する括弧がない場合はNone)
+/// # Notes
+/// * 開き括弧は後ろに、閉じ括弧は前に向かって探す
+/// * 同じ種類の括弧だけを数えて入れ子を扱い、文字列やコメントの中の括弧も区別しない
+pub fn matching_bracket(s: &Rope, position: usize) -> Option<usize> {
+    let (open, close, is_open) = classify(s.byte_slice(position..).chars().next()?)?;
+
+    let mut depth = 0usize;
+    let mut check = |(_, c): (usize, char)| {
+        if c == open || c == close {
+            if (c == open) == is_open {
+                depth += 1;
+            } else {
+                depth -= 1;
+            }
+        }
+        depth == 0
+    };
+
+    if is_open {
+        let mut index = position;
+        s.byte_slice(position..)
+            .chars()
+            .map(|c| {
+                let item = (index, c);
+                index += c.len_utf8();
+                item
+            })
+            .find(|&item| check(item))
+            .map(|(index, _)| index)
+    } else {
+        // 括弧は1バイトなので、括弧の直後までを後ろから探す
+        let mut index = position + 1;
+        let mut chars = s.chars_at(s.byte_to_char(index));
+        std::iter::from_fn(|| chars.prev())
+            .map(|c| {
+                index -= c.len_utf8();
+                (index, c)
+            })
+            .find(|&item| check(item))
+            .map(|(index, _)| index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matching_bracket() {
+        let s = &Rope::from_str("fn f(a: [u8; 2]) { g(h(a)); }");
+        assert_eq!(first_bracket(s, 0, s.len_bytes()), Some(4));
+        assert_eq!(first_bracket(s, 0, 4), None);
+
+        // 入れ子になった括弧は、同じ深さの括弧と対応する
+        assert_eq!(matching_bracket(s, 4), Some(15));
+        assert_eq!(matching_bracket(s, 15), Some(4));
+        assert_eq!(matching_bracket(s, 17), Some(28));
+        assert_eq!(matching_bracket(s, 20), Some(25));
+        assert_eq!(matching_bracket(s, 25), Some(20));
+        assert_eq!(matching_bracket(s, 8), Some(14));
+
+        // 違う種類の括弧は数えない
+        assert_eq!(matching_bracket(&Rope::from_str("(]{)"), 0), Some(3));
+
+        // マルチバイト文字を含む場合もバイト単位の位置を返す
+        assert_eq!(matching_bracket(&Rope::from_str("(あ)"), 0), Some(4));
+
+        assert_eq!(matching_bracket(s, 0), None);
+        assert_eq!(matching_bracket(&Rope::from_str("((a)"), 0), None);
+        assert_eq!(matching_bracket(&Rope::from_str("(a))"), 3), None);
+    }
+}
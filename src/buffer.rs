@@ -0,0 +1,2011 @@
+use std::{borrow::Cow, ops::Range, rc::Rc};
+
+use ropey::Rope;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    bracket, config, contents, diff, editorconfig, file_tree, filetype, git, http, json, loading,
+    mapped, markdown, marks, ssh, status_bar, style,
+};
+
+/// ファイルを開くときに使う設定
+pub struct Settings {
+    /// すべてのファイルに共通する表示の設定
+    pub options: contents::DisplayOptions,
+    /// コマンドライン引数で指定したタブ幅
+    pub tab_width: Option<u16>,
+    /// コマンドライン引数で指定した、長い行を折り返すかどうか
+    pub wrap: Option<bool>,
+    /// 設定ファイルの設定
+    pub config: config::Config,
+}
+
+/// ファイル以外から取得した内容の、取得した場所
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    /// URLから取得した内容と、応答の情報
+    Url(http::Remote),
+    /// SSHで読み込んだ、ホストのファイル
+    Ssh(ssh::Location),
+}
+
+impl Origin {
+    /// 指定したときと同じ形式の名前(URLや`host:path`)
+    pub fn name(&self) -> String {
+        match self {
+            Self::Url(remote) => remote.url.clone(),
+            Self::Ssh(location) => location.to_string(),
+        }
+    }
+
+    /// ファイルの種類を判定するためのパス
+    fn path(&self) -> &str {
+        match self {
+            Self::Url(remote) => http::path(&remote.url),
+            Self::Ssh(location) => &location.path,
+        }
+    }
+}
+
+/// 開いているファイルの内容と、そのファイルを表示する状態
+pub struct Buffer {
+    /// ファイル名(標準入力や2つのファイルの差分の場合はNone)
+    pub file: Option<String>,
+    /// ファイルではない内容の名前(コマンドの出力の場合は、実行したコマンド)
+    label: Option<String>,
+    /// URLやSSHで取得した内容の場合は、取得した場所
+    pub origin: Option<Origin>,
+    /// メモリマップで開いた大きなファイルの場合は、そのファイルと表示する範囲
+    mapped: Option<mapped::MappedFile>,
+    /// 開いたときのファイルの更新日時
+    modified_time: Option<std::time::SystemTime>,
+    /// 開いたときのファイルの内容(設定を変更したときに、表示する文字列を作り直すために使う)
+    source: Rope,
+    /// 表示する文字列(開いたときのファイルの内容をそのまま表示する場合は、`source`と共有する)
+    pub contents: Rope,
+    /// 表示する文字列を折り返した行のキャッシュ(`contents`や折り返した行に関わる設定を変更したときに世代を進める)
+    wrap_cache: contents::WrapCache,
+    /// 表示に関する設定
+    pub options: contents::DisplayOptions,
+    /// ステータスバーに表示する、ファイルに関する項目
+    pub status_items: Vec<status_bar::StatusBarItem>,
+    /// 横方向のスクロール位置
+    pub cursor_x: u16,
+    /// 縦方向のスクロール位置
+    pub cursor_y: usize,
+    /// 単語単位の移動などで操作する位置(表示する文字列でのバイト単位の位置)
+    pub caret: usize,
+    /// 上下の行に移動するときに合わせる桁(左右に移動するまで、短い行を通っても元の桁に戻れるように覚えておく)
+    preferred_column: Option<usize>,
+    /// 操作する位置がある行を強調表示しているか
+    pub show_cursor_line: bool,
+    /// 選択を始めた位置(表示する文字列でのバイト単位の位置、選択していない場合はNone)
+    selection_anchor: Option<usize>,
+    /// 矩形で選択しているか(falseの場合は、選択を始めた位置から操作する位置までの文字を選択する)
+    block_selection: bool,
+
+    /// Markdownのファイルか
+    pub is_markdown: bool,
+    /// Markdownを装飾して表示しているか
+    pub markdown_mode: bool,
+    /// 整形したJSON(JSONとして解析できない場合はNone)
+    pub json_view: Option<json::JsonView>,
+    /// JSONを整形して表示しているか
+    pub json_mode: bool,
+    /// 表示していない方の文字列と、その強調表示や行の印
+    alternate: Alternate,
+    /// 構文の強調表示の状態
+    syntax: Syntax,
+
+    /// 読み込んだgit blameの注釈
+    pub blame: Option<Rc<Vec<String>>>,
+    /// 別のスレッドで読み込んでいるgit blameの結果を受け取る
+    pub blame_receiver: Option<tokio::sync::oneshot::Receiver<Option<Vec<String>>>>,
+    /// git blameを表示しているか
+    pub show_blame: bool,
+
+    /// 行に付けた印
+    pub marks: marks::Marks,
+    /// 行番号の左に印を表示しているか
+    pub show_marks: bool,
+    /// 最後に対応を探した括弧と、対応する括弧の位置(バイト単位、強調表示する)
+    bracket_match: Option<(usize, usize)>,
+
+    /// 差分を表示する2つのファイルの、名前と内容
+    pub diff_files: Option<[(String, String); 2]>,
+    /// 2つのファイルの差分を左右に並べて表示しているか
+    pub side_by_side: bool,
+    /// unified形式の差分の、ファイルと差分のまとまりの位置
+    pub diff_index: Option<diff::DiffIndex>,
+}
+
+/// 構文の強調表示の状態
+/// # Notes
+/// * 文字列を編集した場合に、変更のあった範囲だけを強調表示し直せるように保持する
+#[derive(Default)]
+struct Syntax {
+    /// tree-sitterで構文解析したもの(対応していない言語の場合はNone)
+    #[cfg(feature = "tree-sitter")]
+    highlighter: Option<crate::highlight::Highlighter>,
+}
+
+/// 表示していない方の文字列と、その強調表示や行の印
+#[derive(Default)]
+struct Alternate {
+    contents: Rope,
+    highlights: Rc<Vec<style::Highlight>>,
+    diff_markers: Rc<Vec<Option<git::DiffMarker>>>,
+    annotations: Rc<Vec<String>>,
+    fold_markers: Rc<Vec<Option<bool>>>,
+}
+
+impl Buffer {
+    /// ファイルを開く
+    /// # Arguments
+    /// * `file` - ファイル名(標準入力の場合はNone)
+    /// * `contents` - ファイルの内容
+    /// * `settings` - ファイルを開くときに使う設定
+    pub fn open(file: Option<String>, contents: String, settings: &Settings) -> Self {
+        Self::reopen(file, contents, settings, Syntax::default())
+    }
+
+    /// 構文の強調表示の状態を引き継いで、ファイルを開き直す
+    /// # Arguments
+    /// * `file` - ファイル名(標準入力の場合はNone)
+    /// * `contents` - ファイルの内容
+    /// * `settings` - ファイルを開くときに使う設定
+    /// * `syntax` - 開き直す前の構文の強調表示の状態(同じ文字列を強調表示している場合は使い回す)
+    fn reopen(file: Option<String>, contents: String, settings: &Settings, syntax: Syntax) -> Self {
+        // ファイルの種類を判定して、タブ幅や強調表示に使う
+        let file_type = filetype::FileType::detect(file.as_deref(), &contents);
+        Self::load(file, contents, file_type, settings, syntax)
+    }
+
+    /// コマンドの出力を開く
+    /// # Arguments
+    /// * `label` - 一覧などに表示する名前
+    /// * `contents` - コマンドの出力
+    /// * `settings` - ファイルを開くときに使う設定
+    pub fn open_output(label: String, contents: String, settings: &Settings) -> Self {
+        let mut buffer = Self::open(None, contents, settings);
+        buffer.label = Some(label);
+
+        buffer
+    }
+
+    /// URLやSSHで取得した内容を開く
+    /// # Arguments
+    /// * `origin` - 取得した場所
+    /// * `contents` - 取得した内容
+    /// * `settings` - ファイルを開くときに使う設定
+    /// # Notes
+    /// * ファイルの種類はパスの拡張子から判定する
+    /// * URLの場合はContent-Typeと大きさを、SSHの場合は接続したホストをステータスバーに表示する
+    pub fn open_remote(origin: Origin, contents: String, settings: &Settings) -> Self {
+        Self::reopen_remote(origin, contents, settings, Syntax::default())
+    }
+
+    /// 構文の強調表示の状態を引き継いで、URLやSSHで取得した内容を開き直す
+    /// # Arguments
+    /// * `origin` - 取得した場所
+    /// * `contents` - 取得した内容
+    /// * `settings` - ファイルを開くときに使う設定
+    /// * `syntax` - 開き直す前の構文の強調表示の状態(同じ文字列を強調表示している場合は使い回す)
+    fn reopen_remote(
+        origin: Origin,
+        contents: String,
+        settings: &Settings,
+        syntax: Syntax,
+    ) -> Self {
+        let file_type = filetype::FileType::detect(Some(origin.path()), &contents);
+        let mut buffer = Self::load(None, contents, file_type, settings, syntax);
+        match &origin {
+            Origin::Url(remote) => {
+                if let Some(content_type) = &remote.content_type {
+                    buffer.status_items.push(status_bar::StatusBarItem::new(
+                        "content-type".to_string(),
+                        content_type.clone(),
+                    ));
+                }
+                buffer.status_items.push(status_bar::StatusBarItem::new(
+                    "size".to_string(),
+                    loading::format_bytes(remote.size),
+                ));
+            }
+            Origin::Ssh(location) => {
+                buffer.status_items.push(status_bar::StatusBarItem::new(
+                    "ssh".to_string(),
+                    format!("ssh {}", location.host),
+                ));
+            }
+        }
+        buffer.label = Some(origin.name());
+        buffer.origin = Some(origin);
+
+        buffer
+    }
+
+    /// メモリマップで開いた大きなファイルを開く
+    /// # Arguments
+    /// * `file` - ファイル名
+    /// * `mapped` - メモリマップで開いたファイル
+    /// * `settings` - ファイルを開くときに使う設定
+    /// # Notes
+    /// * 表示する範囲だけを文字列にするので、検索や印は表示する範囲の中だけで使える
+    /// * すべて読み込まないと分からないので、.editorconfigの改行文字やgitの変更の印は使わない
+    pub fn open_mapped(file: String, mapped: mapped::MappedFile, settings: &Settings) -> Self {
+        let text = mapped.text();
+        let file_type = filetype::FileType::detect(Some(&file), &text);
+        let mut buffer = Self::load(None, text, file_type, settings, Syntax::default());
+        buffer.options.line_number_offset = mapped.first_line();
+        buffer.status_items.push(status_bar::StatusBarItem::new(
+            "size".to_string(),
+            loading::format_bytes(mapped.size()),
+        ));
+        buffer.modified_time = modified_time(&file);
+        buffer.file = Some(file);
+        buffer.mapped = Some(mapped);
+
+        buffer
+    }
+
+    /// ディレクトリを開く
+    /// # Arguments
+    /// * `dir` - ディレクトリ
+    /// * `settings` - ファイルを開くときに使う設定
+    /// # Notes
+    /// * ディレクトリの中身の一覧を表示する
+    /// * ファイルではないので、.editorconfigやgitの情報は使わない
+    pub fn open_directory(dir: String, settings: &Settings) -> Self {
+        let listing = file_tree::listing(std::path::Path::new(&dir));
+        let mut buffer = Self::load(None, listing, None, settings, Syntax::default());
+        buffer.modified_time = modified_time(&dir);
+        buffer.file = Some(dir);
+
+        buffer
+    }
+
+    /// 2つのファイルの差分を開く
+    /// # Arguments
+    /// * `files` - 古いファイルと新しいファイルの、名前と内容
+    /// * `side_by_side` - 左右に並べて表示する場合はtrue
+    /// * `term_width` - 端末の横幅
+    /// * `settings` - ファイルを開くときに使う設定
+    pub fn open_diff(
+        files: [(String, String); 2],
+        side_by_side: bool,
+        term_width: u16,
+        settings: &Settings,
+    ) -> Self {
+        // 差分は表示の設定を決めてから計算する
+        let file_type = filetype::FileType::from_name("diff");
+        let mut buffer = Self::load(None, String::new(), file_type, settings, Syntax::default());
+        buffer.diff_files = Some(files);
+        buffer.side_by_side = side_by_side;
+        buffer.show_file_diff(term_width);
+
+        buffer
+    }
+
+    /// ファイルの内容から、表示する文字列と設定を作成する
+    /// # Notes
+    /// * `syntax`が同じファイルの種類とテーマで同じ文字列を強調表示している場合は、構文解析し直さない
+    fn load(
+        file: Option<String>,
+        contents: String,
+        file_type: Option<filetype::FileType>,
+        settings: &Settings,
+        syntax: Syntax,
+    ) -> Self {
+        let source = Rope::from_str(&contents);
+        let mut status_items = vec![status_bar::StatusBarItem::new(
+            "filetype".to_string(),
+            file_type.map_or("text", |t| t.name).to_string(),
+        )];
+
+        // ファイルの種類ごとの設定と.editorconfigは、コマンドライン引数で指定しなかった項目にだけ適用する
+        // 両方で指定されている場合は、プロジェクトの設定である.editorconfigを優先する
+        let filetype_config = file_type
+            .map(|t| settings.config.for_filetype(t.name))
+            .unwrap_or_default();
+        let editorconfig = file
+            .as_deref()
+            .map(|file| editorconfig::EditorConfig::load(std::path::Path::new(file)))
+            .unwrap_or_default();
+        if let Some(indent) = editorconfig.indent_name() {
+            status_items.push(status_bar::StatusBarItem::new("indent".to_string(), indent));
+        }
+
+        // 改行文字が.editorconfigの指定と異なる場合は、ステータスバーで知らせる
+        if let Some(end_of_line) = editorconfig::EndOfLine::detect(&contents) {
+            let mut value = end_of_line.map_or("mixed", |e| e.name()).to_string();
+            if let Some(expected) = editorconfig.end_of_line {
+                if end_of_line != Some(expected) {
+                    value += &format!(" (expected {})", expected.name());
+                }
+            }
+            status_items.push(status_bar::StatusBarItem::new("eol".to_string(), value));
+        }
+
+        let trailing_whitespace = editorconfig
+            .trailing_whitespace()
+            .or(filetype_config.trailing_whitespace)
+            .unwrap_or_default();
+        let contents = if trailing_whitespace == config::TrailingWhitespace::Trim {
+            config::trim_trailing_whitespace(&contents)
+        } else {
+            contents
+        };
+
+        // 表示に関する設定
+        let mut options = settings.options.clone();
+        options.tab_width = settings
+            .tab_width
+            .or(editorconfig.tab_width())
+            .or(filetype_config.tab_width)
+            .or(file_type.and_then(|t| t.tab_width))
+            .or(settings.config.tab_width)
+            .unwrap_or(8);
+        options.chop_long_lines = !settings
+            .wrap
+            .or(filetype_config.wrap)
+            .or(settings.config.wrap)
+            .unwrap_or(true);
+
+        // ファイルを含むリポジトリのブランチと、コミットしていない変更があるかを表示する
+        if let Some(repo_status) = file
+            .as_deref()
+            .and_then(|file| git::repo_status(std::path::Path::new(file)))
+        {
+            status_items.push(status_bar::StatusBarItem::new(
+                "git".to_string(),
+                repo_status.format(),
+            ));
+        }
+
+        // gitで管理しているファイルは、HEADと比べて変更のある行に印を付ける
+        if let Some(file) = &file {
+            options.diff_markers =
+                git::diff_markers(std::path::Path::new(file), contents.lines().count())
+                    .unwrap_or_default()
+                    .into();
+        }
+
+        // ファイルの種類に合わせて、構文を強調表示する
+        // tree-sitterで構文解析できない場合は、行コメントだけを強調表示する
+        #[cfg(feature = "tree-sitter")]
+        let mut syntax = syntax;
+        if let Some(file_type) = &file_type {
+            options.highlights = file_type
+                .comment_highlights(&contents, options.theme.comment)
+                .into();
+
+            #[cfg(feature = "tree-sitter")]
+            {
+                syntax.highlighter = syntax
+                    .highlighter
+                    .take()
+                    .filter(|highlighter| highlighter.is_for(file_type, &options.theme, &contents))
+                    .or_else(|| {
+                        let mut highlighter =
+                            crate::highlight::Highlighter::for_filetype(file_type, &options.theme)?;
+                        highlighter.parse(&contents);
+                        Some(highlighter)
+                    });
+                if let Some(highlighter) = &syntax.highlighter {
+                    options.highlights = highlighter.highlights().to_vec().into();
+                }
+            }
+        }
+
+        // unified形式の差分(`git diff`の出力など)は、ファイルと差分のまとまりの間を移動できるようにする
+        // 差分のまとまりの見出しは、スクロールしても一番上に固定して表示する
+        let diff_index = diff::DiffIndex::parse(&contents);
+        if let Some(diff_index) = &diff_index {
+            options.highlights = diff::highlights(&contents, &options.theme).into();
+            options.sticky_headers = diff_index.hunks.clone();
+        }
+
+        if trailing_whitespace == config::TrailingWhitespace::Highlight {
+            let trailing_whitespace = config::trailing_whitespace_ranges(&contents)
+                .into_iter()
+                .map(|range| style::Highlight {
+                    range,
+                    style: options.theme.trailing_whitespace,
+                })
+                .collect();
+            options.highlights = style::Highlight::overlay(
+                Rc::unwrap_or_clone(std::mem::take(&mut options.highlights)),
+                trailing_whitespace,
+            )
+            .into();
+        }
+
+        // Markdownは装飾して表示し、元の文字列と切り替えられるようにする
+        // 表示していない方の文字列と強調表示、変更の印を保持しておく
+        let is_markdown = file_type.is_some_and(|t| t.name == "markdown");
+        let mut alternate = Alternate::default();
+        let contents = if is_markdown {
+            alternate.highlights = std::mem::take(&mut options.highlights);
+            alternate.diff_markers = std::mem::take(&mut options.diff_markers);
+            let rendered = markdown::render(&contents, &options.theme, options.color_depth);
+            alternate.contents = Rope::from(contents);
+            rendered
+        } else {
+            contents
+        };
+
+        // JSONは整形して表示し、オブジェクトや配列を折りたためるようにする
+        // 最初は元の文字列を表示し、切り替えた場合に整形した文字列を表示する
+        let json_view = if is_markdown {
+            None
+        } else {
+            json::JsonView::parse(&contents)
+        };
+        if let Some(json_view) = &json_view {
+            alternate.contents = Rope::from_str(json_view.text());
+            alternate.fold_markers = json_view.fold_markers().into();
+        }
+
+        // 開いたときの内容をそのまま表示する場合は、同じロープを共有する
+        let contents = if source == contents.as_str() {
+            source.clone()
+        } else {
+            Rope::from(contents)
+        };
+
+        Self {
+            modified_time: file.as_deref().and_then(modified_time),
+            file,
+            label: None,
+            origin: None,
+            mapped: None,
+            source,
+            wrap_cache: contents::WrapCache::default(),
+            contents,
+            options,
+            status_items,
+            cursor_x: 0,
+            cursor_y: 0,
+            caret: 0,
+            preferred_column: None,
+            show_cursor_line: settings.config.cursor_line,
+            selection_anchor: None,
+            block_selection: false,
+            is_markdown,
+            markdown_mode: is_markdown,
+            json_view,
+            json_mode: false,
+            alternate,
+            syntax,
+            blame: None,
+            blame_receiver: None,
+            show_blame: false,
+            marks: marks::Marks::default(),
+            show_marks: false,
+            bracket_match: None,
+            diff_files: None,
+            side_by_side: false,
+            diff_index,
+        }
+    }
+
+    /// 変更した設定で、表示する文字列と設定を作り直す
+    /// # Arguments
+    /// * `settings` - 変更した設定
+    /// * `term_width` - 端末の横幅
+    /// # Notes
+    /// * ファイルは読み込み直さず、開いたときの内容を使う
+    /// * スクロール位置や、Markdown・JSON・git blame・空白文字の表示の切り替えは保持する
+    /// * 整形したJSONの折りたたみは、すべて展開した状態に戻す
+    pub fn reload_settings(&mut self, settings: &Settings, term_width: u16) {
+        let mut buffer = match (self.diff_files.take(), self.mapped.take()) {
+            (Some(files), _) => Self::open_diff(files, self.side_by_side, term_width, settings),
+            (None, Some(mapped)) => {
+                Self::open_mapped(self.file.clone().unwrap_or_default(), mapped, settings)
+            }
+            (None, None) if self.is_directory() => {
+                Self::open_directory(self.file.clone().unwrap_or_default(), settings)
+            }
+            (None, None) => match self.origin.take() {
+                Some(origin) => Self::reopen_remote(
+                    origin,
+                    self.source.to_string(),
+                    settings,
+                    std::mem::take(&mut self.syntax),
+                ),
+                None => Self::reopen(
+                    self.file.clone(),
+                    self.source.to_string(),
+                    settings,
+                    std::mem::take(&mut self.syntax),
+                ),
+            },
+        };
+
+        if buffer.markdown_mode != self.markdown_mode || buffer.json_mode != self.json_mode {
+            buffer.markdown_mode = self.markdown_mode;
+            buffer.json_mode = self.json_mode;
+            buffer.swap_alternate();
+        }
+        buffer.options.show_whitespace = self.options.show_whitespace;
+        buffer.show_cursor_line = self.show_cursor_line;
+        buffer.show_blame = self.show_blame;
+        buffer.blame = self.blame.take();
+        buffer.blame_receiver = self.blame_receiver.take();
+        if buffer.show_blame {
+            if let Some(annotations) = buffer.blame.clone() {
+                buffer.set_blame_annotations(annotations);
+            }
+        }
+        buffer.marks = std::mem::take(&mut self.marks);
+        buffer.show_marks = self.show_marks;
+        buffer.update_mark_markers();
+        buffer.modified_time = self.modified_time;
+        buffer.label = self.label.take();
+        buffer.cursor_x = self.cursor_x;
+        buffer.cursor_y = self.cursor_y;
+        if is_char_boundary(&buffer.contents, self.caret) {
+            buffer.caret = self.caret;
+        }
+
+        *self = buffer;
+    }
+
+    /// 読み込み直したファイルの内容で、表示する文字列を作り直す
+    /// # Arguments
+    /// * `contents` - 読み込み直したファイルの内容
+    /// * `settings` - ファイルを開くときに使う設定
+    /// * `term_width` - 端末の横幅
+    /// # Notes
+    /// * スクロール位置や表示の切り替えは保持する
+    /// * git blameの注釈は古くなるので、表示をやめる
+    pub fn reload(&mut self, contents: String, settings: &Settings, term_width: u16) {
+        self.source = Rope::from(contents);
+        self.blame = None;
+        self.blame_receiver = None;
+        self.show_blame = false;
+        self.selection_anchor = None;
+        self.reload_settings(settings, term_width);
+        self.modified_time = self.file.as_deref().and_then(modified_time);
+    }
+
+    /// メモリマップで開いたファイルを開き直す
+    /// # Arguments
+    /// * `settings` - ファイルを開くときに使う設定
+    /// * `term_width` - 端末の横幅
+    /// # Returns
+    /// * `bool` - 開き直した場合はtrue
+    /// # Notes
+    /// * 追記されたファイルを追いかけられるように、大きさが変わっても開き直す
+    /// * 前と同じ位置から表示する範囲にして、スクロール位置を保持する
+    /// * 小さくなった場合などメモリマップで開けない場合は、メモリマップをやめてfalseを返す(すべて読み込み直す)
+    pub fn remap(&mut self, settings: &Settings, term_width: u16) -> bool {
+        let Some(start) = self.mapped.as_ref().map(mapped::MappedFile::start) else {
+            return false;
+        };
+        let Some(mut mapped) = self.file.as_deref().and_then(mapped::MappedFile::open) else {
+            self.mapped = None;
+            return false;
+        };
+
+        mapped.move_near(start);
+        self.mapped = Some(mapped);
+        self.selection_anchor = None;
+        self.reload_settings(settings, term_width);
+        self.modified_time = self.file.as_deref().and_then(modified_time);
+
+        true
+    }
+
+    /// メモリマップで開いたファイルを、メモリマップで開き直さないようにする
+    /// # Returns
+    /// * `bool` - メモリマップで開いていた場合はtrue(`reload`ですべて読み込み直す)
+    /// # Notes
+    /// * 末尾を追いかけるファイルは、ログのローテーションなどで切り詰められることが多いので、メモリマップで開かない
+    pub fn unmap(&mut self) -> bool {
+        self.mapped.take().is_some()
+    }
+
+    /// メモリマップで開いたファイルで、一番上の行が表示する範囲の端に近づいた場合は、範囲を移す
+    /// # Arguments
+    /// * `settings` - ファイルを開くときに使う設定
+    /// * `term_width` - 端末の横幅
+    /// * `width` - 表示する領域の横幅
+    /// * `height` - 表示する領域の高さ
+    /// # Returns
+    /// * `bool` - 範囲を移した場合はtrue
+    /// # Notes
+    /// * 一番上の行は変わらないように、移した範囲でスクロールし直す
+    pub fn shift_window(
+        &mut self,
+        settings: &Settings,
+        term_width: u16,
+        width: u16,
+        height: u16,
+    ) -> bool {
+        if self.mapped.is_none() {
+            return false;
+        }
+        let Some(top_line) = self.top_line_number(width, height) else {
+            return false;
+        };
+        let Some(mapped) = self.mapped.as_mut() else {
+            return false;
+        };
+        let start = mapped.start();
+        let Some(top_line) = mapped.shift(top_line) else {
+            return false;
+        };
+        let shift = mapped.start() as isize - start as isize;
+
+        self.selection_anchor = None;
+        self.reload_settings(settings, term_width);
+        self.caret = self
+            .caret
+            .checked_add_signed(-shift)
+            .filter(|&caret| is_char_boundary(&self.contents, caret))
+            .unwrap_or_default();
+        self.cursor_y = 0;
+        self.scroll_to_line(top_line, width, height);
+
+        true
+    }
+
+    /// メモリマップで開いたファイルで、指定した行の前後を表示する範囲に移す
+    /// # Arguments
+    /// * `line_number` - ファイルでの行番号(1から始まる)
+    /// * `settings` - ファイルを開くときに使う設定
+    /// * `term_width` - 端末の横幅
+    /// # Returns
+    /// * `usize` - 表示する文字列での行番号(スクロールに使う)
+    /// # Notes
+    /// * メモリマップで開いたファイル以外は、すべての行を表示する文字列にしているので、そのまま返す
+    pub fn move_window_to_line(
+        &mut self,
+        line_number: usize,
+        settings: &Settings,
+        term_width: u16,
+    ) -> usize {
+        let Some(mapped) = self.mapped.as_mut() else {
+            return line_number;
+        };
+        let start = mapped.start();
+        let line_number = mapped.move_to_line(line_number);
+        if mapped.start() != start {
+            self.selection_anchor = None;
+            self.caret = 0;
+            self.reload_settings(settings, term_width);
+        }
+
+        line_number
+    }
+
+    /// ファイルの行数に対する割合の行番号を取得する
+    /// # Arguments
+    /// * `percent` - 割合(0から100)
+    /// # Returns
+    /// * `usize` - 行番号(1から始まる)
+    /// # Notes
+    /// * メモリマップで開いたファイルは、行の索引を作り終えていない場合は作り終えるまで待つ
+    pub fn line_at_percent(&mut self, percent: u8) -> usize {
+        let line_count = match self.mapped.as_mut() {
+            Some(mapped) => mapped.line_count(),
+            None => self.line_count(),
+        };
+        (line_count * percent as usize / 100).max(1)
+    }
+
+    /// メモリマップで開いたファイルで、ファイルの先頭か末尾を表示する範囲に移す
+    /// # Arguments
+    /// * `to_end` - 末尾に移す場合はtrue、先頭に移す場合はfalse
+    /// * `settings` - ファイルを開くときに使う設定
+    /// * `term_width` - 端末の横幅
+    /// # Notes
+    /// * スクロール位置は移さないので、呼び出した後に先頭か末尾までスクロールする
+    pub fn move_window(&mut self, to_end: bool, settings: &Settings, term_width: u16) {
+        if !self
+            .mapped
+            .as_mut()
+            .is_some_and(|mapped| mapped.move_to(to_end))
+        {
+            return;
+        }
+
+        self.selection_anchor = None;
+        self.caret = 0;
+        self.reload_settings(settings, term_width);
+    }
+
+    /// 操作する位置か一番上に表示している行の、ファイルでの行番号を取得する
+    /// # Arguments
+    /// * `width` - 表示する領域の横幅
+    /// * `height` - 表示する領域の高さ
+    /// # Returns
+    /// * `usize` - 行番号(1から始まる)
+    /// # Notes
+    /// * 操作する位置が表示する領域の外にある場合や、整形して表示している場合は一番上の行を使う
+    pub fn current_line_number(&self, width: u16, height: u16) -> usize {
+        if self.contents == self.source && self.caret_screen_position(0, 0, width, height).is_some()
+        {
+            return self.line_number_at(self.caret);
+        }
+        self.top_line_number(width, height).unwrap_or(1)
+    }
+
+    /// 選択している文字列を置き換える
+    /// # Arguments
+    /// * `text` - 置き換える文字列
+    /// * `settings` - 表示する文字列を作り直すときに使う設定
+    /// * `term_width` - 端末の横幅
+    /// # Returns
+    /// * `Result<(), String>` - 置き換えられない場合はエラーメッセージ
+    /// # Notes
+    /// * 選択していない場合は、ファイル全体を置き換える
+    /// * 置き換えた内容は`:w`で書き込める
+    /// * 整形した表示は元の文字列と位置が対応しないので、置き換えられない
+    pub fn replace_selection(
+        &mut self,
+        text: &str,
+        settings: &Settings,
+        term_width: u16,
+    ) -> Result<(), String> {
+        if self.contents != self.source
+            || self.diff_files.is_some()
+            || self.mapped.is_some()
+            || self.is_directory()
+        {
+            return Err("Cannot replace text in this view".to_string());
+        }
+        if self.block_selection && self.selection_anchor.is_some() {
+            return Err("Cannot replace a block selection".to_string());
+        }
+
+        let range = self
+            .selected_ranges()
+            .pop()
+            .unwrap_or(0..self.source.len_bytes());
+        let caret = range.start;
+
+        // 構文解析したものは、変更のあった範囲だけを強調表示し直して使い回す
+        #[cfg(feature = "tree-sitter")]
+        if let Some(highlighter) = &mut self.syntax.highlighter {
+            highlighter.edit(range.start, range.end, text);
+        }
+        let start = self.source.byte_to_char(range.start);
+        let end = self.source.byte_to_char(range.end);
+        self.source.remove(start..end);
+        self.source.insert(start, text);
+        self.selection_anchor = None;
+        self.reload_settings(settings, term_width);
+        self.caret = caret.min(self.contents.len_bytes());
+
+        Ok(())
+    }
+
+    /// 開いたときのファイルの内容を書き込む
+    /// # Arguments
+    /// * `file` - 書き込むファイル(Noneの場合は開いたファイル)
+    /// * `force` - 開いたファイル以外の既存のファイルも上書きする場合はtrue
+    /// # Returns
+    /// * `Result<String, std::io::Error>` - 書き込んだファイル
+    /// # Notes
+    /// * 標準入力を書き込む場合など、開いたファイルがない場合はファイルを指定する必要がある
+    /// * SSHで開いたファイルや、`host:path`の形式で指定したファイルは、sshでホストに書き込む
+    pub fn write(&self, file: Option<&str>, force: bool) -> Result<String, std::io::Error> {
+        let location = match (file, &self.origin) {
+            (Some(file), _) => ssh::Location::parse(file),
+            (None, Some(Origin::Ssh(location))) => Some(location.clone()),
+            _ => None,
+        };
+        if let Some(location) = location {
+            let is_opened = self.origin.as_ref() == Some(&Origin::Ssh(location.clone()));
+            if !force && !is_opened && ssh::exists(&location)? {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    format!("{}: File exists (add ! to overwrite)", location),
+                ));
+            }
+            ssh::write(&location, &self.bytes())?;
+            return Ok(location.to_string());
+        }
+
+        let file = match (file, &self.file) {
+            (Some(file), _) => file,
+            (None, Some(file)) if !self.is_directory() => file,
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "No file name",
+                ))
+            }
+        };
+
+        // 開いたファイル以外を誤って上書きしないように、既存のファイルには書き込まない
+        let is_opened = self.file.as_ref().is_some_and(|opened| {
+            std::fs::canonicalize(opened).ok() == std::fs::canonicalize(file).ok()
+        });
+        if !force && !is_opened && std::path::Path::new(file).exists() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("{}: File exists (add ! to overwrite)", file),
+            ));
+        }
+
+        // メモリマップで開いたファイルは変更できないので、開いたファイルにはそのまま書き込まない
+        // 書き込むとマップしている内容を切り詰めてしまう
+        if is_opened && self.mapped.is_some() {
+            return Ok(file.to_string());
+        }
+        std::fs::write(file, self.bytes())?;
+
+        Ok(file.to_string())
+    }
+
+    /// 書き込む内容(メモリマップで開いたファイルの場合は、表示する範囲ではなくファイル全体)
+    fn bytes(&self) -> Cow<'_, [u8]> {
+        match &self.mapped {
+            Some(mapped) => Cow::Borrowed(mapped.bytes()),
+            None => Cow::Owned(self.source.to_string().into_bytes()),
+        }
+    }
+
+    /// ディレクトリを開いているか
+    fn is_directory(&self) -> bool {
+        self.file
+            .as_deref()
+            .is_some_and(|file| std::path::Path::new(file).is_dir())
+    }
+
+    /// 一覧などに表示する名前を取得する
+    pub fn name(&self) -> String {
+        match (&self.file, &self.diff_files) {
+            (Some(file), _) => file.clone(),
+            (None, Some([(old, _), (new, _)])) => format!("{} → {}", old, new),
+            (None, None) => self.label.clone().unwrap_or_else(|| "[stdin]".to_string()),
+        }
+    }
+
+    /// コマンドの出力を開いたものかを判定する
+    /// # Notes
+    /// * 開いたときのフックは、コマンドの出力には実行しない(フックのコマンドの出力で、繰り返し実行しないように)
+    pub fn is_output(&self) -> bool {
+        self.label.is_some() && self.origin.is_none()
+    }
+
+    /// 開いた後にファイルが変更されたかを判定する
+    /// # Returns
+    /// * `bool` - ファイルの更新日時が開いたときと異なる場合はtrue
+    pub fn is_modified(&self) -> bool {
+        match &self.file {
+            Some(file) => modified_time(file) != self.modified_time,
+            None => false,
+        }
+    }
+
+    /// 表示する領域に合わせて、表示する内容を作成する
+    /// # Arguments
+    /// * `x_start` - 表示する領域の開始位置(X座標)
+    /// * `y_start` - 表示する領域の開始位置(Y座標)
+    /// * `width` - 表示する領域の横幅
+    /// * `height` - 表示する領域の高さ
+    pub fn view(&self, x_start: u16, y_start: u16, width: u16, height: u16) -> contents::Contents {
+        let mut contents = contents::Contents::new(
+            self.contents.clone(),
+            width,
+            height,
+            x_start,
+            y_start,
+            self.cursor_x,
+            self.cursor_y,
+        );
+        contents.options = self.options.clone();
+        contents.cache = self.wrap_cache.clone();
+
+        // 操作する位置がある行や選択、対応する括弧は、折り返した後に重ねる
+        // 操作する位置を動かしただけでは、折り返し直さない
+        if self.show_cursor_line {
+            let (start, end) = self.caret_line();
+            contents
+                .decorations
+                .push(contents::Decoration::Fill(style::Highlight {
+                    range: start..end,
+                    style: self.options.theme.cursor_line,
+                }));
+        }
+        for range in self.selected_ranges() {
+            contents
+                .decorations
+                .push(contents::Decoration::Fill(style::Highlight {
+                    range,
+                    style: self.options.theme.selection,
+                }));
+        }
+        if let Some((from, to)) = self.bracket_match {
+            for position in [from.min(to), from.max(to)] {
+                contents
+                    .decorations
+                    .push(contents::Decoration::Overlay(style::Highlight {
+                        range: position..position + 1,
+                        style: self.options.theme.matching_bracket,
+                    }));
+            }
+        }
+
+        contents
+    }
+
+    /// 折り返した後の内容が、すべて表示する領域に収まるかを判定する
+    /// # Arguments
+    /// * `width` - 表示する領域の横幅
+    /// * `height` - 表示する領域の高さ
+    pub fn fits(&self, width: u16, height: u16) -> bool {
+        self.view(0, 0, width, height).row_count() <= height as usize
+    }
+
+    /// 一番上に表示している行番号を取得する
+    /// # Arguments
+    /// * `width` - 表示する領域の横幅
+    /// * `height` - 表示する領域の高さ
+    /// # Returns
+    /// * `Option<usize>` - 行番号(1から始まる)
+    /// # Notes
+    /// * 開いたときと異なる表示(Markdownの元の文字列や整形したJSON)に切り替えている場合は、
+    ///   開き直したときに行が対応しないのでNoneを返す
+    pub fn top_line_number(&self, width: u16, height: u16) -> Option<usize> {
+        if !self.is_original_view() {
+            return None;
+        }
+
+        self.view(0, 0, width, height).top_line_number()
+    }
+
+    /// 開いたときと同じ表示をしているか(Markdownの元の文字列や整形したJSONに切り替えていないか)
+    fn is_original_view(&self) -> bool {
+        self.markdown_mode == self.is_markdown && !self.json_mode
+    }
+
+    /// 一番上に表示している行に印を付ける
+    /// # Arguments
+    /// * `mark` - 印の名前
+    /// * `width` - 表示する領域の横幅
+    /// * `height` - 表示する領域の高さ
+    /// # Returns
+    /// * `bool` - 印を付けた場合はtrue、開いたときと異なる表示に切り替えている場合はfalse
+    pub fn set_mark(&mut self, mark: char, width: u16, height: u16) -> bool {
+        let Some(line_number) = self.top_line_number(width, height) else {
+            return false;
+        };
+
+        self.marks.set(mark, line_number);
+        self.update_mark_markers();
+
+        true
+    }
+
+    /// 印を付けた行番号を取得する
+    /// # Arguments
+    /// * `mark` - 印の名前
+    /// # Returns
+    /// * `Option<usize>` - 行番号(印を付けていないか、開いたときと異なる表示に切り替えている場合はNone)
+    pub fn mark(&self, mark: char) -> Option<usize> {
+        self.marks.get(mark).filter(|_| self.is_original_view())
+    }
+
+    /// 行番号の左に表示する印を、付けた印と表示の切り替えに合わせて作り直す
+    /// # Notes
+    /// * 開いたときと異なる表示では行が対応しないので、印を表示しない
+    pub fn update_mark_markers(&mut self) {
+        self.options.mark_markers = if self.show_marks && self.is_original_view() {
+            self.marks.markers(self.line_count()).into()
+        } else {
+            Rc::default()
+        };
+    }
+
+    /// 指定した行が一番上に来るようにスクロールする
+    /// # Arguments
+    /// * `line_number` - 行番号(1から始まる)
+    /// * `width` - 表示する領域の横幅
+    /// * `height` - 表示する領域の高さ
+    /// # Notes
+    /// * 折り返した行の数は表示する領域の横幅で変わるので、横幅を指定して計算する
+    pub fn scroll_to_line(&mut self, line_number: usize, width: u16, height: u16) {
+        let mut contents = self.view(0, 0, width, height);
+        if contents.scroll_to_line(line_number) {
+            self.cursor_y = contents.cursor_y;
+        }
+    }
+
+    /// 縦方向のスクロール位置を変更して表示する
+    /// # Arguments
+    /// * `cursor_y` - 新しいスクロール位置
+    /// * `x_start` - 表示する領域の開始位置(X座標)
+    /// * `y_start` - 表示する領域の開始位置(Y座標)
+    /// * `width` - 表示する領域の横幅
+    /// * `height` - 表示する領域の高さ
+    /// * `term_width` - 端末の横幅
+    /// # Notes
+    /// * 最後の行より下までスクロールした場合は、表示するときに最後の行が表示できる位置まで戻す
+    /// * 端末の横幅いっぱいに表示している場合は、端末に行をずらさせて、新しく見えるようになった行だけを出力する
+    pub fn scroll(
+        &mut self,
+        cursor_y: usize,
+        x_start: u16,
+        y_start: u16,
+        width: u16,
+        height: u16,
+        term_width: u16,
+    ) -> std::io::Result<()> {
+        let previous_cursor_y = self.cursor_y;
+        self.cursor_y = cursor_y;
+        let mut contents = self.view(x_start, y_start, width, height);
+        if x_start == 0 && width == term_width {
+            contents.shift_rows(previous_cursor_y)?;
+        }
+        contents.print()?;
+
+        // 表示するときに再計算されるので、cursor_yを更新する
+        self.cursor_y = contents.cursor_y;
+        Ok(())
+    }
+
+    /// 一番上に表示している行の括弧に対応する括弧の行までスクロールし、2つの括弧を強調表示する
+    /// # Arguments
+    /// * `width` - 表示する領域の横幅
+    /// * `height` - 表示する領域の高さ
+    /// # Returns
+    /// * `bool` - 対応する括弧が見つかった場合はtrue
+    /// # Notes
+    /// * 前回移動した先の括弧が一番上の行にある場合はその括弧、それ以外は一番上の行で最初の括弧の対応を探す
+    pub fn jump_to_matching_bracket(&mut self, width: u16, height: u16) -> bool {
+        let Some(line_number) = self.view(0, 0, width, height).top_line_number() else {
+            return false;
+        };
+        let Some((start, end)) = line_range(&self.contents, line_number) else {
+            return false;
+        };
+
+        let from = match self.bracket_match {
+            Some((_, to)) if (start..end).contains(&to) => Some(to),
+            _ => bracket::first_bracket(&self.contents, start, end),
+        };
+        let Some((from, to)) =
+            from.and_then(|from| Some((from, bracket::matching_bracket(&self.contents, from)?)))
+        else {
+            return false;
+        };
+
+        self.bracket_match = Some((from, to));
+        self.scroll_to_line(self.line_number_at(to), width, height);
+
+        true
+    }
+
+    /// 操作する位置を移動し、その位置の行が表示する領域に入るようにスクロールする
+    /// # Arguments
+    /// * `position` - 移動する位置(表示する文字列でのバイト単位の位置)
+    /// * `width` - 表示する領域の横幅
+    /// * `height` - 表示する領域の高さ
+    /// # Notes
+    /// * 上下の行に移動するときに合わせる桁は、移動した位置の桁に戻す
+    pub fn move_caret(&mut self, position: usize, width: u16, height: u16) {
+        self.caret = position;
+        self.preferred_column = None;
+
+        let mut contents = self.view(0, 0, width, height);
+        if contents.scroll_into_view(self.line_number_at(position)) {
+            self.cursor_y = contents.cursor_y;
+        }
+    }
+
+    /// 操作する位置を、同じ行の中で1文字左または右に移動する
+    /// # Arguments
+    /// * `right` - 右に移動する場合はtrue
+    /// * `width` - 表示する領域の横幅
+    /// * `height` - 表示する領域の高さ
+    /// # Returns
+    /// * `bool` - 移動した場合はtrue、行の先頭または末尾で移動できない場合はfalse
+    /// # Notes
+    /// * 結合文字などを含む書記素クラスタは、1文字として扱う
+    pub fn move_caret_horizontally(&mut self, right: bool, width: u16, height: u16) -> bool {
+        let (start, end) = self.caret_line();
+        let position = if right {
+            self.slice(self.caret..end)
+                .grapheme_indices(true)
+                .next()
+                .map(|(_, grapheme)| self.caret + grapheme.len())
+        } else {
+            self.slice(start..self.caret)
+                .grapheme_indices(true)
+                .next_back()
+                .map(|(index, _)| start + index)
+        };
+        let Some(position) = position else {
+            return false;
+        };
+
+        self.move_caret(position, width, height);
+
+        true
+    }
+
+    /// 操作する位置を、上または下の行に移動する
+    /// # Arguments
+    /// * `down` - 下の行に移動する場合はtrue
+    /// * `width` - 表示する領域の横幅
+    /// * `height` - 表示する領域の高さ
+    /// # Returns
+    /// * `bool` - 移動した場合はtrue、最初または最後の行で移動できない場合はfalse
+    /// # Notes
+    /// * 移動する前の桁に合わせ、移動した行が短い場合は行の末尾に移動する
+    /// * 左右に移動するまでは、短い行を通っても最初の桁に合わせる
+    pub fn move_caret_vertically(&mut self, down: bool, width: u16, height: u16) -> bool {
+        let line_number = self.line_number_at(self.caret);
+        let target = if down {
+            line_number.checked_add(1)
+        } else {
+            line_number.checked_sub(1).filter(|&n| n > 0)
+        };
+        let Some((start, end)) = target.and_then(|n| line_range(&self.contents, n)) else {
+            return false;
+        };
+
+        let column = self.preferred_column.unwrap_or(self.caret_column());
+        let position = self
+            .slice(start..end)
+            .grapheme_indices(true)
+            .nth(column)
+            .map_or(end, |(index, _)| start + index);
+
+        self.move_caret(position, width, height);
+        self.preferred_column = Some(column);
+
+        true
+    }
+
+    /// 操作する位置が行の先頭から何文字目かを求める
+    /// # Returns
+    /// * `usize` - 行の先頭からの文字数(0から始まる、書記素クラスタを1文字として数える)
+    pub fn caret_column(&self) -> usize {
+        let (start, _) = self.caret_line();
+        self.slice(start..self.caret).graphemes(true).count()
+    }
+
+    /// 操作する位置が、画面上のどこに表示されるかを求める
+    /// # Arguments
+    /// * `x_start` - 表示する領域の開始位置(X座標)
+    /// * `y_start` - 表示する領域の開始位置(Y座標)
+    /// * `width` - 表示する領域の横幅
+    /// * `height` - 表示する領域の高さ
+    /// # Returns
+    /// * `Option<(u16, u16)>` - 画面上の位置(表示する領域の外にある場合はNone)
+    pub fn caret_screen_position(
+        &self,
+        x_start: u16,
+        y_start: u16,
+        width: u16,
+        height: u16,
+    ) -> Option<(u16, u16)> {
+        let (start, _) = self.caret_line();
+        self.view(x_start, y_start, width, height).screen_position(
+            self.line_number_at(self.caret),
+            &self.slice(start..self.caret),
+        )
+    }
+
+    /// 操作する位置から選択を始める
+    /// # Arguments
+    /// * `block` - 矩形で選択する場合はtrue
+    /// # Notes
+    /// * 選択している間は、操作する位置を移動すると選択する範囲が広がる
+    /// * 選択している場合は、選択を始めた位置をそのままにして選択のしかたを切り替える
+    pub fn start_selection(&mut self, block: bool) {
+        self.selection_anchor.get_or_insert(self.caret);
+        self.block_selection = block;
+    }
+
+    /// 選択をやめる
+    /// # Returns
+    /// * `bool` - 選択していた場合はtrue
+    pub fn clear_selection(&mut self) -> bool {
+        self.selection_anchor.take().is_some()
+    }
+
+    /// 選択のしかたを取得する
+    /// # Returns
+    /// * `Option<bool>` - 矩形で選択している場合はtrue(選択していない場合はNone)
+    pub fn selection_mode(&self) -> Option<bool> {
+        self.selection_anchor.map(|_| self.block_selection)
+    }
+
+    /// 選択している範囲を取得する
+    /// # Returns
+    /// * `Vec<Range<usize>>` - 選択している範囲(表示する文字列でのバイト単位の位置、選択していない場合は空)
+    /// # Notes
+    /// * 選択を始めた位置と操作する位置の文字を両方含む
+    /// * 矩形で選択している場合は、行ごとの範囲を返す
+    pub fn selected_ranges(&self) -> Vec<Range<usize>> {
+        let Some(anchor) = self.selection_anchor else {
+            return Vec::new();
+        };
+        if self.block_selection {
+            return self.block_ranges(anchor);
+        }
+
+        let start = anchor.min(self.caret);
+        let last = anchor.max(self.caret);
+        // 改行文字も1文字として選択できるように、改行文字を含めた行の残りから探す
+        let line_end = self
+            .contents
+            .line_to_byte((self.contents.byte_to_line(last) + 1).min(self.contents.len_lines()));
+        let end = self
+            .slice(last..line_end)
+            .graphemes(true)
+            .next()
+            .map_or(last, |grapheme| last + grapheme.len());
+
+        let range = start..end;
+        vec![range]
+    }
+
+    /// 選択している文字列を取得する
+    /// # Returns
+    /// * `Option<String>` - 選択している文字列(選択していない場合はNone)
+    /// # Notes
+    /// * 矩形で選択している場合は、行ごとの文字列を改行でつなぐ
+    pub fn selected_text(&self) -> Option<String> {
+        self.selection_anchor?;
+        let lines: Vec<Cow<str>> = self
+            .selected_ranges()
+            .into_iter()
+            .map(|range| self.slice(range))
+            .collect();
+        Some(lines.join("\n"))
+    }
+
+    /// 矩形で選択している、行ごとの範囲を取得する
+    /// # Arguments
+    /// * `anchor` - 選択を始めた位置
+    /// # Returns
+    /// * `Vec<Range<usize>>` - 行ごとの選択している範囲(矩形にかからない行は、行末の空の範囲)
+    /// # Notes
+    /// * 全角文字などの一部だけが矩形にかかる場合は、その文字全体を選択する
+    fn block_ranges(&self, anchor: usize) -> Vec<Range<usize>> {
+        let column_span = |position: usize| {
+            let line_number = self.line_number_at(position);
+            let (start, end) =
+                line_range(&self.contents, line_number).unwrap_or((position, position));
+            let columns =
+                contents::Contents::grapheme_columns(&self.options, &self.slice(start..end));
+            columns
+                .iter()
+                .find(|(bytes, _)| bytes.contains(&(position - start)))
+                .map(|(_, columns)| columns.clone())
+                .unwrap_or_else(|| {
+                    // 行末は、最後の文字の右の1桁として扱う
+                    let end = columns.last().map_or(0, |(_, columns)| columns.end);
+                    end..end + 1
+                })
+        };
+
+        let anchor_columns = column_span(anchor);
+        let caret_columns = column_span(self.caret);
+        let left = anchor_columns.start.min(caret_columns.start);
+        let right = anchor_columns.end.max(caret_columns.end);
+
+        let first = self.line_number_at(anchor.min(self.caret));
+        let last = self.line_number_at(anchor.max(self.caret));
+        (first..=last)
+            .filter_map(|line_number| line_range(&self.contents, line_number))
+            .map(|(start, end)| {
+                let selected: Vec<Range<usize>> =
+                    contents::Contents::grapheme_columns(&self.options, &self.slice(start..end))
+                        .into_iter()
+                        .filter(|(_, columns)| columns.start < right && columns.end > left)
+                        .map(|(bytes, _)| bytes)
+                        .collect();
+                match (selected.first(), selected.last()) {
+                    (Some(first), Some(last)) => start + first.start..start + last.end,
+                    _ => end..end,
+                }
+            })
+            .collect()
+    }
+
+    /// 操作する位置を含む行の範囲を取得する
+    /// # Returns
+    /// * `(usize, usize)` - 行の開始位置と、改行文字を除いた終了位置(バイト単位)
+    fn caret_line(&self) -> (usize, usize) {
+        line_range(&self.contents, self.line_number_at(self.caret))
+            .unwrap_or((self.caret, self.caret))
+    }
+
+    /// 操作する位置の行を、改行文字を付けて取得する
+    pub fn caret_line_text(&self) -> String {
+        let (start, end) = self.caret_line();
+        format!("{}\n", self.slice(start..end))
+    }
+
+    /// 端末以外に出力するための、表示する文字列を取得する
+    /// # Arguments
+    /// * `line_numbers` - 行の先頭に行番号を付ける場合はtrue
+    /// # Notes
+    /// * 行番号は、最後の行番号の桁数に右揃えし、空白で区切る
+    /// * 最後の行に改行文字がない場合は、改行文字を付ける
+    pub fn plain_text(&self, line_numbers: bool) -> String {
+        let contents = self.contents.to_string();
+        if !line_numbers {
+            let mut text = contents;
+            if !text.is_empty() && !text.ends_with('\n') {
+                text.push('\n');
+            }
+            return text;
+        }
+
+        let digits = contents.lines().count().to_string().len();
+        (1..)
+            .zip(contents.lines())
+            .map(|(line_number, line)| format!("{:>digits$} {}\n", line_number, line))
+            .collect()
+    }
+
+    /// 表示する文字列を変更する
+    /// # Arguments
+    /// * `contents` - 新しく表示する文字列
+    pub fn set_contents(&mut self, contents: String) {
+        self.wrap_cache.invalidate();
+        self.contents = Rope::from(contents);
+    }
+
+    /// 表示する文字列の末尾に加える
+    /// # Arguments
+    /// * `text` - 加える文字列
+    /// # Notes
+    /// * コマンドの出力を少しずつ受け取る場合などに、それまでの文字列をコピーせずに加える
+    pub fn append_contents(&mut self, text: &str) {
+        self.wrap_cache.invalidate();
+        self.contents.insert(self.contents.len_chars(), text);
+    }
+
+    /// 表示する文字列の行数を取得する
+    /// # Notes
+    /// * `str::lines`と同じように、末尾の改行の後は行として数えない
+    pub fn line_count(&self) -> usize {
+        line_count(&self.contents)
+    }
+
+    /// 表示する文字列の一部を取得する
+    /// # Arguments
+    /// * `range` - 表示する文字列でのバイト単位の範囲
+    /// # Notes
+    /// * ロープの複数の塊にまたがる場合だけ、文字列をコピーする
+    fn slice(&self, range: Range<usize>) -> Cow<'_, str> {
+        self.contents.byte_slice(range).into()
+    }
+
+    /// 表示する文字列の位置が何行目かを求める
+    /// # Arguments
+    /// * `position` - 表示する文字列でのバイト単位の位置
+    /// # Returns
+    /// * `usize` - 行番号(1から始まる)
+    fn line_number_at(&self, position: usize) -> usize {
+        self.contents.byte_to_line(position) + 1
+    }
+
+    /// 表示している文字列と、表示していない方の文字列を入れ替える
+    /// # Notes
+    /// * 強調表示や行の印、注釈も一緒に入れ替える
+    /// * 行の対応が変わるので、先頭から表示する
+    pub fn swap_alternate(&mut self) {
+        std::mem::swap(&mut self.contents, &mut self.alternate.contents);
+        self.wrap_cache.invalidate();
+        std::mem::swap(&mut self.options.highlights, &mut self.alternate.highlights);
+        std::mem::swap(
+            &mut self.options.diff_markers,
+            &mut self.alternate.diff_markers,
+        );
+        std::mem::swap(
+            &mut self.options.annotations,
+            &mut self.alternate.annotations,
+        );
+        std::mem::swap(
+            &mut self.options.fold_markers,
+            &mut self.alternate.fold_markers,
+        );
+        self.update_mark_markers();
+        self.bracket_match = None;
+        self.selection_anchor = None;
+        self.block_selection = false;
+        self.caret = 0;
+        self.preferred_column = None;
+
+        self.cursor_x = 0;
+        self.cursor_y = 0;
+    }
+
+    /// 空白文字を記号で表示するかを切り替える
+    /// # Notes
+    /// * 折り返した行が変わるので、折り返し直す
+    pub fn toggle_whitespace(&mut self) {
+        self.options.show_whitespace = !self.options.show_whitespace;
+        self.wrap_cache.invalidate();
+    }
+
+    /// git blameの注釈を設定する
+    /// # Notes
+    /// * 元の文字列を表示していない場合は、表示していない方の注釈として設定する
+    pub fn set_blame_annotations(&mut self, annotations: Rc<Vec<String>>) {
+        if self.markdown_mode || self.json_mode {
+            self.alternate.annotations = annotations;
+        } else {
+            self.options.annotations = annotations;
+        }
+    }
+
+    /// 2つのファイルの差分を計算して、表示する文字列と設定に反映する
+    /// # Arguments
+    /// * `term_width` - 端末の横幅
+    pub fn show_file_diff(&mut self, term_width: u16) {
+        let Some([(old_name, old), (new_name, new)]) = &self.diff_files else {
+            return;
+        };
+
+        let file_diff = if self.side_by_side {
+            // 行番号の桁数で本文の幅が変わるので、行数の桁数が変わらなくなるまで計算し直す
+            let mut digits = 1;
+            loop {
+                let width = (term_width as usize).saturating_sub(digits + 1);
+                let file_diff = diff::side_by_side(
+                    old,
+                    new,
+                    old_name,
+                    new_name,
+                    width,
+                    self.options.tab_width as usize,
+                    &self.options.theme,
+                );
+                let rows = file_diff.text.lines().count().to_string().len();
+                if rows <= digits {
+                    break file_diff;
+                }
+                digits = rows;
+            }
+        } else {
+            diff::compare(old, new, old_name, new_name, &self.options.theme)
+        };
+
+        // 2つのファイルの差分は、両方のファイルでの行番号を注釈として表示する
+        self.diff_index = diff::DiffIndex::parse(&file_diff.text);
+        self.options.highlights = file_diff.highlights.into();
+        self.options.annotations = file_diff.line_numbers.into();
+        self.options.sticky_headers = self
+            .diff_index
+            .as_ref()
+            .map(|diff_index| diff_index.hunks.clone())
+            .unwrap_or_default();
+        self.set_contents(file_diff.text);
+    }
+}
+
+/// 行の範囲を取得する
+/// # Arguments
+/// * `s` - 文字列
+/// * `line_number` - 行番号(1から始まる)
+/// # Returns
+/// * `Option<(usize, usize)>` - 行の開始位置と、改行文字を除いた終了位置(バイト単位、行がない場合はNone)
+fn line_range(s: &Rope, line_number: usize) -> Option<(usize, usize)> {
+    let line_index = line_number.checked_sub(1)?;
+    if line_index >= line_count(s) {
+        return None;
+    }
+
+    let start = s.line_to_byte(line_index);
+    let mut end = s.line_to_byte(line_index + 1);
+    while end > start && matches!(s.byte(end - 1), b'\r' | b'\n') {
+        end -= 1;
+    }
+
+    Some((start, end))
+}
+
+/// 文字列の行数を取得する
+/// # Notes
+/// * `str::lines`と同じように、末尾の改行の後は行として数えない
+fn line_count(s: &Rope) -> usize {
+    let line_count = s.len_lines();
+    if s.line(line_count - 1).len_bytes() == 0 {
+        line_count - 1
+    } else {
+        line_count
+    }
+}
+
+/// 文字の境界の位置かを判定する
+/// # Arguments
+/// * `s` - 文字列
+/// * `position` - バイト単位の位置
+/// # Returns
+/// * `bool` - 文字列の中の文字の境界か末尾の場合はtrue
+fn is_char_boundary(s: &Rope, position: usize) -> bool {
+    position <= s.len_bytes() && s.char_to_byte(s.byte_to_char(position)) == position
+}
+
+/// ファイルの更新日時を取得する
+fn modified_time(file: &str) -> Option<std::time::SystemTime> {
+    std::fs::metadata(file)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> Settings {
+        Settings {
+            options: contents::DisplayOptions::default(),
+            tab_width: None,
+            wrap: None,
+            config: config::Config::default(),
+        }
+    }
+
+    #[test]
+    fn test_open() {
+        let buffer = Buffer::open(None, "{\"a\": 1}\n".to_string(), &settings());
+        assert_eq!(buffer.name(), "[stdin]");
+        assert!(!buffer.is_modified());
+        assert_eq!(buffer.options.tab_width, 8);
+        assert!(buffer.json_view.is_some());
+
+        // 2つのファイルの差分
+        let files = [
+            ("a".to_string(), "x\n".to_string()),
+            ("b".to_string(), "y\n".to_string()),
+        ];
+        let buffer = Buffer::open_diff(files, false, 80, &settings());
+        assert_eq!(buffer.name(), "a → b");
+        assert_eq!(buffer.contents, "--- a\n+++ b\n@@ -1 +1 @@\n-x\n+y\n");
+        assert!(buffer.diff_index.is_some());
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let mut settings = settings();
+        settings.config.wrap = Some(false);
+        settings.config.tab_width = Some(2);
+        let buffer = Buffer::open(None, "a\n".to_string(), &settings);
+        assert!(buffer.options.chop_long_lines);
+        assert_eq!(buffer.options.tab_width, 2);
+
+        // コマンドライン引数の指定を優先する
+        settings.wrap = Some(true);
+        settings.tab_width = Some(4);
+        let buffer = Buffer::open(None, "a\n".to_string(), &settings);
+        assert!(!buffer.options.chop_long_lines);
+        assert_eq!(buffer.options.tab_width, 4);
+    }
+
+    #[test]
+    fn test_reload_settings() {
+        let mut buffer = Buffer::open(None, "[1]\n".to_string(), &settings());
+        buffer.json_mode = true;
+        buffer.swap_alternate();
+        buffer.cursor_y = 1;
+
+        let mut settings = settings();
+        settings.tab_width = Some(2);
+        buffer.reload_settings(&settings, 80);
+        assert_eq!(buffer.options.tab_width, 2);
+        assert!(buffer.json_mode);
+        assert_eq!(buffer.contents, "[\n  1\n]\n");
+        assert_eq!(buffer.cursor_y, 1);
+    }
+
+    #[test]
+    fn test_write() {
+        let dir = std::env::temp_dir().join(format!("edoc-write-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("out.txt").display().to_string();
+
+        let buffer = Buffer::open(None, "a\tb\n".to_string(), &settings());
+        assert!(buffer.write(None, false).is_err());
+        assert_eq!(buffer.write(Some(&file), false).unwrap(), file);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "a\tb\n");
+
+        // 開いたファイル以外の既存のファイルは、強制しない限り上書きしない
+        assert!(buffer.write(Some(&file), false).is_err());
+        assert!(buffer.write(Some(&file), true).is_ok());
+
+        let buffer = Buffer::open(Some(file.clone()), "c\n".to_string(), &settings());
+        assert!(buffer.write(None, false).is_ok());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "c\n");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    /// `:e`で空のファイルを開いた場合
+    /// 行がなくても表示や移動ができる
+    fn test_open_empty_file() {
+        let dir = std::env::temp_dir().join(format!("edoc-empty-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("empty.txt").display().to_string();
+        std::fs::write(&file, "").unwrap();
+
+        let contents = std::fs::read_to_string(&file).unwrap();
+        let mut buffer = Buffer::open(Some(file), contents, &settings());
+        buffer.scroll_to_line(1, 10, 2);
+        let mut contents = buffer.view(0, 0, 10, 2);
+        let screen = crate::screen::render_to_string(10, 2, || contents.print()).unwrap();
+        assert_eq!(screen, "\n");
+        assert_eq!(buffer.cursor_y, 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_swap_alternate() {
+        let mut buffer = Buffer::open(None, "[1]\n".to_string(), &settings());
+        buffer.cursor_y = 3;
+        buffer.json_mode = true;
+        buffer.swap_alternate();
+        assert_eq!(buffer.contents, "[\n  1\n]\n");
+        assert_eq!(*buffer.options.fold_markers, vec![Some(false), None, None]);
+        assert_eq!(buffer.cursor_y, 0);
+        assert_eq!(buffer.view(0, 0, 80, 3).original_contents, "[\n  1\n]\n");
+        // 行ごとの設定は表示のたびにコピーせず共有する
+        assert!(Rc::ptr_eq(
+            &buffer.options.fold_markers,
+            &buffer.view(0, 0, 80, 3).options.fold_markers
+        ));
+
+        buffer.swap_alternate();
+        assert_eq!(buffer.contents, "[1]\n");
+        assert!(buffer.options.fold_markers.is_empty());
+
+        buffer.set_contents("[]\n".to_string());
+        assert_eq!(buffer.view(0, 0, 80, 3).original_contents, "[]\n");
+    }
+
+    #[test]
+    fn test_marks() {
+        let mut buffer = Buffer::open(None, "[\n1,\n2\n]\n".to_string(), &settings());
+        buffer.scroll_to_line(2, 80, 2);
+        assert!(buffer.set_mark('a', 80, 2));
+        assert_eq!(buffer.mark('a'), Some(2));
+
+        // 印を表示する場合だけ、行番号の左に表示する
+        assert!(buffer.options.mark_markers.is_empty());
+        buffer.show_marks = true;
+        buffer.update_mark_markers();
+        assert_eq!(
+            *buffer.options.mark_markers,
+            vec![None, Some('a'), None, None]
+        );
+
+        // 整形したJSONでは行が対応しないので、印を付けたり表示したりしない
+        buffer.json_mode = true;
+        buffer.swap_alternate();
+        assert!(buffer.options.mark_markers.is_empty());
+        assert!(!buffer.set_mark('b', 80, 2));
+        assert_eq!(buffer.mark('a'), None);
+
+        // 設定を変更しても、付けた印は残る
+        buffer.json_mode = false;
+        buffer.swap_alternate();
+        buffer.reload_settings(&settings(), 80);
+        assert_eq!(buffer.mark('a'), Some(2));
+        assert_eq!(buffer.options.mark_markers.len(), 4);
+    }
+
+    #[test]
+    fn test_move_caret() {
+        let contents = (1..=10).map(|i| format!("line{}\n", i)).collect();
+        let mut buffer = Buffer::open(None, contents, &settings());
+
+        // 表示する領域に入っている行ではスクロールしない
+        buffer.move_caret(12, 80, 3);
+        assert_eq!((buffer.caret, buffer.cursor_y), (12, 0));
+
+        // 下にある行は一番下に、上にある行は一番上に来るようにスクロールする
+        buffer.move_caret(36, 80, 3);
+        assert_eq!(buffer.cursor_y, 4);
+        buffer.move_caret(6, 80, 3);
+        assert_eq!(buffer.cursor_y, 1);
+
+        // 設定を変更しても、操作する位置は残る
+        buffer.reload_settings(&settings(), 80);
+        assert_eq!(buffer.caret, 6);
+    }
+
+    #[test]
+    fn test_move_caret_by_column() {
+        let mut buffer = Buffer::open(None, "abcd\nx\ne\u{301}fgh\n".to_string(), &settings());
+        assert!(!buffer.move_caret_horizontally(false, 80, 3));
+        assert!(buffer.move_caret_horizontally(true, 80, 3));
+        assert!(buffer.move_caret_horizontally(true, 80, 3));
+        assert!(buffer.move_caret_horizontally(true, 80, 3));
+        assert_eq!(buffer.caret_column(), 3);
+
+        // 短い行では行の末尾に移動し、次の行では元の桁に戻る
+        assert!(buffer.move_caret_vertically(true, 80, 3));
+        assert_eq!((buffer.caret, buffer.caret_column()), (6, 1));
+        assert!(buffer.move_caret_vertically(true, 80, 3));
+        assert_eq!((buffer.caret, buffer.caret_column()), (12, 3));
+        assert!(!buffer.move_caret_vertically(true, 80, 3));
+
+        // 左右に移動すると、合わせる桁も変わる
+        assert!(buffer.move_caret_horizontally(true, 80, 3));
+        assert!(!buffer.move_caret_horizontally(true, 80, 3));
+        assert!(buffer.move_caret_vertically(false, 80, 3));
+        assert_eq!(buffer.caret_column(), 1);
+        assert!(buffer.move_caret_vertically(false, 80, 3));
+        assert_eq!(buffer.caret_column(), 4);
+        assert!(!buffer.move_caret_vertically(false, 80, 3));
+
+        // 結合文字を含む文字は1文字として扱う
+        buffer.move_caret(10, 80, 3);
+        assert!(buffer.move_caret_horizontally(false, 80, 3));
+        assert_eq!((buffer.caret, buffer.caret_column()), (7, 0));
+        assert_eq!(
+            buffer.caret_line_text(),
+            buffer.slice(7..buffer.contents.len_bytes())
+        );
+
+        // 行番号の分だけ右にずらして表示する
+        assert_eq!(buffer.caret_screen_position(0, 1, 80, 3), Some((2, 3)));
+
+        // 操作する位置がある行を強調表示する
+        assert!(buffer.view(0, 0, 80, 3).decorations.is_empty());
+        buffer.show_cursor_line = true;
+        let decorations = buffer.view(0, 0, 80, 3).decorations;
+        assert_eq!(
+            decorations
+                .iter()
+                .map(|d| d.highlight().range.clone())
+                .collect::<Vec<_>>(),
+            vec![7..13]
+        );
+    }
+
+    #[test]
+    fn test_selection() {
+        let ranges = |buffer: &Buffer| {
+            buffer
+                .selected_ranges()
+                .into_iter()
+                .map(|range| (range.start, range.end))
+                .collect::<Vec<_>>()
+        };
+        let mut buffer = Buffer::open(None, "ab\ncd\n".to_string(), &settings());
+        assert!(buffer.selected_ranges().is_empty());
+
+        // 選択を始めた位置と操作する位置の文字を両方含む
+        buffer.move_caret(1, 80, 3);
+        buffer.start_selection(false);
+        assert_eq!(ranges(&buffer), vec![(1, 2)]);
+        assert!(buffer.move_caret_vertically(true, 80, 3));
+        assert_eq!(ranges(&buffer), vec![(1, 5)]);
+
+        // 前に戻った場合も、選択を始めた位置の文字を含む
+        buffer.move_caret(0, 80, 3);
+        assert_eq!(ranges(&buffer), vec![(0, 2)]);
+        assert_eq!(
+            buffer.selected_text().as_deref(),
+            Some(buffer.slice(0..2).as_ref())
+        );
+
+        // 選択している範囲を反転して表示する
+        let decorations = buffer.view(0, 0, 80, 3).decorations;
+        assert_eq!(decorations[0].highlight().range, 0..2);
+        assert!(decorations[0].highlight().style.reverse);
+
+        assert!(buffer.clear_selection());
+        assert!(!buffer.clear_selection());
+        assert_eq!(buffer.selected_text(), None);
+        assert!(buffer.view(0, 0, 80, 3).decorations.is_empty());
+    }
+
+    #[test]
+    fn test_block_selection() {
+        fn ranges(buffer: &Buffer) -> Vec<Cow<'_, str>> {
+            buffer
+                .selected_ranges()
+                .into_iter()
+                .map(|range| buffer.slice(range))
+                .collect()
+        }
+        let mut buffer = Buffer::open(None, "abcdef\nあいう\nx\n\tyz\n".to_string(), &settings());
+
+        // 選択を始めた位置と操作する位置の桁の間を、行ごとに選択する
+        buffer.move_caret(1, 80, 5);
+        buffer.start_selection(true);
+        assert_eq!(buffer.selection_mode(), Some(true));
+        buffer.move_caret(10, 80, 5);
+
+        // 一部だけがかかる全角文字は全体を選択する
+        assert_eq!(ranges(&buffer), vec!["bcd", "あい"]);
+
+        // 矩形にかからない短い行は空にし、タブ文字は展開した幅で数える
+        buffer.move_caret(21, 80, 5);
+        assert_eq!(ranges(&buffer), vec!["bcdef", "あいう", "", "\tyz"]);
+        assert_eq!(
+            buffer.selected_text(),
+            Some("bcdef\nあいう\n\n\tyz".to_string())
+        );
+
+        // 選択している間に切り替えると、選択を始めた位置はそのまま
+        buffer.start_selection(false);
+        assert_eq!(buffer.selection_mode(), Some(false));
+        assert_eq!(ranges(&buffer).len(), 1);
+    }
+
+    #[test]
+    fn test_replace_selection() {
+        let mut buffer = Buffer::open(None, "b\na\nc\n".to_string(), &settings());
+        buffer.start_selection(false);
+        buffer.move_caret(3, 80, 3);
+        assert_eq!(buffer.selected_text().as_deref(), Some("b\na\n"));
+
+        // 選択している文字列を置き換えて、選択をやめる
+        assert!(buffer.replace_selection("a\nb\n", &settings(), 80).is_ok());
+        assert_eq!(buffer.contents, "a\nb\nc\n");
+        assert_eq!(buffer.selection_mode(), None);
+
+        // 選択していない場合は、ファイル全体を置き換える
+        assert!(buffer.replace_selection("x\n", &settings(), 80).is_ok());
+        assert_eq!(
+            (buffer.contents.to_string(), buffer.source.to_string()),
+            ("x\n".to_string(), "x\n".to_string())
+        );
+
+        // コマンドの出力は、実行したコマンドの名前で表示する
+        let buffer = Buffer::open_output("|sort".to_string(), "{}".to_string(), &settings());
+        assert_eq!(buffer.name(), "|sort");
+        assert!(buffer.is_output());
+        assert!(!Buffer::open(None, "{}".to_string(), &settings()).is_output());
+
+        // 整形して表示している場合は置き換えられない
+        let mut buffer = Buffer::open(None, "{\"a\":1}\n".to_string(), &settings());
+        buffer.json_mode = true;
+        buffer.swap_alternate();
+        assert!(buffer.replace_selection("b", &settings(), 80).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "tree-sitter")]
+    /// 置き換えた範囲だけを強調表示し直しても、開き直した場合と同じになる
+    fn test_replace_selection_highlights() {
+        let file = Some("main.rs".to_string());
+        let mut buffer = Buffer::open(
+            file.clone(),
+            "fn main() {\n    1\n}\n".to_string(),
+            &settings(),
+        );
+        buffer.caret = buffer.contents.to_string().find('1').unwrap();
+        buffer.start_selection(false);
+        assert!(buffer.replace_selection("\"one\"", &settings(), 80).is_ok());
+        assert_eq!(buffer.contents, "fn main() {\n    \"one\"\n}\n");
+
+        let expected = Buffer::open(file, buffer.contents.to_string(), &settings());
+        assert!(buffer.syntax.highlighter.is_some());
+        assert_eq!(buffer.options.highlights, expected.options.highlights);
+    }
+
+    #[test]
+    /// 何も出力しないコマンドに通した場合
+    /// 出力を開いても、ファイル全体を置き換えても表示できる
+    fn test_pipe_empty_output() {
+        let mut output = Buffer::open_output("|true".to_string(), String::new(), &settings());
+        let mut replaced = Buffer::open(None, "b\na\n".to_string(), &settings());
+        assert!(replaced.replace_selection("", &settings(), 80).is_ok());
+        assert_eq!(replaced.contents, "");
+
+        for buffer in [&mut output, &mut replaced] {
+            let mut contents = buffer.view(0, 0, 10, 2);
+            let screen = crate::screen::render_to_string(10, 2, || contents.print()).unwrap();
+            assert_eq!(screen, "\n");
+            assert!(!buffer.move_caret_vertically(true, 10, 2));
+        }
+    }
+
+    #[test]
+    fn test_fits() {
+        let buffer = Buffer::open(None, "a\nb\nc\n".to_string(), &settings());
+        assert!(buffer.fits(80, 3));
+        assert!(!buffer.fits(80, 2));
+
+        // 折り返した行も数える
+        let buffer = Buffer::open(None, format!("{}\n", "a".repeat(100)), &settings());
+        assert!(!buffer.fits(80, 1));
+        assert!(buffer.fits(80, 2));
+    }
+
+    #[test]
+    fn test_line_at_percent() {
+        let mut buffer = Buffer::open(None, "a\n".repeat(200), &settings());
+        assert_eq!(buffer.line_at_percent(0), 1);
+        assert_eq!(buffer.line_at_percent(50), 100);
+        assert_eq!(buffer.line_at_percent(100), 200);
+        assert_eq!(buffer.move_window_to_line(100, &settings(), 80), 100);
+        assert_eq!(buffer.move_window_to_line(100000, &settings(), 80), 100000);
+    }
+
+    #[test]
+    fn test_plain_text() {
+        let contents: String = (1..=10).map(|i| format!("{}\n", i)).collect();
+        let buffer = Buffer::open(None, contents.clone(), &settings());
+        assert_eq!(buffer.plain_text(false), contents);
+        assert!(buffer.plain_text(true).starts_with(" 1 1\n 2 2\n"));
+        assert!(buffer.plain_text(true).ends_with("10 10\n"));
+
+        let buffer = Buffer::open(None, "a".to_string(), &settings());
+        assert_eq!(buffer.plain_text(false), "a\n");
+        assert_eq!(buffer.plain_text(true), "1 a\n");
+    }
+
+    #[test]
+    fn test_reload() {
+        let contents: String = (1..=10).map(|i| format!("{}\n", i)).collect();
+        let mut buffer = Buffer::open(None, contents, &settings());
+        buffer.move_caret(4, 80, 3);
+        assert_eq!(buffer.current_line_number(80, 3), 3);
+
+        // 操作する位置が表示する領域の外にある場合は、一番上の行を使う
+        buffer.scroll_to_line(5, 80, 3);
+        assert_eq!(buffer.current_line_number(80, 3), 5);
+
+        // スクロール位置を保持して、内容を作り直す
+        buffer.reload("a\nb\nc\nd\ne\nf\n".to_string(), &settings(), 80);
+        assert_eq!(buffer.contents, "a\nb\nc\nd\ne\nf\n");
+        assert_eq!(buffer.top_line_number(80, 3), Some(5));
+    }
+
+    #[test]
+    fn test_jump_to_matching_bracket() {
+        let mut buffer = Buffer::open(None, "a (b\n{\n}\n) c\n".to_string(), &settings());
+        assert_eq!(line_range(&buffer.contents, 2), Some((5, 6)));
+        assert_eq!(line_range(&buffer.contents, 5), None);
+        assert_eq!(buffer.line_number_at(6), 2);
+        assert_eq!(buffer.line_number_at(7), 3);
+
+        // 改行文字はCR LFも除き、末尾に改行がない最後の行も1行とする
+        let text = Rope::from_str("a\r\nあ");
+        assert_eq!(line_range(&text, 1), Some((0, 1)));
+        assert_eq!(line_range(&text, 2), Some((3, 6)));
+        assert_eq!(line_count(&text), 2);
+        assert_eq!(line_count(&Rope::new()), 0);
+        assert!(is_char_boundary(&text, 6));
+        assert!(!is_char_boundary(&text, 4));
+        assert!(!is_char_boundary(&text, 7));
+
+        // 一番上の行で最初の括弧に対応する括弧の行に移動し、もう一度押すと戻る
+        assert!(buffer.jump_to_matching_bracket(80, 2));
+        assert_eq!(buffer.bracket_match, Some((2, 9)));
+        assert_eq!(buffer.top_line_number(80, 2), Some(4));
+        assert!(buffer.jump_to_matching_bracket(80, 2));
+        assert_eq!(buffer.bracket_match, Some((9, 2)));
+        assert_eq!(buffer.top_line_number(80, 2), Some(1));
+
+        // 2つの括弧を強調表示する
+        let decorations = buffer.view(0, 0, 80, 2).decorations;
+        assert_eq!(
+            decorations
+                .iter()
+                .map(|d| d.highlight().range.clone())
+                .collect::<Vec<_>>(),
+            vec![2..3, 9..10]
+        );
+
+        buffer.scroll_to_line(3, 80, 2);
+        assert!(buffer.jump_to_matching_bracket(80, 2));
+        assert_eq!(buffer.top_line_number(80, 2), Some(2));
+
+        // 括弧がない行では移動しない
+        let mut buffer = Buffer::open(None, "a\n(\n".to_string(), &settings());
+        assert!(!buffer.jump_to_matching_bracket(80, 2));
+        assert_eq!(buffer.bracket_match, None);
+    }
+}
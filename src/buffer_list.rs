@@ -0,0 +1,198 @@
+use crate::cell;
+use crate::layout::Rect;
+use crate::overlay::Overlay;
+use crate::screen;
+use crate::style::{ColorDepth, Style};
+
+/// 一覧に表示するファイルの情報
+pub struct BufferListEntry {
+    /// ファイル名
+    pub name: String,
+    /// 開いた後にファイルが変更されたか
+    pub modified: bool,
+    /// 行数
+    pub line_count: usize,
+}
+
+/// 開いているファイルの一覧を、エディタ領域に重ねて表示する
+pub struct BufferList {
+    /// ファイルの情報
+    entries: Vec<BufferListEntry>,
+    /// 選択しているファイルの位置
+    pub selected: usize,
+    /// 選択しているファイルの装飾
+    pub selection_style: Style,
+    /// 端末で表示できる色数(`ColorDepth::None`の場合は装飾しない)
+    pub color_depth: ColorDepth,
+}
+
+impl BufferList {
+    /// BufferListを作成する
+    /// # Arguments
+    /// * `entries` - ファイルの情報
+    /// * `selected` - 最初に選択するファイルの位置
+    pub fn new(entries: Vec<BufferListEntry>, selected: usize) -> Self {
+        Self {
+            selected: selected.min(entries.len().saturating_sub(1)),
+            entries,
+            selection_style: Style {
+                reverse: true,
+                ..Default::default()
+            },
+            color_depth: ColorDepth::Ansi16,
+        }
+    }
+
+    /// ひとつ前のファイルを選択する
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// ひとつ後のファイルを選択する
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.entries.len().saturating_sub(1));
+    }
+
+    /// 各ファイルを表示する行を作成する
+    /// # Notes
+    /// * `番号 ファイル名 [+] 行数`の形式で、ファイル名と行数の桁をそろえる
+    /// * `[+]`は開いた後にファイルが変更された場合だけ表示する
+    fn lines(&self) -> Vec<String> {
+        let ambiguous_wide = screen::ambiguous_wide();
+        let number_width = self.entries.len().to_string().len();
+        let name_width = self
+            .entries
+            .iter()
+            .map(|entry| {
+                cell::text_width(&entry.name, ambiguous_wide) + if entry.modified { 4 } else { 0 }
+            })
+            .max()
+            .unwrap_or(0);
+        let count_width = self
+            .entries
+            .iter()
+            .map(|entry| entry.line_count.to_string().len())
+            .max()
+            .unwrap_or(0);
+
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let name = if entry.modified {
+                    format!("{} [+]", entry.name)
+                } else {
+                    entry.name.clone()
+                };
+                let padding = name_width - cell::text_width(&name, ambiguous_wide);
+                format!(
+                    "{:>number_width$} {}{}  {:>count_width$} lines",
+                    i + 1,
+                    name,
+                    " ".repeat(padding),
+                    entry.line_count
+                )
+            })
+            .collect()
+    }
+}
+
+impl Overlay for BufferList {
+    /// 表示する領域の中央に、枠で囲んだ一覧を表示する
+    /// # Arguments
+    /// * `area` - 表示する領域
+    /// # Notes
+    /// * 領域に収まらない場合は、選択しているファイルが見えるようにスクロールする
+    fn print(&self, area: Rect) -> Option<Rect> {
+        let (x_start, y_start) = (area.x, area.y);
+        let lines = self.lines();
+        let width = area.width as usize;
+        let height = area.height as usize;
+        if width < 3 || height < 3 {
+            return None;
+        }
+
+        let ambiguous_wide = screen::ambiguous_wide();
+        let inner_width = lines
+            .iter()
+            .map(|line| cell::text_width(line, ambiguous_wide))
+            .max()
+            .unwrap_or(0)
+            .max(cell::text_width(" Buffers ", ambiguous_wide))
+            .min(width - 2);
+        let visible = lines.len().min(height - 2);
+        let x = x_start + ((width - inner_width - 2) / 2) as u16;
+        let y = y_start + ((height - visible - 2) / 2) as u16;
+        let scroll = (self.selected + 1).saturating_sub(visible);
+
+        let title = cell::truncate(" Buffers ", inner_width, ambiguous_wide);
+        let top = format!(
+            "┌{}{}┐",
+            title,
+            "─".repeat(inner_width - cell::text_width(&title, ambiguous_wide))
+        );
+        screen::print_row(x, y, inner_width as u16 + 2, &top).unwrap();
+
+        for (row, line) in lines.iter().enumerate().skip(scroll).take(visible) {
+            let line = cell::pad(line, inner_width, ambiguous_wide);
+            let line = if row == self.selected {
+                self.selection_style.paint(&line, self.color_depth)
+            } else {
+                line
+            };
+            screen::print_row(
+                x,
+                y + 1 + (row - scroll) as u16,
+                inner_width as u16 + 2,
+                &format!("│{}│", line),
+            )
+            .unwrap();
+        }
+
+        let bottom = format!("└{}┘", "─".repeat(inner_width));
+        screen::print_row(x, y + 1 + visible as u16, inner_width as u16 + 2, &bottom).unwrap();
+
+        Some(Rect {
+            x,
+            y,
+            width: inner_width as u16 + 2,
+            height: visible as u16 + 2,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, modified: bool, line_count: usize) -> BufferListEntry {
+        BufferListEntry {
+            name: name.to_string(),
+            modified,
+            line_count,
+        }
+    }
+
+    #[test]
+    fn test_lines() {
+        let list = BufferList::new(
+            vec![entry("main.rs", false, 120), entry("a.md", true, 8)],
+            0,
+        );
+        assert_eq!(
+            list.lines(),
+            vec!["1 main.rs   120 lines", "2 a.md [+]    8 lines"]
+        );
+    }
+
+    #[test]
+    fn test_select() {
+        let mut list = BufferList::new(vec![entry("a", false, 1), entry("b", false, 1)], 5);
+        assert_eq!(list.selected, 1);
+        list.select_next();
+        assert_eq!(list.selected, 1);
+        list.select_previous();
+        list.select_previous();
+        assert_eq!(list.selected, 0);
+    }
+}
@@ -0,0 +1,406 @@
+use std::rc::Rc;
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// SGR(文字色などを指定するエスケープシーケンス)で指定する色
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SgrColor {
+    /// 基本色(パラメータの番号をそのまま保持する)
+    Basic(u8),
+    /// 256色のパレットの番号
+    Indexed(u8),
+    /// RGB
+    Rgb(u8, u8, u8),
+}
+
+impl SgrColor {
+    /// SGRのパラメータにする
+    /// # Arguments
+    /// * `is_background` - 背景色か
+    fn parameter(self, is_background: bool) -> String {
+        let prefix = if is_background { 48 } else { 38 };
+        match self {
+            SgrColor::Basic(n) => n.to_string(),
+            SgrColor::Indexed(n) => format!("{};5;{}", prefix, n),
+            SgrColor::Rgb(r, g, b) => format!("{};2;{};{};{}", prefix, r, g, b),
+        }
+    }
+}
+
+/// 1文字分の位置の装飾
+/// # Notes
+/// * 出力した文字列のエスケープシーケンスを解釈して、その位置で有効になっている装飾を保持する
+/// * 同じ見た目の装飾は、指定した順番によらず等しくなる
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CellStyle {
+    /// 太字や下線などの属性(SGRのパラメータの番号のビット)
+    attributes: u16,
+    /// 文字色
+    foreground: Option<SgrColor>,
+    /// 背景色
+    background: Option<SgrColor>,
+    /// ハイパーリンク(OSC 8)を開くエスケープシーケンス(リンクでない場合はNone)
+    hyperlink: Option<Rc<str>>,
+}
+
+impl CellStyle {
+    /// 太字や下線などの属性を表すSGRのパラメータの番号
+    const ATTRIBUTES: [u16; 8] = [1, 2, 3, 4, 5, 7, 8, 9];
+
+    /// SGRを解釈して、装飾を更新する
+    /// # Arguments
+    /// * `parameters` - `ESC [`と`m`の間のパラメータ
+    /// # Notes
+    /// * 解釈できないパラメータは無視する
+    fn apply_sgr(&mut self, parameters: &str) {
+        let mut parameters = parameters
+            .split(';')
+            .map(|parameter| parameter.parse::<u16>().unwrap_or(0));
+        while let Some(parameter) = parameters.next() {
+            match parameter {
+                0 => {
+                    self.attributes = 0;
+                    self.foreground = None;
+                    self.background = None;
+                }
+                n if Self::ATTRIBUTES.contains(&n) => self.attributes |= 1 << n,
+                22 => self.attributes &= !(1 << 1 | 1 << 2),
+                23..=25 | 27..=29 => self.attributes &= !(1 << (parameter - 20)),
+                30..=37 | 90..=97 => self.foreground = Some(SgrColor::Basic(parameter as u8)),
+                40..=47 | 100..=107 => self.background = Some(SgrColor::Basic(parameter as u8)),
+                38 => self.foreground = Self::extended_color(&mut parameters),
+                48 => self.background = Self::extended_color(&mut parameters),
+                39 => self.foreground = None,
+                49 => self.background = None,
+                _ => {}
+            }
+        }
+    }
+
+    /// `38;5;n`や`38;2;r;g;b`のような、256色やRGBの色を読む
+    fn extended_color(parameters: &mut impl Iterator<Item = u16>) -> Option<SgrColor> {
+        let mut next = || parameters.next().map(|n| n.min(u8::MAX as u16) as u8);
+        match next()? {
+            5 => Some(SgrColor::Indexed(next()?)),
+            2 => Some(SgrColor::Rgb(next()?, next()?, next()?)),
+            _ => None,
+        }
+    }
+
+    /// ハイパーリンク(OSC 8)を解釈して、リンクを更新する
+    /// # Arguments
+    /// * `sequence` - エスケープシーケンス
+    /// # Notes
+    /// * OSC 8は`ESC ] 8 ; パラメータ ; URI ST`の形式で、URIが空の場合はリンクを閉じる
+    fn apply_hyperlink(&mut self, sequence: &str) {
+        let Some(body) = sequence.strip_prefix("\x1b]8;") else {
+            return;
+        };
+        let body = body
+            .strip_suffix('\x07')
+            .or_else(|| body.strip_suffix("\x1b\\"))
+            .unwrap_or(body);
+
+        let uri = body.split_once(';').map_or("", |(_, uri)| uri);
+        self.hyperlink = if uri.is_empty() {
+            None
+        } else {
+            Some(Rc::from(format!("\x1b]8;{}\x1b\\", body)))
+        };
+    }
+
+    /// 装飾をすべてリセットしてから、この装飾にするSGR
+    /// # Notes
+    /// * ハイパーリンクは含まないので、`hyperlink`で別に開く
+    pub fn sgr(&self) -> String {
+        let mut parameters = vec!["0".to_string()];
+        parameters.extend(
+            Self::ATTRIBUTES
+                .iter()
+                .filter(|&&n| self.attributes & (1 << n) != 0)
+                .map(|n| n.to_string()),
+        );
+        parameters.extend(self.foreground.map(|color| color.parameter(false)));
+        parameters.extend(self.background.map(|color| color.parameter(true)));
+
+        format!("\x1b[{}m", parameters.join(";"))
+    }
+
+    /// ハイパーリンクを開くエスケープシーケンス
+    /// # Returns
+    /// * `Option<&str>` - リンクを開くOSC 8(リンクでない場合はNone)
+    pub fn hyperlink(&self) -> Option<&str> {
+        self.hyperlink.as_deref()
+    }
+}
+
+/// ハイパーリンクを閉じるOSC 8
+pub const HYPERLINK_END: &str = "\x1b]8;;\x1b\\";
+
+/// 端末の1文字分の位置に表示するもの
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cell {
+    /// 表示する書記素クラスタ(全角文字の右半分は空文字列)
+    pub symbol: String,
+    /// 表示幅(全角文字は2、全角文字の右半分は0)
+    pub width: u8,
+    /// 装飾
+    pub style: CellStyle,
+}
+
+impl Default for Cell {
+    /// 装飾のない空白
+    fn default() -> Self {
+        Self {
+            symbol: " ".to_string(),
+            width: 1,
+            style: CellStyle::default(),
+        }
+    }
+}
+
+impl Cell {
+    /// 全角文字の右半分か
+    pub fn is_continuation(&self) -> bool {
+        self.width == 0
+    }
+}
+
+/// 書記素クラスタの表示幅を計算する
+/// # Arguments
+/// * `grapheme` - 書記素クラスタ
+/// * `ambiguous_wide` - 東アジアの文字幅が曖昧な文字を全角として扱うか
+/// # Returns
+/// * `usize` - 表示幅
+/// # Notes
+/// * ゼロ幅接合子(ZWJ)でつないだ絵文字は、まとめて1つの絵文字として表示されるので幅2とする
+/// * 異体字セレクタ16(VS16)が付いた文字は絵文字として表示されるので幅2、
+///   異体字セレクタ15(VS15)が付いた文字はテキストとして表示されるので幅1とする
+pub fn grapheme_width(grapheme: &str, ambiguous_wide: bool) -> usize {
+    const ZERO_WIDTH_JOINER: char = '\u{200D}';
+    const TEXT_PRESENTATION_SELECTOR: char = '\u{FE0E}';
+    const EMOJI_PRESENTATION_SELECTOR: char = '\u{FE0F}';
+
+    let width = |s: &str| {
+        if ambiguous_wide {
+            s.width_cjk()
+        } else {
+            s.width()
+        }
+    };
+
+    if grapheme.contains(TEXT_PRESENTATION_SELECTOR) {
+        return 1;
+    }
+
+    if grapheme.contains(EMOJI_PRESENTATION_SELECTOR) {
+        return 2;
+    }
+
+    if grapheme.contains(ZERO_WIDTH_JOINER) {
+        // ZWJでつないだ各部分のうち、最も広い幅をクラスタ全体の幅とする
+        return grapheme
+            .split(ZERO_WIDTH_JOINER)
+            .map(width)
+            .max()
+            .unwrap_or(0);
+    }
+
+    width(grapheme)
+}
+
+/// 文字列の表示幅を計算する
+/// # Arguments
+/// * `text` - 装飾のない文字列
+/// * `ambiguous_wide` - 東アジアの文字幅が曖昧な文字を全角として扱うか
+/// # Returns
+/// * `usize` - 書記素クラスタごとの表示幅の合計
+pub fn text_width(text: &str, ambiguous_wide: bool) -> usize {
+    text.graphemes(true)
+        .map(|grapheme| grapheme_width(grapheme, ambiguous_wide))
+        .sum()
+}
+
+/// 表示幅に収まるように、文字列の末尾を切り詰める
+/// # Arguments
+/// * `text` - 装飾のない文字列
+/// * `width` - 表示幅
+/// * `ambiguous_wide` - 東アジアの文字幅が曖昧な文字を全角として扱うか
+/// # Returns
+/// * `String` - 切り詰めた文字列
+/// # Notes
+/// * 書記素クラスタの途中では切らないので、収まらない全角文字や絵文字は丸ごと取り除く
+pub fn truncate(text: &str, width: usize, ambiguous_wide: bool) -> String {
+    let mut total = 0;
+    text.graphemes(true)
+        .take_while(|grapheme| {
+            total += grapheme_width(grapheme, ambiguous_wide);
+            total <= width
+        })
+        .collect()
+}
+
+/// 表示幅に合わせて、文字列を切り詰めるか、足りない分を空白で埋める
+/// # Arguments
+/// * `text` - 装飾のない文字列
+/// * `width` - 表示幅
+/// * `ambiguous_wide` - 東アジアの文字幅が曖昧な文字を全角として扱うか
+/// # Returns
+/// * `String` - 表示幅がちょうど`width`の文字列
+pub fn pad(text: &str, width: usize, ambiguous_wide: bool) -> String {
+    let truncated = truncate(text, width, ambiguous_wide);
+    let padding = width - text_width(&truncated, ambiguous_wide);
+
+    truncated + &" ".repeat(padding)
+}
+
+/// 端末に出力する文字列を、1文字分の位置ごとに分ける
+/// # Arguments
+/// * `text` - 出力する文字列(装飾などのエスケープシーケンスを含む場合もある)
+/// * `ambiguous_wide` - 東アジアの文字幅が曖昧な文字を全角として扱うか
+/// # Returns
+/// * `Vec<Cell>` - 左から順に並べた、1文字分の位置ごとの表示するもの
+/// # Notes
+/// * 文字列の先頭では、装飾のない状態から始める
+/// * SGRとハイパーリンク(OSC 8)以外のエスケープシーケンスと制御文字は、位置が分からないので出力しない
+/// * 基底文字を持たない結合文字などの表示幅が0の書記素クラスタは、直前の文字に加える
+pub fn parse(text: &str, ambiguous_wide: bool) -> Vec<Cell> {
+    let mut cells: Vec<Cell> = Vec::new();
+    let mut style = CellStyle::default();
+    let mut rest = text;
+    while !rest.is_empty() {
+        // エスケープシーケンスまでの文字列を、書記素クラスタごとに分ける
+        let end = rest.find('\x1b').unwrap_or(rest.len());
+        for grapheme in rest[..end].graphemes(true) {
+            if grapheme.chars().all(|c| c.is_control()) {
+                continue;
+            }
+            let width = grapheme_width(grapheme, ambiguous_wide).min(2) as u8;
+            if width == 0 {
+                if let Some(cell) = cells.iter_mut().rev().find(|cell| !cell.is_continuation()) {
+                    cell.symbol.push_str(grapheme);
+                }
+                continue;
+            }
+            cells.push(Cell {
+                symbol: grapheme.to_string(),
+                width,
+                style: style.clone(),
+            });
+            if width == 2 {
+                cells.push(Cell {
+                    symbol: String::new(),
+                    width: 0,
+                    style: style.clone(),
+                });
+            }
+        }
+
+        rest = &rest[end..];
+        if rest.is_empty() {
+            break;
+        }
+        let length = sequence_length(rest);
+        let sequence = &rest[..length];
+        if let Some(parameters) = sequence
+            .strip_prefix("\x1b[")
+            .and_then(|s| s.strip_suffix('m'))
+        {
+            style.apply_sgr(parameters);
+        } else {
+            style.apply_hyperlink(sequence);
+        }
+        rest = &rest[length..];
+    }
+
+    cells
+}
+
+/// 先頭にあるエスケープシーケンスのバイト数
+/// # Notes
+/// * CSIは終わりの文字まで、OSCはBELかESC \まで、それ以外はESCの次の1文字までとする
+fn sequence_length(s: &str) -> usize {
+    let mut chars = s.char_indices().skip(1);
+    match chars.next() {
+        Some((_, '[')) => chars
+            .find(|(_, c)| ('\x40'..='\x7e').contains(c))
+            .map_or(s.len(), |(i, c)| i + c.len_utf8()),
+        Some((_, ']')) => {
+            let mut previous = '\0';
+            for (i, c) in chars {
+                if c == '\x07' || (previous == '\x1b' && c == '\\') {
+                    return i + 1;
+                }
+                previous = c;
+            }
+            s.len()
+        }
+        Some((i, c)) => i + c.len_utf8(),
+        None => s.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbols(cells: &[Cell]) -> Vec<&str> {
+        cells.iter().map(|cell| cell.symbol.as_str()).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let cells = parse("a\x1b[1;31mあ\x1b[22me\u{301}\x1b[0m\x07b", false);
+        assert_eq!(symbols(&cells), ["a", "あ", "", "e\u{301}", "b"]);
+        assert_eq!(cells[1].width, 2);
+        assert!(cells[2].is_continuation());
+
+        // 装飾は、その位置で有効になっているものを保持する
+        assert_eq!(cells[0].style, CellStyle::default());
+        assert_eq!(cells[1].style.sgr(), "\x1b[0;1;31m");
+        assert_eq!(cells[3].style.sgr(), "\x1b[0;31m");
+        assert_eq!(cells[4].style, CellStyle::default());
+
+        // ハイパーリンクは、閉じるまでの文字に付ける
+        let cells = parse("\x1b]8;;https://example.com\x07ab\x1b]8;;\x1b\\c", false);
+        assert_eq!(symbols(&cells), ["a", "b", "c"]);
+        assert_eq!(
+            cells[0].style.hyperlink(),
+            Some("\x1b]8;;https://example.com\x1b\\")
+        );
+        assert_eq!(cells[1].style, cells[0].style);
+        assert_eq!(cells[2].style, CellStyle::default());
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("abc", 2, false), "ab");
+        assert_eq!(truncate("あいう", 5, false), "あい");
+        assert_eq!(pad("あい", 3, false), "あ ");
+        assert_eq!(pad("ab", 4, false), "ab  ");
+
+        // 書記素クラスタの途中では切らない
+        assert_eq!(truncate("e\u{301}e\u{301}", 1, false), "e\u{301}");
+        assert_eq!(text_width("👨\u{200D}👩\u{200D}👧a", false), 3);
+        assert_eq!(truncate("👨\u{200D}👩\u{200D}👧a", 1, false), "");
+
+        // 東アジアの文字幅が曖昧な文字は、設定に合わせて数える
+        assert_eq!(truncate("○○", 2, false), "○○");
+        assert_eq!(truncate("○○", 2, true), "○");
+        assert_eq!(pad("○", 3, true), "○ ");
+    }
+
+    #[test]
+    fn test_apply_sgr() {
+        let mut style = CellStyle::default();
+        style.apply_sgr("38;5;100;48;2;1;2;3;4");
+        assert_eq!(style.sgr(), "\x1b[0;4;38;5;100;48;2;1;2;3m");
+
+        // 属性や色だけを元に戻した場合は、ほかの装飾は残る
+        style.apply_sgr("24;39");
+        assert_eq!(style.sgr(), "\x1b[0;48;2;1;2;3m");
+        style.apply_sgr("");
+        assert_eq!(style, CellStyle::default());
+    }
+}
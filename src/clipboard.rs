@@ -0,0 +1,104 @@
+use std::io::Write;
+
+/// コピーした先
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// OSのクリップボード
+    System,
+    /// 端末(OSC 52で端末にクリップボードへのコピーを頼む)
+    Terminal,
+}
+
+/// OSのクリップボードか端末を通して、文字列をクリップボードにコピーする
+#[derive(Default)]
+pub struct Clipboard {
+    /// OSのクリップボード(初めてコピーするときに開く)
+    /// # Notes
+    /// * X11などではコピーしたプログラムが内容を持ち続けるため、終了するまで開いたままにする
+    system: Option<arboard::Clipboard>,
+}
+
+impl Clipboard {
+    /// 文字列をクリップボードにコピーする
+    /// # Arguments
+    /// * `text` - コピーする文字列
+    /// # Returns
+    /// * `std::io::Result<Target>` - コピーした先
+    /// # Notes
+    /// * SSHで接続している場合は、手元の端末のクリップボードにコピーするためにOSC 52を使う
+    /// * OSのクリップボードを使えない場合も、OSC 52を使う(端末が対応していない場合はコピーできない)
+    pub fn copy(&mut self, text: &str) -> std::io::Result<Target> {
+        if !is_remote() && self.copy_to_system(text) {
+            return Ok(Target::System);
+        }
+
+        let mut stdout = std::io::stdout();
+        stdout.write_all(osc52(text).as_bytes())?;
+        stdout.flush()?;
+        Ok(Target::Terminal)
+    }
+
+    /// 文字列をOSのクリップボードにコピーする
+    /// # Returns
+    /// * `bool` - コピーできた場合はtrue
+    fn copy_to_system(&mut self, text: &str) -> bool {
+        if self.system.is_none() {
+            self.system = arboard::Clipboard::new().ok();
+        }
+        self.system
+            .as_mut()
+            .is_some_and(|clipboard| clipboard.set_text(text).is_ok())
+    }
+}
+
+/// SSHで接続しているかを判定する
+fn is_remote() -> bool {
+    ["SSH_TTY", "SSH_CONNECTION"]
+        .iter()
+        .any(|name| std::env::var_os(name).is_some())
+}
+
+/// 端末にクリップボードへのコピーを頼むエスケープシーケンス(OSC 52)を作成する
+/// # Arguments
+/// * `text` - コピーする文字列
+fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64(text.as_bytes()))
+}
+
+/// バイト列をBase64で符号化する
+fn base64(bytes: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(TABLE[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64("あ".as_bytes()), "44GC");
+
+        assert_eq!(osc52("a\nb"), "\x1b]52;c;YQpi\x07");
+    }
+}
@@ -0,0 +1,231 @@
+use crate::keymap::Action;
+use crate::marks::Marks;
+
+/// `:`に続けて入力するコマンド
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// キーに割り当てる操作と同じ操作を行う(`:q`や`:toggle_whitespace`など)
+    Action(Action),
+    /// 指定した行に移動する(`:42`)
+    GoToLine(usize),
+    /// ファイルの行数に対する割合の行に移動する(`:50%`)
+    GoToPercent(u8),
+    /// 表示に関する設定を変更する(`:set wrap`など)
+    Set(SetOption),
+    /// ファイルを開く(`:e FILE`)
+    Edit(String),
+    /// 一番上に表示している行に印を付ける(`:mark a`)
+    Mark(char),
+    /// 開いたときのファイルの内容を書き込む(`:w [FILE]`)
+    Write {
+        /// 書き込むファイル(Noneの場合は開いたファイル)
+        file: Option<String>,
+        /// `:w!`のように、ほかのファイルを上書きするか
+        force: bool,
+    },
+    /// 選択している文字列かファイル全体を、シェルのコマンドに通す(`:|sort`、`:|!sort`)
+    Pipe {
+        /// 実行するコマンド
+        command: String,
+        /// `:|!`のように、コマンドの出力で選択している文字列を置き換えるか(falseの場合は出力を新しく開く)
+        replace: bool,
+    },
+    /// シェルのコマンドを実行して、出力を新しく開く(`:!ls`)
+    Shell(String),
+    /// プラグインで登録したコマンドを実行する
+    /// (`parse`では作らず、組み込みのコマンドにない名前の場合にプラグインが作る)
+    Plugin {
+        /// 登録したコマンドの名前
+        name: String,
+        /// 名前に続けて入力した文字列
+        argument: Option<String>,
+    },
+}
+
+/// `:set`で変更する設定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOption {
+    /// 長い行を折り返すか(`wrap`と`nowrap`)
+    Wrap(bool),
+    /// タブ文字を展開する幅(`tab_width=N`)
+    TabWidth(u16),
+}
+
+/// 入力したコマンドを解釈する
+/// # Arguments
+/// * `line` - `:`に続けて入力した文字列
+/// # Returns
+/// * `Result<Command, String>` - 解釈したコマンド(解釈できない場合はエラーメッセージ)
+/// # Notes
+/// * `:n`、`:p`、`:b`、`:r`、`:q`は、ファイルの切り替えや終了の短い名前として使える
+/// * それ以外は、設定ファイルの`[keymap]`で使う操作の名前をそのまま使える
+pub fn parse(line: &str) -> Result<Command, String> {
+    let line = line.trim();
+    if let Some(command) = line.strip_prefix('!') {
+        let command = command.trim();
+        if command.is_empty() {
+            return Err("No command".to_string());
+        }
+        return Ok(Command::Shell(command.to_string()));
+    }
+    if let Some(pipe) = line.strip_prefix('|') {
+        let (command, replace) = match pipe.strip_prefix('!') {
+            Some(command) => (command.trim(), true),
+            None => (pipe.trim(), false),
+        };
+        if command.is_empty() {
+            return Err("No command".to_string());
+        }
+        return Ok(Command::Pipe {
+            command: command.to_string(),
+            replace,
+        });
+    }
+
+    let (name, argument) = split_name(line);
+
+    if let Ok(line_number) = name.parse::<usize>() {
+        return Ok(Command::GoToLine(line_number.max(1)));
+    }
+    if let Some(percent) = name.strip_suffix('%') {
+        return match percent.parse() {
+            Ok(percent @ 0..=100) => Ok(Command::GoToPercent(percent)),
+            _ => Err(format!("{}: Invalid percentage", name)),
+        };
+    }
+
+    let command = match (name, argument) {
+        ("", None) => return Err("No command".to_string()),
+        ("q" | "quit", None) => Command::Action(Action::Quit),
+        ("n" | "next", None) => Command::Action(Action::NextFile),
+        ("p" | "prev" | "previous", None) => Command::Action(Action::PreviousFile),
+        ("b" | "buffers" | "ls", None) => Command::Action(Action::BufferList),
+        ("r" | "recent", None) => Command::Action(Action::RecentFiles),
+        ("e" | "edit", Some(file)) => Command::Edit(file.to_string()),
+        ("e" | "edit", None) => return Err("No file name".to_string()),
+        ("w" | "write" | "w!" | "write!", file) => Command::Write {
+            file: file.map(str::to_string),
+            force: name.ends_with('!'),
+        },
+        ("ma" | "mark" | "k", Some(mark)) => {
+            let mut chars = mark.chars();
+            match (chars.next(), chars.next()) {
+                (Some(mark), None) if Marks::is_mark(mark) => Command::Mark(mark),
+                _ => return Err(format!("{}: Invalid mark", mark)),
+            }
+        }
+        ("ma" | "mark" | "k", None) => return Err("No mark name".to_string()),
+        ("set", Some(option)) => Command::Set(parse_set_option(option)?),
+        ("set", None) => return Err("No option".to_string()),
+        (name, None) => match name.parse() {
+            Ok(Action::Unbound) | Err(_) => return Err(format!("{}: Unknown command", name)),
+            Ok(action) => Command::Action(action),
+        },
+        (name, Some(_)) => return Err(format!("{}: Trailing characters", name)),
+    };
+
+    Ok(command)
+}
+
+/// 入力したコマンドを、名前とそれに続く文字列に分ける
+/// # Arguments
+/// * `line` - `:`に続けて入力した文字列
+/// # Returns
+/// * `(&str, Option<&str>)` - 名前と、続けて入力した文字列(名前だけの場合はNone)
+pub fn split_name(line: &str) -> (&str, Option<&str>) {
+    let line = line.trim();
+    match line.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, Some(argument.trim_start())),
+        None => (line, None),
+    }
+}
+
+/// `:set`に続けて入力した設定を解釈する
+fn parse_set_option(option: &str) -> Result<SetOption, String> {
+    match option.split_once('=') {
+        None if option == "wrap" => Ok(SetOption::Wrap(true)),
+        None if option == "nowrap" => Ok(SetOption::Wrap(false)),
+        Some(("tab_width", value)) => match value.parse() {
+            Ok(width @ 1..) => Ok(SetOption::TabWidth(width)),
+            _ => Err(format!("{}: Invalid value", option)),
+        },
+        _ => Err(format!("{}: Unknown option", option)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("q"), Ok(Command::Action(Action::Quit)));
+        assert_eq!(parse(" n "), Ok(Command::Action(Action::NextFile)));
+        assert_eq!(
+            parse("toggle_whitespace"),
+            Ok(Command::Action(Action::ToggleWhitespace))
+        );
+        assert_eq!(parse("42"), Ok(Command::GoToLine(42)));
+        assert_eq!(parse("0"), Ok(Command::GoToLine(1)));
+        assert_eq!(parse("1000000"), Ok(Command::GoToLine(1000000)));
+        assert_eq!(parse("50%"), Ok(Command::GoToPercent(50)));
+        assert_eq!(
+            parse("e  src/main.rs"),
+            Ok(Command::Edit("src/main.rs".to_string()))
+        );
+        assert_eq!(
+            parse("w"),
+            Ok(Command::Write {
+                file: None,
+                force: false
+            })
+        );
+        assert_eq!(
+            parse("w! out.txt"),
+            Ok(Command::Write {
+                file: Some("out.txt".to_string()),
+                force: true
+            })
+        );
+        assert_eq!(parse("mark a"), Ok(Command::Mark('a')));
+        assert_eq!(
+            parse("set nowrap"),
+            Ok(Command::Set(SetOption::Wrap(false)))
+        );
+        assert_eq!(
+            parse("set tab_width=4"),
+            Ok(Command::Set(SetOption::TabWidth(4)))
+        );
+        assert_eq!(
+            parse("| sort -u | head"),
+            Ok(Command::Pipe {
+                command: "sort -u | head".to_string(),
+                replace: false
+            })
+        );
+        assert_eq!(
+            parse("|!jq ."),
+            Ok(Command::Pipe {
+                command: "jq .".to_string(),
+                replace: true
+            })
+        );
+
+        assert!(parse("").is_err());
+        assert!(parse("e").is_err());
+        assert!(parse("none").is_err());
+        assert!(parse("unknown").is_err());
+        assert!(parse("q now").is_err());
+        assert!(parse("set tab_width=0").is_err());
+        assert!(parse("set number").is_err());
+        assert!(parse("101%").is_err());
+        assert!(parse("k ab").is_err());
+        assert!(parse("mark").is_err());
+        assert!(parse("|! ").is_err());
+        assert_eq!(
+            parse("! git status"),
+            Ok(Command::Shell("git status".to_string()))
+        );
+        assert!(parse("!").is_err());
+    }
+}
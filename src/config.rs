@@ -0,0 +1,296 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use serde::Deserialize;
+
+use crate::hooks::Hook;
+use crate::keymap::{Action, KeySequence, KeymapPreset};
+
+/// 設定ディレクトリのパスを取得する
+/// # Returns
+/// * `Option<PathBuf>` - 設定ディレクトリのパス
+/// # Notes
+/// * `XDG_CONFIG_HOME`が設定されている場合は`$XDG_CONFIG_HOME/edoc`
+/// * それ以外は`$HOME/.config/edoc`
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(base.join(env!("CARGO_PKG_NAME")))
+}
+
+/// 状態を保存するディレクトリのパスを取得する
+/// # Returns
+/// * `Option<PathBuf>` - 状態を保存するディレクトリのパス
+/// # Notes
+/// * `XDG_STATE_HOME`が設定されている場合は`$XDG_STATE_HOME/edoc`
+/// * それ以外は`$HOME/.local/state/edoc`
+pub fn state_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?)
+            .join(".local")
+            .join("state"),
+    };
+
+    Some(base.join(env!("CARGO_PKG_NAME")))
+}
+
+/// 設定ファイルの内容
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// 長い行を折り返すかどうか(falseの場合は切り詰める)
+    pub wrap: Option<bool>,
+    /// タブ文字を展開する幅(ファイルの種類の既定値がない場合に使う)
+    #[serde(deserialize_with = "deserialize_tab_width")]
+    pub tab_width: Option<u16>,
+    /// テーマの名前またはテーマファイルのパス
+    pub theme: Option<String>,
+    /// ステータスバーの設定
+    pub status_bar: StatusBarConfig,
+    /// 既定の割り当てに重ねるプリセット(`less`、`vim`、`emacs`)
+    pub keymap_preset: Option<KeymapPreset>,
+    /// キーに割り当てる操作(`"ctrl+q" = "quit"`や`"g g" = "go_to_top"`など、既定の割り当てを上書きする)
+    pub keymap: HashMap<KeySequence, Action>,
+    /// ファイルの種類ごとの設定(`[filetype.python]`など)
+    pub filetype: HashMap<String, FileTypeConfig>,
+    /// 設定ファイルを変更したときに、自動で読み込み直すかどうか
+    pub watch: bool,
+    /// 操作する位置がある行を強調表示するかどうか
+    pub cursor_line: bool,
+    /// 時点ごとに実行するコマンド(`on_save = ["!rustfmt {file}"]`など)
+    pub hooks: HashMap<Hook, Vec<String>>,
+}
+
+/// ステータスバーの設定
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatusBarConfig {
+    /// 表示する項目の名前と順番(指定しない場合は、すべての項目を追加した順に表示する)
+    pub items: Option<Vec<String>>,
+}
+
+/// ファイルの種類ごとの設定
+/// 指定しなかった項目は、コマンドライン引数やファイルの種類の既定値を使う
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileTypeConfig {
+    /// 長い行を折り返すかどうか(falseの場合は切り詰める)
+    pub wrap: Option<bool>,
+    /// タブ文字を展開する幅
+    #[serde(deserialize_with = "deserialize_tab_width")]
+    pub tab_width: Option<u16>,
+    /// 行末の空白の扱い
+    pub trailing_whitespace: Option<TrailingWhitespace>,
+}
+
+/// 行末の空白の扱い
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrailingWhitespace {
+    /// そのまま表示する
+    #[default]
+    Show,
+    /// 目立つように装飾する
+    Highlight,
+    /// 取り除いて表示する
+    Trim,
+}
+
+impl Config {
+    /// 設定ファイルのパスを取得する
+    fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("config.toml"))
+    }
+
+    /// 設定ファイルの更新日時を取得する
+    /// # Returns
+    /// * `Option<SystemTime>` - 更新日時(設定ファイルがない場合はNone)
+    pub fn modified_time() -> Option<std::time::SystemTime> {
+        Self::path()
+            .and_then(|path| std::fs::metadata(path).ok())
+            .and_then(|metadata| metadata.modified().ok())
+    }
+
+    /// 設定ディレクトリの`config.toml`を読み込む
+    /// # Returns
+    /// * `Result<Config, std::io::Error>` - 読み込んだ設定
+    /// # Notes
+    /// * 設定ファイルがない場合は既定の設定を返す
+    pub fn load() -> Result<Self, std::io::Error> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => {
+                return Err(std::io::Error::new(
+                    e.kind(),
+                    format!("{}: {}", path.display(), e),
+                ))
+            }
+        };
+
+        Self::parse(&contents).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })
+    }
+
+    /// TOML形式の文字列から設定を作成する
+    fn parse(contents: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(contents)
+    }
+
+    /// ファイルの種類の設定を取得する
+    /// # Arguments
+    /// * `name` - ファイルの種類の名前
+    /// # Returns
+    /// * `FileTypeConfig` - 設定がない場合は何も指定していない設定
+    pub fn for_filetype(&self, name: &str) -> FileTypeConfig {
+        self.filetype.get(name).cloned().unwrap_or_default()
+    }
+}
+
+/// タブ文字を展開する幅を読み込む
+/// # Notes
+/// * 0の場合はタブ文字を展開できないので、エラーにする
+fn deserialize_tab_width<'de, D>(deserializer: D) -> Result<Option<u16>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Option::<u16>::deserialize(deserializer)? {
+        Some(0) => Err(serde::de::Error::custom("tab_width must be at least 1")),
+        tab_width => Ok(tab_width),
+    }
+}
+
+/// 行末の空白を取り除く
+/// # Arguments
+/// * `s` - 文字列
+/// # Returns
+/// * `String` - 各行の行末の空白を取り除いた文字列(改行文字は残す)
+pub fn trim_trailing_whitespace(s: &str) -> String {
+    s.split_inclusive('\n')
+        .map(|line| {
+            let body = line.trim_end_matches(['\n', '\r']);
+            format!("{}{}", body.trim_end(), &line[body.len()..])
+        })
+        .collect()
+}
+
+/// 行末の空白の範囲を取得する
+/// # Arguments
+/// * `s` - 文字列
+/// # Returns
+/// * `Vec<Range<usize>>` - 行末の空白のバイト単位の範囲(開始位置の昇順)
+pub fn trailing_whitespace_ranges(s: &str) -> Vec<std::ops::Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut offset = 0;
+    for line in s.split_inclusive('\n') {
+        let body = line.trim_end_matches(['\n', '\r']);
+        let trimmed = body.trim_end();
+        if trimmed.len() < body.len() {
+            ranges.push(offset + trimmed.len()..offset + body.len());
+        }
+        offset += line.len();
+    }
+
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_parse() {
+        let config = Config::parse(
+            r#"
+            wrap = false
+            tab_width = 2
+            theme = "dark"
+            keymap_preset = "vim"
+            cursor_line = true
+
+            [status_bar]
+            items = ["line", "filetype"]
+
+            [keymap]
+            "ctrl+q" = "quit"
+            "ctrl+w" = "none"
+            "ctrl+k ctrl+w" = "close_pane"
+
+            [filetype.python]
+            wrap = false
+            tab_width = 4
+            trailing_whitespace = "highlight"
+
+            [filetype.make]
+            trailing_whitespace = "trim"
+
+            [hooks]
+            on_open = ["set nowrap"]
+            on_save = ["!rustfmt {file}", "reload"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.for_filetype("python"),
+            FileTypeConfig {
+                wrap: Some(false),
+                tab_width: Some(4),
+                trailing_whitespace: Some(TrailingWhitespace::Highlight),
+            }
+        );
+        assert_eq!(
+            config.for_filetype("make").trailing_whitespace,
+            Some(TrailingWhitespace::Trim)
+        );
+        assert_eq!(config.for_filetype("rust"), FileTypeConfig::default());
+        assert_eq!(config.wrap, Some(false));
+        assert_eq!(config.tab_width, Some(2));
+        assert_eq!(config.theme.as_deref(), Some("dark"));
+        assert_eq!(config.keymap_preset, Some(KeymapPreset::Vim));
+        assert!(config.cursor_line);
+        assert_eq!(
+            config.status_bar.items,
+            Some(vec!["line".to_string(), "filetype".to_string()])
+        );
+        assert_eq!(
+            config.keymap.get(&"ctrl+q".parse().unwrap()),
+            Some(&Action::Quit)
+        );
+        assert_eq!(
+            config.keymap.get(&"ctrl+k ctrl+w".parse().unwrap()),
+            Some(&Action::ClosePane)
+        );
+        assert_eq!(
+            config.hooks.get(&Hook::OnSave),
+            Some(&vec!["!rustfmt {file}".to_string(), "reload".to_string()])
+        );
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+        assert!(Config::parse("[keymap]\n\"hyper+q\" = \"quit\"").is_err());
+        assert!(Config::parse("[keymap]\n\"q\" = \"unknown\"").is_err());
+
+        assert!(Config::parse("tab_width = 0").is_err());
+        assert!(Config::parse("[filetype.python]\ntab_width = 0").is_err());
+        assert!(Config::parse("[filetype.python]\nwrap = 1").is_err());
+        assert!(Config::parse("[filetype.python]\nunknown = true").is_err());
+        assert!(Config::parse("[hooks]\non_close = []").is_err());
+    }
+
+    #[test]
+    fn test_trailing_whitespace() {
+        let s = "a  \nb\t\r\n  \nc";
+        assert_eq!(trim_trailing_whitespace(s), "a\nb\r\n\nc");
+        assert_eq!(trailing_whitespace_ranges(s), vec![1..3, 5..6, 8..10]);
+    }
+}
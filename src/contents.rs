@@ -1,30 +1,347 @@
-use std::io::stdout;
+use std::{borrow::Cow, cell::RefCell, ops::Range, rc::Rc};
 
-use crossterm::{
-    cursor::{MoveTo, MoveToColumn},
-    style::{Attribute, Print},
-    terminal::{Clear, ClearType},
-    QueueableCommand,
-};
+use ropey::{Rope, RopeSlice};
 
 extern crate unicode_width;
-use unicode_width::UnicodeWidthChar;
+use crate::cell;
+use crate::git::DiffMarker;
+use crate::layout::Rect;
+use crate::screen;
+use crate::style::{ColorDepth, Highlight, Style, Theme};
+
+use unicode_bidi::ParagraphBidiInfo;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 /// 分割した文字列
 pub struct SplitLine {
     /// 行番号
-    pub line_number: u16,
+    pub line_number: usize,
     /// その行の何番目か(0番目から始まる)
-    pub line_index: u16,
+    pub line_index: usize,
     /// 行の内容
     pub contents: String,
+    /// 改ページを表す行か
+    pub is_page_break: bool,
+}
+
+/// 表示に関する設定
+#[derive(Clone, PartialEq)]
+pub struct DisplayOptions {
+    /// タブ文字を展開する幅(タブストップの間隔)
+    pub tab_width: u16,
+    /// 制御文字を変換せずにそのまま出力するか
+    pub raw_control_chars: bool,
+    /// 空白文字を記号で表示するか
+    pub show_whitespace: bool,
+    /// 連続する空行を1行にまとめるか
+    pub squeeze_blank: bool,
+    /// 長い行を折り返さずに切り詰めるか
+    pub chop_long_lines: bool,
+    /// エスケープシーケンスをすべて取り除いて表示するか
+    pub strip_ansi: bool,
+    /// 端末で表示できる色数(`ColorDepth::None`の場合は装飾しない)
+    pub color_depth: ColorDepth,
+    /// 行番号や記号などの装飾
+    pub theme: Theme,
+    /// 元の文字列の一部の装飾(開始位置の昇順で、重ならないこと)
+    /// 以下の行ごとの設定と同じように、表示するたびにコピーしないように共有する
+    pub highlights: Rc<Vec<Highlight>>,
+    /// 東アジアの文字幅が曖昧な文字(○や±など)を全角として扱うか
+    pub ambiguous_wide: bool,
+    /// 行ごとの折りたたみの状態(空の場合は行番号の横に表示しない)
+    /// 折りたためない行はNone、折りたたんでいる行は`Some(true)`
+    pub fold_markers: Rc<Vec<Option<bool>>>,
+    /// 行ごとの変更の状態(空の場合は行番号の左に表示しない)
+    pub diff_markers: Rc<Vec<Option<DiffMarker>>>,
+    /// 行ごとに付けた印の名前(空の場合は行番号の左に表示しない)
+    pub mark_markers: Rc<Vec<Option<char>>>,
+    /// 行ごとに行番号の左に表示する注釈(git blameなど、空の場合は表示しない)
+    pub annotations: Rc<Vec<String>>,
+    /// 一番上に固定して表示する見出しの行番号と、その見出しが続く最後の行番号
+    /// 一番上の行が見出しより後ろで範囲内にある場合は、見出しを一番上に表示する
+    pub sticky_headers: Vec<(usize, usize)>,
+    /// 表示する行番号に足す数(大きなファイルの一部だけを表示する場合に、ファイルでの行番号にする)
+    pub line_number_offset: usize,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        Self {
+            tab_width: 8,
+            raw_control_chars: false,
+            show_whitespace: false,
+            squeeze_blank: false,
+            chop_long_lines: false,
+            strip_ansi: false,
+            color_depth: ColorDepth::Ansi16,
+            theme: Theme::default(),
+            highlights: Rc::default(),
+            ambiguous_wide: false,
+            fold_markers: Rc::default(),
+            diff_markers: Rc::default(),
+            mark_markers: Rc::default(),
+            annotations: Rc::default(),
+            sticky_headers: Vec::new(),
+            line_number_offset: 0,
+        }
+    }
+}
+
+/// 折り返した後に重ねる装飾(操作する位置がある行や選択など)
+/// # Notes
+/// * 操作する位置を動かすたびに折り返し直さないように、折り返した行のキャッシュには含めず、表示する行だけに重ねる
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decoration {
+    /// 範囲の中の強調表示の色や属性を残して重ねる(`Highlight::fill`)
+    Fill(Highlight),
+    /// 範囲の中の強調表示を置き換える(`Highlight::overlay`)
+    Overlay(Highlight),
+}
+
+impl Decoration {
+    /// 重ねる範囲と装飾を取得する
+    pub fn highlight(&self) -> &Highlight {
+        match self {
+            Self::Fill(highlight) | Self::Overlay(highlight) => highlight,
+        }
+    }
+
+    /// 強調表示に重ねる
+    /// # Arguments
+    /// * `highlights` - 強調表示(開始位置の昇順で、互いに重ならない)
+    /// # Returns
+    /// * `Vec<Highlight>` - 重ねた強調表示(開始位置の昇順で、互いに重ならない)
+    fn apply(&self, highlights: Vec<Highlight>) -> Vec<Highlight> {
+        match self {
+            Self::Fill(highlight) => {
+                Highlight::fill(highlights, highlight.range.clone(), highlight.style)
+            }
+            Self::Overlay(highlight) => Highlight::overlay(highlights, vec![highlight.clone()]),
+        }
+    }
+}
+
+/// 表示上の最小単位
+struct Cell {
+    /// 出力する文字列
+    text: String,
+    /// 表示幅(エスケープシーケンスなどは0)
+    width: usize,
+}
+
+impl Cell {
+    fn new(text: String, width: usize) -> Self {
+        Self { text, width }
+    }
+}
+
+/// 有効になっている装飾とハイパーリンク
+/// 行の途中で装飾を出力し直すために、それまでに出力したエスケープシーケンスから求める
+#[derive(Clone, Default, PartialEq)]
+struct ActiveEscapes {
+    /// 有効になっているSGR(文字色などを指定するエスケープシーケンス)
+    styles: Vec<String>,
+    /// 有効になっているハイパーリンク(OSC 8)
+    hyperlink: Option<String>,
+}
+
+impl ActiveEscapes {
+    /// すべての装飾をリセットするSGR
+    const RESET: &'static str = "\x1b[0m";
+    /// ハイパーリンクを閉じるOSC 8
+    const HYPERLINK_END: &'static str = "\x1b]8;;\x1b\\";
+
+    /// 出力したエスケープシーケンスに合わせて更新する
+    /// # Arguments
+    /// * `sequence` - エスケープシーケンス
+    fn update(&mut self, sequence: &str) {
+        self.update_styles(sequence);
+        self.update_hyperlink(sequence);
+    }
+
+    /// 有効になっているハイパーリンクを更新する
+    /// # Arguments
+    /// * `sequence` - エスケープシーケンス
+    /// # Notes
+    /// * OSC 8は`ESC ] 8 ; パラメータ ; URI ST`の形式で、URIが空の場合はリンクを閉じる
+    fn update_hyperlink(&mut self, sequence: &str) {
+        let Some(body) = sequence.strip_prefix("\x1b]8;") else {
+            return;
+        };
+        let body = body
+            .strip_suffix('\x07')
+            .or_else(|| body.strip_suffix("\x1b\\"))
+            .unwrap_or(body);
+
+        let uri = body.split_once(';').map_or("", |(_, uri)| uri);
+        self.hyperlink = if uri.is_empty() {
+            None
+        } else {
+            Some(sequence.to_string())
+        };
+    }
+
+    /// 有効になっているSGRを更新する
+    /// # Arguments
+    /// * `sequence` - エスケープシーケンス
+    fn update_styles(&mut self, sequence: &str) {
+        let Some(parameters) = sequence
+            .strip_prefix("\x1b[")
+            .and_then(|s| s.strip_suffix('m'))
+        else {
+            return;
+        };
+
+        // パラメータが空または0で始まる場合は、それまでの装飾がリセットされる
+        if parameters.is_empty() || parameters == "0" {
+            self.styles.clear();
+            return;
+        }
+        if parameters.starts_with("0;") {
+            self.styles.clear();
+        }
+
+        self.styles.push(sequence.to_string());
+    }
+
+    /// 有効になっている装飾とハイパーリンクを、別の状態に切り替える
+    /// # Arguments
+    /// * `next` - 切り替えた後の状態
+    /// * `cells` - 切り替えるエスケープシーケンスを追加するセル
+    /// # Notes
+    /// * 装飾が変わる場合は、一度リセットしてから出力し直す
+    fn switch_to(&mut self, next: &ActiveEscapes, cells: &mut Vec<Cell>) {
+        if self.styles != next.styles {
+            if !self.styles.is_empty() {
+                cells.push(Cell::new(Self::RESET.to_string(), 0));
+            }
+            for style in &next.styles {
+                cells.push(Cell::new(style.clone(), 0));
+            }
+        }
+        if self.hyperlink != next.hyperlink {
+            let hyperlink = next.hyperlink.as_deref().unwrap_or(Self::HYPERLINK_END);
+            cells.push(Cell::new(hyperlink.to_string(), 0));
+        }
+        *self = next.clone();
+    }
+}
+
+/// 表示上の最小単位を表示幅で折り返しながら組み立てる
+struct LineWrapper {
+    /// 折り返した行
+    result: Vec<Vec<Cell>>,
+    /// 組み立て中の行
+    current_line: Vec<Cell>,
+    /// 組み立て中の行の表示幅
+    current_width: usize,
+    /// 折り返す表示幅
+    width: usize,
+    /// 有効になっている装飾とハイパーリンク
+    /// 行番号などが装飾やリンクに含まれないように、行末で閉じて次の行の先頭で出力し直す
+    active: ActiveEscapes,
+}
+
+impl LineWrapper {
+    fn new(width: usize) -> Self {
+        Self {
+            result: Vec::new(),
+            current_line: Vec::new(),
+            current_width: 0,
+            width,
+            active: ActiveEscapes::default(),
+        }
+    }
+
+    /// セルを追加する
+    /// 追加すると折り返す表示幅を超える場合は、次の行に追加する
+    /// 表示幅を持たないセル(エスケープシーケンスなど)は、常に現在の行に追加する
+    fn push(&mut self, cell: Cell) {
+        if cell.width > 0 && self.current_width + cell.width > self.width {
+            self.current_width = 0;
+
+            // 行ごとに装飾が完結するように、行末でリセットして次の行の先頭で出力し直す
+            self.close_line();
+            self.result.push(std::mem::take(&mut self.current_line));
+            ActiveEscapes::default().switch_to(&self.active, &mut self.current_line);
+        }
+
+        if cell.width == 0 {
+            self.active.update(&cell.text);
+        }
+
+        self.current_width += cell.width;
+
+        self.current_line.push(cell);
+    }
+
+    /// 組み立て中の行の末尾で、有効になっている装飾とハイパーリンクを閉じる
+    fn close_line(&mut self) {
+        self.active
+            .clone()
+            .switch_to(&ActiveEscapes::default(), &mut self.current_line);
+    }
+
+    /// 組み立てを終了して、折り返した行を返す
+    fn finish(mut self) -> Vec<Vec<Cell>> {
+        // 次の行に装飾が残らないように、最後の行の末尾でもリセットする
+        self.close_line();
+        self.result.push(self.current_line);
+        self.result
+    }
+}
+
+/// 折り返した行を、同じ文字列を表示する`Contents`の間で共有するためのキャッシュ
+/// # Notes
+/// * 行の内容を表示する横幅や横方向のスクロール位置が変わった場合だけ、折り返し直す
+/// * 表示する文字列や、折り返した行に関わる設定を変更した場合は、`invalidate`で世代を進める
+#[derive(Clone, Default)]
+pub struct WrapCache(Rc<RefCell<WrapCacheState>>);
+
+impl WrapCache {
+    /// 世代を進めて、前の世代で折り返した行を使わないようにする
+    pub fn invalidate(&self) {
+        self.0.borrow_mut().generation += 1;
+    }
+}
+
+/// 折り返した行のキャッシュの状態
+#[derive(Default)]
+struct WrapCacheState {
+    /// 表示する文字列や、折り返した行に関わる設定を変更した回数
+    generation: u64,
+    /// 最後に折り返した行(まだ折り返していない場合はNone)
+    wrapped: Option<WrappedLines>,
+}
+
+/// 折り返した行と、折り返したときの条件
+struct WrappedLines {
+    /// 折り返したときの世代
+    generation: u64,
+    /// 行の内容を表示する横幅
+    width: usize,
+    /// 長い行を切り詰めたか
+    chop_long_lines: bool,
+    /// 長い行を切り詰める場合の横方向のスクロール位置(折り返す場合は0)
+    cursor_x: u16,
+    /// 横方向のスクロール位置を、スクロールできる範囲に収めた位置
+    clamped_cursor_x: u16,
+    /// 最も長い行の表示幅(長い行を切り詰める場合だけ求める)
+    /// 横にスクロールするたびに、すべての行の表示幅を求め直さないように保持する
+    max_line_width: Option<usize>,
+    /// 折り返した行
+    rows: Rc<Vec<SplitLine>>,
 }
 
 pub struct Contents {
     /// 元の文字列
-    pub original_contents: String,
+    pub original_contents: Rope,
     /// 表示する文字列
-    pub contents: Vec<SplitLine>,
+    pub contents: Rc<Vec<SplitLine>>,
+    /// 折り返した行のキャッシュ
+    pub cache: WrapCache,
     /// 表示する領域の横幅
     pub width: u16,
     /// 表示する領域の縦幅
@@ -36,29 +353,36 @@ pub struct Contents {
     /// カーソルの横位置
     pub cursor_x: u16,
     /// カーソルの縦位置
-    pub cursor_y: u16,
+    pub cursor_y: usize,
+    /// 表示に関する設定
+    pub options: DisplayOptions,
+    /// 折り返した後に重ねる装飾(重ねる順)
+    pub decorations: Vec<Decoration>,
 }
 
 impl Contents {
     /// Contentsを作成する
     pub fn new(
-        original_contents: String,
+        original_contents: Rope,
         width: u16,
         height: u16,
         x_start: u16,
         y_start: u16,
         cursor_x: u16,
-        cursor_y: u16,
+        cursor_y: usize,
     ) -> Self {
         Self {
             original_contents,
-            contents: vec![],
+            contents: Rc::default(),
+            cache: WrapCache::default(),
             width,
             height,
             x_start,
             y_start,
             cursor_x,
             cursor_y,
+            options: DisplayOptions::default(),
+            decorations: Vec::new(),
         }
     }
 
@@ -79,45 +403,425 @@ impl Contents {
     ///
     /// # Notes
     /// * `contents`の文字列の長さが`width`よりも長い場合は、`width`の長さに切り詰める(これを繰り返す)
+    /// * 文字列の変換は`to_cells`を参照
+    /// * 右から左に書く文字を含む場合は、折り返した行ごとに表示順に並べ替える
+    /// * 文字色などの装飾は、折り返した行の末尾でリセットし、次の行の先頭で出力し直す
     fn split_string_by_width(&self, s: &str, width: u16) -> Vec<String> {
-        let mut result = Vec::new();
-        let mut current_width = 0;
-        let mut current_line = String::new();
+        let mut wrapper = LineWrapper::new(width as usize);
+
+        for cell in self.to_cells(s) {
+            wrapper.push(cell);
+        }
+
+        let rows = Self::reorder_bidi(wrapper.finish());
+
+        rows.iter()
+            .map(|row| row.iter().map(|cell| cell.text.as_str()).collect())
+            .collect()
+    }
+
+    /// 右から左に書く文字(アラビア文字やヘブライ文字など)を含む場合に、折り返した行ごとに表示順に並べ替える
+    /// # Arguments
+    /// * `rows` - 折り返した行(論理順)
+    /// # Returns
+    /// * `Vec<Vec<Cell>>` - 折り返した行(表示順)
+    /// # Notes
+    /// * 文字の方向は折り返す前の行全体で判定し、並べ替えは折り返した行ごとに行う
+    /// * 装飾のエスケープシーケンスは並べ替えずに、文字ごとに有効な装飾を求めてから、並べ替えた後に出力し直す
+    /// * 表示幅を持たない文字(結合文字など)は、直前の文字と一緒に移動する
+    fn reorder_bidi(rows: Vec<Vec<Cell>>) -> Vec<Vec<Cell>> {
+        // 文字の方向を判定するための文字列を組み立てる
+        // 装飾した記号などは方向に影響しないように、中立的な文字(U+FFFC)に置き換える
+        let mut text = String::new();
+        let mut offsets = Vec::new();
+        for row in &rows {
+            let mut row_offsets = Vec::new();
+            for cell in row {
+                row_offsets.push(text.len());
+                if cell.width == 0 {
+                    continue;
+                }
+                if cell.text.contains('\x1b') {
+                    text.push('\u{FFFC}');
+                } else {
+                    text.push_str(&cell.text);
+                }
+            }
+            row_offsets.push(text.len());
+            offsets.push(row_offsets);
+        }
+
+        let bidi_info = ParagraphBidiInfo::new(&text, None);
+        if !bidi_info.has_rtl() {
+            return rows;
+        }
+
+        rows.into_iter()
+            .zip(offsets)
+            .map(|(row, row_offsets)| {
+                let range = row_offsets[0]..row_offsets[row_offsets.len() - 1];
+                if range.is_empty() {
+                    return row;
+                }
+
+                // 表示幅を持つセルと、その直後の表示幅を持たない文字をまとめ、そのときに有効な装飾を覚えておく
+                // 行頭の表示幅を持たない文字は、並べ替えずに行頭に残す
+                let mut active = ActiveEscapes::default();
+                let mut leading = (ActiveEscapes::default(), Vec::new());
+                let mut groups: Vec<(usize, ActiveEscapes, Vec<Cell>)> = Vec::new();
+                for (cell, offset) in row.into_iter().zip(row_offsets) {
+                    if cell.width == 0
+                        && (Self::is_sgr(&cell.text) || Self::is_hyperlink(&cell.text))
+                    {
+                        active.update(&cell.text);
+                        continue;
+                    }
+                    match groups.last_mut() {
+                        Some((_, _, group)) if cell.width == 0 => group.push(cell),
+                        None if cell.width == 0 => {
+                            if leading.1.is_empty() {
+                                leading.0 = active.clone();
+                            }
+                            leading.1.push(cell);
+                        }
+                        _ => groups.push((offset, active.clone(), vec![cell])),
+                    }
+                }
+
+                let (levels, runs) = bidi_info.visual_runs(range);
+                let mut ordered = vec![leading];
+                for run in runs {
+                    let mut run_groups = Vec::new();
+                    for (offset, active, group) in groups.iter_mut() {
+                        if run.contains(offset) {
+                            run_groups.push((std::mem::take(active), std::mem::take(group)));
+                        }
+                    }
+                    if levels[run.start].is_rtl() {
+                        run_groups.reverse();
+                    }
+                    ordered.extend(run_groups);
+                }
+
+                // 装飾が変わるところで、装飾を出力し直す
+                let mut reordered = Vec::new();
+                let mut current = ActiveEscapes::default();
+                for (active, group) in ordered {
+                    if group.is_empty() {
+                        continue;
+                    }
+                    current.switch_to(&active, &mut reordered);
+                    reordered.extend(group);
+                }
+                current.switch_to(&ActiveEscapes::default(), &mut reordered);
+
+                reordered
+            })
+            .collect()
+    }
+
+    /// 文字列を表示幅で切り詰める
+    /// # Arguments
+    /// * `s` - 切り詰める文字列
+    /// * `offset` - 表示を開始する位置(行頭からの表示幅)
+    /// * `width` - 表示する幅
+    /// # Returns
+    /// * `String` - `offset`から`width`の幅に収まる文字列
+    /// # Examples
+    /// ```
+    /// let result = chop_string_by_width("Hello, world!", 0, 5);
+    /// assert_eq!(result, "Hell>");
+    /// ```
+    /// # Notes
+    /// * 右側に続きがある場合は、最後の1文字分に`>`を表示する
+    /// * エスケープシーケンスは表示範囲外にあっても出力するので、文字色などは維持される
+    fn chop_string_by_width(&self, s: &str, offset: usize, width: usize) -> String {
+        let cells = self.to_cells(s);
+        let total_width: usize = cells.iter().map(|cell| cell.width).sum();
 
-        // エスケープシーケンス中は、文字列の長さを計算しない
-        let mut is_ansi_escape_sequence = false;
+        // 右側に続きがある場合は、`>`を表示する分の幅を確保する
+        let has_more = total_width > offset + width;
+        let end = if has_more {
+            offset + width.saturating_sub(1)
+        } else {
+            offset + width
+        };
 
-        for (_i, c) in s.char_indices() {
-            if self.is_escape(c) {
-                is_ansi_escape_sequence = true;
-                current_line.push(c);
+        let mut result = String::new();
+        let mut column = 0;
+        for cell in cells {
+            let start = column;
+            column += cell.width;
+
+            if cell.width == 0 {
+                result.push_str(&cell.text);
                 continue;
             }
 
-            if is_ansi_escape_sequence {
-                // エスケープシーケンスの終了を判定する
-                if c == 'm' {
-                    is_ansi_escape_sequence = false;
+            if start >= offset && column <= end {
+                result.push_str(&cell.text);
+            } else if start < end && column > offset {
+                // 全角文字などが表示範囲の境界をまたぐ場合は、表示範囲内の部分を空白で埋める
+                let visible = column.min(end) - start.max(offset);
+                result.push_str(&" ".repeat(visible));
+            }
+        }
+
+        if has_more {
+            result.push('>');
+        }
+
+        result
+    }
+
+    /// 文字列を表示上の最小単位に分割する
+    /// # Arguments
+    /// * `s` - 分割する文字列
+    /// # Returns
+    /// * `Vec<Cell>` - 表示上の最小単位のベクタ
+    /// # Notes
+    /// * 変換のしかたは`visit_cells`を参照
+    fn to_cells(&self, s: &str) -> Vec<Cell> {
+        let mut cells = Vec::new();
+        Self::visit_cells(&self.options, s, |_, cell| cells.push(cell));
+        cells
+    }
+
+    /// 行の中の書記素クラスタごとに、バイト単位の範囲と表示する桁の範囲を求める
+    /// # Arguments
+    /// * `options` - 表示に関する設定
+    /// * `line` - 改行文字を含まない行
+    /// # Returns
+    /// * `Vec<(Range<usize>, Range<usize>)>` - 書記素クラスタごとの、行の先頭からのバイト単位の範囲と桁の範囲
+    /// # Notes
+    /// * 表示するときと同じように桁を数える(制御文字の表記やタブ文字の展開などを含む)
+    /// * エスケープシーケンスは、1つの範囲として扱う
+    pub fn grapheme_columns(
+        options: &DisplayOptions,
+        line: &str,
+    ) -> Vec<(Range<usize>, Range<usize>)> {
+        let mut columns: Vec<(Range<usize>, Range<usize>)> = Vec::new();
+        let mut column = 0;
+        Self::visit_cells(options, line, |bytes, cell| {
+            match columns.last_mut() {
+                Some((last, last_columns)) if *last == bytes => last_columns.end += cell.width,
+                _ if bytes.is_empty() => {}
+                _ => {
+                    // 取り除いたエスケープシーケンスなど、セルにならない部分は表示幅0とする
+                    let previous_end = columns.last().map_or(0, |(last, _)| last.end);
+                    if previous_end < bytes.start {
+                        columns.push((previous_end..bytes.start, column..column));
+                    }
+                    columns.push((bytes, column..column + cell.width));
+                }
+            }
+            column += cell.width;
+        });
+        let previous_end = columns.last().map_or(0, |(last, _)| last.end);
+        if previous_end < line.len() {
+            columns.push((previous_end..line.len(), column..column));
+        }
+
+        columns
+    }
+
+    /// 文字列を表示上の最小単位に分割して、1つずつ渡す
+    /// # Arguments
+    /// * `options` - 表示に関する設定
+    /// * `s` - 分割する文字列
+    /// * `visit` - 元の文字列でのバイト単位の範囲と、その範囲から作った表示上の最小単位を受け取る
+    /// # Notes
+    /// * タブ文字は次のタブストップまでの空白に展開する
+    /// * 制御文字は`^M`や`<U+009B>`のような表記に変換する(`raw_control_chars`が有効な場合はそのまま出力する)
+    /// * `show_whitespace`が有効な場合は、空白を`·`、タブを`→`、行末を`¶`で表示する(行末の範囲は空)
+    /// * 書記素クラスタ単位で分割するので、結合文字などが別の行に分かれることはない
+    fn visit_cells(options: &DisplayOptions, s: &str, mut visit: impl FnMut(Range<usize>, Cell)) {
+        let paint = |style: &Style, s: &str| style.paint(s, options.color_depth);
+
+        // 行頭からの表示幅
+        // タブストップの計算に使う
+        let mut column = 0;
+
+        let mut rest = s;
+        while !rest.is_empty() {
+            let start = s.len() - rest.len();
+            if let Some(len) = Self::escape_sequence_len(rest) {
+                let sequence = &rest[..len];
+                if options.strip_ansi {
+                    // エスケープシーケンスをすべて取り除く
+                    rest = &rest[len..];
+                    continue;
+                }
+                if Self::is_sgr(sequence) || Self::is_hyperlink(sequence) {
+                    // 文字色などを指定するエスケープシーケンスとハイパーリンクはそのまま出力し、表示幅を計算しない
+                    visit(start..start + len, Cell::new(sequence.to_string(), 0));
+                    rest = &rest[len..];
+                    continue;
+                }
+                // それ以外のエスケープシーケンスは表示を崩すことがあるので、ESCを制御文字として表示する
+            }
+
+            // 結合文字や異体字セレクタなどが分割されないように、書記素クラスタ単位で処理する
+            // エスケープシーケンスの直後の結合文字は、基底文字を持たない表示幅0の書記素クラスタになる
+            let grapheme = rest.graphemes(true).next().unwrap_or(rest);
+            rest = &rest[grapheme.len()..];
+            let bytes = start..start + grapheme.len();
+
+            if grapheme == "\t" {
+                // 次のタブストップまでを空白で埋める
+                let tab_width = options.tab_width as usize;
+                let spaces = tab_width - column % tab_width;
+                for i in 0..spaces {
+                    if options.show_whitespace && i == 0 {
+                        visit(
+                            bytes.clone(),
+                            Cell::new(paint(&options.theme.special_char, "→"), 1),
+                        );
+                    } else {
+                        visit(bytes.clone(), Cell::new(" ".to_string(), 1));
+                    }
                 }
-                current_line.push(c);
+                column += spaces;
                 continue;
             }
 
-            if current_width + c.width().unwrap() > width as usize {
-                current_width = 0;
+            if grapheme == " " && options.show_whitespace {
+                visit(bytes, Cell::new(paint(&options.theme.special_char, "·"), 1));
+                column += 1;
+                continue;
+            }
 
-                result.push(current_line.clone());
-                current_line.clear();
+            // 制御文字は単独で書記素クラスタになる(CR LFのみ例外)ので、1文字ずつ処理する
+            if grapheme.chars().all(|c| c.is_control()) {
+                for (offset, c) in grapheme.char_indices() {
+                    let bytes = start + offset..start + offset + c.len_utf8();
+                    if options.raw_control_chars {
+                        // 制御文字は端末に解釈させるので、表示幅は0とする
+                        visit(bytes, Cell::new(c.to_string(), 0));
+                    } else {
+                        let notation = Self::control_char_notation(c);
+                        let notation_width = notation.len();
+                        // 通常の文字と区別しやすいように、薄い色で表示する
+                        visit(
+                            bytes,
+                            Cell::new(
+                                paint(&options.theme.special_char, &notation),
+                                notation_width,
+                            ),
+                        );
+                        column += notation_width;
+                    }
+                }
+                continue;
             }
 
-            current_line.push(c);
+            // 行頭などにある基底文字を持たない結合文字は、表示幅が0の書記素クラスタになる
+            // 表示幅が0のセルは折り返さずに現在の行に追加されるので、直前の文字と別の行に分かれることはない
+            let width = Self::grapheme_width(options, grapheme);
+            visit(bytes, Cell::new(grapheme.to_string(), width));
+            column += width;
+        }
+
+        if options.show_whitespace {
+            // 行末を表す
+            visit(
+                s.len()..s.len(),
+                Cell::new(paint(&options.theme.special_char, "¶"), 1),
+            );
+        }
+    }
+
+    /// 書記素クラスタの表示幅を計算する
+    /// # Arguments
+    /// * `options` - 表示に関する設定
+    /// * `grapheme` - 書記素クラスタ
+    /// # Returns
+    /// * `usize` - 表示幅
+    /// # Notes
+    /// * 東アジアの文字幅が曖昧な文字は、表示に関する設定に合わせて全角か半角として扱う
+    fn grapheme_width(options: &DisplayOptions, grapheme: &str) -> usize {
+        cell::grapheme_width(grapheme, options.ambiguous_wide)
+    }
+
+    /// 文字列を装飾する
+    /// # Arguments
+    /// * `style` - 装飾
+    /// * `s` - 装飾する文字列
+    /// # Returns
+    /// * `String` - 端末で表示できる色数に合わせて装飾した文字列
+    fn paint(&self, style: &Style, s: &str) -> String {
+        style.paint(s, self.options.color_depth)
+    }
+
+    /// 制御文字を表示用の表記に変換する
+    /// # Arguments
+    /// * `c` - 変換する制御文字
+    /// # Returns
+    /// * `String` - C0制御文字とDELはキャレット記法(`^M`など)、それ以外は`<U+009B>`のような表記
+    /// # Examples
+    /// ```
+    /// assert_eq!(Contents::control_char_notation('\r'), "^M");
+    /// assert_eq!(Contents::control_char_notation('\u{9b}'), "<U+009B>");
+    /// ```
+    fn control_char_notation(c: char) -> String {
+        match c {
+            '\x00'..='\x1f' | '\x7f' => format!("^{}", ((c as u8) ^ 0x40) as char),
+            _ => format!("<U+{:04X}>", c as u32),
+        }
+    }
+
+    /// 文字列の先頭にあるエスケープシーケンスの長さを取得する
+    /// # Arguments
+    /// * `s` - 文字列
+    /// # Returns
+    /// * `Option<usize>` - 先頭がエスケープシーケンスの場合はその長さ(バイト数)、それ以外はNone
+    /// # Examples
+    /// ```
+    /// assert_eq!(escape_sequence_len("\x1b[31mabc"), Some(5));
+    /// assert_eq!(escape_sequence_len("abc"), None);
+    /// ```
+    /// # Notes
+    /// * CSI(`ESC [`)、OSC(`ESC ]`、BELまたは`ESC \`で終わる)、文字集合の指定(`ESC ( B`など)、2文字のエスケープシーケンスに対応する
+    /// * 終わりのないエスケープシーケンスはNoneとする
+    fn escape_sequence_len(s: &str) -> Option<usize> {
+        let mut chars = s.chars();
+        if !chars.next().is_some_and(Self::is_escape) {
+            return None;
+        }
 
-            current_width += c.width().unwrap();
+        let bytes = s.as_bytes();
+        match bytes.get(1)? {
+            b'[' => {
+                // パラメータと中間バイトの後に、終端バイトが続く
+                let end = bytes[2..].iter().position(|b| !(0x20..=0x3f).contains(b))? + 2;
+                (0x40..=0x7e).contains(&bytes[end]).then_some(end + 1)
+            }
+            b']' => {
+                let bel = s.find('\x07').map(|i| i + 1);
+                let st = s.find("\x1b\\").map(|i| i + 2);
+                match (bel, st) {
+                    (Some(bel), Some(st)) => Some(bel.min(st)),
+                    (bel, st) => bel.or(st),
+                }
+            }
+            0x20..=0x2f => {
+                // 中間バイトの後に、終端バイトが続く(文字集合の指定など)
+                let end = bytes[1..].iter().position(|b| !(0x20..=0x2f).contains(b))? + 1;
+                (0x30..=0x7e).contains(&bytes[end]).then_some(end + 1)
+            }
+            0x30..=0x7e => Some(2),
+            _ => None,
         }
+    }
 
-        result.push(current_line);
+    /// 文字色などを指定するエスケープシーケンス(SGR)かどうかを判定する
+    fn is_sgr(sequence: &str) -> bool {
+        sequence.starts_with("\x1b[") && sequence.ends_with('m')
+    }
 
-        result
+    /// ハイパーリンクを指定するエスケープシーケンス(OSC 8)かどうかを判定する
+    fn is_hyperlink(sequence: &str) -> bool {
+        sequence.starts_with("\x1b]8;")
     }
 
     /// エスケープシーケンスかどうかを判定する
@@ -132,82 +836,220 @@ impl Contents {
     /// assert_eq!(result, true);
     /// ```
     /// # Panics
-    fn is_escape(&self, c: char) -> bool {
+    fn is_escape(c: char) -> bool {
         c == '\x1b'
     }
 
     /// エディタ領域に表示する文字列を出力する
     pub fn print(&mut self) -> std::io::Result<()> {
+        self.print_rows(0..self.height)
+    }
+
+    /// エディタ領域のうち、指定した行だけに表示する文字列を出力する
+    /// # Arguments
+    /// * `rows` - 出力する行の範囲(エディタ領域の一番上を0とする)
+    /// # Notes
+    /// * 重ねて表示した一覧などを閉じたときに、隠れていた行だけを表示し直すために使う
+    pub fn print_rows(&mut self, rows: std::ops::Range<u16>) -> std::io::Result<()> {
         // エディタ領域に表示する文字列を更新する
-        // 本当は実行時間の短縮のために、リサイズした時と初めて表示する時にのみ更新したい
+        // 横幅や表示に関する設定が変わっていない場合は、前に折り返した結果を使う
         self.update_contents();
-
-        // RAWモードで出力するので、一行一行出力する
-        stdout().queue(MoveTo(self.x_start, self.y_start))?;
-        stdout().queue(Clear(ClearType::All))?;
-
-        // エディタ領域に表示する行数よりも端末の縦幅が小さい場合は、cursor_yを0にして全ての行を表示する
-        if self.height > self.contents.len() as u16 {
-            self.cursor_y = 0;
-        }
-
-        // cursor_yが表示する行数よりも大きい場合は、cursor_yを表示する行数にする
-        let max_cursor_y = if self.contents.len() as u16 > self.height {
-            self.contents.len() as u16 - self.height
-        } else {
-            0
-        };
-
-        // カーソルの縦位置が表示する行数よりも大きい場合は、カーソルの縦位置を表示する行数にする
-        if self.cursor_y > max_cursor_y {
-            self.cursor_y = max_cursor_y;
-        }
+        self.clamp_cursor_y();
 
         // 出力する
         let display_area = self.get_display_area();
         let mut current_y = 0;
-        let line_number_width = self.contents[self.contents.len() - 1]
-            .line_number
+        // 何も出力しないコマンドの出力など、行がない場合は1行目として幅を求める
+        let last_line_number = self
+            .contents
+            .last()
+            .map_or(1, |split_line| split_line.line_number);
+        let line_number_width = (last_line_number + self.options.line_number_offset)
             .to_string()
             .len();
-        for split_line in &self.contents {
-            // 表示する行が表示領域の範囲外の場合は、次の行に移動する
-            if current_y < display_area.1 || current_y >= display_area.3 {
+        let annotation_width = self.annotation_width();
+        let sticky_row = self.sticky_row();
+        let line_width = self.text_width();
+        // 装飾を重ねて折り返し直した行(1行が分割されている場合に、分割した行ごとに折り返し直さないように保持する)
+        let mut decorated: Option<(usize, Option<Vec<SplitLine>>)> = None;
+        for split_line in self.contents.iter() {
+            // 表示する行が表示領域の範囲外か、出力しない行の場合は、次の行に移動する
+            if current_y < display_area.1
+                || current_y >= display_area.3
+                || !rows.contains(&((current_y - display_area.1) as u16))
+            {
                 current_y += 1;
                 continue;
             }
 
-            stdout().queue(MoveToColumn(self.x_start))?;
+            // 見出しを固定して表示する場合は、一番上の行の代わりに見出しを表示する
+            let split_line = match sticky_row {
+                Some(row) if current_y == display_area.1 => &self.contents[row],
+                _ => split_line,
+            };
+
+            // RAWモードで出力するので、一行一行出力する
+            // 前に出力した内容から変わった行だけを出力するように、1行分をまとめてから出力する
+            // 表示する領域の横幅だけを消してから出力するので、分割した隣の領域は消えない
+            let y = self.y_start + (current_y - display_area.1) as u16;
+            let mut row = String::new();
 
             // 1行が分割されている場合があるが、最初だけ行番号を表示する
             if split_line.line_index == 0 {
+                // 注釈は表示幅を揃えて、一番左に表示する
+                if annotation_width > 0 {
+                    let annotation = self
+                        .options
+                        .annotations
+                        .get(split_line.line_number - 1)
+                        .map_or("", |annotation| annotation.as_str());
+                    let annotation = self.chop_string_by_width(annotation, 0, annotation_width - 1);
+                    let padding = (annotation_width - 1).saturating_sub(annotation.width());
+                    row.push_str(&self.paint(
+                        &self.options.theme.annotation,
+                        &format!("{}{} ", annotation, " ".repeat(padding)),
+                    ));
+                }
+
+                // 変更のある行には、行番号の左に変更の種類を表示する
+                if self.diff_marker_width() > 0 {
+                    let marker = self
+                        .options
+                        .diff_markers
+                        .get(split_line.line_number - 1)
+                        .copied()
+                        .flatten();
+                    row.push_str(&match marker {
+                        Some(DiffMarker::Added) => self.paint(&self.options.theme.diff_added, "+"),
+                        Some(DiffMarker::Modified) => {
+                            self.paint(&self.options.theme.diff_modified, "~")
+                        }
+                        Some(DiffMarker::Removed) => {
+                            self.paint(&self.options.theme.diff_removed, "-")
+                        }
+                        None => " ".to_string(),
+                    });
+                }
+
+                // 印を付けた行には、行番号の左に印の名前を表示する
+                if self.mark_marker_width() > 0 {
+                    let marker = self
+                        .options
+                        .mark_markers
+                        .get(split_line.line_number - 1)
+                        .copied()
+                        .flatten();
+                    row.push_str(&match marker {
+                        Some(mark) => self.paint(&self.options.theme.mark, &mark.to_string()),
+                        None => " ".to_string(),
+                    });
+                }
+
                 // 行番号を表示する
-                // 行番号の色は区別しやすいように、薄い色にする
-                stdout().queue(Print(Attribute::Dim)).unwrap();
-                stdout().queue(Print(format!(
-                    "{:>line_number_width$} ",
-                    split_line.line_number
-                )))?;
-
-                // 行番号の色を薄くするために薄暗い色を設定したので、リセットする
-                stdout().queue(Print(Attribute::Reset)).unwrap();
+                // 行番号は区別しやすいように、本文とは別の装飾にする
+                row.push_str(&self.paint(
+                    &self.options.theme.line_number,
+                    &format!(
+                        "{:>line_number_width$} ",
+                        split_line.line_number + self.options.line_number_offset
+                    ),
+                ));
+
+                // 折りたためる行には、折りたたみの状態を表示する
+                if self.fold_marker_width() > 0 {
+                    let marker = match self.options.fold_markers.get(split_line.line_number - 1) {
+                        Some(Some(true)) => "▸ ",
+                        Some(Some(false)) => "▾ ",
+                        _ => "  ",
+                    };
+                    row.push_str(&self.paint(&self.options.theme.line_number, marker));
+                }
             } else {
-                // 行番号の分の空白を表示する
-                stdout().queue(Print(" ".repeat(line_number_width + 1)))?;
+                // 注釈と変更の種類と印と行番号と折りたたみの記号の分の空白を表示する
+                row.push_str(&" ".repeat(
+                    annotation_width
+                        + self.diff_marker_width()
+                        + self.mark_marker_width()
+                        + line_number_width
+                        + 1
+                        + self.fold_marker_width(),
+                ));
             }
 
             // 行の内容を表示する
-            stdout().queue(Print(&split_line.contents))?;
-
-            // 次の行を表示することに備えて改行する
-            stdout().queue(Print("\n"))?;
+            // 操作する位置がある行や選択などの装飾を重ねる行は、その行だけを折り返し直して表示する
+            if decorated
+                .as_ref()
+                .is_none_or(|(line_number, _)| *line_number != split_line.line_number)
+            {
+                decorated = Some((
+                    split_line.line_number,
+                    self.decorate_line(split_line.line_number, line_width),
+                ));
+            }
+            let contents = match &decorated {
+                Some((_, Some(rows))) => rows
+                    .get(split_line.line_index)
+                    .map_or(split_line.contents.as_str(), |row| row.contents.as_str()),
+                _ => split_line.contents.as_str(),
+            };
+            row.push_str(contents);
+            screen::print_row(self.x_start, y, self.width, &row)?;
 
             current_y += 1;
         }
 
+        // 表示する行が表示する領域の高さよりも少ない場合は、残りの行を消す
+        let printed = current_y
+            .saturating_sub(display_area.1)
+            .min(self.height as usize) as u16;
+        for y in (printed..self.height).filter(|y| rows.contains(y)) {
+            screen::print_row(self.x_start, self.y_start + y, self.width, "")?;
+        }
+
         Ok(())
     }
 
+    /// 縦方向のスクロール位置を、表示できる範囲に収める
+    fn clamp_cursor_y(&mut self) {
+        // エディタ領域に表示する行数よりも端末の縦幅が小さい場合は、cursor_yを0にして全ての行を表示する
+        if self.height as usize > self.contents.len() {
+            self.cursor_y = 0;
+        }
+
+        // cursor_yが表示する行数よりも大きい場合は、cursor_yを表示する行数にする
+        let max_cursor_y = if self.contents.len() > self.height as usize {
+            self.contents.len() - self.height as usize
+        } else {
+            0
+        };
+
+        // カーソルの縦位置が表示する行数よりも大きい場合は、カーソルの縦位置を表示する行数にする
+        if self.cursor_y > max_cursor_y {
+            self.cursor_y = max_cursor_y;
+        }
+    }
+
+    /// 前に表示したときから少しだけスクロールした場合に、端末に表示している行をずらす
+    /// # Arguments
+    /// * `previous_cursor_y` - 前に表示したときの縦方向のスクロール位置
+    /// # Notes
+    /// * 端末のスクロール領域は横幅すべてがずれるので、端末の横幅いっぱいに表示している場合だけ使う
+    /// * ずらした後に`print`を呼び出すと、新しく見えるようになった行だけを出力する
+    pub fn shift_rows(&mut self, previous_cursor_y: usize) -> std::io::Result<()> {
+        self.update_contents();
+        self.clamp_cursor_y();
+        screen::scroll_rows(
+            self.y_start,
+            self.height,
+            if self.cursor_y >= previous_cursor_y {
+                (self.cursor_y - previous_cursor_y).min(i32::MAX as usize) as i32
+            } else {
+                -((previous_cursor_y - self.cursor_y).min(i32::MAX as usize) as i32)
+            },
+        )
+    }
+
     /// エディタ領域に表示する文字列を取得する
     /// # Arguments
     /// * `contents` - ファイルの内容
@@ -232,34 +1074,666 @@ impl Contents {
     /// * `contents`の文字列の長さが`term_width`よりも短い場合は、空白を追加する
     /// * `contents`の行数が`term_height`よりも少ない場合は、空白を追加する
     fn update_contents(&mut self) {
-        // 行番号の表示に必要な桁数を計算する
-        let line_number_digits = self.original_contents.lines().count().to_string().len();
+        // 行の内容を表示する横幅と横方向のスクロール位置が前に折り返したときと同じで、
+        // その後に表示する文字列や設定を変更していない場合は、その結果を使う
+        // 操作する位置がある行や選択などの装飾は表示するときに重ねるので、条件に含めない
+        let width = self.text_width();
+        let chop_long_lines = self.options.chop_long_lines;
+        let cursor_x = if chop_long_lines { self.cursor_x } else { 0 };
+        let state = self.cache.0.borrow();
+        let generation = state.generation;
+        let current = state
+            .wrapped
+            .as_ref()
+            .filter(|wrapped| wrapped.generation == generation);
+        if let Some(wrapped) = current.filter(|wrapped| {
+            wrapped.width == width
+                && wrapped.chop_long_lines == chop_long_lines
+                && wrapped.cursor_x == cursor_x
+        }) {
+            self.contents = Rc::clone(&wrapped.rows);
+            if chop_long_lines {
+                self.cursor_x = wrapped.clamped_cursor_x;
+            }
+            return;
+        }
+        let cached_max_line_width = current.and_then(|wrapped| wrapped.max_line_width);
+        drop(state);
 
-        // 行番号とコンテンツの間の空白の数
-        let line_number_space = 1;
+        // 横方向のスクロール位置や横幅だけが変わった場合は、前に求めた最も長い行の表示幅を使う
+        let max_line_width = if chop_long_lines {
+            let max_line_width = cached_max_line_width.unwrap_or_else(|| self.max_line_width());
 
-        // 1行の横幅を計算する
-        // 1行の横幅 = エディタ領域の横幅 - 行番号の桁数 - 行番号の後の空白(1文字)
-        let line_width = self.width as usize - line_number_digits - line_number_space;
+            // 最も長い行の右端が表示する領域の右端になるまでしか、横にスクロールできないようにする
+            let max_cursor_x = max_line_width.saturating_sub(width);
+            if self.cursor_x as usize > max_cursor_x {
+                self.cursor_x = max_cursor_x.min(u16::MAX as usize) as u16;
+            }
+            Some(max_line_width)
+        } else {
+            None
+        };
 
-        // contentsの各行の文字列の長さがline_widthよりも長い場合は、長い部分を次の行に移動する
-        // 次の行に移動した部分の文字列の長さがline_widthよりも長い場合は、さらに次の行に移動する(これを繰り返す)
+        let rows = Rc::new(self.wrap_lines());
+        self.contents = Rc::clone(&rows);
+        self.cache.0.borrow_mut().wrapped = Some(WrappedLines {
+            generation,
+            width,
+            chop_long_lines,
+            cursor_x,
+            clamped_cursor_x: self.cursor_x,
+            max_line_width,
+            rows,
+        });
+    }
 
-        let mut line_number = 1;
-        for line in self.original_contents.lines() {
-            // 行を表示幅に分割したベクタを取得する
-            let split_line = self.split_string_by_width(line, line_width as u16);
+    /// 元の文字列で最も長い行の表示幅を取得する
+    fn max_line_width(&self) -> usize {
+        lines(&self.original_contents)
+            .map(|(_, line)| self.to_cells(&line).iter().map(|cell| cell.width).sum())
+            .max()
+            .unwrap_or(0)
+    }
 
-            for (i, line) in split_line.iter().enumerate() {
-                let split_line = SplitLine {
-                    line_number,
-                    line_index: i as u16,
-                    contents: line.to_string(),
+    /// 元の文字列の各行を、表示する領域の横幅で折り返す
+    /// # Returns
+    /// * `Vec<SplitLine>` - 折り返した行
+    /// # Notes
+    /// * 長い行を切り詰める場合は、横方向のスクロール位置をスクロールできる範囲に収めてから呼び出す
+    fn wrap_lines(&self) -> Vec<SplitLine> {
+        let mut rows = Vec::new();
+        let line_width = self.text_width();
+
+        // 直前の行が空行だったか
+        let mut is_previous_blank = false;
+
+        for (line_number, (line_start, line)) in (1..).zip(lines(&self.original_contents)) {
+            // 連続する空行は最初の1行だけ表示する
+            // 行番号は元の行番号のままにするので、表示されない行の分だけ行番号が飛ぶ
+            let is_blank = line.is_empty();
+            if self.options.squeeze_blank && is_blank && is_previous_blank {
+                continue;
+            }
+            is_previous_blank = is_blank;
+
+            rows.extend(self.wrap_line(
+                line_number,
+                line_start,
+                &line,
+                &self.options.highlights,
+                line_width,
+            ));
+        }
+
+        rows
+    }
+
+    /// 元の文字列の1行を、表示する領域の横幅で折り返す
+    /// # Arguments
+    /// * `line_number` - 行番号(1から始まる)
+    /// * `line_start` - 元の文字列での行の開始位置(バイト単位)
+    /// * `line` - 改行を除いた行
+    /// * `highlights` - 元の文字列の一部の装飾(開始位置の昇順で、重ならないこと)
+    /// * `line_width` - 行の内容を表示する横幅
+    /// # Returns
+    /// * `Vec<SplitLine>` - 折り返した行
+    fn wrap_line(
+        &self,
+        line_number: usize,
+        line_start: usize,
+        line: &str,
+        highlights: &[Highlight],
+        line_width: usize,
+    ) -> Vec<SplitLine> {
+        let mut rows = Vec::new();
+
+        // 構文の強調表示などの装飾を適用する
+        // 装飾の位置は元の文字列での位置なので、正規化する前に適用する
+        let line = self.apply_highlights(line_start, line, highlights);
+
+        // manページなどのバックスペースによる重ね打ちを、太字や下線に変換する
+        let line = if self.options.raw_control_chars {
+            line
+        } else {
+            self.apply_overstrike(&line)
+        };
+
+        // macOSで作成したファイルなどは濁点が分解されていることがあるので、NFCに正規化して表示する
+        let line: String = line.nfc().collect();
+
+        // 改ページ(\f)で区切った部分ごとに表示する
+        // 改ページは表示する領域の横幅いっぱいの罫線で表す
+        let segments: Vec<&str> = line.split('\x0c').collect();
+        let mut line_index = 0;
+        for (i, segment) in segments.iter().enumerate() {
+            if i > 0 {
+                rows.push(SplitLine {
+                    line_number,
+                    line_index,
+                    contents: self.paint(&self.options.theme.page_break, &"─".repeat(line_width)),
+                    is_page_break: true,
+                });
+                line_index += 1;
+            }
+
+            // 改ページの前後の空文字列は表示しない
+            if segment.is_empty() && segments.len() > 1 {
+                continue;
+            }
+
+            // contentsの各行の文字列の長さがline_widthよりも長い場合は、長い部分を次の行に移動する
+            // 次の行に移動した部分の文字列の長さがline_widthよりも長い場合は、さらに次の行に移動する(これを繰り返す)
+            let split_line = if self.options.chop_long_lines {
+                // 折り返さずに、表示する領域だけを切り出す
+                vec![self.chop_string_by_width(segment, self.cursor_x as usize, line_width)]
+            } else {
+                // 行を表示幅に分割したベクタを取得する
+                self.split_string_by_width(segment, line_width as u16)
+            };
+
+            for line in split_line {
+                rows.push(SplitLine {
+                    line_number,
+                    line_index,
+                    contents: line,
+                    is_page_break: false,
+                });
+                line_index += 1;
+            }
+        }
+
+        rows
+    }
+
+    /// 折り返した後に重ねる装飾がある行を、装飾を重ねて折り返し直す
+    /// # Arguments
+    /// * `line_number` - 行番号(1から始まる)
+    /// * `line_width` - 行の内容を表示する横幅
+    /// # Returns
+    /// * `Option<Vec<SplitLine>>` - 折り返し直した行(重ねる装飾がない行はNone)
+    /// # Notes
+    /// * 装飾は表示幅を変えないので、折り返し直しても行の分け方は変わらない
+    fn decorate_line(&self, line_number: usize, line_width: usize) -> Option<Vec<SplitLine>> {
+        if self.decorations.is_empty() {
+            return None;
+        }
+
+        let (line_start, line) = line_at(&self.original_contents, line_number.checked_sub(1)?)?;
+        let line_end = line_start + line.len();
+        let mut decorations = self
+            .decorations
+            .iter()
+            .filter(|decoration| {
+                let range = &decoration.highlight().range;
+                range.start < line_end && range.end > line_start
+            })
+            .peekable();
+        decorations.peek()?;
+
+        // 行の中の強調表示だけに重ねる
+        let highlights = &self.options.highlights;
+        let first = highlights.partition_point(|h| h.range.end <= line_start);
+        let highlights = highlights[first..]
+            .iter()
+            .take_while(|h| h.range.start < line_end)
+            .cloned()
+            .collect();
+        let highlights = decorations.fold(highlights, |highlights, decoration| {
+            decoration.apply(highlights)
+        });
+
+        Some(self.wrap_line(line_number, line_start, &line, &highlights, line_width))
+    }
+
+    /// バックスペースによる重ね打ちを装飾に変換する
+    /// # Arguments
+    /// * `line` - 行
+    /// # Returns
+    /// * `String` - 重ね打ちを装飾に置き換えた行
+    /// # Examples
+    /// ```
+    /// let contents = Contents::new(Rope::new(), 0, 0, 0, 0, 0, 0);
+    /// assert_eq!(contents.apply_overstrike("a\x08ab"), "\x1b[1ma\x1b[22mb");
+    /// ```
+    /// # Notes
+    /// * `c\bc`は太字、`_\bc`と`c\b_`は下線として扱う(`less`と同じ)
+    /// * 同じ装飾の文字が続く場合は、まとめて装飾する
+    fn apply_overstrike(&self, line: &str) -> String {
+        if !line.contains('\x08') {
+            return line.to_string();
+        }
+
+        let chars: Vec<char> = line.chars().collect();
+        let mut result = String::new();
+        // 装飾中の文字列とその装飾
+        let mut run = String::new();
+        let mut run_style = Style::default();
+
+        let mut i = 0;
+        while i < chars.len() {
+            let mut c = chars[i];
+            let mut style = Style::default();
+
+            // 重ね打ちは何度でも続けられる(`c\bc\bc`など)
+            while chars.get(i + 1) == Some(&'\x08') {
+                let Some(&next) = chars.get(i + 2) else {
+                    break;
                 };
-                self.contents.push(split_line);
+                if next == c {
+                    style.bold = true;
+                } else if c == '_' {
+                    style.underline = true;
+                    c = next;
+                } else if next == '_' {
+                    style.underline = true;
+                } else {
+                    // 異なる文字の重ね打ちは、後の文字を表示する
+                    c = next;
+                }
+                i += 2;
             }
+            i += 1;
+
+            if style != run_style {
+                result.push_str(&self.paint(&run_style, &run));
+                run.clear();
+                run_style = style;
+            }
+            run.push(c);
+        }
+        result.push_str(&self.paint(&run_style, &run));
+
+        result
+    }
+
+    /// 行に装飾を適用する
+    /// # Arguments
+    /// * `line_start` - 元の文字列での行の開始位置(バイト単位)
+    /// * `line` - 改行を除いた行
+    /// * `highlights` - 元の文字列の一部の装飾(開始位置の昇順で、重ならないこと)
+    /// # Returns
+    /// * `String` - 装飾を開始・終了するエスケープシーケンスを挿入した行
+    fn apply_highlights(&self, line_start: usize, line: &str, highlights: &[Highlight]) -> String {
+        if highlights.is_empty() {
+            return line.to_string();
+        }
+
+        let line_end = line_start + line.len();
+
+        let mut result = String::new();
+        let mut position = line_start;
+        let first = highlights.partition_point(|h| h.range.end <= line_start);
+        for highlight in highlights[first..]
+            .iter()
+            .take_while(|h| h.range.start < line_end)
+        {
+            let start = highlight.range.start.max(position);
+            let end = highlight.range.end.min(line_end);
+            if start >= end {
+                continue;
+            }
+
+            result.push_str(&line[position - line_start..start - line_start]);
+            result.push_str(&self.paint(
+                &highlight.style,
+                &line[start - line_start..end - line_start],
+            ));
+            position = end;
+        }
+        result.push_str(&line[position - line_start..]);
+
+        result
+    }
+
+    /// 元の文字列の行数を取得する
+    /// # Notes
+    /// * `str::lines`と同じように、末尾の改行の後は行として数えない
+    fn line_count(&self) -> usize {
+        let line_count = self.original_contents.len_lines();
+        let last_line = self.original_contents.line(line_count - 1);
+        if last_line.len_bytes() == 0 {
+            line_count - 1
+        } else {
+            line_count
+        }
+    }
+
+    /// 折りたたみの記号を表示する幅を取得する
+    fn fold_marker_width(&self) -> usize {
+        if self.options.fold_markers.is_empty() {
+            0
+        } else {
+            2
+        }
+    }
+
+    /// 行番号などを表示する、行の内容の左側の横幅を取得する
+    /// # Notes
+    /// * 注釈 + 変更の種類(1文字) + 印(1文字) + 行番号の桁数 + 行番号の後の空白(1文字) + 折りたたみの記号(2文字)
+    fn gutter_width(&self) -> usize {
+        // 行番号の表示に必要な桁数を計算する
+        let line_number_digits = (self.line_count() + self.options.line_number_offset)
+            .to_string()
+            .len();
+
+        // 行番号とコンテンツの間の空白の数
+        let line_number_space = 1;
+
+        self.annotation_width()
+            + self.diff_marker_width()
+            + self.mark_marker_width()
+            + line_number_digits
+            + line_number_space
+            + self.fold_marker_width()
+    }
+
+    /// 表示する領域を、行番号などを表示する左側の領域と、行の内容を表示する領域に分ける
+    /// # Returns
+    /// * `(Rect, Rect)` - 行番号などを表示する領域と、行の内容を表示する領域
+    pub fn regions(&self) -> (Rect, Rect) {
+        let area = Rect {
+            x: self.x_start,
+            y: self.y_start,
+            width: self.width,
+            height: self.height,
+        };
+        area.split_left(self.gutter_width().min(u16::MAX as usize) as u16)
+    }
+
+    /// 行の内容を表示する横幅を取得する
+    fn text_width(&self) -> usize {
+        self.regions().1.width as usize
+    }
+
+    /// 行の途中の位置が、画面上のどこに表示されるかを求める
+    /// # Arguments
+    /// * `line_number` - 行番号(1から始まる)
+    /// * `prefix` - 行の先頭からその位置までの文字列
+    /// # Returns
+    /// * `Option<(u16, u16)>` - 画面上の位置(表示する領域の外にある場合や、表示しない行の場合はNone)
+    /// # Notes
+    /// * 右から左に書く文字を含む行では、並べ替える前の順で求める
+    pub fn screen_position(&mut self, line_number: usize, prefix: &str) -> Option<(u16, u16)> {
+        self.update_contents();
+
+        let first_row = self
+            .contents
+            .iter()
+            .position(|split_line| split_line.line_number == line_number)?;
+        let line_width = self.text_width();
+
+        // 折り返す場合は、折り返しながら表示幅を数える
+        let mut row = 0;
+        let mut column = 0;
+        for cell in self.to_cells(prefix) {
+            if !self.options.chop_long_lines && cell.width > 0 && column + cell.width > line_width {
+                row += 1;
+                column = 0;
+            }
+            column += cell.width;
+        }
+
+        let column = if self.options.chop_long_lines {
+            column.checked_sub(self.cursor_x as usize)?
+        } else if column >= line_width {
+            // 行の右端に収まった次の位置は、次の行の先頭
+            row += 1;
+            0
+        } else {
+            column
+        };
+        if column >= line_width {
+            return None;
+        }
+
+        // 一番上に見出しを固定して表示している場合は、一番上の行は隠れている
+        let y = (first_row + row).checked_sub(self.cursor_y)?;
+        if y >= self.height as usize || (y == 0 && self.sticky_row().is_some()) {
+            return None;
+        }
+
+        let (_, text) = self.regions();
+        Some((text.x + column as u16, text.y + y as u16))
+    }
+
+    /// 一番上に固定して表示する見出しの位置を取得する
+    /// # Returns
+    /// * `Option<usize>` - 固定して表示する見出しの`contents`での位置
+    fn sticky_row(&self) -> Option<usize> {
+        let top_line_number = self.contents.get(self.cursor_y)?.line_number;
+        let &(header, _) = self
+            .options
+            .sticky_headers
+            .iter()
+            .find(|&&(header, end)| header < top_line_number && top_line_number <= end)?;
+
+        self.contents
+            .iter()
+            .position(|split_line| split_line.line_number == header)
+    }
+
+    /// 注釈を表示する幅(注釈の後の空白を含む)を取得する
+    /// 本文を表示できるように、表示する領域の横幅の半分までにする
+    fn annotation_width(&self) -> usize {
+        self.options
+            .annotations
+            .iter()
+            .map(|annotation| annotation.width() + 1)
+            .max()
+            .unwrap_or(0)
+            .min(self.width as usize / 2)
+    }
+
+    /// 変更の種類を表示する幅を取得する
+    fn diff_marker_width(&self) -> usize {
+        if self.options.diff_markers.is_empty() {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// 印を表示する幅を取得する
+    fn mark_marker_width(&self) -> usize {
+        if self.options.mark_markers.is_empty() {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// 折り返した後の、表示する行の数を取得する
+    pub fn row_count(&mut self) -> usize {
+        self.update_contents();
+
+        self.contents.len()
+    }
+
+    /// 表示する領域の一番上の行の行番号を取得する
+    /// # Returns
+    /// * `Option<usize>` - 行番号(1から始まる)、表示する行がない場合はNone
+    pub fn top_line_number(&mut self) -> Option<usize> {
+        self.update_contents();
+
+        self.contents
+            .get(self.cursor_y)
+            .map(|split_line| split_line.line_number)
+    }
+
+    /// 指定した行までスクロールする
+    /// 指定した行が表示する領域の一番上に来るようにする
+    /// # Arguments
+    /// * `line_number` - 行番号(1から始まる)
+    /// # Returns
+    /// * `bool` - 指定した行がある場合はtrue、それ以外はfalse
+    pub fn scroll_to_line(&mut self, line_number: usize) -> bool {
+        self.update_contents();
+
+        match self
+            .contents
+            .iter()
+            .position(|split_line| split_line.line_number >= line_number)
+        {
+            Some(y) => {
+                self.cursor_y = y;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 指定した行が表示する領域に入るようにスクロールする
+    /// 表示する領域に入っている場合はスクロールせず、上にある場合は一番上に、下にある場合は一番下に来るようにする
+    /// # Arguments
+    /// * `line_number` - 行番号(1から始まる)
+    /// # Returns
+    /// * `bool` - 指定した行がある場合はtrue、それ以外はfalse
+    pub fn scroll_into_view(&mut self, line_number: usize) -> bool {
+        self.update_contents();
+
+        let Some(y) = self
+            .contents
+            .iter()
+            .position(|split_line| split_line.line_number >= line_number)
+        else {
+            return false;
+        };
+
+        let height = self.height as usize;
+        if y < self.cursor_y {
+            self.cursor_y = y;
+        } else if y >= self.cursor_y.saturating_add(height) {
+            self.cursor_y = y + 1 - height.max(1);
+        }
+
+        true
+    }
+
+    /// 文字列を検索して、見つかった行までスクロールする
+    /// 表示する領域の一番上の行から検索を始め、見つかった行が表示する領域の一番上に来るようにする
+    /// # Arguments
+    /// * `pattern` - 検索する文字列
+    /// # Returns
+    /// * `bool` - 見つかった場合はtrue、それ以外はfalse
+    /// # Notes
+    /// * 合成済みの文字と分解された文字を区別しないように、どちらもNFCに正規化して比較する
+    pub fn scroll_to_match(&mut self, pattern: &str) -> bool {
+        self.scroll_to_match_from(pattern, 0, false)
+    }
+
+    /// 文字列を検索して、一番上の行より後で見つかった行までスクロールする
+    /// # Arguments
+    /// * `pattern` - 検索する文字列
+    /// # Returns
+    /// * `bool` - 見つかった場合はtrue、それ以外はfalse
+    pub fn scroll_to_next_match(&mut self, pattern: &str) -> bool {
+        self.scroll_to_match_from(pattern, 1, false)
+    }
+
+    /// 文字列を検索して、一番上の行より前で見つかった行までスクロールする
+    /// # Arguments
+    /// * `pattern` - 検索する文字列
+    /// # Returns
+    /// * `bool` - 見つかった場合はtrue、それ以外はfalse
+    pub fn scroll_to_previous_match(&mut self, pattern: &str) -> bool {
+        self.scroll_to_match_from(pattern, 1, true)
+    }
+
+    /// 表示する領域の一番上の行から指定した行数だけ離れた行から検索を始め、見つかった行までスクロールする
+    /// # Arguments
+    /// * `pattern` - 検索する文字列
+    /// * `offset` - 検索を始める行の、一番上の行からの行数
+    /// * `backward` - 前に向かって検索する場合はtrue
+    /// # Returns
+    /// * `bool` - 見つかった場合はtrue、それ以外はfalse
+    fn scroll_to_match_from(&mut self, pattern: &str, offset: usize, backward: bool) -> bool {
+        self.update_contents();
+
+        let pattern: String = pattern.nfc().collect();
+        let start_line_number = match self.contents.get(self.cursor_y) {
+            Some(split_line) => split_line.line_number,
+            None => return false,
+        };
+        let is_match =
+            |(_, line): &(usize, Cow<str>)| line.nfc().collect::<String>().contains(&pattern);
+
+        let found = if backward {
+            let Some(end) = start_line_number.checked_sub(offset) else {
+                return false;
+            };
+            (1..)
+                .zip(lines(&self.original_contents))
+                .take(end)
+                .filter(|(_, line)| is_match(line))
+                .last()
+        } else {
+            (1..)
+                .zip(lines(&self.original_contents))
+                .skip(start_line_number - 1 + offset)
+                .find(|(_, line)| is_match(line))
+        };
+
+        let Some((line_number, _)) = found else {
+            return false;
+        };
+
+        // 連続する空行をまとめている場合などは、見つかった行が表示されないこともある
+        match self
+            .contents
+            .iter()
+            .position(|split_line| split_line.line_number >= line_number)
+        {
+            Some(y) => {
+                self.cursor_y = y;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 次の改ページまでスクロールする
+    /// 改ページが表示する領域の一番上に来るようにする
+    /// # Returns
+    /// * `bool` - 次の改ページがある場合はtrue、それ以外はfalse
+    pub fn scroll_to_next_page_break(&mut self) -> bool {
+        self.update_contents();
+
+        let next = self
+            .contents
+            .iter()
+            .enumerate()
+            .skip(self.cursor_y + 1)
+            .find(|(_, split_line)| split_line.is_page_break);
+
+        match next {
+            Some((y, _)) => {
+                self.cursor_y = y;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 前の改ページまでスクロールする
+    /// 改ページが表示する領域の一番上に来るようにする
+    /// # Returns
+    /// * `bool` - 前の改ページがある場合はtrue、それ以外はfalse
+    pub fn scroll_to_previous_page_break(&mut self) -> bool {
+        self.update_contents();
+
+        let previous = self
+            .contents
+            .iter()
+            .enumerate()
+            .take(self.cursor_y)
+            .rfind(|(_, split_line)| split_line.is_page_break);
 
-            line_number += 1;
+        match previous {
+            Some((y, _)) => {
+                self.cursor_y = y;
+                true
+            }
+            None => false,
         }
     }
 
@@ -281,34 +1755,115 @@ impl Contents {
     /// # Panics
     ///
     /// # Notes
-    fn get_display_area(&self) -> (u16, u16, u16, u16) {
+    fn get_display_area(&self) -> (u16, usize, u16, usize) {
         // カーソルの位置から表示する領域を計算する
         let start_x = self.cursor_x;
         let start_y = self.cursor_y;
-        let end_x = start_x + self.width;
-        let end_y = start_y + self.height;
+        let end_x = start_x.saturating_add(self.width);
+        let end_y = start_y + self.height as usize;
 
         (start_x, start_y, end_x, end_y)
     }
 }
 
+/// ロープの行を取得する
+/// # Arguments
+/// * `contents` - 行に分ける文字列
+/// # Returns
+/// * `impl Iterator<Item = (usize, Cow<str>)>` - 文字列での行の開始位置(バイト単位)と、改行を除いた行
+/// # Notes
+/// * `str::lines`と同じように`\n`と`\r\n`で区切り、末尾の改行の後は行としない
+/// * 行がロープの複数の塊にまたがる場合だけ、文字列をコピーする
+fn lines(contents: &Rope) -> impl Iterator<Item = (usize, Cow<'_, str>)> {
+    let mut line_start = 0;
+    contents
+        .lines()
+        .filter(|line| line.len_bytes() > 0)
+        .map(move |line| {
+            let start = line_start;
+            line_start += line.len_bytes();
+            (start, trim_newline(line))
+        })
+}
+
+/// ロープの1行を取得する
+/// # Arguments
+/// * `contents` - 文字列
+/// * `line_index` - 行の位置(0から始まる)
+/// # Returns
+/// * `Option<(usize, Cow<str>)>` - 文字列での行の開始位置(バイト単位)と、改行を除いた行(行がない場合はNone)
+/// # Notes
+/// * `lines`と同じように、末尾の改行の後は行としない
+fn line_at(contents: &Rope, line_index: usize) -> Option<(usize, Cow<'_, str>)> {
+    let line = contents.get_line(line_index)?;
+    if line.len_bytes() == 0 {
+        return None;
+    }
+
+    Some((contents.line_to_byte(line_index), trim_newline(line)))
+}
+
+/// 行の末尾の改行(`\n`または`\r\n`)を除く
+fn trim_newline(line: RopeSlice<'_>) -> Cow<'_, str> {
+    let mut end = line.len_bytes();
+    if end > 0 && line.byte(end - 1) == b'\n' {
+        end -= 1;
+        if end > 0 && line.byte(end - 1) == b'\r' {
+            end -= 1;
+        }
+    }
+    line.byte_slice(..end).into()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_lines() {
+        let rope = Rope::from_str("a\r\nb\rc\n\nd\n");
+        let result: Vec<(usize, Cow<str>)> = lines(&rope).collect();
+        assert_eq!(
+            result,
+            vec![
+                (0, "a".into()),
+                (3, "b\rc".into()),
+                (7, "".into()),
+                (8, "d".into())
+            ]
+        );
+
+        // 塊にまたがる長い行も1行として取得する
+        let long_line = "x".repeat(100_000);
+        let rope = Rope::from_str(&format!("{}\ny", long_line));
+        let result: Vec<(usize, Cow<str>)> = lines(&rope).collect();
+        assert_eq!(result, vec![(0, long_line.into()), (100_001, "y".into())]);
+
+        let contents = Contents::new(rope, 0, 0, 0, 0, 0, 0);
+        assert_eq!(contents.line_count(), 2);
+        assert_eq!(Contents::new(Rope::new(), 0, 0, 0, 0, 0, 0).line_count(), 0);
+        assert_eq!(
+            Contents::new("a\n".into(), 0, 0, 0, 0, 0, 0).line_count(),
+            1
+        );
+    }
+
     #[test]
     /// ASCII文字列の場合
     fn test_split_string_by_width_all_ascii() {
         // インスタンスの値はなんでもいい
         let contents = Contents {
-            original_contents: String::new(),
+            original_contents: Rope::new(),
             cursor_x: 0,
             cursor_y: 0,
             width: 0,
             height: 0,
-            contents: Vec::new(),
+            contents: Rc::default(),
+            cache: WrapCache::default(),
             x_start: 0,
             y_start: 0,
+            options: DisplayOptions::default(),
+            decorations: Vec::new(),
         };
 
         let string = "Hello, world!";
@@ -325,14 +1880,17 @@ mod tests {
     fn test_split_string_by_width_ascii_and_japanese() {
         // インスタンスの値はなんでもいい
         let contents = Contents {
-            original_contents: String::new(),
+            original_contents: Rope::new(),
             cursor_x: 0,
             cursor_y: 0,
             width: 0,
             height: 0,
-            contents: Vec::new(),
+            contents: Rc::default(),
+            cache: WrapCache::default(),
             x_start: 0,
             y_start: 0,
+            options: DisplayOptions::default(),
+            decorations: Vec::new(),
         };
 
         let string = "Hello, 世界!";
@@ -351,21 +1909,41 @@ mod tests {
     fn test_split_string_by_width_with_escape_sequence() {
         // インスタンスの値はなんでもいい
         let contents = Contents {
-            original_contents: String::new(),
+            original_contents: Rope::new(),
             cursor_x: 0,
             cursor_y: 0,
             width: 0,
             height: 0,
-            contents: Vec::new(),
+            contents: Rc::default(),
+            cache: WrapCache::default(),
             x_start: 0,
             y_start: 0,
+            options: DisplayOptions::default(),
+            decorations: Vec::new(),
         };
 
         // エスケープシーケンスが含まれる場合
         let string = "\x1b[31mHello, world!\x1b[0m";
         let width = 5;
         let result = contents.split_string_by_width(string, width);
-        assert_eq!(result, vec!["\x1b[31mHello", ", wor", "ld!\x1b[0m"]);
+        // 折り返した行ごとに装飾が完結するように、行末でリセットして次の行の先頭で出力し直す
+        assert_eq!(
+            result,
+            vec![
+                "\x1b[31mHello\x1b[0m",
+                "\x1b[31m, wor\x1b[0m",
+                "\x1b[31mld!\x1b[0m"
+            ]
+        );
+
+        // 複数の装飾が有効な場合は、すべて出力し直す
+        let string = "\x1b[1m\x1b[31mabcd";
+        let width = 2;
+        let result = contents.split_string_by_width(string, width);
+        assert_eq!(
+            result,
+            vec!["\x1b[1m\x1b[31mab\x1b[0m", "\x1b[1m\x1b[31mcd\x1b[0m"]
+        );
 
         // マルチバイト文字列の場合
         let string = "\x1b[31mあい\x1b[0mう";
@@ -375,17 +1953,664 @@ mod tests {
         assert_eq!(result, vec!["\x1b[31mあい\x1b[0m", "う"]);
     }
 
+    #[test]
+    /// タブ文字が含まれる場合
+    /// タブ文字は、行頭から数えた次のタブストップまでの空白に展開する
+    fn test_split_string_by_width_with_tab() {
+        let mut contents = Contents::new(Rope::new(), 0, 0, 0, 0, 0, 0);
+        contents.options.tab_width = 4;
+
+        let string = "a\tbc\td";
+        let width = 20;
+        let result = contents.split_string_by_width(string, width);
+        assert_eq!(result, vec!["a   bc  d"]);
+
+        // 折り返しをまたいでもタブストップは行頭から数える
+        let string = "abc\td";
+        let width = 3;
+        let result = contents.split_string_by_width(string, width);
+        assert_eq!(result, vec!["abc", " d"]);
+    }
+
+    #[test]
+    /// 制御文字が含まれる場合
+    /// 制御文字は、薄い色のキャレット記法などで表示する
+    fn test_split_string_by_width_with_control_chars() {
+        let mut contents = Contents::new(Rope::new(), 0, 0, 0, 0, 0, 0);
+
+        let string = "a\rb\x1bc\u{9b}";
+        let width = 20;
+        let result = contents.split_string_by_width(string, width);
+        assert_eq!(
+            result,
+            vec!["a\x1b[2m^M\x1b[22mb\x1b[2m^[\x1b[22mc\x1b[2m<U+009B>\x1b[22m"]
+        );
+
+        // 表記の途中では折り返さない
+        let string = "ab\r";
+        let width = 3;
+        let result = contents.split_string_by_width(string, width);
+        assert_eq!(result, vec!["ab", "\x1b[2m^M\x1b[22m"]);
+
+        // raw_control_charsが有効な場合は、そのまま出力する
+        contents.options.raw_control_chars = true;
+        let string = "a\rb";
+        let width = 2;
+        let result = contents.split_string_by_width(string, width);
+        assert_eq!(result, vec!["a\rb"]);
+    }
+
+    #[test]
+    /// 空白文字を記号で表示する場合
+    fn test_split_string_by_width_show_whitespace() {
+        let mut contents = Contents::new(Rope::new(), 0, 0, 0, 0, 0, 0);
+        contents.options.tab_width = 4;
+        contents.options.show_whitespace = true;
+
+        let string = "a \tb";
+        let width = 20;
+        let result = contents.split_string_by_width(string, width);
+        assert_eq!(
+            result,
+            vec!["a\x1b[2m·\x1b[22m\x1b[2m→\x1b[22m b\x1b[2m¶\x1b[22m"]
+        );
+    }
+
+    #[test]
+    /// 連続する空行を1行にまとめる場合
+    /// 行番号は元の行番号のままにする
+    fn test_update_contents_squeeze_blank() {
+        let mut contents = Contents::new("a\n\n\n\nb\n\nc".into(), 10, 10, 0, 0, 0, 0);
+        contents.options.squeeze_blank = true;
+
+        contents.update_contents();
+
+        let line_numbers: Vec<usize> = contents.contents.iter().map(|l| l.line_number).collect();
+        assert_eq!(line_numbers, vec![1, 2, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_update_contents_with_cache() {
+        let cache = WrapCache::default();
+        let view = |width| {
+            let mut contents = Contents::new("abcdef\ngh".into(), width, 10, 0, 0, 0, 0);
+            contents.cache = cache.clone();
+            contents.update_contents();
+            contents.contents
+        };
+
+        // 横幅が同じ場合は、前に折り返した結果を使う
+        let rows = view(7);
+        assert_eq!(rows.len(), 3);
+        assert!(Rc::ptr_eq(&rows, &view(7)));
+
+        // 横幅が変わった場合や、キャッシュを破棄した場合は折り返し直す
+        let rows = view(10);
+        assert_eq!(rows.len(), 2);
+        cache.invalidate();
+        assert!(!Rc::ptr_eq(&rows, &view(10)));
+    }
+
+    #[test]
+    /// 折り返した後に重ねる装飾は、折り返した行のキャッシュを使ったまま、表示する行だけに重ねる
+    fn test_decorations() {
+        let cache = WrapCache::default();
+        let view = |decorations| {
+            let mut contents = Contents::new("abcdef\ngh".into(), 7, 10, 0, 0, 0, 0);
+            contents.cache = cache.clone();
+            contents.decorations = decorations;
+            contents.update_contents();
+            contents
+        };
+        let bold = Style {
+            bold: true,
+            ..Default::default()
+        };
+
+        let rows = view(Vec::new()).contents;
+        let contents = view(vec![Decoration::Fill(Highlight {
+            range: 4..6,
+            style: bold,
+        })]);
+        assert!(Rc::ptr_eq(&rows, &contents.contents));
+
+        // 装飾を重ねる行だけを、分け方を変えずに折り返し直す
+        let decorated = contents.decorate_line(1, 5).unwrap();
+        assert_eq!(
+            decorated
+                .iter()
+                .map(|row| row.contents.as_str())
+                .collect::<Vec<_>>(),
+            vec!["abcd\x1b[1me\x1b[0m", "\x1b[1mf\x1b[22m\x1b[0m"]
+        );
+        assert!(contents.decorate_line(2, 5).is_none());
+
+        // 世代を進めた場合は折り返し直す
+        cache.invalidate();
+        assert!(!Rc::ptr_eq(&rows, &view(Vec::new()).contents));
+    }
+
+    #[test]
+    fn test_print() {
+        // 端末の代わりにメモリ上に表示して、表示した内容をそのまま見比べる
+        let mut contents = Contents::new(
+            "abcdefgh
+あいう
+"
+            .into(),
+            8,
+            4,
+            1,
+            0,
+            0,
+            0,
+        );
+        let screen = crate::screen::render_to_string(10, 5, || contents.print()).unwrap();
+        assert_eq!(screen, " 1 abcdef\n   gh\n 2 あいう\n\n");
+    }
+
+    #[test]
+    /// 行がない場合
+    /// 何も表示せずに、空の行を表示する
+    fn test_print_empty() {
+        let mut contents = Contents::new(Rope::new(), 8, 3, 1, 0, 0, 0);
+        let screen = crate::screen::render_to_string(10, 3, || contents.print()).unwrap();
+        assert_eq!(screen, "\n\n");
+    }
+
+    #[test]
+    /// 長い行を切り詰める場合
+    /// 右側に続きがある場合は、`>`を表示する
+    fn test_chop_string_by_width() {
+        let contents = Contents::new(Rope::new(), 0, 0, 0, 0, 0, 0);
+
+        assert_eq!(
+            contents.chop_string_by_width("Hello, world!", 0, 5),
+            "Hell>"
+        );
+        assert_eq!(
+            contents.chop_string_by_width("Hello, world!", 7, 6),
+            "world!"
+        );
+        assert_eq!(contents.chop_string_by_width("Hello", 0, 10), "Hello");
+
+        // 全角文字が表示範囲の境界をまたぐ場合は、空白で埋める
+        assert_eq!(contents.chop_string_by_width("あいう", 1, 4), " い>");
+        assert_eq!(contents.chop_string_by_width("あいう", 1, 5), " いう");
+
+        // エスケープシーケンスは表示範囲外にあっても出力する
+        assert_eq!(
+            contents.chop_string_by_width("\x1b[31mHello\x1b[0m", 2, 3),
+            "\x1b[31mllo\x1b[0m"
+        );
+    }
+
+    #[test]
+    /// 改ページが含まれる場合
+    /// 改ページは罫線の行として表示し、前後の移動ができる
+    fn test_page_break() {
+        let mut contents = Contents::new("a\n\x0c\nb\x0cc\nd".into(), 7, 2, 0, 0, 0, 0);
+
+        contents.update_contents();
+
+        let page_breaks: Vec<bool> = contents.contents.iter().map(|l| l.is_page_break).collect();
+        assert_eq!(page_breaks, vec![false, true, false, true, false, false]);
+        assert_eq!(
+            contents.contents[1].contents,
+            contents.paint(&contents.options.theme.page_break, "─────")
+        );
+
+        assert!(contents.scroll_to_next_page_break());
+        assert_eq!(contents.cursor_y, 1);
+        assert!(contents.scroll_to_next_page_break());
+        assert_eq!(contents.cursor_y, 3);
+        assert!(!contents.scroll_to_next_page_break());
+        assert_eq!(contents.cursor_y, 3);
+
+        assert!(contents.scroll_to_previous_page_break());
+        assert_eq!(contents.cursor_y, 1);
+        assert!(!contents.scroll_to_previous_page_break());
+        assert_eq!(contents.cursor_y, 1);
+    }
+
+    #[test]
+    /// バックスペースによる重ね打ちの場合
+    /// 同じ文字の重ね打ちは太字、`_`との重ね打ちは下線にする
+    fn test_apply_overstrike() {
+        let contents = Contents::new(Rope::new(), 0, 0, 0, 0, 0, 0);
+
+        assert_eq!(
+            contents.apply_overstrike("N\x08NA\x08AME x"),
+            "\x1b[1mNA\x1b[22mME x"
+        );
+        assert_eq!(
+            contents.apply_overstrike("_\x08f_\x08o\x08_ o"),
+            "\x1b[4mfo\x1b[24m o"
+        );
+        assert_eq!(
+            contents.apply_overstrike("_\x08あ\x08あ"),
+            "\x1b[1;4mあ\x1b[22;24m"
+        );
+
+        // 重ね打ちでないバックスペースはそのまま残す
+        assert_eq!(contents.apply_overstrike("a\x08"), "a\x08");
+
+        // 装飾しない場合は、重ね打ちを取り除くだけにする
+        let mut contents = contents;
+        contents.options.color_depth = ColorDepth::None;
+        assert_eq!(contents.apply_overstrike("N\x08NA\x08A"), "NA");
+    }
+
+    #[test]
+    /// 見出しを固定して表示する場合
+    /// 一番上の行が見出しの範囲内にある場合だけ、見出しを固定する
+    fn test_sticky_row() {
+        let mut contents = Contents::new("a\nb\nc\nd\ne".into(), 10, 2, 0, 0, 0, 0);
+        contents.options.sticky_headers = vec![(2, 3), (4, 5)];
+        contents.update_contents();
+
+        let sticky_rows: Vec<Option<usize>> = (0..5)
+            .map(|y| {
+                contents.cursor_y = y;
+                contents.sticky_row()
+            })
+            .collect();
+        assert_eq!(sticky_rows, vec![None, None, Some(1), None, Some(3)]);
+    }
+
+    #[test]
+    /// 折りたたみの記号を表示する場合
+    /// 記号の分だけ1行の横幅が狭くなる
+    fn test_update_contents_with_fold_markers() {
+        let mut contents = Contents::new("abcd\nef\ngh".into(), 7, 10, 0, 0, 0, 0);
+
+        contents.update_contents();
+        assert_eq!(contents.contents.len(), 3);
+
+        contents.options.fold_markers = vec![Some(false), None, None].into();
+        contents.update_contents();
+        let lines: Vec<&str> = contents
+            .contents
+            .iter()
+            .map(|l| l.contents.as_str())
+            .collect();
+        assert_eq!(lines, vec!["abc", "d", "ef", "gh"]);
+
+        assert!(contents.scroll_to_line(2));
+        assert_eq!(contents.cursor_y, 2);
+        assert_eq!(contents.top_line_number(), Some(2));
+        assert!(!contents.scroll_to_line(4));
+
+        // 表示する領域に入っている行ではスクロールせず、下にある行は一番下に来るようにする
+        contents.height = 2;
+        assert!(contents.scroll_into_view(3));
+        assert_eq!(contents.cursor_y, 2);
+        contents.cursor_y = 0;
+        assert!(contents.scroll_into_view(2));
+        assert_eq!(contents.cursor_y, 1);
+        assert!(contents.scroll_into_view(3));
+        assert_eq!(contents.cursor_y, 2);
+        assert!(contents.scroll_into_view(1));
+        assert_eq!(contents.cursor_y, 0);
+        assert!(!contents.scroll_into_view(4));
+    }
+
+    #[test]
+    /// 行の途中の位置が表示される画面上の位置
+    /// 行番号の分だけ右にずれ、折り返した場合は次の行になる
+    fn test_screen_position() {
+        let mut contents = Contents::new("abcdef\nあい\n".into(), 6, 3, 1, 2, 0, 0);
+        assert_eq!(contents.screen_position(1, ""), Some((3, 2)));
+        assert_eq!(contents.screen_position(1, "abc"), Some((6, 2)));
+        assert_eq!(contents.screen_position(1, "abcd"), Some((3, 3)));
+        assert_eq!(contents.screen_position(2, "あ"), Some((5, 4)));
+        assert_eq!(contents.screen_position(3, ""), None);
+
+        // 表示する領域より上の行は表示しない
+        contents.cursor_y = 1;
+        assert_eq!(contents.screen_position(1, ""), None);
+
+        // 切り詰める場合は、横にスクロールした分だけ左にずれる
+        let mut contents = Contents::new("abcdef".into(), 6, 3, 0, 0, 2, 0);
+        contents.options.chop_long_lines = true;
+        assert_eq!(contents.screen_position(1, "abc"), Some((3, 0)));
+        assert_eq!(contents.screen_position(1, "a"), None);
+        assert_eq!(contents.screen_position(1, "abcdef"), None);
+    }
+
+    #[test]
+    /// 行番号などを表示する領域と、行の内容を表示する領域
+    /// 行番号の桁数が増えると、行の内容を表示する領域が狭くなる
+    fn test_regions() {
+        let contents = Contents::new("a\n".repeat(10).into(), 8, 3, 1, 2, 0, 0);
+        let (gutter, text) = contents.regions();
+        assert_eq!(
+            gutter,
+            Rect {
+                x: 1,
+                y: 2,
+                width: 3,
+                height: 3
+            }
+        );
+        assert_eq!(
+            text,
+            Rect {
+                x: 4,
+                y: 2,
+                width: 5,
+                height: 3
+            }
+        );
+    }
+
+    #[test]
+    /// 書記素クラスタが含まれる場合
+    /// 結合文字などを含む書記素クラスタは、途中で折り返さない
+    fn test_split_string_by_width_grapheme_cluster() {
+        let contents = Contents::new(Rope::new(), 0, 0, 0, 0, 0, 0);
+
+        // "e" + 結合アキュート・アクセント
+        let string = "abe\u{301}c";
+        let width = 2;
+        let result = contents.split_string_by_width(string, width);
+        assert_eq!(result, vec!["ab", "e\u{301}c"]);
+
+        // "か" + 結合濁点
+        let string = "aか\u{3099}";
+        let width = 2;
+        let result = contents.split_string_by_width(string, width);
+        assert_eq!(result, vec!["a", "か\u{3099}"]);
+    }
+
+    #[test]
+    /// 東アジアの文字幅が曖昧な文字が含まれる場合
+    /// ambiguous_wideが有効な場合は、全角として扱う
+    fn test_split_string_by_width_ambiguous_width() {
+        let mut contents = Contents::new(Rope::new(), 0, 0, 0, 0, 0, 0);
+
+        let string = "○±a";
+        let width = 2;
+        let result = contents.split_string_by_width(string, width);
+        assert_eq!(result, vec!["○±", "a"]);
+
+        contents.options.ambiguous_wide = true;
+        let result = contents.split_string_by_width(string, width);
+        assert_eq!(result, vec!["○", "±", "a"]);
+    }
+
+    #[test]
+    /// 絵文字のZWJシーケンスや異体字セレクタが含まれる場合
+    fn test_grapheme_width_emoji() {
+        let contents = Contents::new(Rope::new(), 0, 0, 0, 0, 0, 0);
+
+        // 家族の絵文字(男性 + ZWJ + 女性 + ZWJ + 女の子)
+        assert_eq!(
+            Contents::grapheme_width(
+                &contents.options,
+                "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}"
+            ),
+            2
+        );
+        // ハート + VS16
+        assert_eq!(
+            Contents::grapheme_width(&contents.options, "\u{2764}\u{FE0F}"),
+            2
+        );
+        // ハート + VS15
+        assert_eq!(
+            Contents::grapheme_width(&contents.options, "\u{2764}\u{FE0E}"),
+            1
+        );
+
+        let string = "a\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}b";
+        let width = 3;
+        let result = contents.split_string_by_width(string, width);
+        assert_eq!(
+            result,
+            vec!["a\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}", "b"]
+        );
+    }
+
+    #[test]
+    /// 結合文字が含まれる場合
+    /// 結合文字は表示幅を0とし、直前の文字と別の行に分けない
+    fn test_split_string_by_width_combining_character() {
+        let contents = Contents::new(Rope::new(), 0, 0, 0, 0, 0, 0);
+
+        // 行頭の結合文字
+        let string = "\u{301}ab";
+        let width = 2;
+        let result = contents.split_string_by_width(string, width);
+        assert_eq!(result, vec!["\u{301}ab"]);
+
+        // 行の幅いっぱいの文字の直後にエスケープシーケンスと結合文字がある場合
+        let string = "ab\x1b[0m\u{301}c";
+        let width = 2;
+        let result = contents.split_string_by_width(string, width);
+        assert_eq!(result, vec!["ab\x1b[0m\u{301}", "c"]);
+    }
+
+    #[test]
+    /// 合成済みの文字と分解された文字が含まれる場合
+    /// 表示はNFCに正規化し、検索ではどちらの形式でも一致する
+    fn test_normalization() {
+        // 1行目は合成済みの"が"、3行目は"か" + 結合濁点
+        let mut contents =
+            Contents::new("\u{304C}\nabc\n\u{304B}\u{3099}".into(), 10, 1, 0, 0, 0, 0);
+
+        contents.update_contents();
+        assert_eq!(contents.contents[2].contents, "\u{304C}");
+
+        assert!(contents.scroll_to_match("\u{304B}\u{3099}"));
+        assert_eq!(contents.cursor_y, 0);
+
+        contents.cursor_y = 1;
+        assert!(contents.scroll_to_match("\u{304C}"));
+        assert_eq!(contents.cursor_y, 2);
+
+        assert!(!contents.scroll_to_match("xyz"));
+        assert_eq!(contents.cursor_y, 2);
+    }
+
+    #[test]
+    fn test_scroll_to_next_match() {
+        let mut contents = Contents::new("a\nb\na\nc\na".into(), 10, 1, 0, 0, 0, 0);
+
+        // 一番上の行は検索しない
+        assert!(contents.scroll_to_next_match("a"));
+        assert_eq!(contents.cursor_y, 2);
+        assert!(contents.scroll_to_next_match("a"));
+        assert_eq!(contents.cursor_y, 4);
+        assert!(!contents.scroll_to_next_match("a"));
+        assert_eq!(contents.cursor_y, 4);
+
+        assert!(contents.scroll_to_previous_match("a"));
+        assert_eq!(contents.cursor_y, 2);
+        assert!(contents.scroll_to_previous_match("a"));
+        assert_eq!(contents.cursor_y, 0);
+        assert!(!contents.scroll_to_previous_match("a"));
+        assert_eq!(contents.cursor_y, 0);
+    }
+
+    #[test]
+    /// 65535行より多い場合
+    /// 行番号とスクロール位置が溢れずに、最後の行まで移動できる
+    fn test_many_lines() {
+        let text: String = (1..=70000).map(|i| format!("{}\n", i)).collect();
+        let mut contents = Contents::new(text.as_str().into(), 20, 2, 0, 0, 0, 0);
+
+        assert!(contents.scroll_to_line(69999));
+        assert_eq!(contents.cursor_y, 69998);
+        assert_eq!(contents.top_line_number(), Some(69999));
+
+        contents.cursor_y = usize::MAX;
+        let screen = crate::screen::render_to_string(20, 2, || contents.print()).unwrap();
+        assert_eq!(screen, "69999 69999\n70000 70000");
+        assert_eq!(contents.cursor_y, 69998);
+    }
+
+    #[test]
+    /// 右から左に書く文字が含まれる場合
+    /// 折り返した行ごとに表示順に並べ替える
+    fn test_split_string_by_width_bidi() {
+        let contents = Contents::new(Rope::new(), 0, 0, 0, 0, 0, 0);
+
+        // ヘブライ文字のみ
+        let string = "\u{5D0}\u{5D1}\u{5D2}\u{5D3}";
+        let width = 2;
+        let result = contents.split_string_by_width(string, width);
+        assert_eq!(result, vec!["\u{5D1}\u{5D0}", "\u{5D3}\u{5D2}"]);
+
+        // 左から右に書く文字と混在する場合
+        let string = "ab \u{5D0}\u{5D1}";
+        let width = 10;
+        let result = contents.split_string_by_width(string, width);
+        assert_eq!(result, vec!["ab \u{5D1}\u{5D0}"]);
+
+        // 装飾は並べ替えた後の文字に合わせて出力し直す
+        let string = "\x1b[31m\u{5D0}\x1b[0m\u{5D1}";
+        let width = 10;
+        let result = contents.split_string_by_width(string, width);
+        assert_eq!(result, vec!["\u{5D1}\x1b[31m\u{5D0}\x1b[0m"]);
+
+        // 左から右に書く文字のみの場合は並べ替えない
+        let string = "\x1b[31mabc\x1b[0m";
+        let width = 10;
+        let result = contents.split_string_by_width(string, width);
+        assert_eq!(result, vec!["\x1b[31mabc\x1b[0m"]);
+    }
+
+    #[test]
+    /// エスケープシーケンスを取り除く場合
+    fn test_split_string_by_width_strip_ansi() {
+        let mut contents = Contents::new(Rope::new(), 0, 0, 0, 0, 0, 0);
+        contents.options.strip_ansi = true;
+
+        let string = "\x1b[1;31mHello\x1b[0m, \x1b[2Kwor\x1b]0;title\x07ld\x1b(B!";
+        let width = 20;
+        let result = contents.split_string_by_width(string, width);
+        assert_eq!(result, vec!["Hello, world!"]);
+    }
+
+    #[test]
+    /// SGR以外のエスケープシーケンスが含まれる場合
+    /// 表示を崩さないように、ESCを制御文字として表示する
+    fn test_split_string_by_width_non_sgr_escape_sequence() {
+        let contents = Contents::new(Rope::new(), 0, 0, 0, 0, 0, 0);
+
+        let string = "a\x1b[2Kb";
+        let width = 20;
+        let result = contents.split_string_by_width(string, width);
+        assert_eq!(result, vec!["a\x1b[2m^[\x1b[22m[2Kb"]);
+    }
+
+    #[test]
+    /// ハイパーリンク(OSC 8)が含まれる場合
+    /// ハイパーリンクは表示幅に含めず、折り返した行ごとに閉じて開き直す
+    fn test_split_string_by_width_hyperlink() {
+        let contents = Contents::new(Rope::new(), 0, 0, 0, 0, 0, 0);
+
+        let string = "\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\!";
+        let width = 3;
+        let result = contents.split_string_by_width(string, width);
+        assert_eq!(
+            result,
+            vec![
+                "\x1b]8;;https://example.com\x1b\\lin\x1b]8;;\x1b\\",
+                "\x1b]8;;https://example.com\x1b\\k\x1b]8;;\x1b\\!"
+            ]
+        );
+    }
+
+    #[test]
+    /// 色を使わない場合
+    /// 記号などを装飾せずに表示する
+    fn test_split_string_by_width_without_color() {
+        let mut contents = Contents::new(Rope::new(), 0, 0, 0, 0, 0, 0);
+        contents.options.color_depth = ColorDepth::None;
+        contents.options.show_whitespace = true;
+
+        let string = "a b\r";
+        let width = 20;
+        let result = contents.split_string_by_width(string, width);
+        assert_eq!(result, vec!["a·b^M¶"]);
+    }
+
+    #[test]
+    /// 装飾する範囲が指定されている場合
+    /// 複数の行にまたがる装飾は、行ごとに適用する
+    fn test_update_contents_with_highlights() {
+        let mut contents = Contents::new("ab\ncd".into(), 10, 10, 0, 0, 0, 0);
+        let bold = Style {
+            bold: true,
+            ..Default::default()
+        };
+        contents.options.highlights = vec![Highlight {
+            range: 1..4,
+            style: bold,
+        }]
+        .into();
+
+        contents.update_contents();
+
+        // 折り返した行ごとに装飾が完結するように、行末でリセットする
+        assert_eq!(contents.contents[0].contents, "a\x1b[1mb\x1b[22m\x1b[0m");
+        assert_eq!(contents.contents[1].contents, "\x1b[1mc\x1b[22md\x1b[0m");
+    }
+
+    #[test]
+    /// 表示するときと同じように桁を数える
+    fn test_grapheme_columns() {
+        let mut options = DisplayOptions {
+            tab_width: 4,
+            ..Default::default()
+        };
+        let line = "a\tb\x01\x1b[31m○\x1b[0m";
+        assert_eq!(
+            Contents::grapheme_columns(&options, line),
+            vec![
+                (0..1, 0..1),
+                (1..2, 1..4),
+                (2..3, 4..5),
+                (3..4, 5..7),
+                (4..9, 7..7),
+                (9..12, 7..8),
+                (12..16, 8..8),
+            ]
+        );
+
+        // 曖昧な幅の文字を全角にする場合や、エスケープシーケンスを取り除く場合
+        options.ambiguous_wide = true;
+        options.strip_ansi = true;
+        assert_eq!(
+            Contents::grapheme_columns(&options, line),
+            vec![
+                (0..1, 0..1),
+                (1..2, 1..4),
+                (2..3, 4..5),
+                (3..4, 5..7),
+                (4..9, 7..7),
+                (9..12, 7..9),
+                (12..16, 9..9),
+            ]
+        );
+    }
+
     #[test]
     fn test_get_display_area() {
         let contents = Contents {
-            original_contents: String::new(),
+            original_contents: Rope::new(),
             cursor_x: 2,
             cursor_y: 3,
             width: 10,
             height: 5,
-            contents: Vec::new(),
+            contents: Rc::default(),
+            cache: WrapCache::default(),
             x_start: 0,
             y_start: 0,
+            options: DisplayOptions::default(),
+            decorations: Vec::new(),
         };
 
         let (start_x, start_y, end_x, end_y) = contents.get_display_area();
@@ -394,5 +2619,43 @@ mod tests {
         assert_eq!(start_y, 3);
         assert_eq!(end_x, 12);
         assert_eq!(end_y, 8);
+
+        // 右端を超えて横にスクロールしても、あふれない
+        let contents = Contents {
+            cursor_x: u16::MAX - 1,
+            ..contents
+        };
+        assert_eq!(contents.get_display_area().2, u16::MAX);
+    }
+
+    #[test]
+    /// 長い行を切り詰める場合は、最も長い行の右端までしか横にスクロールできない
+    /// 横にスクロールしただけの場合は、前に求めた最も長い行の表示幅を使う
+    fn test_update_contents_chop_long_lines() {
+        let cache = WrapCache::default();
+        let view = |cursor_x| {
+            let mut contents = Contents::new("abcdefghij\nab".into(), 7, 10, 0, 0, cursor_x, 0);
+            contents.options.chop_long_lines = true;
+            contents.cache = cache.clone();
+            contents.update_contents();
+            contents
+        };
+
+        let contents = view(100);
+        assert_eq!(contents.cursor_x, 5);
+        assert_eq!(contents.contents[0].contents, "fghij");
+
+        let contents = view(2);
+        assert_eq!(contents.cursor_x, 2);
+        assert_eq!(contents.contents[0].contents, "cdef>");
+        assert_eq!(
+            cache
+                .0
+                .borrow()
+                .wrapped
+                .as_ref()
+                .and_then(|wrapped| wrapped.max_line_width),
+            Some(10)
+        );
     }
 }
@@ -0,0 +1,172 @@
+use std::io::Read;
+
+/// 読み込むときに展開する圧縮形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+}
+
+impl Format {
+    /// 圧縮形式ごとの、先頭のバイト列と拡張子
+    const FORMATS: [(Self, &'static [u8], &'static [&'static str]); 4] = [
+        (Self::Gzip, &[0x1f, 0x8b], &["gz", "tgz"]),
+        (Self::Zstd, &[0x28, 0xb5, 0x2f, 0xfd], &["zst", "zstd"]),
+        (Self::Bzip2, b"BZh", &["bz2", "tbz2"]),
+        (
+            Self::Xz,
+            &[0xfd, b'7', b'z', b'X', b'Z', 0x00],
+            &["xz", "txz"],
+        ),
+    ];
+
+    /// 圧縮形式を判定する
+    /// # Arguments
+    /// * `file` - ファイル名(標準入力の場合はNone)
+    /// * `data` - 読み込んだ内容
+    /// # Returns
+    /// * `Option<Format>` - 圧縮されている場合は圧縮形式、それ以外はNone
+    /// # Notes
+    /// * 拡張子がなくても展開できるように、先頭のバイト列、拡張子の順に判定する
+    pub fn detect(file: Option<&str>, data: &[u8]) -> Option<Self> {
+        Self::from_magic(data).or_else(|| Self::from_extension(file?))
+    }
+
+    /// 先頭のバイト列から圧縮形式を判定する
+    fn from_magic(data: &[u8]) -> Option<Self> {
+        Self::FORMATS
+            .iter()
+            .find(|(_, magic, _)| data.starts_with(magic))
+            .map(|(format, _, _)| *format)
+    }
+
+    /// 拡張子から圧縮形式を判定する
+    fn from_extension(file: &str) -> Option<Self> {
+        let extension = std::path::Path::new(file).extension()?.to_str()?;
+        Self::FORMATS
+            .iter()
+            .find(|(_, _, extensions)| extensions.contains(&extension))
+            .map(|(format, _, _)| *format)
+    }
+
+    /// エラーなどに表示する名前
+    fn name(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+            Self::Bzip2 => "bzip2",
+            Self::Xz => "xz",
+        }
+    }
+}
+
+/// 圧縮されている場合は展開する
+/// # Arguments
+/// * `file` - ファイル名(標準入力の場合はNone)
+/// * `data` - 読み込んだ内容
+/// # Returns
+/// * `std::io::Result<Vec<u8>>` - 展開した内容(圧縮されていない場合はそのまま)
+/// # Notes
+/// * 複数つなげた圧縮データは、つなげて展開する
+/// * 拡張子が圧縮形式で展開できない場合は、`InvalidData`のエラーにする
+/// * 先頭のバイト列だけが一致した場合は、たまたま一致した文字列かもしれないので、展開できなければそのまま返す
+pub fn decompress(file: Option<&str>, data: Vec<u8>) -> std::io::Result<Vec<u8>> {
+    let Some(format) = Format::detect(file, &data) else {
+        return Ok(data);
+    };
+
+    let mut contents = Vec::new();
+    let result = match format {
+        Format::Gzip => flate2::read::MultiGzDecoder::new(&data[..])
+            .read_to_end(&mut contents)
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        Format::Zstd => {
+            let mut input = &data[..];
+            let mut result = Ok(());
+            while result.is_ok() && !input.is_empty() {
+                result = ruzstd::StreamingDecoder::new(&mut input)
+                    .map_err(|e| e.to_string())
+                    .and_then(|mut decoder| {
+                        decoder
+                            .read_to_end(&mut contents)
+                            .map(|_| ())
+                            .map_err(|e| e.to_string())
+                    });
+            }
+            result
+        }
+        Format::Bzip2 => bzip2::read::MultiBzDecoder::new(&data[..])
+            .read_to_end(&mut contents)
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        Format::Xz => {
+            lzma_rs::xz_decompress(&mut &data[..], &mut contents).map_err(|e| e.to_string())
+        }
+    };
+
+    match result {
+        Ok(()) => Ok(contents),
+        Err(_) if file.and_then(Format::from_extension) != Some(format) => Ok(data),
+        Err(e) => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Cannot decompress {} data: {}", format.name(), e),
+        )),
+    }
+}
+
+/// 圧縮形式の拡張子を取り除いたファイル名を取得する
+/// # Arguments
+/// * `file` - ファイル名
+/// # Notes
+/// * `main.rs.gz`を`main.rs`として、展開した内容の種類を判定するために使う
+pub fn strip_extension(file: &str) -> &str {
+    Format::FORMATS
+        .iter()
+        .flat_map(|(_, _, extensions)| extensions.iter())
+        .find_map(|extension| {
+            file.strip_suffix(extension)
+                .and_then(|file| file.strip_suffix('.'))
+        })
+        .unwrap_or(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decompress() {
+        let mut data = Vec::new();
+        for _ in 0..2 {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            std::io::Write::write_all(&mut encoder, b"line\n").unwrap();
+            data.extend(encoder.finish().unwrap());
+        }
+        assert_eq!(Format::detect(None, &data), Some(Format::Gzip));
+        assert_eq!(decompress(None, data).unwrap(), b"line\nline\n");
+
+        // 圧縮されていない場合や、先頭がたまたま一致しただけの場合はそのまま
+        assert_eq!(decompress(Some("a.txt"), b"a\n".to_vec()).unwrap(), b"a\n");
+        assert_eq!(decompress(None, b"BZh?\n".to_vec()).unwrap(), b"BZh?\n");
+
+        // 拡張子が圧縮形式なのに展開できない場合はエラー
+        assert_eq!(
+            Format::detect(Some("app.log.zst"), b"a\n"),
+            Some(Format::Zstd)
+        );
+        assert_eq!(
+            decompress(Some("app.log.xz"), b"a\n".to_vec())
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::InvalidData
+        );
+
+        assert_eq!(strip_extension("src/main.rs.gz"), "src/main.rs");
+        assert_eq!(strip_extension("app.log"), "app.log");
+        assert_eq!(strip_extension("gz"), "gz");
+    }
+}
@@ -0,0 +1,894 @@
+use std::ops::Range;
+
+use unicode_width::UnicodeWidthChar;
+
+use crate::style::{Highlight, Style, Theme};
+
+/// unified形式の差分の、ファイルと差分のまとまり(hunk)の位置
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiffIndex {
+    /// ファイルの見出し(`diff --git`や`---`)の行番号(1から始まる)
+    pub files: Vec<usize>,
+    /// 差分のまとまりの見出し(`@@`)の行番号と、そのまとまりの最後の行番号
+    pub hunks: Vec<(usize, usize)>,
+}
+
+impl DiffIndex {
+    /// unified形式の差分を解析する
+    /// # Arguments
+    /// * `s` - 文字列(`git diff`の色付きの出力でもよい)
+    /// # Returns
+    /// * `Option<DiffIndex>` - unified形式の差分の場合はDiffIndex、それ以外はNone
+    pub fn parse(s: &str) -> Option<Self> {
+        let lines: Vec<String> = s.lines().map(strip_sgr).collect();
+
+        let mut index = Self::default();
+        // `diff --git`の見出しの後で、まだ差分のまとまりがないか
+        let mut in_git_header = false;
+        for (i, line) in lines.iter().enumerate() {
+            let line_number = i + 1;
+
+            // `diff --git`の見出しに続く`---`と`+++`は、同じファイルの見出しの一部
+            let is_file_header = line.starts_with("diff ")
+                || (line.starts_with("--- ")
+                    && !in_git_header
+                    && lines.get(i + 1).is_some_and(|l| l.starts_with("+++ ")));
+
+            if is_file_header {
+                index.close_hunk(line_number - 1);
+                index.files.push(line_number);
+                in_git_header = line.starts_with("diff ");
+            } else if line.starts_with("@@ ") {
+                index.close_hunk(line_number - 1);
+                index.hunks.push((line_number, usize::MAX));
+                in_git_header = false;
+            } else if line.starts_with("commit ") {
+                // `git log -p`のコミットの見出しは、差分のまとまりに含めない
+                index.close_hunk(line_number - 1);
+            }
+        }
+        index.close_hunk(lines.len());
+
+        (!index.hunks.is_empty()).then_some(index)
+    }
+
+    /// 最後の差分のまとまりの終わりを設定する
+    fn close_hunk(&mut self, end: usize) {
+        if let Some(hunk) = self.hunks.last_mut().filter(|(_, e)| *e == usize::MAX) {
+            hunk.1 = end;
+        }
+    }
+
+    /// 次の見出しの行番号を取得する
+    /// # Arguments
+    /// * `headers` - 見出しの行番号(昇順)
+    /// * `current` - 現在の行番号
+    pub fn next(headers: &[usize], current: usize) -> Option<usize> {
+        headers.iter().copied().find(|&line| line > current)
+    }
+
+    /// 前の見出しの行番号を取得する
+    /// # Arguments
+    /// * `headers` - 見出しの行番号(昇順)
+    /// * `current` - 現在の行番号
+    pub fn previous(headers: &[usize], current: usize) -> Option<usize> {
+        headers.iter().copied().rfind(|&line| line < current)
+    }
+
+    /// 差分のまとまりの見出しの行番号を取得する
+    pub fn hunk_headers(&self) -> Vec<usize> {
+        self.hunks.iter().map(|&(header, _)| header).collect()
+    }
+}
+
+/// SGR(文字色などを指定するエスケープシーケンス)を取り除く
+fn strip_sgr(line: &str) -> String {
+    let mut result = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find("\x1b[") {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find(|c: char| !(c.is_ascii_digit() || c == ';')) {
+            Some(end) if after[end..].starts_with('m') => rest = &after[end + 1..],
+            _ => {
+                result.push_str("\x1b[");
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+
+    result
+}
+
+/// 差分の追加した行と削除した行を強調表示する
+/// # Arguments
+/// * `s` - unified形式の差分
+/// * `theme` - 追加した行と削除した行の装飾
+/// # Returns
+/// * `Vec<Highlight>` - 強調表示する範囲
+/// # Notes
+/// * すでにエスケープシーケンスで色が付いている行は、そのままにする
+pub fn highlights(s: &str, theme: &Theme) -> Vec<Highlight> {
+    let mut highlights = Vec::new();
+    let mut offset = 0;
+    for line in s.split_inclusive('\n') {
+        let text = line.trim_end_matches(['\n', '\r']);
+        let style = if text.contains('\x1b') || text.starts_with("+++ ") || text.starts_with("--- ")
+        {
+            None
+        } else if text.starts_with('+') {
+            Some(theme.diff_added)
+        } else if text.starts_with('-') {
+            Some(theme.diff_removed)
+        } else if text.starts_with("@@ ") {
+            Some(theme.diff_modified)
+        } else {
+            None
+        };
+
+        if let Some(style) = style.filter(|_| !text.is_empty()) {
+            highlights.push(Highlight {
+                range: offset..offset + text.len(),
+                style,
+            });
+        }
+        offset += line.len();
+    }
+
+    highlights
+}
+
+/// 2つの列を比べたときの、要素ごとの操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    /// 両方にある
+    Equal,
+    /// 古い列にだけある
+    Delete,
+    /// 新しい列にだけある
+    Insert,
+}
+
+/// 2つの列の差分を計算する
+/// # Arguments
+/// * `old` - 古い列
+/// * `new` - 新しい列
+/// # Returns
+/// * `Vec<Edit>` - 先頭から順に、古い列を新しい列に変える操作
+/// # Notes
+/// * Myersのアルゴリズムで、操作の数が最も少ない差分を求める
+pub fn edits<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Edit> {
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    let offset = n + m + 1;
+
+    // 対角線kごとに、到達した古い列の位置
+    // 各ステップの開始時の-(d+1)..=d+1の範囲を、後から経路をたどるために保持しておく
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    'search: for d in 0..=n + m {
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let i = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+                v[i + 1]
+            } else {
+                v[i - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[i] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    // 終点から始点に向かって経路をたどる
+    let mut result = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let get = |k: isize| v[(k + d + 1) as usize];
+        let previous_k = if k == -d || (k != d && get(k - 1) < get(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = get(previous_k);
+        let previous_y = previous_x - previous_k;
+
+        while x > previous_x && y > previous_y {
+            result.push(Edit::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            result.push(if x == previous_x {
+                Edit::Insert
+            } else {
+                Edit::Delete
+            });
+        }
+        x = previous_x;
+        y = previous_y;
+    }
+    result.reverse();
+
+    result
+}
+
+/// 2つのファイルの差分を表示するための情報
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileDiff {
+    /// 差分を表示する文字列
+    pub text: String,
+    /// 追加した行と削除した行の強調表示
+    pub highlights: Vec<Highlight>,
+    /// 各行の、古いファイルと新しいファイルでの行番号
+    pub line_numbers: Vec<String>,
+}
+
+/// 装飾した文字列の断片
+type Piece = (String, Option<Style>);
+
+/// 行の中の、変更した部分の範囲
+type Ranges = Vec<Range<usize>>;
+
+impl FileDiff {
+    /// 装飾した断片をつなげた1行を追加する
+    fn push(&mut self, pieces: &[Piece]) {
+        for (text, style) in pieces {
+            if let Some(style) = style.filter(|_| !text.is_empty()) {
+                let start = self.text.len();
+                match self.highlights.last_mut() {
+                    // 同じ装飾が続く場合は、ひとつの範囲にまとめる
+                    Some(last) if last.range.end == start && last.style == style => {
+                        last.range.end += text.len();
+                    }
+                    _ => self.highlights.push(Highlight {
+                        range: start..start + text.len(),
+                        style,
+                    }),
+                }
+            }
+            self.text.push_str(text);
+        }
+        self.text.push('\n');
+    }
+}
+
+/// 差分のまとまりの前後に表示する、変更のない行の数
+const CONTEXT_LINES: usize = 3;
+
+/// 2つのファイルの行ごとの差分
+struct LineDiff<'a> {
+    old_lines: Vec<&'a str>,
+    new_lines: Vec<&'a str>,
+    /// 各操作と、古いファイルと新しいファイルでの位置(0から始まる)
+    operations: Vec<(Edit, usize, usize)>,
+    /// 各操作の行の中で、変更した単語の範囲
+    words: Vec<Ranges>,
+}
+
+impl<'a> LineDiff<'a> {
+    fn new(old_lines: Vec<&'a str>, new_lines: Vec<&'a str>) -> Self {
+        let mut operations = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        for edit in edits(&old_lines, &new_lines) {
+            operations.push((edit, i, j));
+            match edit {
+                Edit::Equal => {
+                    i += 1;
+                    j += 1;
+                }
+                Edit::Delete => i += 1,
+                Edit::Insert => j += 1,
+            }
+        }
+
+        // 連続して変更した行は、削除した行と追加した行を順に組にして単語の差分を計算する
+        let mut words = vec![Vec::new(); operations.len()];
+        for block in change_blocks(&operations) {
+            let (deleted, inserted) = Self::split_block(&operations, block);
+            for (&d, &n) in deleted.iter().zip(&inserted) {
+                let old = old_lines[operations[d].1];
+                let new = new_lines[operations[n].2];
+                if let Some((old_words, new_words)) = changed_words(old, new) {
+                    words[d] = old_words;
+                    words[n] = new_words;
+                }
+            }
+        }
+
+        Self {
+            old_lines,
+            new_lines,
+            operations,
+            words,
+        }
+    }
+
+    /// 変更した行のまとまりを、削除した行の操作と追加した行の操作に分ける
+    fn split_block(
+        operations: &[(Edit, usize, usize)],
+        block: Range<usize>,
+    ) -> (Vec<usize>, Vec<usize>) {
+        block.partition(|&i| operations[i].0 == Edit::Delete)
+    }
+}
+
+/// 2つのファイルの差分を計算する
+/// # Arguments
+/// * `old` - 古いファイルの内容
+/// * `new` - 新しいファイルの内容
+/// * `old_name` - 古いファイルの名前
+/// * `new_name` - 新しいファイルの名前
+/// * `theme` - 追加した行と削除した行の装飾
+/// # Returns
+/// * `FileDiff` - unified形式の差分と、両方のファイルでの行番号
+pub fn compare(old: &str, new: &str, old_name: &str, new_name: &str, theme: &Theme) -> FileDiff {
+    let line_diff = LineDiff::new(old.lines().collect(), new.lines().collect());
+    let operations = &line_diff.operations;
+
+    let mut diff = FileDiff::default();
+    let width = line_diff
+        .old_lines
+        .len()
+        .max(line_diff.new_lines.len())
+        .to_string()
+        .len();
+    for header in [format!("--- {}", old_name), format!("+++ {}", new_name)] {
+        diff.push(&[(header, None)]);
+        diff.line_numbers.push(String::new());
+    }
+
+    for hunk in hunk_ranges(operations) {
+        diff.push(&[(
+            hunk_header(&operations[hunk.clone()]),
+            Some(theme.diff_modified),
+        )]);
+        diff.line_numbers.push(String::new());
+
+        for k in hunk {
+            let (edit, i, j) = operations[k];
+            let (marker, line, style, word_style) = match edit {
+                Edit::Equal => (' ', line_diff.old_lines[i], None, None),
+                Edit::Delete => (
+                    '-',
+                    line_diff.old_lines[i],
+                    Some(theme.diff_removed),
+                    Some(theme.diff_removed_word),
+                ),
+                Edit::Insert => (
+                    '+',
+                    line_diff.new_lines[j],
+                    Some(theme.diff_added),
+                    Some(theme.diff_added_word),
+                ),
+            };
+            let mut row = vec![(marker.to_string(), style)];
+            row.extend(pieces(line, style, &line_diff.words[k], word_style));
+            diff.push(&row);
+
+            let old_number = (edit != Edit::Insert).then(|| (i + 1).to_string());
+            let new_number = (edit != Edit::Delete).then(|| (j + 1).to_string());
+            diff.line_numbers.push(format!(
+                "{:>width$} {:>width$}",
+                old_number.unwrap_or_default(),
+                new_number.unwrap_or_default()
+            ));
+        }
+    }
+
+    diff
+}
+
+/// 2つのファイルの差分を、古いファイルと新しいファイルを左右に並べて表示する
+/// # Arguments
+/// * `old` - 古いファイルの内容
+/// * `new` - 新しいファイルの内容
+/// * `old_name` - 古いファイルの名前
+/// * `new_name` - 新しいファイルの名前
+/// * `width` - 表示する領域の横幅
+/// * `tab_width` - タブ文字を展開する幅
+/// * `theme` - 追加した行と削除した行の装飾
+/// # Returns
+/// * `FileDiff` - 左右に並べた差分(行番号は各行の文字列に含める)
+/// # Notes
+/// * 左右それぞれの領域に収まらない行は、その領域の中で折り返す
+/// * 左右の位置がずれないように、タブ文字は空白に展開する
+pub fn side_by_side(
+    old: &str,
+    new: &str,
+    old_name: &str,
+    new_name: &str,
+    width: usize,
+    tab_width: usize,
+    theme: &Theme,
+) -> FileDiff {
+    let old_lines: Vec<String> = old.lines().map(|l| expand_tabs(l, tab_width)).collect();
+    let new_lines: Vec<String> = new.lines().map(|l| expand_tabs(l, tab_width)).collect();
+    let line_diff = LineDiff::new(
+        old_lines.iter().map(String::as_str).collect(),
+        new_lines.iter().map(String::as_str).collect(),
+    );
+    let operations = &line_diff.operations;
+
+    let mut diff = FileDiff::default();
+    for header in [format!("--- {}", old_name), format!("+++ {}", new_name)] {
+        diff.push(&[(header, None)]);
+    }
+
+    // 行番号と区切りの` │ `を除いた幅を、左右で半分ずつ使う
+    let number_width = old_lines.len().max(new_lines.len()).to_string().len();
+    let pane_width = (width.saturating_sub(2 * (number_width + 1) + 3) / 2).max(1);
+
+    // 左右の片方の、行番号と行の内容
+    let side = |k: Option<usize>, old_side: bool| -> (String, Vec<Piece>) {
+        let Some(k) = k else {
+            return (String::new(), Vec::new());
+        };
+        let (_, i, j) = operations[k];
+        let (number, line, style, word_style) = match (operations[k].0, old_side) {
+            (Edit::Equal, true) => (i, line_diff.old_lines[i], None, None),
+            (Edit::Equal, false) => (j, line_diff.new_lines[j], None, None),
+            (Edit::Delete, _) => (
+                i,
+                line_diff.old_lines[i],
+                Some(theme.diff_removed),
+                Some(theme.diff_removed_word),
+            ),
+            (Edit::Insert, _) => (
+                j,
+                line_diff.new_lines[j],
+                Some(theme.diff_added),
+                Some(theme.diff_added_word),
+            ),
+        };
+        let pieces = pieces(line, style, &line_diff.words[k], word_style);
+        ((number + 1).to_string(), pieces)
+    };
+
+    for hunk in hunk_ranges(operations) {
+        diff.push(&[(
+            hunk_header(&operations[hunk.clone()]),
+            Some(theme.diff_modified),
+        )]);
+
+        // 変更のない行は左右に同じ行を、変更した行は削除した行と追加した行を組にして並べる
+        let mut rows: Vec<(Option<usize>, Option<usize>)> = Vec::new();
+        let mut k = hunk.start;
+        while k < hunk.end {
+            if operations[k].0 == Edit::Equal {
+                rows.push((Some(k), Some(k)));
+                k += 1;
+                continue;
+            }
+            let end = (k..hunk.end)
+                .find(|&i| operations[i].0 == Edit::Equal)
+                .unwrap_or(hunk.end);
+            let (deleted, inserted) = LineDiff::split_block(operations, k..end);
+            for n in 0..deleted.len().max(inserted.len()) {
+                rows.push((deleted.get(n).copied(), inserted.get(n).copied()));
+            }
+            k = end;
+        }
+
+        for (left, right) in rows {
+            let (left_number, left_pieces) = side(left, true);
+            let (right_number, right_pieces) = side(right, false);
+            let left_rows = wrap(&left_pieces, pane_width);
+            let right_rows = wrap(&right_pieces, pane_width);
+
+            for n in 0..left_rows.len().max(right_rows.len()) {
+                let number = |number: &str| {
+                    let number = if n == 0 { number } else { "" };
+                    (
+                        format!("{:>number_width$} ", number),
+                        Some(theme.line_number),
+                    )
+                };
+                let (left_row, left_width) = left_rows.get(n).cloned().unwrap_or_default();
+                let mut row = vec![number(&left_number)];
+                row.extend(left_row);
+                row.push((" ".repeat(pane_width - left_width), None));
+                row.push((" │ ".to_string(), Some(theme.line_number)));
+                row.push(number(&right_number));
+                row.extend(right_rows.get(n).cloned().unwrap_or_default().0);
+                diff.push(&row);
+            }
+        }
+    }
+
+    diff
+}
+
+/// 変更した行が連続している範囲を取得する
+fn change_blocks(operations: &[(Edit, usize, usize)]) -> Vec<Range<usize>> {
+    let mut blocks: Vec<Range<usize>> = Vec::new();
+    for (i, (edit, _, _)) in operations.iter().enumerate() {
+        if *edit == Edit::Equal {
+            continue;
+        }
+        match blocks.last_mut() {
+            Some(last) if last.end == i => last.end = i + 1,
+            _ => blocks.push(i..i + 1),
+        }
+    }
+
+    blocks
+}
+
+/// 変更のある操作を、前後の変更のない行を含めて差分のまとまりに分ける
+/// # Returns
+/// * `Vec<Range<usize>>` - 各まとまりの操作の範囲
+fn hunk_ranges(operations: &[(Edit, usize, usize)]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for block in change_blocks(operations) {
+        let start = block.start.saturating_sub(CONTEXT_LINES);
+        let end = (block.end + CONTEXT_LINES).min(operations.len());
+        match ranges.last_mut() {
+            Some(last) if last.end >= start => last.end = end,
+            _ => ranges.push(start..end),
+        }
+    }
+
+    ranges
+}
+
+/// 差分のまとまりの見出し(`@@ -1,3 +1,4 @@`)を作成する
+fn hunk_header(hunk: &[(Edit, usize, usize)]) -> String {
+    let (_, old_start, new_start) = hunk[0];
+    let old_count = hunk.iter().filter(|(e, _, _)| *e != Edit::Insert).count();
+    let new_count = hunk.iter().filter(|(e, _, _)| *e != Edit::Delete).count();
+    format!(
+        "@@ -{} +{} @@",
+        hunk_range(old_start, old_count),
+        hunk_range(new_start, new_count)
+    )
+}
+
+/// 差分のまとまりの見出しに表示する範囲を取得する
+/// # Arguments
+/// * `start` - 範囲の最初の行の位置(0から始まる)
+/// * `count` - 範囲の行数
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        // 行がない場合は、直前の行の番号を表示する
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+/// 単語の差分を計算するために、行を単語と空白、記号に分ける
+/// # Returns
+/// * `Vec<(usize, &str)>` - 各部分の行の中での位置と文字列
+fn words(line: &str) -> Vec<(usize, &str)> {
+    // 英数字と`_`の並び、空白の並びはまとめ、それ以外の文字は1文字ずつ分ける
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            Some(0)
+        } else if c.is_whitespace() {
+            Some(1)
+        } else {
+            None
+        }
+    };
+
+    let mut result: Vec<(usize, &str)> = Vec::new();
+    let mut previous = None;
+    for (i, c) in line.char_indices() {
+        match result.last_mut() {
+            Some((start, word)) if class(c).is_some() && class(c) == previous => {
+                *word = &line[*start..i + c.len_utf8()];
+            }
+            _ => result.push((i, &line[i..i + c.len_utf8()])),
+        }
+        previous = class(c);
+    }
+
+    result
+}
+
+/// 削除した行と追加した行で、変更した単語の範囲を取得する
+/// # Arguments
+/// * `old` - 削除した行
+/// * `new` - 追加した行
+/// # Returns
+/// * `Option<(Ranges, Ranges)>` - 削除した行と追加した行の、変更した部分の範囲
+/// # Notes
+/// * 空白以外に共通する部分がない場合は、行全体を変更したものとしてNoneを返す
+fn changed_words(old: &str, new: &str) -> Option<(Ranges, Ranges)> {
+    let old_words = words(old);
+    let new_words = words(new);
+    let old_texts: Vec<&str> = old_words.iter().map(|(_, w)| *w).collect();
+    let new_texts: Vec<&str> = new_words.iter().map(|(_, w)| *w).collect();
+
+    let mut old_ranges: Ranges = Vec::new();
+    let mut new_ranges: Ranges = Vec::new();
+    let mut has_common = false;
+    let (mut i, mut j) = (0, 0);
+    for edit in edits(&old_texts, &new_texts) {
+        let (ranges, (start, word), index) = match edit {
+            Edit::Equal => {
+                has_common |= !old_texts[i].trim().is_empty();
+                i += 1;
+                j += 1;
+                continue;
+            }
+            Edit::Delete => (&mut old_ranges, old_words[i], &mut i),
+            Edit::Insert => (&mut new_ranges, new_words[j], &mut j),
+        };
+        *index += 1;
+        match ranges.last_mut() {
+            Some(last) if last.end == start => last.end = start + word.len(),
+            _ => ranges.push(start..start + word.len()),
+        }
+    }
+
+    has_common.then_some((old_ranges, new_ranges))
+}
+
+/// 行を、変更した単語とそれ以外の部分に分けて装飾する
+fn pieces(
+    line: &str,
+    style: Option<Style>,
+    words: &[Range<usize>],
+    word_style: Option<Style>,
+) -> Vec<Piece> {
+    let mut result = Vec::new();
+    let mut position = 0;
+    for range in words {
+        result.push((line[position..range.start].to_string(), style));
+        result.push((line[range.clone()].to_string(), word_style));
+        position = range.end;
+    }
+    result.push((line[position..].to_string(), style));
+    result.retain(|(text, _)| !text.is_empty());
+
+    result
+}
+
+/// 装飾した断片を、指定した幅で折り返す
+/// # Returns
+/// * `Vec<(Vec<Piece>, usize)>` - 折り返した各行の断片と、その表示幅(空の場合も1行になる)
+fn wrap(pieces: &[Piece], width: usize) -> Vec<(Vec<Piece>, usize)> {
+    let mut rows: Vec<(Vec<Piece>, usize)> = vec![(Vec::new(), 0)];
+    for (text, style) in pieces {
+        for c in text.chars() {
+            let c_width = c.width().unwrap_or(0);
+            if rows
+                .last()
+                .is_some_and(|(_, w)| w + c_width > width && *w > 0)
+            {
+                rows.push((Vec::new(), 0));
+            }
+            let (row, row_width) = rows.last_mut().unwrap();
+            match row.last_mut() {
+                Some((last, last_style)) if last_style == style => last.push(c),
+                _ => row.push((c.to_string(), *style)),
+            }
+            *row_width += c_width;
+        }
+    }
+
+    rows
+}
+
+/// タブ文字を空白に展開する
+fn expand_tabs(line: &str, tab_width: usize) -> String {
+    let mut result = String::new();
+    let mut column = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = tab_width - column % tab_width;
+            result.push_str(&" ".repeat(spaces));
+            column += spaces;
+        } else {
+            result.push(c);
+            column += c.width().unwrap_or(0);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GIT_DIFF: &str = "\
+diff --git a/a.txt b/a.txt
+index 0000000..1111111 100644
+--- a/a.txt
++++ b/a.txt
+@@ -1 +1 @@
+-a
++A
+@@ -5 +5 @@
+-e
++E
+diff --git a/b.txt b/b.txt
+--- a/b.txt
++++ b/b.txt
+@@ -1 +1 @@
+-b
++B
+";
+
+    #[test]
+    fn test_parse() {
+        let index = DiffIndex::parse(GIT_DIFF).unwrap();
+        assert_eq!(index.files, vec![1, 11]);
+        assert_eq!(index.hunks, vec![(5, 7), (8, 10), (14, 16)]);
+
+        // 色付きの出力でも解析できる
+        let colored = GIT_DIFF
+            .replace("diff --git", "\x1b[1mdiff --git")
+            .replace("@@ -", "\x1b[36m@@ -");
+        assert_eq!(DiffIndex::parse(&colored), Some(index));
+
+        assert_eq!(DiffIndex::parse("hello\n--- a\n"), None);
+    }
+
+    #[test]
+    /// 65535行より多い差分の場合
+    /// 行番号が溢れずに、最後の差分のまとまりの位置を求められる
+    fn test_parse_many_lines() {
+        let mut diff = format!("--- a\n+++ b\n@@ -1 +1,70000 @@\n{}", "+a\n".repeat(70000));
+        diff.push_str("@@ -2 +70002 @@\n-b\n+c\n");
+        let index = DiffIndex::parse(&diff).unwrap();
+        assert_eq!(index.hunks, vec![(3, 70003), (70004, 70006)]);
+    }
+
+    #[test]
+    /// `diff -u`のように`diff --git`の見出しがない場合
+    fn test_parse_plain_unified_diff() {
+        let diff = "--- a.txt\n+++ b.txt\n@@ -1 +1 @@\n-a\n+b\n--- c.txt\n+++ d.txt\n@@ -1 +1 @@\n-c\n+d\n";
+        let index = DiffIndex::parse(diff).unwrap();
+        assert_eq!(index.files, vec![1, 6]);
+        assert_eq!(index.hunks, vec![(3, 5), (8, 10)]);
+    }
+
+    #[test]
+    fn test_next_previous() {
+        let headers = [5, 8, 14];
+        assert_eq!(DiffIndex::next(&headers, 1), Some(5));
+        assert_eq!(DiffIndex::next(&headers, 5), Some(8));
+        assert_eq!(DiffIndex::next(&headers, 14), None);
+        assert_eq!(DiffIndex::previous(&headers, 8), Some(5));
+        assert_eq!(DiffIndex::previous(&headers, 5), None);
+    }
+
+    #[test]
+    fn test_highlights() {
+        let theme = Theme::default();
+        let diff = "--- a\n+++ b\n@@ -1 +1 @@\n-x\n+y\n z\n";
+        let ranges: Vec<&str> = highlights(diff, &theme)
+            .iter()
+            .map(|h| &diff[h.range.clone()])
+            .collect();
+        assert_eq!(ranges, vec!["@@ -1 +1 @@", "-x", "+y"]);
+    }
+
+    #[test]
+    fn test_edits() {
+        use Edit::*;
+        assert_eq!(edits(&[1, 2, 3], &[1, 2, 3]), vec![Equal, Equal, Equal]);
+        assert_eq!(edits::<u8>(&[], &[]), vec![]);
+        assert_eq!(edits(&[1, 2], &[]), vec![Delete, Delete]);
+        assert_eq!(
+            edits(&['a', 'b', 'c'], &['a', 'x', 'c', 'd']),
+            vec![Equal, Delete, Insert, Equal, Insert]
+        );
+
+        // 操作を適用すると新しい列になる
+        let old: Vec<char> = "ABCABBA".chars().collect();
+        let new: Vec<char> = "CBABAC".chars().collect();
+        let result = edits(&old, &new);
+        assert_eq!(result.iter().filter(|e| **e != Equal).count(), 5);
+        let (mut i, mut applied) = (0, Vec::new());
+        let mut j = 0;
+        for edit in result {
+            match edit {
+                Equal => {
+                    applied.push(old[i]);
+                    i += 1;
+                    j += 1;
+                }
+                Delete => i += 1,
+                Insert => {
+                    applied.push(new[j]);
+                    j += 1;
+                }
+            }
+        }
+        assert_eq!(applied, new);
+    }
+
+    #[test]
+    fn test_compare() {
+        let theme = Theme::default();
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+        let new = "1\n2\n3\n4\n5\n6\n7\n8\nnine\n10\n11\n";
+        let diff = compare(old, new, "a", "b", &theme);
+        assert_eq!(
+            diff.text,
+            "--- a\n+++ b\n@@ -6,5 +6,6 @@\n 6\n 7\n 8\n-9\n+nine\n 10\n+11\n"
+        );
+        assert_eq!(
+            diff.line_numbers,
+            vec!["", "", "", " 6  6", " 7  7", " 8  8", " 9   ", "    9", "10 10", "   11"]
+        );
+        let ranges: Vec<&str> = diff
+            .highlights
+            .iter()
+            .map(|h| &diff.text[h.range.clone()])
+            .collect();
+        assert_eq!(ranges, vec!["@@ -6,5 +6,6 @@", "-9", "+nine", "+11"]);
+
+        // 差分のまとまりの間を移動できる
+        assert!(DiffIndex::parse(&diff.text).is_some());
+
+        // 同じ内容の場合は見出しだけ
+        assert_eq!(compare(old, old, "a", "b", &theme).text, "--- a\n+++ b\n");
+        assert_eq!(
+            compare("", "x\n", "a", "b", &theme).text,
+            "--- a\n+++ b\n@@ -0,0 +1 @@\n+x\n"
+        );
+    }
+
+    #[test]
+    fn test_changed_words() {
+        assert_eq!(
+            changed_words("let x = 1;", "let y = 10;"),
+            Some((vec![4..5, 8..9], vec![4..5, 8..10]))
+        );
+        // 共通する単語がない場合は、行全体を変更したものとする
+        assert_eq!(changed_words("abc", "x y"), None);
+    }
+
+    #[test]
+    fn test_side_by_side() {
+        let theme = Theme::default();
+        let old = "a\tb\nsame\nold line\n";
+        let new = "a\tb\nsame\nnew line that wraps\n";
+        let diff = side_by_side(old, new, "a", "b", 30, 4, &theme);
+        assert_eq!(
+            diff.text,
+            "--- a\n+++ b\n@@ -1,3 +1,3 @@\n\
+             1 a   b       │ 1 a   b\n\
+             2 same        │ 2 same\n\
+             3 old line    │ 3 new line th\n\
+             \x20             │   at wraps\n"
+        );
+        assert!(diff.line_numbers.is_empty());
+
+        // 変更した単語は行とは別に強調表示する
+        let styles: Vec<(&str, Style)> = diff
+            .highlights
+            .iter()
+            .filter(|h| h.style != theme.line_number)
+            .map(|h| (&diff.text[h.range.clone()], h.style))
+            .collect();
+        assert_eq!(
+            styles,
+            vec![
+                ("@@ -1,3 +1,3 @@", theme.diff_modified),
+                ("old", theme.diff_removed_word),
+                (" line", theme.diff_removed),
+                ("new", theme.diff_added_word),
+                (" line", theme.diff_added),
+                (" th", theme.diff_added_word),
+                ("at wraps", theme.diff_added_word),
+            ]
+        );
+    }
+}
@@ -0,0 +1,2983 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    io::{stdout, Write},
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crossterm::{
+    cursor::{Hide, Show},
+    event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    terminal::{
+        self, disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+    },
+};
+
+use crate::{
+    apply_plugin_effects, buffer, buffer_list, clipboard, command_line, config, diff, file_tree,
+    finder, get_contents, get_remote_contents, git, help, history, hooks, input, jump_list,
+    key_hint, keymap, layout, load_config, macros, marks, open_file, overlay, parse_command,
+    plugin, plugin_context, print_panes, print_tab_bar, prompt, registers, restore_region,
+    run_hook, save_cursor, screen, screen_regions, shell, status_bar, style, tab_bar,
+    update_follow_item, update_pane_item, update_status_items, watcher, word, Args,
+};
+
+/// 開いているファイルや表示しているものなど、起動してから終了するまで保持する状態
+/// # Notes
+/// * 押したキーなどに割り当てた操作は、それぞれのメソッドで行う
+pub struct Editor {
+    /// コマンドライン引数
+    pub args: Args,
+    /// 端末の横幅
+    pub term_width: u16,
+    /// 端末の高さ
+    pub term_height: u16,
+    /// 設定ファイルを変更したかを確認するために覚えておく、読み込んだときの更新日時
+    pub config_modified_time: Option<SystemTime>,
+    /// ステータスバー
+    pub status_bar: status_bar::StatusBar,
+    /// すべてのファイルに共通する表示の設定
+    pub settings: buffer::Settings,
+    /// 開いているファイル
+    pub buffers: Vec<buffer::Buffer>,
+    /// 操作しているファイルの位置
+    pub current: usize,
+    /// タブページごとの、エディタ領域の分割と各領域で表示しているファイルの位置
+    pub tabs: Vec<layout::Layout>,
+    /// 表示しているタブページの位置
+    pub current_tab: usize,
+    /// タブバー
+    pub tab_bar: tab_bar::TabBar,
+    /// キーと操作の対応
+    pub keymap: keymap::Keymap,
+    /// 設定ディレクトリから読み込んだプラグイン
+    pub plugins: plugin::Plugins,
+    /// ファイルを開いたときや書き込んだときなどに実行する、設定ファイルとプラグインで登録したコマンド
+    pub hooks: hooks::Hooks,
+    /// フックやプラグインで実行する、入力を待たずに順に実行するコマンド
+    pub queued_commands: VecDeque<String>,
+    /// 開いたときのフックを実行したバッファの数(バッファは取り除かないので、これより後ろが新しく開いたバッファ)
+    pub opened_buffers: usize,
+    /// nキーやNキーで検索する文字列
+    pub search_pattern: Option<String>,
+    /// 設定ファイルを読み込み直すか
+    pub reload: bool,
+    /// 開いているファイルを監視して、変更されたファイルを知らせる
+    pub file_watcher: watcher::FileWatcher,
+    /// 変更されたファイル
+    pub changed_files: HashSet<PathBuf>,
+    /// 操作しているファイルの末尾を追いかけているか(Ctrl+Cでやめる)
+    pub follow: bool,
+    /// コマンドラインの入力(:キーで開き、開いていない場合はNone)
+    pub command_line: Option<prompt::Prompt>,
+    /// 続けて押すキーを待っているキーの列
+    pub pending: Vec<keymap::KeyChord>,
+    /// 最後にキーを押した時刻
+    pub pending_since: Instant,
+    /// マクロとして記録したキー
+    pub macros: macros::Macros,
+    /// 次に押す文字のレジスタで行う、マクロの記録または再生
+    pub register_action: Option<keymap::Action>,
+    /// 先に押した数字による、マクロを再生する回数
+    pub count: Option<usize>,
+    /// 大きな移動をする前の位置(Ctrl+Oで戻り、Alt+Iで進む)
+    pub jumps: jump_list::JumpList,
+    /// クリップボード
+    pub clipboard: clipboard::Clipboard,
+    /// コピーした文字列
+    pub registers: registers::Registers,
+    /// 次にコピーする先に選んだレジスタ
+    pub yank_register: Option<char>,
+    /// 開いているファイルの一覧(表示していない場合はNone)
+    pub buffer_list: Option<buffer_list::BufferList>,
+    /// キーの割り当ての一覧(表示していない場合はNone)
+    pub help: Option<help::Help>,
+    /// 確認のダイアログと、はいと答えた場合に実行するコマンド(表示していない場合はNone)
+    pub confirm: Option<(overlay::Confirm, command_line::Command)>,
+    /// 一覧やダイアログを重ねて表示したことで隠れた部分
+    pub overlays: overlay::OverlayLayer,
+    /// ファイルを探して開くための一覧(表示していない場合はNone)
+    pub finder: Option<finder::Finder>,
+    /// エディタ領域の左に表示するファイルツリー(展開の状態は、隠しても保持する)
+    pub file_tree: file_tree::FileTree,
+    /// 最近開いたファイルと、それぞれの最後の表示位置
+    pub history: history::History,
+    /// 別のスレッドで読む入力
+    pub input: input::Input,
+    /// 端末で表示できる色数
+    pub color_depth: style::ColorDepth,
+    /// 代替画面に表示しているか
+    pub alternate_screen: bool,
+}
+
+impl Editor {
+    /// 終了する操作をするまで、入力を待って操作を行うことを繰り返す
+    pub async fn run(&mut self) -> std::io::Result<()> {
+        loop {
+            // 入力を待つまでの出力を、1つの画面の更新としてまとめて表示させる
+            screen::begin_frame()?;
+
+            // タブバーやファイルツリーを表示するかによって、エディタ領域の位置と大きさが変わる
+            let regions = screen_regions(
+                Some(&self.file_tree),
+                self.term_width,
+                self.term_height,
+                self.tabs.len(),
+            );
+
+            // 設定ファイルとテーマを読み込み直して、表示しているものすべてに反映する
+            if std::mem::take(&mut self.reload) {
+                self.reload_config(regions)?;
+            }
+
+            // 開いているファイルが変更された場合は、読み込み直す
+            let changed = std::mem::take(&mut self.changed_files);
+            if !changed.is_empty() {
+                self.reload_changed_files(changed, regions)?;
+            }
+
+            // 新しく開いたバッファごとに、開いたときのフックを実行する
+            if self.opened_buffers < self.buffers.len() {
+                self.run_open_hooks()?;
+            }
+
+            let buffer = &mut self.buffers[self.current];
+
+            // 操作する領域にだけ表示するので、その位置と大きさを求めておく
+            let area = regions.editor;
+            let rect = self.tabs[self.current_tab].focused_rect(area);
+
+            // スクロールを連動させるために、操作する前のスクロール位置を覚えておく
+            let previous = *self.tabs[self.current_tab].focused_pane();
+
+            // 操作する位置に端末のカーソルを表示する
+            // コマンドラインで入力している間はコマンドラインに表示し、一覧やダイアログを表示している間は隠す
+            if self.command_line.is_none() {
+                let caret = if self.buffer_list.is_none()
+                    && self.help.is_none()
+                    && self.finder.is_none()
+                    && self.confirm.is_none()
+                    && !self.file_tree.focused
+                {
+                    buffer.caret_screen_position(rect.x, rect.y, rect.width, rect.height)
+                } else {
+                    None
+                };
+                screen::set_cursor(caret);
+            }
+
+            // 入力を待つ間は、ここまでに出力した画面を表示させておく
+            screen::end_frame()?;
+
+            // 入力か、git blameの読み込みやファイルの変更か、待ち時間を過ぎるのを、まとめて待つ
+            let playing = self.macros.is_playing();
+            let mut timed_out = false;
+            let mut queued_command = None;
+            let wakeup = self.wait(playing).await?;
+
+            match wakeup {
+                Wakeup::Input => {}
+                Wakeup::Command(command) => queued_command = Some(command),
+                // 待ち時間を過ぎた場合は、最後に押したキーをもう一度押したものとして扱う
+                Wakeup::SequenceTimeout => timed_out = true,
+                Wakeup::BlameLoaded(result) => {
+                    self.show_blame(result, regions)?;
+                    continue;
+                }
+                // 表示をやめる時刻を過ぎたメッセージは消して、ステータスバーの項目の表示に戻す
+                Wakeup::MessageExpired => {
+                    self.status_bar.clear_expired_message();
+                    if self.command_line.is_none() {
+                        self.status_bar.print(&self.buffers[self.current]);
+                        screen::flush()?;
+                    }
+                    continue;
+                }
+                Wakeup::FilesChanged(files) => {
+                    self.changed_files = files;
+                    continue;
+                }
+                Wakeup::Tick => {
+                    self.reload = self.settings.config.watch
+                        && config::Config::modified_time() != self.config_modified_time;
+                    self.changed_files = self.file_watcher.changed();
+                    if self.follow {
+                        self.changed_files.extend(
+                            self.buffers[self.current]
+                                .file
+                                .as_ref()
+                                .and_then(|file| std::fs::canonicalize(file).ok()),
+                        );
+                    }
+                    continue;
+                }
+            }
+
+            // 待ち時間を過ぎた場合は、最後に押したキーをもう一度押したものとして扱う
+            // マクロを再生している場合は、記録したキーを押したものとして扱う
+            // フックなどで実行するコマンドの場合は、端末のイベントを読まない(None)
+            let event = match self.pending.last() {
+                _ if queued_command.is_some() => None,
+                Some(chord) if timed_out => {
+                    Some(Event::Key(KeyEvent::new(chord.code, chord.modifiers)))
+                }
+                _ => Some(match self.macros.next_key() {
+                    Some(key) => Event::Key(key),
+                    None => self.input.read().await?,
+                }),
+            };
+            screen::begin_frame()?;
+            let was_pending = !self.pending.is_empty();
+
+            // キーを押したときのフックを実行する
+            // 待ち時間を過ぎて、もう一度押したものとして扱うキーは除く
+            if let Some(Event::Key(key)) = &event {
+                if !timed_out
+                    && run_hook(
+                        hooks::Hook::OnKey,
+                        &keymap::KeyChord::from(key).to_string(),
+                        &self.buffers[self.current],
+                        &self.hooks,
+                        &mut self.plugins,
+                        &mut self.status_bar,
+                        &mut self.queued_commands,
+                    )
+                {
+                    self.status_bar.print(&self.buffers[self.current]);
+                    screen::flush()?;
+                }
+            }
+
+            let had_count = self.count.is_some();
+
+            // 押したキーに割り当てた操作を求める
+            let resolved = match &event {
+                Some(Event::Key(key)) => self.resolve_key(key, timed_out, regions)?,
+                _ => ResolvedKey::default(),
+            };
+            let mut command = resolved.command;
+
+            // 押したキーの列に割り当てたコマンドか、フックなどで実行するコマンド(`:`に続けて入力する文字列)
+            let bound_command = resolved.bound_command.or(queued_command);
+
+            // キーの列に割り当てたコマンドなどは、コマンドラインで入力した場合と同じように行う
+            let action = match bound_command.map(|line| parse_command(&line, &self.plugins)) {
+                Some(Ok(command_line::Command::Action(action))) => Some(action),
+                Some(Ok(parsed)) => {
+                    command = Some(parsed);
+                    None
+                }
+                Some(Err(e)) => {
+                    self.status_bar.set_message(prompt::Message::error(e));
+                    self.status_bar.print(&self.buffers[self.current]);
+                    screen::flush()?;
+                    None
+                }
+                None => resolved.action,
+            };
+
+            // 続けて押すキーを待っている間は、それまでに押したキーをステータスバーに表示し、
+            // 続けて押せるキーと割り当てた操作の一覧をエディタ領域の下端に重ねて表示する
+            if !self.pending.is_empty() {
+                let keys = keymap::KeySequence(self.pending.clone());
+                let hint = key_hint::KeyHint::new(&keys, &self.keymap.continuations(&self.pending));
+                self.overlays.show(&hint, regions.overlay());
+
+                self.status_bar.add_item(status_bar::StatusBarItem::new(
+                    "keys".to_string(),
+                    keys.to_string(),
+                ));
+                self.status_bar.print(&self.buffers[self.current]);
+                screen::flush()?;
+            } else if was_pending {
+                // 一覧に隠れていた部分だけを表示し直す
+                if let Some(region) = self.overlays.take_damaged() {
+                    restore_region(
+                        &mut self.buffers,
+                        &mut self.tabs[self.current_tab],
+                        &self.file_tree,
+                        regions,
+                        region,
+                        &self.settings.options,
+                    )?;
+                }
+
+                self.status_bar.remove_item("keys");
+                self.status_bar.print(&self.buffers[self.current]);
+                screen::flush()?;
+            }
+            let buffer = &mut self.buffers[self.current];
+
+            // マクロを記録している場合は、押したキーを加える
+            // マクロの記録や再生に使ったキーと、待ち時間を過ぎたときにもう一度押したものとして扱うキーは加えない
+            let macro_key = resolved.macro_key
+                || matches!(
+                    action,
+                    Some(keymap::Action::RecordMacro | keymap::Action::PlayMacro)
+                );
+            if let Some(Event::Key(key)) = &event {
+                if !macro_key && !timed_out {
+                    self.macros.record(*key);
+                }
+            }
+
+            // マクロを再生する回数は、続けて再生する場合だけ残す
+            if !macro_key {
+                self.count = None;
+            }
+            match self.count {
+                Some(count) => self.status_bar.add_item(status_bar::StatusBarItem::new(
+                    "count".to_string(),
+                    count.to_string(),
+                )),
+                None => self.status_bar.remove_item("count"),
+            }
+            if macro_key || had_count != self.count.is_some() {
+                self.status_bar.print(buffer);
+                screen::flush()?;
+            }
+
+            // 同じスクロールのキーを続けて押した場合は(押し続けた場合など)、まとめて1回でスクロールして表示する
+            // 続けて押すキーの列で決まった操作や、一覧などを表示している場合は、押したキーごとに処理する
+            let mut repeat: u16 = 1;
+            if let Some(Event::Key(key)) = &event {
+                if !timed_out
+                    && !playing
+                    && !was_pending
+                    && self.pending.is_empty()
+                    && !macro_key
+                    && matches!(
+                        action,
+                        Some(
+                            keymap::Action::ScrollUp
+                                | keymap::Action::ScrollDown
+                                | keymap::Action::ScrollLeft
+                                | keymap::Action::ScrollRight
+                        )
+                    )
+                    && self.command_line.is_none()
+                    && self.confirm.is_none()
+                    && self.buffer_list.is_none()
+                    && self.help.is_none()
+                    && self.finder.is_none()
+                    && !self.file_tree.focused
+                {
+                    repeat = repeat.saturating_add(
+                        coalesce_key(key, &mut self.input, &mut self.macros).await?,
+                    );
+                }
+            }
+
+            // 操作する領域をスクロールする操作か
+            let is_scroll = matches!(
+                action,
+                Some(
+                    keymap::Action::ScrollUp
+                        | keymap::Action::ScrollDown
+                        | keymap::Action::ScrollLeft
+                        | keymap::Action::ScrollRight
+                        | keymap::Action::PageDown
+                        | keymap::Action::PageUp
+                        | keymap::Action::HalfPageDown
+                        | keymap::Action::HalfPageUp
+                        | keymap::Action::GoToTop
+                        | keymap::Action::GoToBottom
+                        | keymap::Action::SearchNext
+                        | keymap::Action::SearchPrevious
+                        | keymap::Action::NextPageBreak
+                        | keymap::Action::PreviousPageBreak
+                        | keymap::Action::NextHunk
+                        | keymap::Action::PreviousHunk
+                        | keymap::Action::NextDiffFile
+                        | keymap::Action::PreviousDiffFile
+                        | keymap::Action::MatchingBracket
+                        | keymap::Action::NextWord
+                        | keymap::Action::PreviousWord
+                        | keymap::Action::NextWordEnd
+                        | keymap::Action::CursorLeft
+                        | keymap::Action::CursorRight
+                        | keymap::Action::CursorUp
+                        | keymap::Action::CursorDown
+                )
+            ) && self.buffer_list.is_none()
+                && self.help.is_none();
+
+            // レジスタを選んだ後にコピー以外の操作をした場合は、選んだレジスタを使わない
+            if action.is_some_and(|action| {
+                !matches!(
+                    action,
+                    keymap::Action::Yank
+                        | keymap::Action::YankLine
+                        | keymap::Action::SelectRegister
+                )
+            }) && self.yank_register.take().is_some()
+            {
+                self.status_bar.remove_item("register");
+                self.status_bar.print(buffer);
+                screen::flush()?;
+            }
+
+            if action == Some(keymap::Action::Quit) {
+                break;
+            }
+            self.dispatch(event, action, command, regions, previous, repeat)
+                .await?;
+
+            // 操作する領域のスクロール位置を、表示したファイルのスクロール位置にそろえる
+            let layout = &mut self.tabs[self.current_tab];
+            save_cursor(layout, &self.buffers[self.current]);
+
+            // スクロールを連動させる場合は、ほかの領域も操作する領域と同じだけスクロールする
+            // スクロールできる範囲の端で位置が変わらなかった場合は、ほかの領域もスクロールしない
+            let pane = *layout.focused_pane();
+            if layout.scroll_lock
+                && is_scroll
+                && (pane.cursor_x, pane.cursor_y) != (previous.cursor_x, previous.cursor_y)
+            {
+                layout.scroll_others(
+                    pane.cursor_x as i32 - previous.cursor_x as i32,
+                    pane.cursor_y as i32 - previous.cursor_y as i32,
+                );
+                print_panes(&mut self.buffers, layout, area, &self.settings.options)?;
+                self.status_bar.print(&self.buffers[self.current]);
+                screen::flush()?;
+            }
+
+            // メモリマップで開いた大きなファイルは、表示する範囲の端に近づいたら範囲を移す
+            // 一番上の行は変わらないので、表示し直さずにスクロール位置だけをそろえる
+            let area = screen_regions(
+                Some(&self.file_tree),
+                self.term_width,
+                self.term_height,
+                self.tabs.len(),
+            )
+            .editor;
+            let rect = self.tabs[self.current_tab].focused_rect(area);
+            if self.buffers[self.current].shift_window(
+                &self.settings,
+                self.term_width,
+                rect.width,
+                rect.height,
+            ) {
+                save_cursor(
+                    &mut self.tabs[self.current_tab],
+                    &self.buffers[self.current],
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 押したキーに割り当てた操作を求める
+    /// # Arguments
+    /// * `key` - 押したキー
+    /// * `timed_out` - 続けて押すキーを待つ時間を過ぎたか
+    /// * `regions` - 画面の各部分の位置と大きさ
+    /// # Returns
+    /// * `std::io::Result<ResolvedKey>` - 押したキーから求めた、行うこと
+    /// # Notes
+    /// * 文字を入力している場合は、修飾キーを押していない文字は割り当てより入力を優先する
+    fn resolve_key(
+        &mut self,
+        key: &KeyEvent,
+        timed_out: bool,
+        regions: layout::Regions,
+    ) -> std::io::Result<ResolvedKey> {
+        let mut macro_key = false;
+        let mut command = None;
+        let mut bound_command = None;
+        let action = match key {
+            // 確認のダイアログを表示している場合は、押したキーを答えとして扱い、ほかの操作は行わない
+            // はいと答えた場合は、確認したコマンドを実行する
+            _ if self.confirm.is_some() => {
+                let answer = self
+                    .confirm
+                    .as_ref()
+                    .and_then(|(dialog, _)| dialog.answer(key));
+                if let Some(yes) = answer {
+                    if let Some((_, confirmed)) = self.confirm.take().filter(|_| yes) {
+                        command = Some(confirmed);
+                    }
+                    if let Some(region) = self.overlays.take_damaged() {
+                        restore_region(
+                            &mut self.buffers,
+                            &mut self.tabs[self.current_tab],
+                            &self.file_tree,
+                            regions,
+                            region,
+                            &self.settings.options,
+                        )?;
+                        screen::flush()?;
+                    }
+                }
+                None
+            }
+            // コマンドラインでEnterキーを押した場合は、入力したコマンドを解釈する
+            // キーに割り当てる操作と同じコマンドは、キーを押した場合と同じように行う
+            _ if self.command_line.is_some() && key.code == KeyCode::Enter => {
+                let line = self
+                    .command_line
+                    .take()
+                    .map(|line| line.input().to_string());
+                let parsed = parse_command(&line.unwrap_or_default(), &self.plugins);
+                if let Err(e) = &parsed {
+                    self.status_bar
+                        .set_message(prompt::Message::error(e.clone()));
+                }
+                screen::set_cursor(None);
+                self.status_bar.print(&self.buffers[self.current]);
+                screen::flush()?;
+
+                match parsed {
+                    Ok(command_line::Command::Action(action)) => Some(action),
+                    Ok(parsed) => {
+                        command = Some(parsed);
+                        None
+                    }
+                    Err(_) => None,
+                }
+            }
+            _ if self.command_line.is_some() => None,
+            // レジスタを選ぶ文字を待っている場合は、押した文字のレジスタでマクロを記録または再生する
+            // 印の名前を選ぶ文字を待っている場合は、押した文字の名前で印を付けるか、印を付けた行に移動する
+            _ if self.register_action.is_some() => {
+                macro_key = matches!(
+                    self.register_action,
+                    Some(keymap::Action::RecordMacro | keymap::Action::PlayMacro)
+                );
+                match (self.register_action.take(), key.code) {
+                    (Some(keymap::Action::SetMark), KeyCode::Char(c))
+                        if marks::Marks::is_mark(c) =>
+                    {
+                        command = Some(command_line::Command::Mark(c));
+                    }
+                    (Some(keymap::Action::JumpToMark), KeyCode::Char(c))
+                        if marks::Marks::is_mark(c) =>
+                    {
+                        match self.buffers[self.current].mark(c) {
+                            Some(line_number) => {
+                                command = Some(command_line::Command::GoToLine(line_number))
+                            }
+                            None => {
+                                self.status_bar.set_message(prompt::Message::error(format!(
+                                    "Mark not set: {}",
+                                    c
+                                )));
+                                self.status_bar.print(&self.buffers[self.current]);
+                                screen::flush()?;
+                            }
+                        }
+                    }
+                    (Some(keymap::Action::RecordMacro), KeyCode::Char(c))
+                        if macros::Macros::is_register(c) =>
+                    {
+                        self.macros.start_recording(c);
+                        let status_bar_macro = status_bar::StatusBarItem::new(
+                            "macro".to_string(),
+                            format!("recording @{}", c),
+                        );
+                        self.status_bar.add_item(status_bar_macro);
+                    }
+                    (Some(keymap::Action::PlayMacro), KeyCode::Char(c))
+                        if macros::Macros::is_register(c) =>
+                    {
+                        self.macros.play(c, self.count.take().unwrap_or(1));
+                    }
+                    (Some(keymap::Action::SelectRegister), KeyCode::Char(c))
+                        if registers::Registers::is_register(c) =>
+                    {
+                        self.yank_register = Some(c);
+                        let status_bar_register = status_bar::StatusBarItem::new(
+                            "register".to_string(),
+                            format!("\"{}", c),
+                        );
+                        self.status_bar.add_item(status_bar_register);
+                        self.status_bar.print(&self.buffers[self.current]);
+                        screen::flush()?;
+                    }
+                    _ => {}
+                }
+                None
+            }
+            _ if (self.finder.is_some() || self.file_tree.focused)
+                && matches!(key.code, KeyCode::Char(_))
+                && (key.modifiers - KeyModifiers::SHIFT).is_empty() =>
+            {
+                None
+            }
+            // 一覧や入力欄を表示している場合は、続けて押すキーを待たない
+            _ if self.buffer_list.is_some()
+                || self.help.is_some()
+                || self.finder.is_some()
+                || self.file_tree.focused =>
+            {
+                self.keymap.action(key)
+            }
+            // 待ち時間を過ぎた場合は、それまでに押したキーの列に割り当てた操作を行う
+            _ if timed_out => {
+                let keys = std::mem::take(&mut self.pending);
+                bound_command = self.keymap.command(&keys).map(str::to_string);
+                self.keymap.get(&keys)
+            }
+            // 割り当てのない数字は、マクロを再生する回数にする(0は2桁目以降だけ)
+            KeyEvent {
+                code: KeyCode::Char(c @ '0'..='9'),
+                modifiers: KeyModifiers::NONE,
+                ..
+            } if self.pending.is_empty()
+                && (*c != '0' || self.count.is_some())
+                && self.keymap.action(key).is_none() =>
+            {
+                macro_key = true;
+                let digit = c.to_digit(10).unwrap_or(0) as usize;
+                self.count = Some(
+                    self.count
+                        .unwrap_or(0)
+                        .saturating_mul(10)
+                        .saturating_add(digit),
+                );
+                None
+            }
+            _ => {
+                self.pending.push(keymap::KeyChord::from(key));
+                match self.keymap.lookup(&self.pending) {
+                    keymap::Lookup::Action(action) => {
+                        self.pending.clear();
+                        Some(action)
+                    }
+                    keymap::Lookup::Pending => {
+                        self.pending_since = Instant::now();
+                        None
+                    }
+                    keymap::Lookup::Command(line) => {
+                        self.pending.clear();
+                        bound_command = Some(line);
+                        None
+                    }
+                    keymap::Lookup::Unbound => {
+                        self.pending.clear();
+                        None
+                    }
+                }
+            }
+        };
+        Ok(ResolvedKey {
+            action,
+            command,
+            bound_command,
+            macro_key,
+        })
+    }
+
+    /// 読み込み終えたgit blameを、表示しているファイルに反映する
+    /// # Arguments
+    /// * `result` - 読み込んだ各行の注釈(読み込めなかった場合はNone)
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn show_blame(
+        &mut self,
+        result: Option<Vec<String>>,
+        regions: layout::Regions,
+    ) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        buffer.blame_receiver = None;
+        match result {
+            Some(annotations) => {
+                let annotations = std::rc::Rc::new(annotations);
+                self.status_bar.remove_item("blame");
+                if buffer.show_blame {
+                    buffer.set_blame_annotations(annotations.clone());
+                }
+                buffer.blame = Some(annotations);
+            }
+            None => {
+                let status_bar_blame = status_bar::StatusBarItem::new(
+                    "blame".to_string(),
+                    "blame: unavailable".to_string(),
+                );
+                self.status_bar.add_item(status_bar_blame);
+            }
+        }
+
+        // 同じファイルを表示している領域がほかにもある場合があるので、すべての領域を表示し直す
+        print_panes(
+            &mut self.buffers,
+            &mut self.tabs[self.current_tab],
+            regions.editor,
+            &self.settings.options,
+        )?;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// 入力か、git blameの読み込みやファイルの変更か、待ち時間を過ぎるのを、まとめて待つ
+    /// # Arguments
+    /// * `playing` - マクロを再生しているか
+    /// # Returns
+    /// * `std::io::Result<Wakeup>` - 待っている間に起きたこと
+    /// # Notes
+    /// * マクロを再生している場合は、続けて押すキーもすぐに再生するので待たない
+    /// * フックなどで実行するコマンドがある場合も、入力を待たずに実行する
+    /// * コマンドラインで入力している間や、確認のダイアログを表示している間は、閉じるまでコマンドを実行しない
+    async fn wait(&mut self, playing: bool) -> std::io::Result<Wakeup> {
+        let next_command = if self.command_line.is_none() && self.confirm.is_none() {
+            self.queued_commands.pop_front()
+        } else {
+            None
+        };
+        let wakeup = if let Some(command) = next_command {
+            Wakeup::Command(command)
+        } else if playing {
+            Wakeup::Input
+        } else {
+            // 設定ファイルや開いているファイルを監視している場合は、変更されたかを確認する
+            // 末尾を追いかけている場合は、変更を知らせない環境でも追加された内容を読み込めるように、更新日時も定期的に確認する
+            self.file_watcher.sync(
+                self.buffers
+                    .iter()
+                    .filter_map(|buffer| buffer.file.clone())
+                    .collect(),
+            );
+            let interval = Duration::from_millis(if self.follow { 200 } else { 500 });
+            let ticking = self.settings.config.watch || self.follow;
+            let sequence_deadline = self.pending_since + keymap::SEQUENCE_TIMEOUT;
+            let message_expires_at = self.status_bar.message_expires_at();
+            let blame_receiver = &mut self.buffers[self.current].blame_receiver;
+            let loading_blame = blame_receiver.is_some();
+
+            tokio::select! {
+                biased;
+                ready = self.input.wait() => {
+                    ready?;
+                    Wakeup::Input
+                }
+                // 続けて押すキーを待っている場合は、待ち時間を過ぎるまで
+                _ = tokio::time::sleep_until(sequence_deadline.into()), if !self.pending.is_empty() => {
+                    Wakeup::SequenceTimeout
+                }
+                result = async { blame_receiver.as_mut().unwrap().await }, if loading_blame => {
+                    Wakeup::BlameLoaded(result.ok().flatten())
+                }
+                // メッセージを表示している場合は、表示をやめる時刻まで
+                _ = tokio::time::sleep_until(message_expires_at.unwrap_or_else(Instant::now).into()),
+                    if message_expires_at.is_some() => {
+                    Wakeup::MessageExpired
+                }
+                files = self.file_watcher.next_change(), if self.file_watcher.is_watching() => {
+                    Wakeup::FilesChanged(files)
+                }
+                _ = tokio::time::sleep(interval), if ticking => Wakeup::Tick,
+            }
+        };
+        Ok(wakeup)
+    }
+
+    /// 設定ファイルとテーマを読み込み直して、表示しているものすべてに反映する
+    /// # Arguments
+    /// * `regions` - 画面の各部分の位置と大きさ
+    /// # Notes
+    /// * 読み込めない場合は、前の設定のままにしてステータスバーで知らせる
+    fn reload_config(&mut self, regions: layout::Regions) -> std::io::Result<()> {
+        self.config_modified_time = config::Config::modified_time();
+        match load_config(self.args.theme.as_deref()) {
+            Ok((config, theme)) => {
+                self.status_bar.remove_item("config");
+                self.keymap = keymap::Keymap::new(
+                    self.args
+                        .keymap
+                        .or(config.keymap_preset)
+                        .unwrap_or_default(),
+                    &config.keymap,
+                );
+
+                // プラグインも読み込み直して、前に加えたステータスバーの項目は取り除く
+                for name in self.plugins.status_items() {
+                    self.status_bar.remove_item(&name);
+                }
+                let plugin_errors;
+                (self.plugins, plugin_errors) = plugin::Plugins::load();
+                self.plugins.bind_keys(&mut self.keymap);
+                self.hooks = hooks::Hooks::new(&config.hooks);
+                self.plugins.register_hooks(&mut self.hooks);
+                apply_plugin_effects(
+                    &mut self.plugins,
+                    &mut self.status_bar,
+                    &mut self.queued_commands,
+                );
+                if !plugin_errors.is_empty() {
+                    self.status_bar
+                        .set_message(prompt::Message::error(plugin_errors.join(", ")));
+                }
+                self.status_bar.layout = config.status_bar.items.clone();
+                self.status_bar.style = theme.status_bar;
+                self.status_bar.error_style = theme.error;
+                self.tab_bar.style = theme.tab_bar;
+                self.tab_bar.current_style = theme.tab_bar_current;
+                self.file_tree.selection_style = theme.selection;
+                self.file_tree.separator_style = theme.separator;
+
+                self.settings.options.chop_long_lines =
+                    !self.settings.wrap.or(config.wrap).unwrap_or(true);
+                self.settings.options.theme = theme;
+                self.settings.config = config;
+                for buffer in self.buffers.iter_mut() {
+                    buffer.reload_settings(&self.settings, self.term_width);
+                }
+                update_status_items(&mut self.status_bar, &self.buffers, self.current);
+            }
+            Err(e) => {
+                let status_bar_config =
+                    status_bar::StatusBarItem::new("config".to_string(), format!("config: {}", e));
+                self.status_bar.add_item(status_bar_config);
+            }
+        }
+
+        print_tab_bar(&self.tab_bar, &self.buffers, &self.tabs, self.current_tab);
+        if self.file_tree.visible {
+            self.file_tree.print(regions.sidebar);
+        }
+        print_panes(
+            &mut self.buffers,
+            &mut self.tabs[self.current_tab],
+            regions.editor,
+            &self.settings.options,
+        )?;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// 変更されたファイルを、スクロール位置を保ったまま読み込み直して、読み込み直したことを知らせる
+    /// # Arguments
+    /// * `changed` - 変更されたファイル
+    /// * `regions` - 画面の各部分の位置と大きさ
+    /// # Notes
+    /// * 自分で書き込んだ場合などは、開いたときから更新日時が変わっていなければ読み込み直さない
+    fn reload_changed_files(
+        &mut self,
+        changed: HashSet<PathBuf>,
+        regions: layout::Regions,
+    ) -> std::io::Result<()> {
+        let mut reloaded = Vec::new();
+        for buffer in self
+            .buffers
+            .iter_mut()
+            .filter(|buffer| buffer.is_modified())
+        {
+            let Some(file) = buffer.file.clone() else {
+                continue;
+            };
+            if !std::fs::canonicalize(&file).is_ok_and(|path| changed.contains(&path)) {
+                continue;
+            }
+            // メモリマップで開いたファイルは、すべて読み込まずに開き直す
+            if buffer.remap(&self.settings, self.term_width) {
+                reloaded.push(file);
+            } else if let Ok(contents) = get_contents(Some(file.clone()), false) {
+                buffer.reload(contents, &self.settings, self.term_width);
+                reloaded.push(file);
+            }
+        }
+
+        // 末尾を追いかけている場合は、追加された内容が見えるように末尾までスクロールし、知らせない
+        let followed = self.follow
+            && self.buffers[self.current]
+                .file
+                .as_ref()
+                .is_some_and(|file| {
+                    let index = reloaded.iter().position(|reloaded| reloaded == file);
+                    index.map(|index| reloaded.remove(index)).is_some()
+                });
+        if followed {
+            self.buffers[self.current].move_window(true, &self.settings, self.term_width);
+            self.tabs[self.current_tab].focused_pane_mut().cursor_y = usize::MAX;
+        }
+
+        if followed || !reloaded.is_empty() {
+            if !reloaded.is_empty() {
+                self.status_bar.set_message(prompt::Message::info(format!(
+                    "{}: Reloaded",
+                    reloaded.join(", ")
+                )));
+            }
+            let area = regions.editor;
+            print_panes(
+                &mut self.buffers,
+                &mut self.tabs[self.current_tab],
+                area,
+                &self.settings.options,
+            )?;
+            update_status_items(&mut self.status_bar, &self.buffers, self.current);
+            update_follow_item(&mut self.status_bar, self.follow);
+            self.status_bar.print(&self.buffers[self.current]);
+            screen::flush()?;
+        }
+        Ok(())
+    }
+
+    /// 新しく開いたバッファごとに、開いたときのフックを実行する
+    /// # Notes
+    /// * コマンドの出力には実行しないので、フックで`:!`を実行しても繰り返し開かない
+    fn run_open_hooks(&mut self) -> std::io::Result<()> {
+        for buffer in self.buffers[self.opened_buffers..]
+            .iter()
+            .filter(|buffer| !buffer.is_output())
+        {
+            run_hook(
+                hooks::Hook::OnOpen,
+                buffer.file.as_deref().unwrap_or_default(),
+                buffer,
+                &self.hooks,
+                &mut self.plugins,
+                &mut self.status_bar,
+                &mut self.queued_commands,
+            );
+        }
+        self.opened_buffers = self.buffers.len();
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// 押したキーに割り当てた操作や、入力したコマンドを行う
+    /// # Arguments
+    /// * `event` - 端末から届いたイベント(フックなどで実行するコマンドの場合はNone)
+    /// * `action` - 押したキーに割り当てた操作
+    /// * `command` - コマンドラインで入力した、キーに割り当てる操作以外のコマンド
+    /// * `regions` - 画面の各部分の位置と大きさ
+    /// * `previous` - 操作する前の、操作する領域のスクロール位置
+    /// * `repeat` - 同じスクロールのキーを続けて押した回数
+    async fn dispatch(
+        &mut self,
+        event: Option<Event>,
+        action: Option<keymap::Action>,
+        command: Option<command_line::Command>,
+        regions: layout::Regions,
+        previous: layout::Pane,
+        repeat: u16,
+    ) -> std::io::Result<()> {
+        let buffer = &self.buffers[self.current];
+
+        match (event, action) {
+            // 末尾を追いかけている場合は、Ctrl+Cで追いかけるのをやめて普通に読む状態に戻る
+            (
+                Some(Event::Key(KeyEvent {
+                    code: KeyCode::Char('c'),
+                    modifiers: KeyModifiers::CONTROL,
+                    ..
+                })),
+                _,
+            ) if self.follow => {
+                self.follow = false;
+                update_follow_item(&mut self.status_bar, self.follow);
+                self.status_bar.print(&self.buffers[self.current]);
+                screen::flush()?;
+            }
+            // 確認のダイアログを表示している間は、答え以外のキーでは何もしない
+            (Some(Event::Key(_)), _) if self.confirm.is_some() => {}
+            (Some(Event::Key(key)), _) if self.command_line.is_some() => {
+                self.edit_command_line(key)?
+            }
+            _ if command.is_some() => {
+                if let Some(command) = command {
+                    self.run_command(command, regions, previous)?;
+                }
+            }
+            (
+                Some(Event::Key(KeyEvent {
+                    code,
+                    modifiers: _,
+                    kind: _,
+                    state: _,
+                })),
+                _,
+            ) if self.buffer_list.is_some() => self.handle_buffer_list_key(code, regions)?,
+            (Some(Event::Key(KeyEvent { code, .. })), _) if self.help.is_some() => {
+                self.handle_help_key(code, action, regions)?
+            }
+            (
+                Some(Event::Key(KeyEvent {
+                    code,
+                    modifiers,
+                    kind: _,
+                    state: _,
+                })),
+                _,
+            ) if self.finder.is_some() => self.handle_finder_key(code, modifiers, regions)?,
+            (_, Some(action @ (keymap::Action::FindFile | keymap::Action::RecentFiles))) => {
+                self.open_finder(action, regions)?
+            }
+            (_, Some(keymap::Action::ToggleFileTree)) => self.toggle_file_tree()?,
+            (
+                Some(Event::Key(KeyEvent {
+                    code,
+                    modifiers,
+                    kind: _,
+                    state: _,
+                })),
+                _,
+            ) if self.file_tree.focused && modifiers != KeyModifiers::ALT => {
+                self.handle_file_tree_key(code, regions)?
+            }
+            (
+                _,
+                Some(
+                    action @ (keymap::Action::SplitHorizontal
+                    | keymap::Action::SplitVertical
+                    | keymap::Action::ClosePane
+                    | keymap::Action::FocusNextPane
+                    | keymap::Action::ToggleScrollLock
+                    | keymap::Action::ResizePaneUp
+                    | keymap::Action::ResizePaneDown
+                    | keymap::Action::ResizePaneLeft
+                    | keymap::Action::ResizePaneRight),
+                ),
+            ) => self.change_layout(action, regions)?,
+            (_, Some(keymap::Action::ScrollUp)) => self.scroll_up(regions, repeat)?,
+            (_, Some(keymap::Action::ScrollDown)) => self.scroll_down(regions, repeat)?,
+            (
+                _,
+                Some(
+                    action @ (keymap::Action::PageDown
+                    | keymap::Action::PageUp
+                    | keymap::Action::HalfPageDown
+                    | keymap::Action::HalfPageUp
+                    | keymap::Action::GoToTop
+                    | keymap::Action::GoToBottom),
+                ),
+            ) => self.move_page(action, regions, previous)?,
+            (_, Some(action @ (keymap::Action::SearchNext | keymap::Action::SearchPrevious))) => {
+                self.search(action, regions, previous)?
+            }
+            // 次の繰り返しの最初で、設定ファイルを読み込み直す
+            (_, Some(keymap::Action::ReloadConfig)) => {
+                self.reload = true;
+            }
+            // マクロを記録している場合は記録を終え、それ以外は続けて押す文字のレジスタで記録または再生する
+            (_, Some(action @ (keymap::Action::RecordMacro | keymap::Action::PlayMacro))) => {
+                if action == keymap::Action::RecordMacro && self.macros.recording().is_some() {
+                    self.macros.stop_recording();
+                    self.status_bar.remove_item("macro");
+                    self.status_bar.print(&self.buffers[self.current]);
+                    screen::flush()?;
+                } else {
+                    self.register_action = Some(action);
+                }
+            }
+            // 続けて押す文字の名前で、印を付けるか印を付けた行に移動する
+            // 続けて押す文字のレジスタを、次にコピーする先にする
+            (
+                _,
+                Some(
+                    action @ (keymap::Action::SetMark
+                    | keymap::Action::JumpToMark
+                    | keymap::Action::SelectRegister),
+                ),
+            ) => {
+                self.register_action = Some(action);
+            }
+            // コマンドラインを開く
+            // コマンドに通す場合は`|`を、書き込む場合は`w `を入力した状態で開く
+            (
+                _,
+                Some(
+                    action
+                    @ (keymap::Action::Command | keymap::Action::Pipe | keymap::Action::Save),
+                ),
+            ) => {
+                let mut line = prompt::Prompt::new(":");
+                match action {
+                    keymap::Action::Pipe => line = line.with_input("|"),
+                    keymap::Action::Save => line = line.with_input("w "),
+                    _ => {}
+                }
+                line.print(&self.status_bar);
+                self.command_line = Some(line);
+                screen::flush()?;
+            }
+            (_, Some(action @ (keymap::Action::NextFile | keymap::Action::PreviousFile))) => {
+                self.switch_file(action, regions)?
+            }
+            (_, Some(keymap::Action::BufferList)) => self.show_buffer_list(regions)?,
+            // 今の割り当てから作ったキーの割り当ての一覧を表示する
+            (_, Some(keymap::Action::Help)) => {
+                let keys = help::Help::new(&self.keymap.bindings());
+                self.overlays.show(&keys, regions.overlay());
+                self.help = Some(keys);
+
+                self.status_bar.print(&self.buffers[self.current]);
+                screen::flush()?;
+            }
+            (
+                _,
+                Some(
+                    action @ (keymap::Action::NewTab
+                    | keymap::Action::CloseTab
+                    | keymap::Action::NextTab
+                    | keymap::Action::PreviousTab),
+                ),
+            ) => self.change_tab(action)?,
+            (_, Some(keymap::Action::ToggleWhitespace)) => self.toggle_whitespace(regions)?,
+            (
+                _,
+                Some(
+                    action @ (keymap::Action::ToggleSelection
+                    | keymap::Action::ToggleBlockSelection
+                    | keymap::Action::ClearSelection),
+                ),
+            ) => self.toggle_selection(action, regions)?,
+            (_, Some(keymap::Action::Reload)) => self.reload_file(regions)?,
+            (_, Some(keymap::Action::Follow)) => self.start_follow(regions, previous)?,
+            (_, Some(keymap::Action::OpenEditor)) => self.open_editor(regions)?,
+            (_, Some(action @ (keymap::Action::Yank | keymap::Action::YankLine))) => {
+                self.yank(action, regions)?
+            }
+            (_, Some(keymap::Action::ToggleCursorLine)) => self.toggle_cursor_line(regions)?,
+            (_, Some(keymap::Action::ToggleMarks)) => self.toggle_marks(regions)?,
+            (_, Some(keymap::Action::MatchingBracket)) => {
+                self.jump_to_matching_bracket(regions, previous)?
+            }
+            (
+                _,
+                Some(
+                    action @ (keymap::Action::NextWord
+                    | keymap::Action::PreviousWord
+                    | keymap::Action::NextWordEnd),
+                ),
+            ) => self.move_by_word(action, regions)?,
+            (
+                _,
+                Some(
+                    action @ (keymap::Action::CursorLeft
+                    | keymap::Action::CursorRight
+                    | keymap::Action::CursorUp
+                    | keymap::Action::CursorDown),
+                ),
+            ) => self.move_caret(action, regions)?,
+            (_, Some(action @ (keymap::Action::JumpBack | keymap::Action::JumpForward))) => {
+                self.jump(action, regions, previous)?
+            }
+            (_, Some(keymap::Action::ToggleBlame))
+                if !buffer.markdown_mode && !buffer.json_mode =>
+            {
+                self.toggle_blame(regions)?
+            }
+            (_, Some(action @ (keymap::Action::ToggleMarkdown | keymap::Action::ToggleJson)))
+                if (action == keymap::Action::ToggleMarkdown && buffer.is_markdown)
+                    || (action == keymap::Action::ToggleJson && buffer.json_view.is_some()) =>
+            {
+                self.toggle_rendering(action, regions)?
+            }
+            (_, Some(keymap::Action::ToggleFold)) if buffer.json_mode => {
+                self.toggle_fold(regions)?
+            }
+            (
+                _,
+                Some(action @ (keymap::Action::NextPageBreak | keymap::Action::PreviousPageBreak)),
+            ) => self.move_to_page_break(action, regions)?,
+            (_, Some(keymap::Action::ToggleSideBySide)) if buffer.diff_files.is_some() => {
+                self.toggle_side_by_side(regions)?
+            }
+            (
+                _,
+                Some(
+                    action @ (keymap::Action::NextHunk
+                    | keymap::Action::PreviousHunk
+                    | keymap::Action::NextDiffFile
+                    | keymap::Action::PreviousDiffFile),
+                ),
+            ) if buffer.diff_index.is_some() => self.move_to_diff(action, regions)?,
+            (_, Some(action @ (keymap::Action::ScrollLeft | keymap::Action::ScrollRight)))
+                if buffer.options.chop_long_lines =>
+            {
+                self.scroll_horizontally(action, regions, repeat)?
+            }
+            // フックなどで実行したコマンドが、何もしない操作だった場合
+            (None, _) => {}
+            (Some(Event::Resize(columns, rows)), _) => self.resize(columns, rows).await?,
+            // フォーカスの変化やマウス、貼り付けのイベントは使わないので、何もしない
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// コマンドラインを開いている場合は、押したキーで入力したコマンドを編集する
+    /// 何も入力していない状態のBackspaceキーか、Escキーで閉じる
+    /// # Arguments
+    /// * `key` - 押したキー
+    fn edit_command_line(&mut self, key: KeyEvent) -> std::io::Result<()> {
+        let Some(line) = self.command_line.as_mut() else {
+            return Ok(());
+        };
+
+        match line.handle_key(&key) {
+            prompt::PromptEvent::Changed => line.print(&self.status_bar),
+            prompt::PromptEvent::Submit(_) | prompt::PromptEvent::Cancel => {
+                self.command_line = None;
+                screen::set_cursor(None);
+                self.status_bar.print(&self.buffers[self.current]);
+            }
+            prompt::PromptEvent::Ignored => return Ok(()),
+        }
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// コマンドラインで入力した、キーに割り当てる操作以外のコマンドを実行する
+    /// # Arguments
+    /// * `command` - 実行するコマンド
+    /// * `regions` - 画面の各部分の位置と大きさ
+    /// * `previous` - 操作する前の、操作する領域のスクロール位置
+    fn run_command(
+        &mut self,
+        command: command_line::Command,
+        regions: layout::Regions,
+        previous: layout::Pane,
+    ) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        let area = regions.editor;
+        let rect = self.tabs[self.current_tab].focused_rect(area);
+        match command {
+            // 指定した行か、ファイルの行数に対する割合の行に移動する
+            // (ファイルの行数より大きい場合は末尾に移動する)
+            command @ (command_line::Command::GoToLine(_)
+            | command_line::Command::GoToPercent(_)) => {
+                self.jumps.push(previous);
+
+                let line_number = match command {
+                    command_line::Command::GoToPercent(percent) => buffer.line_at_percent(percent),
+                    command_line::Command::GoToLine(line_number) => line_number,
+                    _ => unreachable!(),
+                };
+                // メモリマップで開いたファイルは、指定した行の前後を表示する範囲に移してから移動する
+                let line_number =
+                    buffer.move_window_to_line(line_number, &self.settings, self.term_width);
+                let mut contents = buffer.view(rect.x, rect.y, rect.width, rect.height);
+                if !contents.scroll_to_line(line_number) {
+                    contents.cursor_y = usize::MAX;
+                }
+                contents.print()?;
+
+                // 表示するときに再計算されるので、cursor_yを更新する
+                buffer.cursor_y = contents.cursor_y;
+            }
+            // 表示に関する設定を変更して、開いているすべてのファイルに反映する
+            command_line::Command::Set(option) => {
+                match option {
+                    command_line::SetOption::Wrap(wrap) => self.settings.wrap = Some(wrap),
+                    command_line::SetOption::TabWidth(tab_width) => {
+                        self.settings.tab_width = Some(tab_width)
+                    }
+                }
+                self.settings.options.chop_long_lines = !self
+                    .settings
+                    .wrap
+                    .or(self.settings.config.wrap)
+                    .unwrap_or(true);
+                for buffer in self.buffers.iter_mut() {
+                    buffer.reload_settings(&self.settings, self.term_width);
+                }
+
+                print_panes(
+                    &mut self.buffers,
+                    &mut self.tabs[self.current_tab],
+                    area,
+                    &self.settings.options,
+                )?;
+            }
+            // 一番上に表示している行に印を付ける
+            command_line::Command::Mark(mark) => {
+                if !buffer.set_mark(mark, rect.width, rect.height) {
+                    self.status_bar.set_message(prompt::Message::error(
+                        "Marks are not available in this view".to_string(),
+                    ));
+                } else if self.buffers[self.current].show_marks {
+                    print_panes(
+                        &mut self.buffers,
+                        &mut self.tabs[self.current_tab],
+                        area,
+                        &self.settings.options,
+                    )?;
+                }
+            }
+            // ファイルを開いて、操作する領域で表示する
+            command_line::Command::Edit(file) => {
+                let path = std::path::PathBuf::from(&file);
+                match open_file(
+                    &mut self.buffers,
+                    &path,
+                    &self.settings,
+                    &self.history,
+                    rect,
+                ) {
+                    Some(index) => {
+                        self.current = index;
+                        let buffer = &self.buffers[self.current];
+                        *self.tabs[self.current_tab].focused_pane_mut() =
+                            layout::Pane::new(self.current, buffer.cursor_x, buffer.cursor_y);
+                        update_status_items(&mut self.status_bar, &self.buffers, self.current);
+                        update_pane_item(&mut self.status_bar, &self.tabs[self.current_tab]);
+                        print_tab_bar(&self.tab_bar, &self.buffers, &self.tabs, self.current_tab);
+                        print_panes(
+                            &mut self.buffers,
+                            &mut self.tabs[self.current_tab],
+                            area,
+                            &self.settings.options,
+                        )?;
+                    }
+                    None => {
+                        self.status_bar
+                            .set_message(prompt::Message::error(format!("{}: Cannot open", file)));
+                    }
+                }
+            }
+            // 開いたときのファイルの内容を書き込む
+            command_line::Command::Write { file, force } => {
+                match buffer.write(file.as_deref(), force) {
+                    Ok(file) => {
+                        self.status_bar
+                            .set_message(prompt::Message::info(format!("{}: Written", file)));
+                        run_hook(
+                            hooks::Hook::OnSave,
+                            &file,
+                            buffer,
+                            &self.hooks,
+                            &mut self.plugins,
+                            &mut self.status_bar,
+                            &mut self.queued_commands,
+                        );
+                    }
+                    // 開いたファイル以外の既存のファイルは、上書きするかを確認してから書き込む
+                    Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                        let dialog = overlay::Confirm::new(format!(
+                            "{}: File exists. Overwrite?",
+                            file.as_deref().unwrap_or_default()
+                        ));
+                        self.overlays.show(&dialog, regions.overlay());
+                        self.confirm =
+                            Some((dialog, command_line::Command::Write { file, force: true }));
+                    }
+                    Err(e) => self
+                        .status_bar
+                        .set_message(prompt::Message::error(e.to_string())),
+                }
+            }
+            // 選択している文字列かファイル全体をコマンドに通して、出力を新しく開くか置き換える
+            command_line::Command::Pipe { command, replace } => {
+                let input = buffer
+                    .selected_text()
+                    .unwrap_or_else(|| buffer.contents.to_string());
+                match shell::run(&command, Some(&input)) {
+                    Ok(output) if !output.success => self
+                        .status_bar
+                        .set_message(prompt::Message::error(output.error_message())),
+                    Ok(output) if replace => {
+                        match buffer.replace_selection(
+                            &output.stdout,
+                            &self.settings,
+                            self.term_width,
+                        ) {
+                            Ok(()) => {
+                                update_status_items(
+                                    &mut self.status_bar,
+                                    &self.buffers,
+                                    self.current,
+                                );
+                                print_panes(
+                                    &mut self.buffers,
+                                    &mut self.tabs[self.current_tab],
+                                    area,
+                                    &self.settings.options,
+                                )?;
+                            }
+                            Err(e) => self.status_bar.set_message(prompt::Message::error(e)),
+                        }
+                    }
+                    Ok(output) => {
+                        buffer.clear_selection();
+                        self.buffers.push(buffer::Buffer::open_output(
+                            format!("|{}", command),
+                            output.stdout,
+                            &self.settings,
+                        ));
+                        self.current = self.buffers.len() - 1;
+                        *self.tabs[self.current_tab].focused_pane_mut() =
+                            layout::Pane::new(self.current, 0, 0);
+                        update_status_items(&mut self.status_bar, &self.buffers, self.current);
+                        print_tab_bar(&self.tab_bar, &self.buffers, &self.tabs, self.current_tab);
+                        print_panes(
+                            &mut self.buffers,
+                            &mut self.tabs[self.current_tab],
+                            area,
+                            &self.settings.options,
+                        )?;
+                    }
+                    Err(e) => self
+                        .status_bar
+                        .set_message(prompt::Message::error(e.to_string())),
+                }
+            }
+            // シェルのコマンドを実行して、標準出力と標準エラー出力を新しく開く
+            command_line::Command::Shell(command) => match shell::run(&command, None) {
+                Ok(output) => {
+                    if !output.success {
+                        self.status_bar
+                            .set_message(prompt::Message::error("Command failed".to_string()));
+                    }
+                    self.buffers.push(buffer::Buffer::open_output(
+                        format!("!{}", command),
+                        output.combined(),
+                        &self.settings,
+                    ));
+                    self.current = self.buffers.len() - 1;
+                    *self.tabs[self.current_tab].focused_pane_mut() =
+                        layout::Pane::new(self.current, 0, 0);
+                    update_status_items(&mut self.status_bar, &self.buffers, self.current);
+                    print_tab_bar(&self.tab_bar, &self.buffers, &self.tabs, self.current_tab);
+                    print_panes(
+                        &mut self.buffers,
+                        &mut self.tabs[self.current_tab],
+                        area,
+                        &self.settings.options,
+                    )?;
+                }
+                Err(e) => self
+                    .status_bar
+                    .set_message(prompt::Message::error(e.to_string())),
+            },
+            // プラグインで登録したコマンドを実行して、行った操作をステータスバーに反映する
+            command_line::Command::Plugin { name, argument } => {
+                if let Err(e) = self.plugins.run_command(
+                    &name,
+                    argument.as_deref().unwrap_or_default(),
+                    plugin_context(buffer),
+                ) {
+                    self.status_bar.set_message(prompt::Message::error(e));
+                }
+                apply_plugin_effects(
+                    &mut self.plugins,
+                    &mut self.status_bar,
+                    &mut self.queued_commands,
+                );
+            }
+            command_line::Command::Action(_) => {}
+        }
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// ファイルの一覧を表示している場合は、UpキーとDownキーで選択し、Enterキーで選択したファイルを表示する
+    /// Escキーで一覧を閉じる
+    /// # Arguments
+    /// * `code` - 押したキー
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn handle_buffer_list_key(
+        &mut self,
+        code: KeyCode,
+        regions: layout::Regions,
+    ) -> std::io::Result<()> {
+        let rect = self.tabs[self.current_tab].focused_rect(regions.editor);
+        let Some(list) = self.buffer_list.as_mut() else {
+            return Ok(());
+        };
+
+        match code {
+            KeyCode::Up => list.select_previous(),
+            KeyCode::Down => list.select_next(),
+            KeyCode::Enter => {
+                self.current = list.selected;
+                let buffer = &self.buffers[self.current];
+                *self.tabs[self.current_tab].focused_pane_mut() =
+                    layout::Pane::new(self.current, buffer.cursor_x, buffer.cursor_y);
+                self.buffer_list = None;
+                update_status_items(&mut self.status_bar, &self.buffers, self.current);
+                print_tab_bar(&self.tab_bar, &self.buffers, &self.tabs, self.current_tab);
+            }
+            KeyCode::Esc => self.buffer_list = None,
+            _ => return Ok(()),
+        }
+
+        // 閉じた場合は、一覧に隠れていた部分だけを表示し直す
+        // ファイルを切り替えた場合は、操作する領域もすべて表示し直す
+        match &self.buffer_list {
+            Some(list) => self.overlays.show(list, regions.overlay()),
+            None => {
+                if let Some(region) = self.overlays.take_damaged() {
+                    let region = if code == KeyCode::Enter {
+                        region.union(&rect)
+                    } else {
+                        region
+                    };
+                    restore_region(
+                        &mut self.buffers,
+                        &mut self.tabs[self.current_tab],
+                        &self.file_tree,
+                        regions,
+                        region,
+                        &self.settings.options,
+                    )?;
+                }
+            }
+        }
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// キーの割り当ての一覧を表示している場合は、Up/DownキーとPageUp/PageDownキーでスクロールする
+    /// Escキーか、一覧を表示するキーで閉じる
+    /// # Arguments
+    /// * `code` - 押したキー
+    /// * `action` - 行う操作
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn handle_help_key(
+        &mut self,
+        code: KeyCode,
+        action: Option<keymap::Action>,
+        regions: layout::Regions,
+    ) -> std::io::Result<()> {
+        let area = regions.editor;
+        let Some(keys) = self.help.as_mut() else {
+            return Ok(());
+        };
+
+        match code {
+            KeyCode::Up => keys.scroll_up(1),
+            KeyCode::Down => keys.scroll_down(1, area.height),
+            KeyCode::PageUp => keys.scroll_up(area.height as usize),
+            KeyCode::PageDown => keys.scroll_down(area.height as usize, area.height),
+            _ if code == KeyCode::Esc || action == Some(keymap::Action::Help) => self.help = None,
+            _ => return Ok(()),
+        }
+
+        match &self.help {
+            Some(keys) => self.overlays.show(keys, regions.overlay()),
+            None => {
+                // 一覧に隠れていた部分だけを表示し直す
+                if let Some(region) = self.overlays.take_damaged() {
+                    restore_region(
+                        &mut self.buffers,
+                        &mut self.tabs[self.current_tab],
+                        &self.file_tree,
+                        regions,
+                        region,
+                        &self.settings.options,
+                    )?;
+                }
+            }
+        }
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// ファイルを探している場合は、入力した文字でファイルを絞り込み、Enterキーで選択したファイルを開く
+    /// UpキーとDownキー(Ctrl + PとCtrl + N)で選択し、Escキーで閉じる
+    /// # Arguments
+    /// * `code` - 押したキー
+    /// * `modifiers` - 押した修飾キー
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn handle_finder_key(
+        &mut self,
+        code: KeyCode,
+        modifiers: KeyModifiers,
+        regions: layout::Regions,
+    ) -> std::io::Result<()> {
+        let rect = self.tabs[self.current_tab].focused_rect(regions.editor);
+        let Some(finder_list) = self.finder.as_mut() else {
+            return Ok(());
+        };
+
+        match (code, modifiers) {
+            (KeyCode::Up, _) | (KeyCode::Char('p'), KeyModifiers::CONTROL) => {
+                finder_list.select_previous()
+            }
+            (KeyCode::Down, _) | (KeyCode::Char('n'), KeyModifiers::CONTROL) => {
+                finder_list.select_next()
+            }
+            (KeyCode::Backspace, _) => {
+                let mut query = finder_list.query.clone();
+                query.pop();
+                finder_list.set_query(query);
+            }
+            (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                finder_list.set_query(format!("{}{}", finder_list.query, c))
+            }
+            (KeyCode::Enter, _) => {
+                let Some(file) = finder_list.selected_file() else {
+                    return Ok(());
+                };
+                let path = std::path::PathBuf::from(file);
+                self.finder = None;
+                if let Some(index) = open_file(
+                    &mut self.buffers,
+                    &path,
+                    &self.settings,
+                    &self.history,
+                    rect,
+                ) {
+                    self.current = index;
+                    let buffer = &self.buffers[self.current];
+                    *self.tabs[self.current_tab].focused_pane_mut() =
+                        layout::Pane::new(self.current, buffer.cursor_x, buffer.cursor_y);
+                    update_status_items(&mut self.status_bar, &self.buffers, self.current);
+                    update_pane_item(&mut self.status_bar, &self.tabs[self.current_tab]);
+                    print_tab_bar(&self.tab_bar, &self.buffers, &self.tabs, self.current_tab);
+                }
+            }
+            (KeyCode::Esc, _) => self.finder = None,
+            _ => return Ok(()),
+        }
+
+        match &self.finder {
+            Some(finder) => self.overlays.show(finder, regions.overlay()),
+            None => {
+                // 閉じた場合は、一覧に隠れていた部分だけを表示し直す
+                // ファイルを開いた場合は、操作する領域もすべて表示し直す
+                if let Some(region) = self.overlays.take_damaged() {
+                    let region = if code == KeyCode::Enter {
+                        region.union(&rect)
+                    } else {
+                        region
+                    };
+                    restore_region(
+                        &mut self.buffers,
+                        &mut self.tabs[self.current_tab],
+                        &self.file_tree,
+                        regions,
+                        region,
+                        &self.settings.options,
+                    )?;
+                }
+            }
+        }
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// カレントディレクトリの下にあるファイルか、最近開いたファイルから探して、操作する領域で開く
+    /// # Arguments
+    /// * `action` - 行う操作
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn open_finder(
+        &mut self,
+        action: keymap::Action,
+        regions: layout::Regions,
+    ) -> std::io::Result<()> {
+        let mut finder_list = if action == keymap::Action::FindFile {
+            finder::Finder::new(finder::index(std::path::Path::new(".")))
+        } else {
+            let files = self
+                .history
+                .files
+                .iter()
+                .map(|entry| entry.path.clone())
+                .filter(|path| std::path::Path::new(path).is_file())
+                .collect();
+            let mut finder_list = finder::Finder::new(files);
+            finder_list.title = "Recent files".to_string();
+            finder_list
+        };
+        finder_list.selection_style = self.settings.options.theme.selection;
+        finder_list.match_style = self.settings.options.theme.finder_match;
+        finder_list.color_depth = self.color_depth;
+        self.overlays.show(&finder_list, regions.overlay());
+        self.finder = Some(finder_list);
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// ファイルツリーを表示して操作し、もう一度押すと隠す
+    /// ファイルツリーを表示していて、エディタ領域を操作している場合は、ファイルツリーの操作に戻る
+    fn toggle_file_tree(&mut self) -> std::io::Result<()> {
+        if self.file_tree.focused {
+            self.file_tree.visible = false;
+            self.file_tree.focused = false;
+        } else {
+            self.file_tree.visible = true;
+            self.file_tree.focused = true;
+            self.file_tree.refresh();
+        }
+
+        let regions = screen_regions(
+            Some(&self.file_tree),
+            self.term_width,
+            self.term_height,
+            self.tabs.len(),
+        );
+        if self.file_tree.visible {
+            self.file_tree.print(regions.sidebar);
+        }
+        let area = regions.editor;
+        print_panes(
+            &mut self.buffers,
+            &mut self.tabs[self.current_tab],
+            area,
+            &self.settings.options,
+        )?;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// ファイルツリーを操作している場合は、UpキーとDownキーで選択し、Rightキーで展開、Leftキーで折りたたむ
+    /// Enterキーでディレクトリの展開を切り替えるか、ファイルを開いてエディタ領域の操作に戻る
+    /// 文字を入力すると名前で絞り込み、Escキーで絞り込みを解除するか、エディタ領域の操作に戻る
+    /// # Arguments
+    /// * `code` - 押したキー
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn handle_file_tree_key(
+        &mut self,
+        code: KeyCode,
+        regions: layout::Regions,
+    ) -> std::io::Result<()> {
+        let area = regions.editor;
+        let rect = self.tabs[self.current_tab].focused_rect(area);
+        match code {
+            KeyCode::Up => self.file_tree.select_previous(),
+            KeyCode::Down => self.file_tree.select_next(),
+            KeyCode::Right => self.file_tree.expand(),
+            KeyCode::Left => self.file_tree.collapse(),
+            KeyCode::Backspace => {
+                let mut filter = self.file_tree.filter.clone();
+                filter.pop();
+                self.file_tree.set_filter(filter);
+            }
+            KeyCode::Esc if !self.file_tree.filter.is_empty() => {
+                self.file_tree.set_filter(String::new())
+            }
+            KeyCode::Esc => self.file_tree.focused = false,
+            KeyCode::Char(c) => self
+                .file_tree
+                .set_filter(format!("{}{}", self.file_tree.filter, c)),
+            KeyCode::Enter => match self.file_tree.selected_row() {
+                Some(row) if row.is_dir => self.file_tree.toggle(),
+                Some(row) => {
+                    let path = row.path.strip_prefix(".").unwrap_or(&row.path);
+                    let Some(index) =
+                        open_file(&mut self.buffers, path, &self.settings, &self.history, rect)
+                    else {
+                        return Ok(());
+                    };
+                    self.current = index;
+                    let buffer = &self.buffers[self.current];
+                    *self.tabs[self.current_tab].focused_pane_mut() =
+                        layout::Pane::new(self.current, buffer.cursor_x, buffer.cursor_y);
+                    self.file_tree.focused = false;
+                    update_status_items(&mut self.status_bar, &self.buffers, self.current);
+                    update_pane_item(&mut self.status_bar, &self.tabs[self.current_tab]);
+                    print_tab_bar(&self.tab_bar, &self.buffers, &self.tabs, self.current_tab);
+
+                    print_panes(
+                        &mut self.buffers,
+                        &mut self.tabs[self.current_tab],
+                        area,
+                        &self.settings.options,
+                    )?;
+                }
+                None => return Ok(()),
+            },
+            _ => return Ok(()),
+        }
+
+        self.file_tree.print(regions.sidebar);
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// エディタ領域を上下または左右に分割し、操作する領域を閉じる
+    /// 操作する領域を切り替え、領域の境界線を動かす
+    /// ほかの領域も同じだけスクロールするかを切り替える
+    /// # Arguments
+    /// * `action` - 行う操作
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn change_layout(
+        &mut self,
+        action: keymap::Action,
+        regions: layout::Regions,
+    ) -> std::io::Result<()> {
+        let area = regions.editor;
+        let layout = &mut self.tabs[self.current_tab];
+        let changed = match action {
+            keymap::Action::SplitHorizontal => layout.split(layout::SplitDirection::Horizontal),
+            keymap::Action::SplitVertical => layout.split(layout::SplitDirection::Vertical),
+            keymap::Action::ClosePane => layout.close(),
+            keymap::Action::FocusNextPane => {
+                layout.focus_next();
+                layout.panes.len() > 1
+            }
+            keymap::Action::ToggleScrollLock if layout.panes.len() > 1 => {
+                layout.scroll_lock = !layout.scroll_lock;
+                true
+            }
+            keymap::Action::ToggleScrollLock => false,
+            _ if layout.panes.len() == 1 => false,
+            _ => {
+                // 境界線と直交する向きの操作だけ使い、1回で5%ずつ動かす
+                let delta = match (layout.direction, action) {
+                    (layout::SplitDirection::Horizontal, keymap::Action::ResizePaneUp)
+                    | (layout::SplitDirection::Vertical, keymap::Action::ResizePaneLeft) => -5,
+                    (layout::SplitDirection::Horizontal, keymap::Action::ResizePaneDown)
+                    | (layout::SplitDirection::Vertical, keymap::Action::ResizePaneRight) => 5,
+                    _ => 0,
+                };
+                layout.resize(delta);
+                delta != 0
+            }
+        };
+        if !changed {
+            return Ok(());
+        }
+        self.current = layout.focused_pane().buffer;
+        update_status_items(&mut self.status_bar, &self.buffers, self.current);
+        update_pane_item(&mut self.status_bar, layout);
+        print_tab_bar(&self.tab_bar, &self.buffers, &self.tabs, self.current_tab);
+
+        print_panes(
+            &mut self.buffers,
+            &mut self.tabs[self.current_tab],
+            area,
+            &self.settings.options,
+        )?;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// カーソルを上に移動する
+    /// # Arguments
+    /// * `regions` - 画面の各部分の位置と大きさ
+    /// * `repeat` - 同じキーを続けて押した回数
+    fn scroll_up(&mut self, regions: layout::Regions, repeat: u16) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        let rect = self.tabs[self.current_tab].focused_rect(regions.editor);
+        let cursor_y = buffer.cursor_y.saturating_sub(repeat as usize);
+        buffer.scroll(
+            cursor_y,
+            rect.x,
+            rect.y,
+            rect.width,
+            rect.height,
+            self.term_width,
+        )?;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// カーソルを下に移動する
+    /// # Arguments
+    /// * `regions` - 画面の各部分の位置と大きさ
+    /// * `repeat` - 同じキーを続けて押した回数
+    fn scroll_down(&mut self, regions: layout::Regions, repeat: u16) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        let rect = self.tabs[self.current_tab].focused_rect(regions.editor);
+        let cursor_y = buffer.cursor_y.saturating_add(repeat as usize);
+        buffer.scroll(
+            cursor_y,
+            rect.x,
+            rect.y,
+            rect.width,
+            rect.height,
+            self.term_width,
+        )?;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// 1画面分または半画面分スクロールする、先頭または末尾に移動する
+    /// # Arguments
+    /// * `action` - 行う操作
+    /// * `regions` - 画面の各部分の位置と大きさ
+    /// * `previous` - 操作する前の、操作する領域のスクロール位置
+    fn move_page(
+        &mut self,
+        action: keymap::Action,
+        regions: layout::Regions,
+        previous: layout::Pane,
+    ) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        let rect = self.tabs[self.current_tab].focused_rect(regions.editor);
+        if matches!(action, keymap::Action::GoToTop | keymap::Action::GoToBottom) {
+            self.jumps.push(previous);
+            buffer.move_window(
+                action == keymap::Action::GoToBottom,
+                &self.settings,
+                self.term_width,
+            );
+        }
+
+        let page = rect.height.max(1) as usize;
+        let half_page = (rect.height / 2).max(1) as usize;
+        let cursor_y = match action {
+            keymap::Action::PageDown => buffer.cursor_y.saturating_add(page),
+            keymap::Action::PageUp => buffer.cursor_y.saturating_sub(page),
+            keymap::Action::HalfPageDown => buffer.cursor_y.saturating_add(half_page),
+            keymap::Action::HalfPageUp => buffer.cursor_y.saturating_sub(half_page),
+            keymap::Action::GoToTop => 0,
+            // 表示するときに最後の行が表示できる位置まで戻される
+            _ => usize::MAX,
+        };
+        buffer.scroll(
+            cursor_y,
+            rect.x,
+            rect.y,
+            rect.width,
+            rect.height,
+            self.term_width,
+        )?;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// --patternで指定した文字列を含む次/前の行に移動する
+    /// # Arguments
+    /// * `action` - 行う操作
+    /// * `regions` - 画面の各部分の位置と大きさ
+    /// * `previous` - 操作する前の、操作する領域のスクロール位置
+    fn search(
+        &mut self,
+        action: keymap::Action,
+        regions: layout::Regions,
+        previous: layout::Pane,
+    ) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        let rect = self.tabs[self.current_tab].focused_rect(regions.editor);
+        let Some(pattern) = &self.search_pattern else {
+            return Ok(());
+        };
+        self.jumps.push(previous);
+
+        let mut contents = buffer.view(rect.x, rect.y, rect.width, rect.height);
+        let found = if action == keymap::Action::SearchNext {
+            contents.scroll_to_next_match(pattern)
+        } else {
+            contents.scroll_to_previous_match(pattern)
+        };
+        contents.print()?;
+
+        // 表示するときに再計算されるので、cursor_yを更新する
+        buffer.cursor_y = contents.cursor_y;
+
+        if !found {
+            self.status_bar.set_message(prompt::Message::error(format!(
+                "Pattern not found: {}",
+                pattern
+            )));
+        }
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// :nで次のファイル、:pで前のファイルを表示する
+    /// ファイルごとにスクロール位置を保持しておき、切り替えたときに元の位置から表示する
+    /// # Arguments
+    /// * `action` - 行う操作
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn switch_file(
+        &mut self,
+        action: keymap::Action,
+        regions: layout::Regions,
+    ) -> std::io::Result<()> {
+        let area = regions.editor;
+        self.current = if action == keymap::Action::NextFile {
+            (self.current + 1).min(self.buffers.len() - 1)
+        } else {
+            self.current.saturating_sub(1)
+        };
+        let buffer = &self.buffers[self.current];
+        *self.tabs[self.current_tab].focused_pane_mut() =
+            layout::Pane::new(self.current, buffer.cursor_x, buffer.cursor_y);
+        update_status_items(&mut self.status_bar, &self.buffers, self.current);
+        print_tab_bar(&self.tab_bar, &self.buffers, &self.tabs, self.current_tab);
+
+        print_panes(
+            &mut self.buffers,
+            &mut self.tabs[self.current_tab],
+            area,
+            &self.settings.options,
+        )?;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// :bで開いているファイルの一覧を表示する
+    /// # Arguments
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn show_buffer_list(&mut self, regions: layout::Regions) -> std::io::Result<()> {
+        let entries = self
+            .buffers
+            .iter()
+            .map(|buffer| buffer_list::BufferListEntry {
+                name: buffer.name(),
+                modified: buffer.is_modified(),
+                line_count: buffer.line_count(),
+            })
+            .collect();
+        let mut list = buffer_list::BufferList::new(entries, self.current);
+        list.selection_style = self.settings.options.theme.selection;
+        list.color_depth = self.color_depth;
+        self.overlays.show(&list, regions.overlay());
+        self.buffer_list = Some(list);
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// 表示しているファイルを新しいタブページで開くか、表示しているタブページを閉じる
+    /// 次/前のタブページを表示する
+    /// # Arguments
+    /// * `action` - 行う操作
+    fn change_tab(&mut self, action: keymap::Action) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        match action {
+            keymap::Action::NewTab => {
+                let pane = layout::Pane::new(self.current, buffer.cursor_x, buffer.cursor_y);
+                self.tabs
+                    .insert(self.current_tab + 1, layout::Layout::new(pane));
+                self.current_tab += 1;
+            }
+            keymap::Action::CloseTab => {
+                // 最後のタブページは閉じない
+                if self.tabs.len() == 1 {
+                    return Ok(());
+                }
+                self.tabs.remove(self.current_tab);
+                self.current_tab = self.current_tab.min(self.tabs.len() - 1);
+            }
+            keymap::Action::NextTab => self.current_tab = (self.current_tab + 1) % self.tabs.len(),
+            _ => self.current_tab = (self.current_tab + self.tabs.len() - 1) % self.tabs.len(),
+        }
+        self.current = self.tabs[self.current_tab].focused_pane().buffer;
+        update_status_items(&mut self.status_bar, &self.buffers, self.current);
+        update_pane_item(&mut self.status_bar, &self.tabs[self.current_tab]);
+
+        // タブページが2つ以上ある場合だけ、一番上にタブバーを表示する
+        print_tab_bar(&self.tab_bar, &self.buffers, &self.tabs, self.current_tab);
+
+        let regions = screen_regions(
+            Some(&self.file_tree),
+            self.term_width,
+            self.term_height,
+            self.tabs.len(),
+        );
+        let area = regions.editor;
+        if self.file_tree.visible {
+            self.file_tree.print(regions.sidebar);
+        }
+        print_panes(
+            &mut self.buffers,
+            &mut self.tabs[self.current_tab],
+            area,
+            &self.settings.options,
+        )?;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// 空白文字の表示を切り替える
+    /// # Arguments
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn toggle_whitespace(&mut self, regions: layout::Regions) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        let area = regions.editor;
+        buffer.toggle_whitespace();
+
+        print_panes(
+            &mut self.buffers,
+            &mut self.tabs[self.current_tab],
+            area,
+            &self.settings.options,
+        )?;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// 操作する位置から選択を始める、選択をやめる
+    /// 選択している間は、操作する位置を移動すると選択する範囲が広がる
+    /// 違う選択のしかたで選択している場合は、選択を始めた位置のまま切り替える
+    /// # Arguments
+    /// * `action` - 行う操作
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn toggle_selection(
+        &mut self,
+        action: keymap::Action,
+        regions: layout::Regions,
+    ) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        let rect = self.tabs[self.current_tab].focused_rect(regions.editor);
+        let block = action == keymap::Action::ToggleBlockSelection;
+        if action == keymap::Action::ClearSelection || buffer.selection_mode() == Some(block) {
+            if !buffer.clear_selection() {
+                return Ok(());
+            }
+        } else {
+            buffer.start_selection(block);
+        }
+
+        let mut contents = buffer.view(rect.x, rect.y, rect.width, rect.height);
+        contents.print()?;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// ファイルを読み込み直して、一番上に表示していた行から表示し直す
+    /// 検索する文字列や行に付けた印はそのまま使える
+    /// # Arguments
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn reload_file(&mut self, regions: layout::Regions) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        let area = regions.editor;
+        let rect = self.tabs[self.current_tab].focused_rect(area);
+        let Some(file) = buffer
+            .file
+            .clone()
+            .filter(|file| std::path::Path::new(file).is_file())
+            .or_else(|| buffer.origin.as_ref().map(buffer::Origin::name))
+        else {
+            self.status_bar.set_message(prompt::Message::error(
+                "Nothing to reload in this view".to_string(),
+            ));
+            self.status_bar.print(&self.buffers[self.current]);
+            screen::flush()?;
+            return Ok(());
+        };
+
+        // URLやSSHの場合は取得し直して、応答の情報なども新しくする
+        // メモリマップで開いたファイルは、すべて読み込まずに開き直す
+        let contents = match get_remote_contents(&file, false) {
+            Some(result) => result.map(|(origin, contents)| {
+                buffer.origin = Some(origin);
+                Some(contents)
+            }),
+            None if buffer.remap(&self.settings, self.term_width) => Ok(None),
+            None => get_contents(Some(file.clone()), false).map(Some),
+        };
+        match contents {
+            Ok(contents) => {
+                if let Some(contents) = contents {
+                    let top_line_number = buffer.top_line_number(rect.width, rect.height);
+                    buffer.reload(contents, &self.settings, self.term_width);
+                    if let Some(line_number) = top_line_number {
+                        buffer.scroll_to_line(line_number, rect.width, rect.height);
+                    }
+                }
+                save_cursor(&mut self.tabs[self.current_tab], buffer);
+                self.status_bar
+                    .set_message(prompt::Message::info(format!("{}: Reloaded", file)));
+            }
+            Err(e) => self
+                .status_bar
+                .set_message(prompt::Message::error(e.to_string())),
+        }
+
+        print_panes(
+            &mut self.buffers,
+            &mut self.tabs[self.current_tab],
+            area,
+            &self.settings.options,
+        )?;
+        update_status_items(&mut self.status_bar, &self.buffers, self.current);
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// ファイルの末尾を追いかけ始め、末尾までスクロールする
+    /// # Arguments
+    /// * `regions` - 画面の各部分の位置と大きさ
+    /// * `previous` - 操作する前の、操作する領域のスクロール位置
+    fn start_follow(
+        &mut self,
+        regions: layout::Regions,
+        previous: layout::Pane,
+    ) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        let area = regions.editor;
+        if !buffer
+            .file
+            .as_ref()
+            .is_some_and(|file| std::path::Path::new(file).is_file())
+        {
+            self.status_bar.set_message(prompt::Message::error(
+                "Nothing to follow in this view".to_string(),
+            ));
+            self.status_bar.print(&self.buffers[self.current]);
+            screen::flush()?;
+            return Ok(());
+        }
+
+        // メモリマップで開いたファイルは、切り詰められても異常終了しないように、すべて読み込み直す
+        if buffer.unmap() {
+            match get_contents(buffer.file.clone(), false) {
+                Ok(contents) => buffer.reload(contents, &self.settings, self.term_width),
+                Err(e) => {
+                    self.status_bar
+                        .set_message(prompt::Message::error(e.to_string()));
+                    self.status_bar.print(&self.buffers[self.current]);
+                    screen::flush()?;
+                    return Ok(());
+                }
+            }
+        }
+        self.jumps.push(previous);
+        self.follow = true;
+
+        buffer.move_window(true, &self.settings, self.term_width);
+        self.tabs[self.current_tab].focused_pane_mut().cursor_y = usize::MAX;
+        print_panes(
+            &mut self.buffers,
+            &mut self.tabs[self.current_tab],
+            area,
+            &self.settings.options,
+        )?;
+        update_status_items(&mut self.status_bar, &self.buffers, self.current);
+        update_follow_item(&mut self.status_bar, self.follow);
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// 端末を通常の状態に戻してエディタでファイルを編集し、終了したら読み込み直して表示し直す
+    /// # Arguments
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn open_editor(&mut self, regions: layout::Regions) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        let area = regions.editor;
+        let rect = self.tabs[self.current_tab].focused_rect(area);
+        let Some(file) = buffer
+            .file
+            .clone()
+            .filter(|file| std::path::Path::new(file).is_file())
+        else {
+            self.status_bar
+                .set_message(prompt::Message::error("No file to edit".to_string()));
+            self.status_bar.print(&self.buffers[self.current]);
+            screen::flush()?;
+            return Ok(());
+        };
+        let line_number = buffer.current_line_number(rect.width, rect.height);
+
+        screen::end_frame()?;
+        queue!(stdout(), Show)?;
+        if self.alternate_screen {
+            queue!(stdout(), LeaveAlternateScreen)?;
+        }
+        // エディタに押したキーを横取りしないように、入力を読むのを止めておく
+        let paused = self.input.pause();
+        disable_raw_mode()?;
+        stdout().flush()?;
+        let edited = shell::edit(&file, line_number);
+        enable_raw_mode()?;
+        drop(paused);
+        if self.alternate_screen {
+            queue!(stdout(), EnterAlternateScreen)?;
+        }
+        execute!(stdout(), Hide, terminal::Clear(terminal::ClearType::All))?;
+        screen::invalidate_all();
+        screen::begin_frame()?;
+
+        // エディタが失敗した場合も、途中で保存したかもしれないので読み込み直す
+        // メモリマップで開いたファイルは、すべて読み込まずに開き直す
+        let buffer = &mut self.buffers[self.current];
+        let reloaded = edited.and_then(|success| {
+            if !buffer.remap(&self.settings, self.term_width) {
+                let contents = get_contents(Some(file.clone()), false)?;
+                buffer.reload(contents, &self.settings, self.term_width);
+            }
+            Ok(success)
+        });
+        match reloaded {
+            Ok(success) => {
+                if !success {
+                    self.status_bar.set_message(prompt::Message::error(
+                        "Editor exited with an error".to_string(),
+                    ));
+                }
+            }
+            Err(e) => self
+                .status_bar
+                .set_message(prompt::Message::error(e.to_string())),
+        }
+        update_status_items(&mut self.status_bar, &self.buffers, self.current);
+
+        print_tab_bar(&self.tab_bar, &self.buffers, &self.tabs, self.current_tab);
+        if self.file_tree.visible {
+            self.file_tree.print(regions.sidebar);
+        }
+        print_panes(
+            &mut self.buffers,
+            &mut self.tabs[self.current_tab],
+            area,
+            &self.settings.options,
+        )?;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// 選択している文字列か、操作する位置の行をコピーして、選択をやめる
+    /// 英字のレジスタを選んでいる場合はそのレジスタに、それ以外はクリップボードにコピーする
+    /// # Arguments
+    /// * `action` - 行う操作
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn yank(&mut self, action: keymap::Action, regions: layout::Regions) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        let rect = self.tabs[self.current_tab].focused_rect(regions.editor);
+        let text = if action == keymap::Action::YankLine {
+            Some(buffer.caret_line_text())
+        } else {
+            buffer.selected_text()
+        };
+        let Some(text) = text else {
+            self.status_bar
+                .set_message(prompt::Message::error("Nothing selected".to_string()));
+            self.status_bar.print(&self.buffers[self.current]);
+            screen::flush()?;
+            return Ok(());
+        };
+
+        let register = self.yank_register.take();
+        self.status_bar.remove_item("register");
+        let yanked = self.registers.yank(register, &text);
+        let lines = match yanked.lines().count() {
+            0 | 1 => "1 line".to_string(),
+            n => format!("{} lines", n),
+        };
+        let message = match register {
+            Some(c) if !registers::Registers::is_clipboard(c) => prompt::Message::info(format!(
+                "{} yanked into \"{}",
+                lines,
+                c.to_ascii_lowercase()
+            )),
+            _ => match self.clipboard.copy(&text) {
+                Ok(clipboard::Target::System) => prompt::Message::info(format!("{} copied", lines)),
+                Ok(clipboard::Target::Terminal) => {
+                    prompt::Message::info(format!("{} sent to the terminal clipboard", lines))
+                }
+                Err(e) => prompt::Message::error(e.to_string()),
+            },
+        };
+        buffer.clear_selection();
+
+        let mut contents = buffer.view(rect.x, rect.y, rect.width, rect.height);
+        contents.print()?;
+
+        self.status_bar.set_message(message);
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// 操作する位置がある行を強調表示するかを切り替える
+    /// # Arguments
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn toggle_cursor_line(&mut self, regions: layout::Regions) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        let area = regions.editor;
+        buffer.show_cursor_line = !buffer.show_cursor_line;
+
+        print_panes(
+            &mut self.buffers,
+            &mut self.tabs[self.current_tab],
+            area,
+            &self.settings.options,
+        )?;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// 行番号の左に印を表示するかを切り替える
+    /// # Arguments
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn toggle_marks(&mut self, regions: layout::Regions) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        let area = regions.editor;
+        buffer.show_marks = !buffer.show_marks;
+        buffer.update_mark_markers();
+
+        print_panes(
+            &mut self.buffers,
+            &mut self.tabs[self.current_tab],
+            area,
+            &self.settings.options,
+        )?;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// 一番上の行の括弧に対応する括弧の行に移動し、2つの括弧を強調表示する
+    /// # Arguments
+    /// * `regions` - 画面の各部分の位置と大きさ
+    /// * `previous` - 操作する前の、操作する領域のスクロール位置
+    fn jump_to_matching_bracket(
+        &mut self,
+        regions: layout::Regions,
+        previous: layout::Pane,
+    ) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        let rect = self.tabs[self.current_tab].focused_rect(regions.editor);
+        if !buffer.jump_to_matching_bracket(rect.width, rect.height) {
+            self.status_bar
+                .set_message(prompt::Message::error("No matching bracket".to_string()));
+            self.status_bar.print(&self.buffers[self.current]);
+            screen::flush()?;
+            return Ok(());
+        }
+        self.jumps.push(previous);
+
+        let mut contents = buffer.view(rect.x, rect.y, rect.width, rect.height);
+        contents.print()?;
+
+        // 表示するときに再計算されるので、cursor_yを更新する
+        buffer.cursor_y = contents.cursor_y;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// 単語単位で移動し、移動した位置の行が表示する領域に入るようにスクロールする
+    /// # Arguments
+    /// * `action` - 行う操作
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn move_by_word(
+        &mut self,
+        action: keymap::Action,
+        regions: layout::Regions,
+    ) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        let rect = self.tabs[self.current_tab].focused_rect(regions.editor);
+        let position = match action {
+            keymap::Action::NextWord => word::next_word_start(&buffer.contents, buffer.caret),
+            keymap::Action::PreviousWord => {
+                word::previous_word_start(&buffer.contents, buffer.caret)
+            }
+            _ => word::next_word_end(&buffer.contents, buffer.caret),
+        };
+        let Some(position) = position else {
+            return Ok(());
+        };
+        buffer.move_caret(position, rect.width, rect.height);
+
+        let mut contents = buffer.view(rect.x, rect.y, rect.width, rect.height);
+        contents.print()?;
+
+        // 表示するときに再計算されるので、cursor_yを更新する
+        buffer.cursor_y = contents.cursor_y;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// 1文字左右に移動する、上下の行に移動する
+    /// 移動した位置の行が表示する領域に入るようにスクロールする
+    /// # Arguments
+    /// * `action` - 行う操作
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn move_caret(
+        &mut self,
+        action: keymap::Action,
+        regions: layout::Regions,
+    ) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        let rect = self.tabs[self.current_tab].focused_rect(regions.editor);
+        let moved = match action {
+            keymap::Action::CursorLeft => {
+                buffer.move_caret_horizontally(false, rect.width, rect.height)
+            }
+            keymap::Action::CursorRight => {
+                buffer.move_caret_horizontally(true, rect.width, rect.height)
+            }
+            keymap::Action::CursorUp => {
+                buffer.move_caret_vertically(false, rect.width, rect.height)
+            }
+            _ => buffer.move_caret_vertically(true, rect.width, rect.height),
+        };
+        if !moved {
+            return Ok(());
+        }
+
+        let mut contents = buffer.view(rect.x, rect.y, rect.width, rect.height);
+        contents.print()?;
+
+        // 表示するときに再計算されるので、cursor_yを更新する
+        buffer.cursor_y = contents.cursor_y;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// 大きな移動をする前の位置に戻る、戻る前の位置に進む
+    /// 別のファイルで移動した位置の場合は、そのファイルに切り替える
+    /// # Arguments
+    /// * `action` - 行う操作
+    /// * `regions` - 画面の各部分の位置と大きさ
+    /// * `previous` - 操作する前の、操作する領域のスクロール位置
+    fn jump(
+        &mut self,
+        action: keymap::Action,
+        regions: layout::Regions,
+        previous: layout::Pane,
+    ) -> std::io::Result<()> {
+        let area = regions.editor;
+        let position = if action == keymap::Action::JumpBack {
+            self.jumps.back(previous)
+        } else {
+            self.jumps.forward()
+        };
+        let Some(position) = position else {
+            return Ok(());
+        };
+
+        self.current = position.buffer;
+        *self.tabs[self.current_tab].focused_pane_mut() = position;
+        update_status_items(&mut self.status_bar, &self.buffers, self.current);
+        print_tab_bar(&self.tab_bar, &self.buffers, &self.tabs, self.current_tab);
+
+        print_panes(
+            &mut self.buffers,
+            &mut self.tabs[self.current_tab],
+            area,
+            &self.settings.options,
+        )?;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// git blameの表示を切り替える
+    /// 行の対応が変わらないように、元の文字列を表示している場合だけ切り替えられる
+    /// # Arguments
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn toggle_blame(&mut self, regions: layout::Regions) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        let area = regions.editor;
+        let Some(file) = &buffer.file else {
+            return Ok(());
+        };
+
+        buffer.show_blame = !buffer.show_blame;
+        if !buffer.show_blame {
+            buffer.options.annotations = Default::default();
+        } else if let Some(annotations) = &buffer.blame {
+            buffer.options.annotations = annotations.clone();
+        } else if buffer.blame_receiver.is_none() {
+            let (sender, receiver) = tokio::sync::oneshot::channel();
+            let path = std::path::PathBuf::from(file);
+            std::thread::spawn(move || {
+                let annotations =
+                    git::blame(&path).map(|lines| lines.iter().map(|line| line.format()).collect());
+                let _ = sender.send(annotations);
+            });
+            buffer.blame_receiver = Some(receiver);
+
+            let status_bar_blame =
+                status_bar::StatusBarItem::new("blame".to_string(), "blame: loading".to_string());
+            self.status_bar.add_item(status_bar_blame);
+        }
+
+        print_panes(
+            &mut self.buffers,
+            &mut self.tabs[self.current_tab],
+            area,
+            &self.settings.options,
+        )?;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// Markdownの装飾した表示、またはJSONを整形した表示と、元の文字列の表示を切り替える
+    /// # Arguments
+    /// * `action` - 行う操作
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn toggle_rendering(
+        &mut self,
+        action: keymap::Action,
+        regions: layout::Regions,
+    ) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        let area = regions.editor;
+        if action == keymap::Action::ToggleMarkdown {
+            buffer.markdown_mode = !buffer.markdown_mode;
+        } else {
+            buffer.json_mode = !buffer.json_mode;
+        }
+        buffer.swap_alternate();
+        save_cursor(&mut self.tabs[self.current_tab], buffer);
+
+        print_panes(
+            &mut self.buffers,
+            &mut self.tabs[self.current_tab],
+            area,
+            &self.settings.options,
+        )?;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// JSONを整形して表示している場合は、一番上の行のオブジェクトや配列を折りたたむ/展開する
+    /// # Arguments
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn toggle_fold(&mut self, regions: layout::Regions) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        let area = regions.editor;
+        let rect = self.tabs[self.current_tab].focused_rect(area);
+        let mut contents = buffer.view(rect.x, rect.y, rect.width, rect.height);
+        let Some(top_line_number) = contents.top_line_number() else {
+            return Ok(());
+        };
+        let Some(json_view) = buffer.json_view.as_mut() else {
+            return Ok(());
+        };
+        let Some(line) = json_view.toggle(top_line_number - 1) else {
+            return Ok(());
+        };
+        let (text, fold_markers) = (json_view.text().to_string(), json_view.fold_markers());
+        buffer.set_contents(text);
+        buffer.options.fold_markers = fold_markers.into();
+
+        // 折りたたみを切り替えた行が一番上に来るようにする
+        let mut contents = buffer.view(rect.x, rect.y, rect.width, rect.height);
+        contents.scroll_to_line(line + 1);
+        buffer.cursor_y = contents.cursor_y;
+        save_cursor(&mut self.tabs[self.current_tab], buffer);
+
+        print_panes(
+            &mut self.buffers,
+            &mut self.tabs[self.current_tab],
+            area,
+            &self.settings.options,
+        )?;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// 次/前の改ページに移動する
+    /// # Arguments
+    /// * `action` - 行う操作
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn move_to_page_break(
+        &mut self,
+        action: keymap::Action,
+        regions: layout::Regions,
+    ) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        let rect = self.tabs[self.current_tab].focused_rect(regions.editor);
+        let mut contents = buffer.view(rect.x, rect.y, rect.width, rect.height);
+        if action == keymap::Action::NextPageBreak {
+            contents.scroll_to_next_page_break();
+        } else {
+            contents.scroll_to_previous_page_break();
+        }
+        contents.print()?;
+
+        // 表示するときに再計算されるので、cursor_yを更新する
+        buffer.cursor_y = contents.cursor_y;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// 2つのファイルの差分を表示している場合は、左右に並べる表示と切り替える
+    /// # Arguments
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn toggle_side_by_side(&mut self, regions: layout::Regions) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        let area = regions.editor;
+        buffer.side_by_side = !buffer.side_by_side;
+        buffer.show_file_diff(self.term_width);
+        buffer.cursor_y = 0;
+        save_cursor(&mut self.tabs[self.current_tab], buffer);
+
+        print_panes(
+            &mut self.buffers,
+            &mut self.tabs[self.current_tab],
+            area,
+            &self.settings.options,
+        )?;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// 差分を表示している場合は、次/前の差分のまとまりか、次/前のファイルに移動する
+    /// # Arguments
+    /// * `action` - 行う操作
+    /// * `regions` - 画面の各部分の位置と大きさ
+    fn move_to_diff(
+        &mut self,
+        action: keymap::Action,
+        regions: layout::Regions,
+    ) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        let rect = self.tabs[self.current_tab].focused_rect(regions.editor);
+        let Some(diff_index) = &buffer.diff_index else {
+            return Ok(());
+        };
+
+        let mut contents = buffer.view(rect.x, rect.y, rect.width, rect.height);
+        let current = contents.top_line_number().unwrap_or(1);
+        let target = match action {
+            keymap::Action::NextHunk => diff::DiffIndex::next(&diff_index.hunk_headers(), current),
+            keymap::Action::PreviousHunk => {
+                diff::DiffIndex::previous(&diff_index.hunk_headers(), current)
+            }
+            keymap::Action::NextDiffFile => diff::DiffIndex::next(&diff_index.files, current),
+            _ => diff::DiffIndex::previous(&diff_index.files, current),
+        };
+        if let Some(target) = target {
+            contents.scroll_to_line(target);
+        }
+        contents.print()?;
+
+        // 表示するときに再計算されるので、cursor_yを更新する
+        buffer.cursor_y = contents.cursor_y;
+
+        self.status_bar.print(buffer);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// 横にスクロールする
+    /// 折り返して表示する場合は横にスクロールする必要がないので、何もしない
+    /// # Arguments
+    /// * `action` - 行う操作
+    /// * `regions` - 画面の各部分の位置と大きさ
+    /// * `repeat` - 同じキーを続けて押した回数
+    fn scroll_horizontally(
+        &mut self,
+        action: keymap::Action,
+        regions: layout::Regions,
+        repeat: u16,
+    ) -> std::io::Result<()> {
+        let buffer = &mut self.buffers[self.current];
+        let rect = self.tabs[self.current_tab].focused_rect(regions.editor);
+        buffer.cursor_x = if action == keymap::Action::ScrollLeft {
+            buffer.cursor_x.saturating_sub(repeat)
+        } else {
+            buffer.cursor_x.saturating_add(repeat)
+        };
+
+        let mut contents = buffer.view(rect.x, rect.y, rect.width, rect.height);
+        contents.print()?;
+
+        // 表示するときに再計算されるので、cursor_xとcursor_yを更新する
+        buffer.cursor_x = contents.cursor_x;
+        buffer.cursor_y = contents.cursor_y;
+
+        self.status_bar.print(&self.buffers[self.current]);
+        screen::flush()?;
+        Ok(())
+    }
+
+    /// # Arguments
+    /// * `columns` - 変更した端末の横幅
+    /// * `rows` - 変更した端末の高さ
+    async fn resize(&mut self, columns: u16, rows: u16) -> std::io::Result<()> {
+        // ウィンドウをドラッグしている間などは続けて届くので、大きさが決まってから1回だけ計算し直す
+        let (columns, rows) = settle_resize(columns, rows, &mut self.input).await?;
+
+        // 端末によっては大きさを変えたときに表示が崩れるので、すべて出力し直す
+        screen::invalidate_all();
+        self.term_width = columns;
+        self.term_height = rows;
+        let regions = screen_regions(
+            Some(&self.file_tree),
+            self.term_width,
+            self.term_height,
+            self.tabs.len(),
+        );
+
+        self.tab_bar.width = self.term_width;
+        print_tab_bar(&self.tab_bar, &self.buffers, &self.tabs, self.current_tab);
+
+        // 左右に並べた差分は、端末の幅に合わせて計算し直す
+        for buffer in self.buffers.iter_mut().filter(|buffer| buffer.side_by_side) {
+            buffer.show_file_diff(self.term_width);
+        }
+
+        self.status_bar.width = regions.status_bar.width;
+        self.status_bar.y_start = regions.status_bar.y;
+
+        if self.file_tree.visible {
+            self.file_tree.print(regions.sidebar);
+        }
+        print_panes(
+            &mut self.buffers,
+            &mut self.tabs[self.current_tab],
+            regions.editor,
+            &self.settings.options,
+        )?;
+
+        // すべて表示し直したので、重ねて表示したものを新しい大きさで表示し直す
+        self.overlays.take_damaged();
+        let screen = regions.overlay();
+        if let Some(list) = &self.buffer_list {
+            self.overlays.show(list, screen);
+        }
+        if let Some(keys) = &self.help {
+            self.overlays.show(keys, screen);
+        }
+        if let Some(finder) = &self.finder {
+            self.overlays.show(finder, screen);
+        }
+        if let Some((dialog, _)) = &self.confirm {
+            self.overlays.show(dialog, screen);
+        }
+
+        match &self.command_line {
+            Some(line) => line.print(&self.status_bar),
+            None => self.status_bar.print(&self.buffers[self.current]),
+        }
+        screen::flush()?;
+        Ok(())
+    }
+}
+
+/// 同じキーを続けて押したイベントを読んで、まとめる
+/// # Arguments
+/// * `key` - 処理するキー
+/// * `input` - 入力を読むスレッドから、イベントを受け取る
+/// * `macros` - マクロを記録している場合に、まとめたキーも加える
+/// # Returns
+/// * `std::io::Result<u16>` - まとめたキーの数(`key`を除く)
+/// # Notes
+/// * 前に画面を更新してから`screen::FRAME_INTERVAL`が過ぎるまでに押したキーと、すでに届いているキーをまとめる
+/// * 別のキーを押した場合は、それ以降のキーはまとめない(読み捨てずに、次に処理する)
+async fn coalesce_key(
+    key: &KeyEvent,
+    input: &mut input::Input,
+    macros: &mut macros::Macros,
+) -> std::io::Result<u16> {
+    let next_frame_at = screen::next_frame_at();
+    let mut repeat: u16 = 0;
+    while input
+        .poll(next_frame_at.saturating_duration_since(Instant::now()))
+        .await?
+    {
+        match input.read().await? {
+            Event::Key(next)
+                if next.code == key.code
+                    && next.modifiers == key.modifiers
+                    && next.kind != KeyEventKind::Release =>
+            {
+                macros.record(next);
+                repeat = repeat.saturating_add(1);
+            }
+            event => {
+                input.unread(event);
+                break;
+            }
+        }
+    }
+    Ok(repeat)
+}
+
+/// 端末の大きさが決まるまで、続けて届く大きさの変更を読む
+/// # Arguments
+/// * `columns` - 変更した端末の横幅
+/// * `rows` - 変更した端末の高さ
+/// * `input` - 入力を読むスレッドから、イベントを受け取る
+/// # Returns
+/// * `std::io::Result<(u16, u16)>` - 最後に変更した端末の横幅と高さ
+/// # Notes
+/// * `screen::RESIZE_SETTLE_TIME`の間、大きさの変更が届かなくなるまで待つ
+/// * 別のイベントが届いた場合は、その時点の大きさで決まったものとする
+async fn settle_resize(
+    mut columns: u16,
+    mut rows: u16,
+    input: &mut input::Input,
+) -> std::io::Result<(u16, u16)> {
+    while input.poll(screen::RESIZE_SETTLE_TIME).await? {
+        match input.read().await? {
+            Event::Resize(next_columns, next_rows) => (columns, rows) = (next_columns, next_rows),
+            event => {
+                input.unread(event);
+                break;
+            }
+        }
+    }
+    Ok((columns, rows))
+}
+
+/// 押したキーから求めた、行うこと
+#[derive(Default)]
+struct ResolvedKey {
+    /// 押したキーに割り当てた操作
+    action: Option<keymap::Action>,
+    /// コマンドラインで入力した、キーに割り当てる操作以外のコマンド
+    command: Option<command_line::Command>,
+    /// 押したキーの列に割り当てたコマンド(`:`に続けて入力する文字列)
+    bound_command: Option<String>,
+    /// マクロの記録や再生に使ったキーか
+    macro_key: bool,
+}
+
+/// 入力を待っている間に起きたこと
+enum Wakeup {
+    /// キーを押したなど、端末からイベントが届いた(マクロを再生している場合も含む)
+    Input,
+    /// フックなどで実行するコマンドがある(端末のイベントは読まない)
+    Command(String),
+    /// 続けて押すキーを待つ時間を過ぎた
+    SequenceTimeout,
+    /// git blameを読み込み終えた(読み込めなかった場合はNone)
+    BlameLoaded(Option<Vec<String>>),
+    /// メッセージを表示する時間を過ぎた
+    MessageExpired,
+    /// 監視しているファイルが変更された
+    FilesChanged(HashSet<PathBuf>),
+    /// 設定ファイルや、末尾を追いかけているファイルの更新日時を確認する時刻になった
+    Tick,
+}
@@ -0,0 +1,414 @@
+use std::path::Path;
+
+use crate::config::TrailingWhitespace;
+
+/// インデントに使う文字
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentStyle {
+    /// タブ文字
+    Tab,
+    /// 空白文字
+    Space,
+}
+
+/// 改行文字
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndOfLine {
+    /// `\n`
+    Lf,
+    /// `\r\n`
+    CrLf,
+    /// `\r`
+    Cr,
+}
+
+impl EndOfLine {
+    /// 改行文字の名前を取得する
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Lf => "LF",
+            Self::CrLf => "CRLF",
+            Self::Cr => "CR",
+        }
+    }
+
+    /// 文字列で使われている改行文字を判定する
+    /// # Arguments
+    /// * `s` - 文字列
+    /// # Returns
+    /// * `Option<Option<EndOfLine>>` - 改行文字がない場合はNone、複数の種類が混ざっている場合は`Some(None)`
+    pub fn detect(s: &str) -> Option<Option<Self>> {
+        let mut found = None;
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            let end_of_line = match c {
+                '\r' if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                    Self::CrLf
+                }
+                '\r' => Self::Cr,
+                '\n' => Self::Lf,
+                _ => continue,
+            };
+
+            match found {
+                None => found = Some(end_of_line),
+                Some(previous) if previous != end_of_line => return Some(None),
+                _ => {}
+            }
+        }
+
+        found.map(Some)
+    }
+}
+
+/// `.editorconfig`で指定された設定
+/// 指定されていない項目はNone
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EditorConfig {
+    /// インデントに使う文字
+    pub indent_style: Option<IndentStyle>,
+    /// インデントの幅
+    pub indent_size: Option<u16>,
+    /// タブ文字を展開する幅
+    pub tab_width: Option<u16>,
+    /// 改行文字
+    pub end_of_line: Option<EndOfLine>,
+    /// 行末の空白を取り除くか
+    pub trim_trailing_whitespace: Option<bool>,
+}
+
+impl EditorConfig {
+    /// ファイルに適用される`.editorconfig`の設定を読み込む
+    /// # Arguments
+    /// * `path` - ファイルのパス
+    /// # Returns
+    /// * `EditorConfig` - ファイルに近いディレクトリの設定を優先して合わせた設定
+    /// # Notes
+    /// * ファイルのディレクトリから親ディレクトリへ向かって探し、`root = true`のファイルで止める
+    /// * 読み込めない`.editorconfig`は無視する
+    pub fn load(path: &Path) -> Self {
+        let Ok(path) = std::path::absolute(path) else {
+            return Self::default();
+        };
+
+        // 遠いディレクトリから順に適用して、近いディレクトリの設定で上書きする
+        let mut files = Vec::new();
+        for dir in path.ancestors().skip(1) {
+            let Ok(contents) = std::fs::read_to_string(dir.join(".editorconfig")) else {
+                continue;
+            };
+            let is_root = Self::is_root(&contents);
+            files.push((dir.to_path_buf(), contents));
+            if is_root {
+                break;
+            }
+        }
+
+        let mut config = Self::default();
+        for (dir, contents) in files.iter().rev() {
+            let Ok(relative) = path.strip_prefix(dir) else {
+                continue;
+            };
+            config.apply(contents, &relative.to_string_lossy().replace('\\', "/"));
+        }
+
+        config
+    }
+
+    /// タブ文字を展開する幅を取得する
+    /// `tab_width`がない場合は`indent_size`を使う
+    pub fn tab_width(&self) -> Option<u16> {
+        self.tab_width.or(self.indent_size)
+    }
+
+    /// 行末の空白の扱いを取得する
+    pub fn trailing_whitespace(&self) -> Option<TrailingWhitespace> {
+        self.trim_trailing_whitespace.map(|trim| {
+            if trim {
+                TrailingWhitespace::Trim
+            } else {
+                TrailingWhitespace::Show
+            }
+        })
+    }
+
+    /// インデントの設定を表す文字列を取得する(`spaces:4`や`tabs`など)
+    pub fn indent_name(&self) -> Option<String> {
+        match self.indent_style? {
+            IndentStyle::Tab => Some("tabs".to_string()),
+            IndentStyle::Space => Some(match self.indent_size {
+                Some(size) => format!("spaces:{}", size),
+                None => "spaces".to_string(),
+            }),
+        }
+    }
+
+    /// 先頭のセクションより前に`root = true`があるか
+    fn is_root(contents: &str) -> bool {
+        Self::parse(contents)
+            .take_while(|(section, _, _)| section.is_none())
+            .any(|(_, key, value)| key == "root" && value == "true")
+    }
+
+    /// `.editorconfig`のうち、ファイルに一致するセクションの設定を適用する
+    /// # Arguments
+    /// * `contents` - `.editorconfig`の内容
+    /// * `relative` - `.editorconfig`のディレクトリから見たファイルのパス(区切りは`/`)
+    fn apply(&mut self, contents: &str, relative: &str) {
+        for (section, key, value) in Self::parse(contents) {
+            let Some(section) = section else {
+                continue;
+            };
+            if !Self::section_matches(section, relative) {
+                continue;
+            }
+
+            match key.as_str() {
+                "indent_style" => {
+                    self.indent_style = match value.as_str() {
+                        "tab" => Some(IndentStyle::Tab),
+                        "space" => Some(IndentStyle::Space),
+                        _ => None,
+                    }
+                }
+                "indent_size" => {
+                    self.indent_size = match value.as_str() {
+                        // `tab`の場合は`tab_width`を使う
+                        "tab" => self.tab_width,
+                        _ => value.parse().ok().filter(|&size| size > 0),
+                    }
+                }
+                "tab_width" => self.tab_width = value.parse().ok().filter(|&size| size > 0),
+                "end_of_line" => {
+                    self.end_of_line = match value.as_str() {
+                        "lf" => Some(EndOfLine::Lf),
+                        "crlf" => Some(EndOfLine::CrLf),
+                        "cr" => Some(EndOfLine::Cr),
+                        _ => None,
+                    }
+                }
+                "trim_trailing_whitespace" => {
+                    self.trim_trailing_whitespace = match value.as_str() {
+                        "true" => Some(true),
+                        "false" => Some(false),
+                        _ => None,
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// `.editorconfig`を(セクション, キー, 値)の組に分解する
+    /// キーと値は小文字に変換する
+    fn parse(contents: &str) -> impl Iterator<Item = (Option<&str>, String, String)> {
+        let mut section = None;
+        contents.lines().filter_map(move |line| {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                return None;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = Some(name);
+                return None;
+            }
+
+            let (key, value) = line.split_once('=')?;
+            Some((
+                section,
+                key.trim().to_lowercase(),
+                value.trim().to_lowercase(),
+            ))
+        })
+    }
+
+    /// セクションのパターンがファイルのパスに一致するか
+    /// # Notes
+    /// * `/`を含まないパターンは、どのディレクトリのファイル名にも一致する
+    fn section_matches(section: &str, relative: &str) -> bool {
+        let pattern = if section.contains('/') {
+            section.strip_prefix('/').unwrap_or(section).to_string()
+        } else {
+            format!("**/{}", section)
+        };
+
+        Self::expand_braces(&pattern).iter().any(|pattern| {
+            let pattern: Vec<char> = pattern.chars().collect();
+            let path: Vec<char> = relative.chars().collect();
+            Self::glob_match(&pattern, &path)
+        })
+    }
+
+    /// `{a,b}`と`{1..3}`を展開する
+    fn expand_braces(pattern: &str) -> Vec<String> {
+        let Some(open) = pattern.find('{') else {
+            return vec![pattern.to_string()];
+        };
+        let Some(close) = pattern[open..].find('}').map(|i| i + open) else {
+            return vec![pattern.to_string()];
+        };
+
+        let inner = &pattern[open + 1..close];
+        let alternatives: Vec<String> = match inner.split_once("..") {
+            Some((start, end)) if !inner.contains(',') => {
+                match (start.parse::<i64>(), end.parse::<i64>()) {
+                    (Ok(start), Ok(end)) => (start.min(end)..=start.max(end))
+                        .map(|n| n.to_string())
+                        .collect(),
+                    _ => vec![format!("{{{}}}", inner)],
+                }
+            }
+            _ if inner.contains(',') => inner.split(',').map(|s| s.to_string()).collect(),
+            _ => vec![format!("{{{}}}", inner)],
+        };
+
+        let prefix = &pattern[..open];
+        Self::expand_braces(&pattern[close + 1..])
+            .iter()
+            .flat_map(|suffix| {
+                alternatives
+                    .iter()
+                    .map(move |alternative| format!("{}{}{}", prefix, alternative, suffix))
+            })
+            .collect()
+    }
+
+    /// グロブパターンがパスに一致するか
+    /// `*`は`/`以外の0文字以上、`**`は任意の0文字以上、`?`は`/`以外の1文字に一致する
+    fn glob_match(pattern: &[char], path: &[char]) -> bool {
+        match pattern {
+            [] => path.is_empty(),
+            ['*', '*', '/', rest @ ..] => {
+                // `**/`はディレクトリがない場合にも一致する
+                Self::glob_match(rest, path)
+                    || (0..path.len())
+                        .filter(|&i| path[i] == '/')
+                        .any(|i| Self::glob_match(rest, &path[i + 1..]))
+            }
+            ['*', '*', rest @ ..] => (0..=path.len()).any(|i| Self::glob_match(rest, &path[i..])),
+            ['*', rest @ ..] => {
+                let end = path.iter().position(|&c| c == '/').unwrap_or(path.len());
+                (0..=end).any(|i| Self::glob_match(rest, &path[i..]))
+            }
+            ['?', rest @ ..] => {
+                matches!(path.first(), Some(&c) if c != '/') && Self::glob_match(rest, &path[1..])
+            }
+            ['[', rest @ ..] => {
+                let Some(close) = rest.iter().position(|&c| c == ']') else {
+                    return path.first() == Some(&'[') && Self::glob_match(rest, &path[1..]);
+                };
+                let Some(&c) = path.first() else {
+                    return false;
+                };
+
+                let (negate, set) = match &rest[..close] {
+                    ['!', set @ ..] => (true, set),
+                    set => (false, set),
+                };
+                let mut found = false;
+                let mut i = 0;
+                while i < set.len() {
+                    if i + 2 < set.len() && set[i + 1] == '-' {
+                        found |= set[i] <= c && c <= set[i + 2];
+                        i += 3;
+                    } else {
+                        found |= set[i] == c;
+                        i += 1;
+                    }
+                }
+
+                found != negate && c != '/' && Self::glob_match(&rest[close + 1..], &path[1..])
+            }
+            ['\\', c, rest @ ..] | [c, rest @ ..] => {
+                path.first() == Some(c) && Self::glob_match(rest, &path[1..])
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_section_matches() {
+        assert!(EditorConfig::section_matches("*", "a.rs"));
+        assert!(EditorConfig::section_matches("*.rs", "src/main.rs"));
+        assert!(!EditorConfig::section_matches("*.rs", "src/main.py"));
+        assert!(EditorConfig::section_matches("*.{rs,toml}", "Cargo.toml"));
+        assert!(EditorConfig::section_matches("Makefile", "a/b/Makefile"));
+        assert!(EditorConfig::section_matches("/src/*.rs", "src/main.rs"));
+        assert!(!EditorConfig::section_matches("src/*.rs", "src/a/main.rs"));
+        assert!(EditorConfig::section_matches("src/**.rs", "src/a/main.rs"));
+        assert!(EditorConfig::section_matches("file[0-9].txt", "file3.txt"));
+        assert!(!EditorConfig::section_matches(
+            "file[!0-9].txt",
+            "file3.txt"
+        ));
+        assert!(EditorConfig::section_matches("v{1..3}.txt", "v2.txt"));
+        assert!(!EditorConfig::section_matches("v{1..3}.txt", "v4.txt"));
+    }
+
+    #[test]
+    fn test_apply() {
+        let contents = r#"
+            root = true
+
+            [*]
+            indent_style = space
+            indent_size = 4
+            end_of_line = lf
+
+            # Makefileはタブでインデントする
+            [Makefile]
+            indent_style = tab
+            tab_width = 8
+            indent_size = tab
+            trim_trailing_whitespace = true
+        "#;
+        assert!(EditorConfig::is_root(contents));
+
+        let mut config = EditorConfig::default();
+        config.apply(contents, "src/main.rs");
+        assert_eq!(config.indent_name(), Some("spaces:4".to_string()));
+        assert_eq!(config.tab_width(), Some(4));
+        assert_eq!(config.end_of_line, Some(EndOfLine::Lf));
+        assert_eq!(config.trailing_whitespace(), None);
+
+        let mut config = EditorConfig::default();
+        config.apply(contents, "Makefile");
+        assert_eq!(config.indent_name(), Some("tabs".to_string()));
+        assert_eq!(config.tab_width(), Some(8));
+        assert_eq!(config.trailing_whitespace(), Some(TrailingWhitespace::Trim));
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = std::env::temp_dir().join(format!("edoc-editorconfig-{}", std::process::id()));
+        let sub = dir.join("sub");
+        std::fs::create_dir_all(&sub).unwrap();
+        std::fs::write(
+            dir.join(".editorconfig"),
+            "root = true\n[*]\nindent_size = 2\nend_of_line = crlf\n",
+        )
+        .unwrap();
+        std::fs::write(sub.join(".editorconfig"), "[*.py]\nindent_size = 4\n").unwrap();
+
+        let config = EditorConfig::load(&sub.join("a.py"));
+        assert_eq!(config.indent_size, Some(4));
+        assert_eq!(config.end_of_line, Some(EndOfLine::CrLf));
+        assert_eq!(EditorConfig::load(&sub.join("a.rs")).indent_size, Some(2));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_end_of_line_detect() {
+        assert_eq!(EndOfLine::detect("a"), None);
+        assert_eq!(EndOfLine::detect("a\nb\n"), Some(Some(EndOfLine::Lf)));
+        assert_eq!(EndOfLine::detect("a\r\nb\r\n"), Some(Some(EndOfLine::CrLf)));
+        assert_eq!(EndOfLine::detect("a\r\nb\n"), Some(None));
+    }
+}
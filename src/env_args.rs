@@ -0,0 +1,115 @@
+use std::ffi::OsString;
+
+/// 既定のオプションを指定する環境変数の名前
+pub const VAR_NAME: &str = "EDOC";
+
+/// シェルと同じように、空白で区切った文字列を引数に分ける
+/// # Arguments
+/// * `s` - 空白で区切った文字列
+/// # Returns
+/// * `Result<Vec<String>, String>` - 分けた引数(引用符を閉じていない場合はエラー)
+/// # Notes
+/// * `'`で囲んだ部分はそのまま、`"`で囲んだ部分は`\`による`"`と`\`のエスケープだけ解釈する
+/// * 引用符の外では、`\`に続く1文字をそのまま使う
+pub fn split(s: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    // 引数の途中か(`""`のような空の引数も1つの引数にする)
+    let mut current: Option<String> = None;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                args.extend(current.take());
+            }
+            '\'' => {
+                let arg = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => return Err("Unterminated single quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let arg = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => arg.push(c),
+                            Some(c) => {
+                                arg.push('\\');
+                                arg.push(c);
+                            }
+                            None => return Err("Unterminated double quote".to_string()),
+                        },
+                        Some(c) => arg.push(c),
+                        None => return Err("Unterminated double quote".to_string()),
+                    }
+                }
+            }
+            '\\' => {
+                let arg = current.get_or_insert_with(String::new);
+                arg.extend(chars.next());
+            }
+            c => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    args.extend(current);
+
+    Ok(args)
+}
+
+/// コマンドライン引数の前に、環境変数で指定した既定のオプションを加える
+/// # Arguments
+/// * `args` - コマンドライン引数(最初はプログラム名)
+/// * `env` - 環境変数の値(設定されていない場合はNone)
+/// # Returns
+/// * `Result<Vec<OsString>, String>` - プログラム名、既定のオプション、コマンドライン引数の順に並べた引数
+/// # Notes
+/// * 後に指定したオプションが優先されるので、コマンドライン引数で既定のオプションを上書きできる
+pub fn merge(args: Vec<OsString>, env: Option<&str>) -> Result<Vec<OsString>, String> {
+    let Some(env) = env else {
+        return Ok(args);
+    };
+    let defaults = split(env).map_err(|e| format!("{}: {}", VAR_NAME, e))?;
+
+    let mut args = args.into_iter();
+    Ok(args
+        .next()
+        .into_iter()
+        .chain(defaults.into_iter().map(OsString::from))
+        .chain(args)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        assert_eq!(
+            split("  --theme dark\t-S ").unwrap(),
+            vec!["--theme", "dark", "-S"]
+        );
+        assert_eq!(
+            split(r#"--theme 'my theme' -p "a \"b\" \n" c\ d """#).unwrap(),
+            vec!["--theme", "my theme", "-p", r#"a "b" \n"#, "c d", ""]
+        );
+        assert!(split("--theme 'dark").is_err());
+        assert!(split("--theme \"dark").is_err());
+    }
+
+    #[test]
+    fn test_merge() {
+        let args = vec![OsString::from("edoc"), OsString::from("--theme=light")];
+        assert_eq!(
+            merge(args.clone(), Some("-S --theme dark")).unwrap(),
+            vec!["edoc", "-S", "--theme", "dark", "--theme=light"]
+        );
+        assert_eq!(merge(args.clone(), None).unwrap(), args);
+        assert!(merge(args, Some("'")).is_err());
+    }
+}
@@ -0,0 +1,60 @@
+/// 終了コード
+/// # Notes
+/// * スクリプトで失敗した理由を区別できるように、理由ごとに異なる値にする
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    /// 正常に終了した
+    Success = 0,
+    /// ファイルが見つからない
+    NotFound = 1,
+    /// ファイルの内容をUTF-8として読み取れない
+    InvalidData = 2,
+    /// 権限がないなど、そのほかの理由で読み書きできない
+    Io = 3,
+    /// 設定ファイルやテーマ、セッションを読み込めない
+    Config = 4,
+    /// コマンドライン引数が正しくないか、表示するものがない
+    Usage = 64,
+}
+
+impl ExitCode {
+    /// 終了コードを返して終了する
+    pub fn exit(self) -> ! {
+        std::process::exit(self as i32)
+    }
+}
+
+impl From<&std::io::Error> for ExitCode {
+    /// 読み書きのエラーの種類から、終了コードを決める
+    fn from(e: &std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::NotFound => Self::NotFound,
+            std::io::ErrorKind::InvalidData => Self::InvalidData,
+            std::io::ErrorKind::InvalidInput => Self::Usage,
+            _ => Self::Io,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_io_error() {
+        let error = |kind| std::io::Error::new(kind, "error");
+        assert_eq!(
+            ExitCode::from(&error(std::io::ErrorKind::NotFound)),
+            ExitCode::NotFound
+        );
+        assert_eq!(
+            ExitCode::from(&error(std::io::ErrorKind::InvalidData)),
+            ExitCode::InvalidData
+        );
+        assert_eq!(
+            ExitCode::from(&error(std::io::ErrorKind::PermissionDenied)),
+            ExitCode::Io
+        );
+        assert_eq!(ExitCode::Usage as i32, 64);
+    }
+}
@@ -0,0 +1,360 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use crate::cell;
+use crate::layout::Rect;
+use crate::screen;
+use crate::style::{ColorDepth, Style};
+
+/// ファイルツリーに表示する行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileTreeRow {
+    /// ファイルまたはディレクトリのパス
+    pub path: PathBuf,
+    /// 階層の深さ(一番上の階層は0)
+    pub depth: usize,
+    /// ディレクトリか
+    pub is_dir: bool,
+}
+
+/// エディタ領域の左に表示する、ディレクトリのツリー
+pub struct FileTree {
+    /// 一番上のディレクトリ
+    root: PathBuf,
+    /// 展開しているディレクトリ
+    expanded: HashSet<PathBuf>,
+    /// 名前に含まれる文字列で絞り込む(空の場合は絞り込まない)
+    pub filter: String,
+    /// 表示する行
+    rows: Vec<FileTreeRow>,
+    /// 選択している行の位置
+    pub selected: usize,
+    /// 表示しているか
+    pub visible: bool,
+    /// キーの入力をファイルツリーで受け付けるか
+    pub focused: bool,
+    /// 選択している行の装飾
+    pub selection_style: Style,
+    /// エディタ領域との境界線の装飾
+    pub separator_style: Style,
+    /// 端末で表示できる色数(`ColorDepth::None`の場合は装飾しない)
+    pub color_depth: ColorDepth,
+}
+
+impl FileTree {
+    /// 最大の横幅(境界線を含む)
+    const MAX_WIDTH: u16 = 30;
+
+    /// FileTreeを作成する
+    /// # Arguments
+    /// * `root` - 一番上のディレクトリ
+    pub fn new(root: PathBuf) -> Self {
+        let mut file_tree = Self {
+            root,
+            expanded: HashSet::new(),
+            filter: String::new(),
+            rows: Vec::new(),
+            selected: 0,
+            visible: false,
+            focused: false,
+            selection_style: Style {
+                reverse: true,
+                ..Default::default()
+            },
+            separator_style: Style::default(),
+            color_depth: ColorDepth::Ansi16,
+        };
+        file_tree.refresh();
+        file_tree
+    }
+
+    /// ディレクトリを読み直して、表示する行を作り直す
+    /// # Notes
+    /// * 選択している行が範囲外になった場合は、最後の行を選択する
+    pub fn refresh(&mut self) {
+        self.rows = self.read_rows(&self.root.clone(), 0);
+        self.selected = self.selected.min(self.rows.len().saturating_sub(1));
+    }
+
+    /// ディレクトリの中身を、展開しているディレクトリの中身も含めて読み込む
+    /// # Arguments
+    /// * `dir` - 読み込むディレクトリ
+    /// * `depth` - `dir`の中身の階層の深さ
+    /// # Notes
+    /// * 絞り込む場合は、名前が一致するか、中に一致する行があるディレクトリだけ表示する
+    fn read_rows(&self, dir: &Path, depth: usize) -> Vec<FileTreeRow> {
+        let filter = self.filter.to_lowercase();
+        let mut rows = Vec::new();
+        for (is_dir, path) in entries(dir) {
+            let children = if is_dir && self.expanded.contains(&path) {
+                self.read_rows(&path, depth + 1)
+            } else {
+                Vec::new()
+            };
+            let matched = name(&path).to_lowercase().contains(&filter);
+            if !matched && children.is_empty() {
+                continue;
+            }
+
+            rows.push(FileTreeRow {
+                path,
+                depth,
+                is_dir,
+            });
+            rows.extend(children);
+        }
+
+        rows
+    }
+
+    /// 表示する横幅を取得する
+    /// # Arguments
+    /// * `term_width` - 端末の横幅
+    /// # Returns
+    /// * `u16` - 境界線を含む横幅(表示していない場合は0)
+    pub fn width(&self, term_width: u16) -> u16 {
+        if self.visible {
+            (term_width / 3).min(Self::MAX_WIDTH)
+        } else {
+            0
+        }
+    }
+
+    /// 選択している行を取得する
+    pub fn selected_row(&self) -> Option<&FileTreeRow> {
+        self.rows.get(self.selected)
+    }
+
+    /// ひとつ前の行を選択する
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// ひとつ後の行を選択する
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.rows.len().saturating_sub(1));
+    }
+
+    /// 選択しているディレクトリを展開する
+    pub fn expand(&mut self) {
+        if let Some(row) = self.selected_row().filter(|row| row.is_dir) {
+            self.expanded.insert(row.path.clone());
+            self.refresh();
+        }
+    }
+
+    /// 選択しているディレクトリを折りたたむ
+    /// # Notes
+    /// * ファイルや折りたたんでいるディレクトリを選択している場合は、親のディレクトリを選択する
+    pub fn collapse(&mut self) {
+        let Some(row) = self.selected_row().cloned() else {
+            return;
+        };
+
+        if row.is_dir && self.expanded.remove(&row.path) {
+            self.refresh();
+        } else if let Some(parent) = self.rows[..self.selected]
+            .iter()
+            .rposition(|parent| parent.depth + 1 == row.depth)
+        {
+            self.selected = parent;
+        }
+    }
+
+    /// 選択しているディレクトリの展開と折りたたみを切り替える
+    pub fn toggle(&mut self) {
+        match self.selected_row() {
+            Some(row) if self.expanded.contains(&row.path) => self.collapse(),
+            _ => self.expand(),
+        }
+    }
+
+    /// 絞り込む文字列を変更する
+    /// # Arguments
+    /// * `filter` - 名前に含まれる文字列
+    pub fn set_filter(&mut self, filter: String) {
+        self.filter = filter;
+        self.selected = 0;
+        self.refresh();
+    }
+
+    /// 各行に表示する文字列を作成する
+    /// # Notes
+    /// * 階層の深さだけ字下げし、ディレクトリには展開の状態と末尾の`/`を付ける
+    fn lines(&self) -> Vec<String> {
+        self.rows
+            .iter()
+            .map(|row| {
+                let marker = match (row.is_dir, self.expanded.contains(&row.path)) {
+                    (true, true) => "▾ ",
+                    (true, false) => "▸ ",
+                    _ => "  ",
+                };
+                let suffix = if row.is_dir { "/" } else { "" };
+                format!(
+                    "{}{}{}{}",
+                    "  ".repeat(row.depth),
+                    marker,
+                    name(&row.path),
+                    suffix
+                )
+            })
+            .collect()
+    }
+
+    /// ファイルツリーを表示する
+    /// # Arguments
+    /// * `area` - 表示する領域(`width`で求めた横幅の、境界線を含む領域)
+    /// # Notes
+    /// * 一番上の行には、一番上のディレクトリの名前か絞り込む文字列を表示する
+    /// * 右端にエディタ領域との境界線を表示する
+    /// * 選択している行が見えるようにスクロールする
+    pub fn print(&self, area: Rect) {
+        let width = area.width as usize;
+        let height = area.height as usize;
+        if width < 2 || height == 0 {
+            return;
+        }
+        let inner_width = width - 1;
+
+        let title = if self.filter.is_empty() {
+            // `.`のような相対パスは分かりにくいので、ディレクトリの名前を表示する
+            let root = std::fs::canonicalize(&self.root).unwrap_or_else(|_| self.root.clone());
+            format!(" {}/", name(&root))
+        } else {
+            format!(" /{}", self.filter)
+        };
+        let ambiguous_wide = screen::ambiguous_wide();
+        let mut lines = vec![cell::pad(&title, inner_width, ambiguous_wide)];
+
+        let visible = height - 1;
+        let scroll = (self.selected + 1).saturating_sub(visible);
+        for (row, line) in self.lines().iter().enumerate().skip(scroll).take(visible) {
+            let line = cell::pad(line, inner_width, ambiguous_wide);
+            lines.push(if row == self.selected && self.focused {
+                self.selection_style.paint(&line, self.color_depth)
+            } else {
+                line
+            });
+        }
+        lines.resize(height, " ".repeat(inner_width));
+
+        let separator = self.separator_style.paint("│", self.color_depth);
+        for (y, line) in lines.iter().enumerate() {
+            screen::print_row(
+                area.x,
+                area.y + y as u16,
+                width as u16,
+                &format!("{}{}", line, separator),
+            )
+            .unwrap();
+        }
+    }
+}
+
+/// ディレクトリの中身を取得する
+/// # Arguments
+/// * `dir` - ディレクトリ
+/// # Returns
+/// * `Vec<(bool, PathBuf)>` - ディレクトリかと、パスの組
+/// # Notes
+/// * ディレクトリを先に、それぞれ名前の順に並べる
+/// * `.`で始まる名前は隠しファイルとして含めない
+fn entries(dir: &Path) -> Vec<(bool, PathBuf)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<(bool, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .map(|entry| (entry.path().is_dir(), entry.path()))
+        .collect();
+    entries.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+
+    entries
+}
+
+/// ディレクトリの中身の一覧を、表示する文字列にする
+/// # Arguments
+/// * `dir` - ディレクトリ
+/// # Returns
+/// * `String` - 1行目にディレクトリ、2行目以降に中身の名前を字下げして並べた文字列
+/// # Notes
+/// * ディレクトリには、名前の末尾に`/`を付ける
+pub fn listing(dir: &Path) -> String {
+    let mut lines = vec![format!(
+        "{}/",
+        dir.display().to_string().trim_end_matches('/')
+    )];
+    for (is_dir, path) in entries(dir) {
+        lines.push(format!(
+            "  {}{}",
+            name(&path),
+            if is_dir { "/" } else { "" }
+        ));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+/// パスの最後の部分の名前を取得する
+fn name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_tree() {
+        let dir = std::env::temp_dir().join(format!("edoc-file-tree-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::create_dir_all(dir.join(".git")).unwrap();
+        std::fs::write(dir.join("README.md"), "").unwrap();
+        std::fs::write(dir.join("src").join("main.rs"), "").unwrap();
+        std::fs::write(dir.join("src").join("lib.rs"), "").unwrap();
+
+        let mut file_tree = FileTree::new(dir.clone());
+        assert_eq!(file_tree.lines(), vec!["▸ src/", "  README.md"]);
+
+        file_tree.expand();
+        assert_eq!(
+            file_tree.lines(),
+            vec!["▾ src/", "    lib.rs", "    main.rs", "  README.md"]
+        );
+
+        // ファイルを選択して折りたたむと、親のディレクトリを選択する
+        file_tree.select_next();
+        file_tree.collapse();
+        assert_eq!(file_tree.selected, 0);
+
+        file_tree.set_filter("MAIN".to_string());
+        assert_eq!(file_tree.lines(), vec!["▾ src/", "    main.rs"]);
+
+        file_tree.toggle();
+        assert_eq!(file_tree.lines(), Vec::<String>::new());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_listing() {
+        let dir = std::env::temp_dir().join(format!("edoc-listing-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("b")).unwrap();
+        std::fs::write(dir.join("a.txt"), "").unwrap();
+        std::fs::write(dir.join(".hidden"), "").unwrap();
+
+        assert_eq!(
+            listing(&dir),
+            format!("{}/\n  b/\n  a.txt\n", dir.display())
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,337 @@
+use crate::style::{Highlight, Style};
+
+/// ファイルの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileType {
+    /// 名前(ステータスバーやモードラインで使う)
+    pub name: &'static str,
+    /// 拡張子
+    extensions: &'static [&'static str],
+    /// ファイル名(拡張子がないファイルなど)
+    file_names: &'static [&'static str],
+    /// シバンで指定するインタプリタ
+    interpreters: &'static [&'static str],
+    /// タブ文字を展開する幅
+    pub tab_width: Option<u16>,
+    /// 行コメントの開始文字列
+    pub line_comment: Option<&'static str>,
+}
+
+/// 対応しているファイルの種類
+const FILE_TYPES: &[FileType] = &[
+    FileType {
+        name: "rust",
+        extensions: &["rs"],
+        file_names: &[],
+        interpreters: &[],
+        tab_width: Some(4),
+        line_comment: Some("//"),
+    },
+    FileType {
+        name: "python",
+        extensions: &["py", "pyw"],
+        file_names: &[],
+        interpreters: &["python", "python2", "python3"],
+        tab_width: Some(4),
+        line_comment: Some("#"),
+    },
+    FileType {
+        name: "sh",
+        extensions: &["sh", "bash", "zsh"],
+        file_names: &[".bashrc", ".zshrc", ".profile"],
+        interpreters: &["sh", "bash", "zsh", "dash"],
+        tab_width: None,
+        line_comment: Some("#"),
+    },
+    FileType {
+        name: "javascript",
+        extensions: &["js", "mjs", "cjs"],
+        file_names: &[],
+        interpreters: &["node"],
+        tab_width: Some(2),
+        line_comment: Some("//"),
+    },
+    FileType {
+        name: "typescript",
+        extensions: &["ts", "tsx"],
+        file_names: &[],
+        interpreters: &[],
+        tab_width: Some(2),
+        line_comment: Some("//"),
+    },
+    FileType {
+        name: "c",
+        extensions: &["c", "h"],
+        file_names: &[],
+        interpreters: &[],
+        tab_width: None,
+        line_comment: Some("//"),
+    },
+    FileType {
+        name: "cpp",
+        extensions: &["cpp", "cc", "cxx", "hpp", "hh"],
+        file_names: &[],
+        interpreters: &[],
+        tab_width: None,
+        line_comment: Some("//"),
+    },
+    FileType {
+        name: "go",
+        extensions: &["go"],
+        file_names: &[],
+        interpreters: &[],
+        tab_width: Some(4),
+        line_comment: Some("//"),
+    },
+    FileType {
+        name: "json",
+        extensions: &["json"],
+        file_names: &[],
+        interpreters: &[],
+        tab_width: Some(2),
+        line_comment: None,
+    },
+    FileType {
+        name: "toml",
+        extensions: &["toml"],
+        file_names: &["Cargo.lock"],
+        interpreters: &[],
+        tab_width: None,
+        line_comment: Some("#"),
+    },
+    FileType {
+        name: "yaml",
+        extensions: &["yaml", "yml"],
+        file_names: &[],
+        interpreters: &[],
+        tab_width: Some(2),
+        line_comment: Some("#"),
+    },
+    FileType {
+        name: "markdown",
+        extensions: &["md", "markdown"],
+        file_names: &[],
+        interpreters: &[],
+        tab_width: None,
+        line_comment: None,
+    },
+    FileType {
+        name: "make",
+        extensions: &["mk"],
+        file_names: &["Makefile", "makefile", "GNUmakefile"],
+        interpreters: &[],
+        tab_width: Some(8),
+        line_comment: Some("#"),
+    },
+    FileType {
+        name: "diff",
+        extensions: &["diff", "patch"],
+        file_names: &[],
+        interpreters: &[],
+        tab_width: None,
+        line_comment: None,
+    },
+];
+
+impl FileType {
+    /// ファイルの種類を判定する
+    /// # Arguments
+    /// * `path` - ファイルのパス(標準入力の場合はNone)
+    /// * `contents` - ファイルの内容
+    /// # Returns
+    /// * `Option<FileType>` - 判定できた場合はファイルの種類、それ以外はNone
+    /// # Notes
+    /// * モードライン、ファイル名と拡張子、シバンの順に判定する
+    pub fn detect(path: Option<&str>, contents: &str) -> Option<Self> {
+        Self::from_modeline(contents)
+            .or_else(|| path.and_then(Self::from_path))
+            .or_else(|| Self::from_shebang(contents))
+    }
+
+    /// 名前からファイルの種類を取得する
+    pub fn from_name(name: &str) -> Option<Self> {
+        FILE_TYPES.iter().find(|t| t.name == name).copied()
+    }
+
+    /// ファイル名と拡張子からファイルの種類を判定する
+    /// # Notes
+    /// * 圧縮したファイルは、圧縮形式の拡張子を取り除いて判定する
+    fn from_path(path: &str) -> Option<Self> {
+        let path = std::path::Path::new(crate::decompress::strip_extension(path));
+        let file_name = path.file_name()?.to_str()?;
+        if let Some(file_type) = FILE_TYPES
+            .iter()
+            .find(|t| t.file_names.contains(&file_name))
+        {
+            return Some(*file_type);
+        }
+
+        let extension = path.extension()?.to_str()?;
+        FILE_TYPES
+            .iter()
+            .find(|t| t.extensions.contains(&extension))
+            .copied()
+    }
+
+    /// シバン(`#!/usr/bin/env python3`など)からファイルの種類を判定する
+    fn from_shebang(contents: &str) -> Option<Self> {
+        let shebang = contents.lines().next()?.strip_prefix("#!")?;
+        let mut words = shebang.split_whitespace();
+        let mut interpreter = words.next()?.rsplit('/').next()?;
+        if interpreter == "env" {
+            // `env -S`などのオプションは読み飛ばす
+            interpreter = words.find(|word| !word.starts_with('-'))?;
+        }
+
+        FILE_TYPES
+            .iter()
+            .find(|t| t.interpreters.contains(&interpreter))
+            .copied()
+    }
+
+    /// モードラインからファイルの種類を判定する
+    /// # Notes
+    /// * 先頭と末尾の5行から、Vim(`vim: set ft=python:`)とEmacs(`-*- mode: python -*-`)の形式を探す
+    fn from_modeline(contents: &str) -> Option<Self> {
+        let lines: Vec<&str> = contents.lines().collect();
+        let head = lines.iter().take(5);
+        let tail = lines.iter().skip(lines.len().saturating_sub(5).max(5));
+
+        head.chain(tail).find_map(|line| {
+            let name = Self::vim_modeline(line).or_else(|| Self::emacs_modeline(line))?;
+            Self::from_name(&name)
+        })
+    }
+
+    /// Vimのモードラインから`filetype`(`ft`)の値を取得する
+    fn vim_modeline(line: &str) -> Option<String> {
+        let index = ["vim:", "vi:", "ex:"]
+            .iter()
+            .filter_map(|marker| line.find(marker).map(|i| i + marker.len()))
+            .min()?;
+
+        line[index..]
+            .split(|c: char| c.is_whitespace() || c == ':')
+            .find_map(|option| {
+                option
+                    .strip_prefix("ft=")
+                    .or_else(|| option.strip_prefix("filetype="))
+            })
+            .map(|name| name.to_string())
+    }
+
+    /// Emacsのモードラインから`mode`の値を取得する
+    fn emacs_modeline(line: &str) -> Option<String> {
+        let start = line.find("-*-")? + 3;
+        let end = line[start..].find("-*-")? + start;
+        let body = line[start..end].trim();
+
+        // `-*- python -*-`の形式と`-*- mode: python; -*-`の形式がある
+        let name = if body.contains(':') {
+            body.split(';').find_map(|pair| {
+                let (key, value) = pair.split_once(':')?;
+                (key.trim() == "mode").then(|| value.trim())
+            })?
+        } else {
+            body
+        };
+
+        Some(name.to_lowercase())
+    }
+
+    /// 行コメントを強調表示する範囲を取得する
+    /// 構文解析をしない場合でも、コメントだけは区別できるようにする
+    /// # Arguments
+    /// * `contents` - ファイルの内容
+    /// * `style` - コメントの装飾
+    /// # Returns
+    /// * `Vec<Highlight>` - 行頭(空白を除く)から始まる行コメントの範囲
+    pub fn comment_highlights(&self, contents: &str, style: Style) -> Vec<Highlight> {
+        let Some(line_comment) = self.line_comment else {
+            return Vec::new();
+        };
+
+        let mut highlights = Vec::new();
+        let mut offset = 0;
+        for line in contents.split_inclusive('\n') {
+            let text = line.trim_end_matches(['\n', '\r']);
+            let indent = text.len() - text.trim_start().len();
+            if text[indent..].starts_with(line_comment) {
+                highlights.push(Highlight {
+                    range: offset + indent..offset + text.len(),
+                    style,
+                });
+            }
+            offset += line.len();
+        }
+
+        highlights
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(path: Option<&str>, contents: &str) -> Option<&'static str> {
+        FileType::detect(path, contents).map(|t| t.name)
+    }
+
+    #[test]
+    fn test_detect_by_path() {
+        assert_eq!(detect(Some("src/main.rs"), ""), Some("rust"));
+        assert_eq!(detect(Some("/tmp/Makefile"), ""), Some("make"));
+        assert_eq!(detect(Some("a.unknown"), ""), None);
+        assert_eq!(detect(None, "abc"), None);
+    }
+
+    #[test]
+    fn test_detect_by_shebang() {
+        assert_eq!(detect(None, "#!/bin/bash\necho"), Some("sh"));
+        assert_eq!(
+            detect(Some("script"), "#!/usr/bin/env python3\n"),
+            Some("python")
+        );
+        assert_eq!(
+            detect(None, "#!/usr/bin/env -S node --flag\n"),
+            Some("javascript")
+        );
+        assert_eq!(detect(None, "#!/usr/bin/env unknown\n"), None);
+    }
+
+    #[test]
+    /// モードラインはファイル名やシバンよりも優先する
+    fn test_detect_by_modeline() {
+        assert_eq!(
+            detect(Some("a.txt"), "# vim: set ft=python:\n"),
+            Some("python")
+        );
+        assert_eq!(detect(Some("a.rs"), "x\n# vi: filetype=sh\n"), Some("sh"));
+        assert_eq!(
+            detect(None, "# -*- mode: Python; coding: utf-8 -*-\n"),
+            Some("python")
+        );
+        assert_eq!(detect(None, "/* -*- c -*- */\n"), Some("c"));
+
+        // 先頭と末尾の5行以外は探さない
+        let contents = format!("{}# vim: ft=python\n{}", "\n".repeat(5), "\n".repeat(5));
+        assert_eq!(detect(None, &contents), None);
+    }
+
+    #[test]
+    fn test_comment_highlights() {
+        let style = Style {
+            dim: true,
+            ..Default::default()
+        };
+        let file_type = FileType::from_name("python").unwrap();
+        let contents = "# a\nx = 1 # b\n  # c\r\n";
+
+        let ranges: Vec<&str> = file_type
+            .comment_highlights(contents, style)
+            .iter()
+            .map(|h| &contents[h.range.clone()])
+            .collect();
+        assert_eq!(ranges, vec!["# a", "# c"]);
+    }
+}
@@ -0,0 +1,449 @@
+use std::path::Path;
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::cell;
+use crate::git;
+use crate::layout::Rect;
+use crate::overlay::Overlay;
+use crate::screen;
+use crate::style::{ColorDepth, Style};
+
+/// 一致した文字ごとの点数
+const SCORE_MATCH: i64 = 16;
+/// 単語の先頭(パスの区切りや`_`の直後、大文字)で一致した場合の加点
+const BONUS_BOUNDARY: i64 = 8;
+/// 直前の文字に続けて一致した場合の加点
+const BONUS_CONSECUTIVE: i64 = 8;
+/// ファイル名(パスの最後の部分)で一致した場合の加点
+const BONUS_FILE_NAME: i64 = 4;
+/// 一致した文字の間で飛ばした1文字ごとの減点
+const PENALTY_GAP: i64 = 1;
+
+/// gitのリポジトリでない場合に、ディレクトリをたどって集めるファイル数の上限
+const WALK_LIMIT: usize = 10000;
+
+/// 入力した文字を順番に含むかで、ファイルのパスを評価する
+/// # Arguments
+/// * `pattern` - 入力した文字列
+/// * `candidate` - ファイルのパス
+/// # Returns
+/// * `Option<(i64, Vec<usize>)>` - 点数と、一致した文字の位置(文字単位)
+///   `pattern`の文字を順番に含まない場合はNone
+/// # Examples
+/// ```
+/// let (_, positions) = fuzzy_match("mr", "src/main.rs").unwrap();
+/// assert_eq!(positions, vec![4, 9]);
+/// ```
+/// # Notes
+/// * `pattern`に大文字を含まない場合は、大文字と小文字を区別しない
+/// * 単語の先頭や連続して一致した場合の点数が高くなるように、最も点数の高い一致の仕方を選ぶ
+pub fn fuzzy_match(pattern: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+    let ignore_case = !pattern.chars().any(|c| c.is_uppercase());
+    let normalize = |c: char| {
+        if ignore_case {
+            c.to_lowercase().next().unwrap_or(c)
+        } else {
+            c
+        }
+    };
+    let pattern: Vec<char> = pattern.chars().map(normalize).collect();
+    let original: Vec<char> = candidate.chars().collect();
+    let chars: Vec<char> = original.iter().map(|&c| normalize(c)).collect();
+    if pattern.is_empty() {
+        return Some((0, Vec::new()));
+    }
+
+    let file_name_start = original
+        .iter()
+        .rposition(|&c| c == '/')
+        .map_or(0, |i| i + 1);
+    let bonus = |j: usize| {
+        let boundary = match j.checked_sub(1).map(|i| original[i]) {
+            None => true,
+            Some(previous) => {
+                matches!(previous, '/' | '_' | '-' | '.' | ' ')
+                    || (previous.is_lowercase() && original[j].is_uppercase())
+            }
+        };
+        SCORE_MATCH
+            + if boundary { BONUS_BOUNDARY } else { 0 }
+            + if j >= file_name_start {
+                BONUS_FILE_NAME
+            } else {
+                0
+            }
+    };
+
+    // scores[i][j]は、pattern[..=i]をpattern[i]がchars[j]と一致するように一致させたときの最高点
+    // 一致の仕方を復元するために、pattern[i - 1]と一致させた位置も覚えておく
+    let mut scores: Vec<Vec<Option<i64>>> = vec![vec![None; chars.len()]; pattern.len()];
+    let mut previous: Vec<Vec<usize>> = vec![vec![0; chars.len()]; pattern.len()];
+    for (j, &c) in chars.iter().enumerate() {
+        if c == pattern[0] {
+            scores[0][j] = Some(bonus(j));
+        }
+    }
+    for i in 1..pattern.len() {
+        // 2文字以上離れた位置のうち、飛ばした文字の減点を考えたときに最も点数の高い位置
+        // 減点は位置の差に比例するので、score + k * PENALTY_GAPが最大の位置kを覚えておけばよい
+        let mut best_gap: Option<(i64, usize)> = None;
+        for j in 1..chars.len() {
+            if j >= 2 {
+                if let Some(score) = scores[i - 1][j - 2] {
+                    let value = score + (j - 2) as i64 * PENALTY_GAP;
+                    if best_gap.is_none_or(|(best, _)| value > best) {
+                        best_gap = Some((value, j - 2));
+                    }
+                }
+            }
+            if chars[j] != pattern[i] {
+                continue;
+            }
+
+            let consecutive = scores[i - 1][j - 1].map(|score| (score + BONUS_CONSECUTIVE, j - 1));
+            let gap = best_gap.map(|(value, k)| (value - (j - 1) as i64 * PENALTY_GAP, k));
+            let best = match (consecutive, gap) {
+                (Some(a), Some(b)) => Some(if a.0 >= b.0 { a } else { b }),
+                (a, b) => a.or(b),
+            };
+            if let Some((score, k)) = best {
+                scores[i][j] = Some(score + bonus(j));
+                previous[i][j] = k;
+            }
+        }
+    }
+
+    let last = pattern.len() - 1;
+    let (mut j, score) = scores[last]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, score)| score.map(|score| (j, score)))
+        .max_by_key(|&(j, score)| (score, std::cmp::Reverse(j)))?;
+    let mut positions = vec![j];
+    for i in (1..pattern.len()).rev() {
+        j = previous[i][j];
+        positions.push(j);
+    }
+    positions.reverse();
+
+    Some((score, positions))
+}
+
+/// ディレクトリの下にあるファイルの一覧を取得する
+/// # Arguments
+/// * `dir` - ディレクトリ
+/// # Returns
+/// * `Vec<String>` - `dir`からの相対パスを名前の順に並べたもの
+/// # Notes
+/// * gitのリポジトリの場合は、.gitignoreで除外したファイルを含めない
+/// * gitのリポジトリでない場合は、`.`で始まる名前を除いてディレクトリをたどる
+pub fn index(dir: &Path) -> Vec<String> {
+    let mut files: Vec<String> = match git::list_files(dir) {
+        // 削除したがコミットしていないファイルも含まれるので、存在するファイルだけにする
+        Some(files) => files
+            .into_iter()
+            .filter(|file| dir.join(file).is_file())
+            .collect(),
+        None => {
+            let mut files = Vec::new();
+            walk(dir, dir, &mut files);
+            files
+        }
+    };
+    files.sort();
+
+    files
+}
+
+/// ディレクトリをたどって、ファイルの相対パスを集める
+fn walk(root: &Path, dir: &Path, files: &mut Vec<String>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        if files.len() >= WALK_LIMIT {
+            return;
+        }
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        let path = entry.path();
+        if path.is_dir() {
+            walk(root, &path, files);
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            files.push(relative.display().to_string());
+        }
+    }
+}
+
+/// ファイルのパスをあいまいに検索して開くための一覧を、エディタ領域に重ねて表示する
+pub struct Finder {
+    /// 枠の上に表示する見出し
+    pub title: String,
+    /// 検索するファイルのパス
+    files: Vec<String>,
+    /// 入力した文字列
+    pub query: String,
+    /// 一致したファイルの位置と、一致した文字の位置(点数の高い順)
+    matches: Vec<(usize, Vec<usize>)>,
+    /// 選択しているファイルの、`matches`での位置
+    pub selected: usize,
+    /// 選択しているファイルの装飾
+    pub selection_style: Style,
+    /// 一致した文字の装飾
+    pub match_style: Style,
+    /// 端末で表示できる色数(`ColorDepth::None`の場合は装飾しない)
+    pub color_depth: ColorDepth,
+}
+
+impl Finder {
+    /// 一度に表示するファイルの最大数
+    const MAX_VISIBLE: usize = 15;
+
+    /// Finderを作成する
+    /// # Arguments
+    /// * `files` - 検索するファイルのパス
+    pub fn new(files: Vec<String>) -> Self {
+        let mut finder = Self {
+            title: "Find files".to_string(),
+            files,
+            query: String::new(),
+            matches: Vec::new(),
+            selected: 0,
+            selection_style: Style {
+                reverse: true,
+                ..Default::default()
+            },
+            match_style: Style {
+                bold: true,
+                ..Default::default()
+            },
+            color_depth: ColorDepth::Ansi16,
+        };
+        finder.set_query(String::new());
+        finder
+    }
+
+    /// 入力した文字列を変更して、検索し直す
+    /// # Arguments
+    /// * `query` - 入力した文字列
+    /// # Notes
+    /// * 点数の高い順に、同じ点数の場合は短いパスから並べる
+    /// * 何も入力していない場合は、`files`の順に並べる
+    pub fn set_query(&mut self, query: String) {
+        let mut matches: Vec<(i64, usize, Vec<usize>)> = self
+            .files
+            .iter()
+            .enumerate()
+            .filter_map(|(i, file)| {
+                fuzzy_match(&query, file).map(|(score, positions)| (score, i, positions))
+            })
+            .collect();
+        if !query.is_empty() {
+            matches.sort_by(|a, b| {
+                b.0.cmp(&a.0)
+                    .then_with(|| self.files[a.1].len().cmp(&self.files[b.1].len()))
+                    .then_with(|| a.1.cmp(&b.1))
+            });
+        }
+
+        self.query = query;
+        self.matches = matches
+            .into_iter()
+            .map(|(_, i, positions)| (i, positions))
+            .collect();
+        self.selected = 0;
+    }
+
+    /// 選択しているファイルのパスを取得する
+    pub fn selected_file(&self) -> Option<&str> {
+        self.matches
+            .get(self.selected)
+            .map(|(i, _)| self.files[*i].as_str())
+    }
+
+    /// ひとつ前のファイルを選択する
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// ひとつ後のファイルを選択する
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.matches.len().saturating_sub(1));
+    }
+
+    /// 一致した文字を装飾したパスを作成する
+    /// # Arguments
+    /// * `index` - `matches`での位置
+    /// * `width` - 表示する横幅
+    /// # Notes
+    /// * 横幅に収まらない場合は、ファイル名が見えるように先頭を`…`で省略する
+    /// * 横幅に満たない部分は空白で埋める
+    fn line(&self, index: usize, width: usize) -> String {
+        let (i, positions) = &self.matches[index];
+        let chars: Vec<char> = self.files[*i].chars().collect();
+
+        let mut start = 0;
+        let mut total: usize = chars.iter().map(|c| c.width().unwrap_or(0)).sum();
+        let ellipsis = total > width;
+        if ellipsis {
+            total += 1;
+            while total > width && start < chars.len() {
+                total -= chars[start].width().unwrap_or(0);
+                start += 1;
+            }
+        }
+
+        let mut line = if ellipsis {
+            "…".to_string()
+        } else {
+            String::new()
+        };
+        for (j, c) in chars.iter().enumerate().skip(start) {
+            if positions.contains(&j) {
+                line.push_str(&self.match_style.paint(&c.to_string(), self.color_depth));
+            } else {
+                line.push(*c);
+            }
+        }
+
+        line + " ".repeat(width.saturating_sub(total)).as_str()
+    }
+}
+
+impl Overlay for Finder {
+    /// 表示する領域の中央に、枠で囲んだ入力欄と一致したファイルの一覧を表示する
+    /// # Arguments
+    /// * `area` - 表示する領域
+    /// # Notes
+    /// * 入力に合わせて枠の大きさが変わらないように、一覧の行数は一致したファイルの数によらず一定にする
+    /// * 一覧に収まらない場合は、選択しているファイルが見えるようにスクロールする
+    fn print(&self, area: Rect) -> Option<Rect> {
+        let (x_start, y_start) = (area.x, area.y);
+        let width = area.width as usize;
+        let height = area.height as usize;
+        if width < 3 || height < 5 {
+            return None;
+        }
+
+        let inner_width = (width * 3 / 5).max(40).min(width - 2);
+        let visible = (height - 4).min(Self::MAX_VISIBLE);
+        let x = x_start + ((width - inner_width - 2) / 2) as u16;
+        let y = y_start + ((height - visible - 4) / 2) as u16;
+        let scroll = (self.selected + 1).saturating_sub(visible);
+
+        let ambiguous_wide = screen::ambiguous_wide();
+        let title = cell::truncate(&format!(" {} ", self.title), inner_width, ambiguous_wide);
+        let title_width = cell::text_width(&title, ambiguous_wide);
+        let count = cell::truncate(
+            &format!(" {}/{} ", self.matches.len(), self.files.len()),
+            inner_width - title_width,
+            ambiguous_wide,
+        );
+        let mut lines = vec![
+            format!(
+                "┌{}{}{}┐",
+                title,
+                "─".repeat(inner_width - title_width - cell::text_width(&count, ambiguous_wide)),
+                count
+            ),
+            {
+                // 入力した文字列が長い場合は、末尾が見えるようにする
+                let query: String = format!("> {}", self.query);
+                let chars: Vec<char> = query.chars().collect();
+                let mut start = 0;
+                while chars[start..]
+                    .iter()
+                    .map(|c| c.width().unwrap_or(0))
+                    .sum::<usize>()
+                    > inner_width
+                {
+                    start += 1;
+                }
+                let query: String = chars[start..].iter().collect();
+                format!("│{}{}│", query, " ".repeat(inner_width - query.width()))
+            },
+            format!("├{}┤", "─".repeat(inner_width)),
+        ];
+
+        for row in scroll..scroll + visible {
+            let line = if row < self.matches.len() {
+                self.line(row, inner_width)
+            } else {
+                " ".repeat(inner_width)
+            };
+            let line = if row == self.selected && row < self.matches.len() {
+                self.selection_style.paint(&line, self.color_depth)
+            } else {
+                line
+            };
+            lines.push(format!("│{}│", line));
+        }
+        lines.push(format!("└{}┘", "─".repeat(inner_width)));
+
+        for (i, line) in lines.iter().enumerate() {
+            screen::print_row(x, y + i as u16, inner_width as u16 + 2, line).unwrap();
+        }
+
+        Some(Rect {
+            x,
+            y,
+            width: inner_width as u16 + 2,
+            height: lines.len() as u16,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_match() {
+        assert_eq!(fuzzy_match("", "a"), Some((0, vec![])));
+        assert_eq!(fuzzy_match("xyz", "src/main.rs"), None);
+        assert_eq!(fuzzy_match("mr", "src/main.rs").unwrap().1, vec![4, 9]);
+
+        // 単語の先頭や連続して一致するほうを選ぶ
+        assert_eq!(fuzzy_match("ma", "src/main.rs").unwrap().1, vec![4, 5]);
+        assert_eq!(
+            fuzzy_match("sb", "src/status_bar.rs").unwrap().1,
+            vec![4, 11]
+        );
+
+        // 大文字を含む場合だけ、大文字と小文字を区別する
+        assert!(fuzzy_match("readme", "README.md").is_some());
+        assert!(fuzzy_match("Readme", "readme.md").is_none());
+    }
+
+    #[test]
+    fn test_set_query() {
+        let mut finder = Finder::new(vec![
+            "src/contents.rs".to_string(),
+            "src/main.rs".to_string(),
+            "Cargo.toml".to_string(),
+        ]);
+        assert_eq!(finder.matches.len(), 3);
+
+        finder.set_query("main".to_string());
+        assert_eq!(finder.selected_file(), Some("src/main.rs"));
+
+        finder.set_query("rs".to_string());
+        assert_eq!(finder.matches.len(), 2);
+        finder.select_next();
+        finder.select_next();
+        assert_eq!(finder.selected, 1);
+    }
+
+    #[test]
+    fn test_line() {
+        let mut finder = Finder::new(vec!["src/main.rs".to_string()]);
+        finder.color_depth = ColorDepth::None;
+        finder.set_query("main".to_string());
+        assert_eq!(finder.line(0, 12), "src/main.rs ");
+        assert_eq!(finder.line(0, 8), "…main.rs");
+    }
+}
@@ -0,0 +1,447 @@
+use std::{collections::HashMap, path::Path, process::Command};
+
+use unicode_width::UnicodeWidthChar;
+
+/// HEADと比べた行の変更の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffMarker {
+    /// 追加した行
+    Added,
+    /// 変更した行
+    Modified,
+    /// 直前の行を削除した行
+    Removed,
+}
+
+/// gitのコマンドを実行して、標準出力を取得する
+/// # Arguments
+/// * `dir` - コマンドを実行するディレクトリ
+/// * `args` - gitのコマンドライン引数
+/// # Returns
+/// * `Option<String>` - コマンドが成功した場合は標準出力、それ以外はNone
+fn run(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// ファイルの各行がHEADと比べてどのように変更されたかを取得する
+/// # Arguments
+/// * `path` - ファイルのパス
+/// * `line_count` - ファイルの行数
+/// # Returns
+/// * `Option<Vec<Option<DiffMarker>>>` - 行ごとの変更の種類
+///   gitのリポジトリでない場合や、gitで管理していないファイルの場合はNone
+/// # Notes
+/// * ファイルを保存したり読み込み直したりした場合は、もう一度呼び出して計算し直す
+pub fn diff_markers(path: &Path, line_count: usize) -> Option<Vec<Option<DiffMarker>>> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let file_name = path.file_name()?.to_str()?;
+
+    // gitで管理していないファイルは、すべての行が追加した行になってしまうので表示しない
+    run(dir, &["ls-files", "--error-unmatch", "--", file_name])?;
+    let diff = run(
+        dir,
+        &[
+            "diff",
+            "--no-color",
+            "--no-ext-diff",
+            "-U0",
+            "HEAD",
+            "--",
+            file_name,
+        ],
+    )?;
+
+    Some(parse_diff(&diff, line_count))
+}
+
+/// unified形式の差分から、行ごとの変更の種類を計算する
+/// # Arguments
+/// * `diff` - 前後の行を含まない(`-U0`)unified形式の差分
+/// * `line_count` - 変更後のファイルの行数
+/// # Returns
+/// * `Vec<Option<DiffMarker>>` - 行ごとの変更の種類
+/// # Notes
+/// * 削除と追加を含む部分は、変更後の行数分を変更した行とし、残りを追加した行とする
+/// * 削除だけの部分は、削除した位置の次の行に削除の印を付ける
+pub fn parse_diff(diff: &str, line_count: usize) -> Vec<Option<DiffMarker>> {
+    let mut markers = vec![None; line_count];
+
+    for line in diff.lines() {
+        let Some(header) = line.strip_prefix("@@ -") else {
+            continue;
+        };
+        let Some((old, rest)) = header.split_once(" +") else {
+            continue;
+        };
+        let Some((new, _)) = rest.split_once(" @@") else {
+            continue;
+        };
+        let (_, old_count) = parse_range(old);
+        let (new_start, new_count) = parse_range(new);
+
+        if new_count == 0 {
+            // 削除だけの場合は、変更後の削除した位置(直前の行)が示される
+            let index = new_start.min(line_count.saturating_sub(1));
+            if let Some(marker) = markers.get_mut(index) {
+                marker.get_or_insert(DiffMarker::Removed);
+            }
+            continue;
+        }
+
+        for i in 0..new_count {
+            let marker = if i < old_count {
+                DiffMarker::Modified
+            } else {
+                DiffMarker::Added
+            };
+            if let Some(slot) = markers.get_mut(new_start - 1 + i) {
+                *slot = Some(marker);
+            }
+        }
+    }
+
+    markers
+}
+
+/// 差分の範囲(`開始行,行数`または`開始行`)を解析する
+fn parse_range(range: &str) -> (usize, usize) {
+    match range.split_once(',') {
+        Some((start, count)) => (start.parse().unwrap_or(0), count.parse().unwrap_or(0)),
+        None => (range.parse().unwrap_or(0), 1),
+    }
+}
+
+/// リポジトリの状態
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoStatus {
+    /// ブランチ名(ブランチがない場合は短いハッシュ)
+    pub branch: String,
+    /// コミットしていない変更があるか
+    pub dirty: bool,
+}
+
+impl RepoStatus {
+    /// ステータスバーに表示する文字列を取得する
+    /// 変更がある場合は、ブランチ名の後ろに`*`を付ける
+    pub fn format(&self) -> String {
+        format!("{}{}", self.branch, if self.dirty { "*" } else { "" })
+    }
+}
+
+/// ファイルを含むリポジトリの状態を取得する
+/// # Arguments
+/// * `path` - ファイルのパス
+/// # Returns
+/// * `Option<RepoStatus>` - gitのリポジトリでない場合はNone
+pub fn repo_status(path: &Path) -> Option<RepoStatus> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let output = run(dir, &["status", "--porcelain=v2", "--branch"])?;
+    parse_status(&output)
+}
+
+/// ディレクトリの下にある、.gitignoreで除外していないファイルの一覧を取得する
+/// # Arguments
+/// * `dir` - ディレクトリ
+/// # Returns
+/// * `Option<Vec<String>>` - `dir`からの相対パス(gitのリポジトリでない場合はNone)
+/// # Notes
+/// * 管理しているファイルに加えて、まだ追加していないファイルも含める
+pub fn list_files(dir: &Path) -> Option<Vec<String>> {
+    // 特殊な文字を含むパスが引用符で囲まれないように、NUL文字で区切って出力する
+    let output = run(
+        dir,
+        &[
+            "ls-files",
+            "-z",
+            "--cached",
+            "--others",
+            "--exclude-standard",
+        ],
+    )?;
+
+    Some(
+        output
+            .split('\0')
+            .filter(|file| !file.is_empty())
+            .map(|file| file.to_string())
+            .collect(),
+    )
+}
+
+/// `git status --porcelain=v2 --branch`の出力を解析する
+fn parse_status(porcelain: &str) -> Option<RepoStatus> {
+    let mut head = None;
+    let mut oid = None;
+    let mut dirty = false;
+    for line in porcelain.lines() {
+        if let Some(name) = line.strip_prefix("# branch.head ") {
+            head = Some(name);
+        } else if let Some(hash) = line.strip_prefix("# branch.oid ") {
+            oid = Some(hash);
+        } else if !line.starts_with('#') && !line.is_empty() {
+            dirty = true;
+        }
+    }
+
+    // HEADがブランチを指していない場合は`(detached)`になる
+    let branch = match head? {
+        "(detached)" => oid?.chars().take(8).collect(),
+        name => name.to_string(),
+    };
+
+    Some(RepoStatus { branch, dirty })
+}
+
+/// 行ごとの最後に変更したコミットの情報
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlameLine {
+    /// コミットのハッシュ
+    pub hash: String,
+    /// 作者
+    pub author: String,
+    /// 作成日時(UNIX時間に作者のタイムゾーンのオフセットを加えた秒数)
+    pub time: i64,
+}
+
+impl BlameLine {
+    /// 表示する作者の幅
+    const AUTHOR_WIDTH: usize = 12;
+
+    /// `短いハッシュ 作者 日付`の形式で表示する文字列を取得する
+    /// # Examples
+    /// ```
+    /// let line = BlameLine { hash: "0123456789abcdef".to_string(), author: "Alice".to_string(), time: 0 };
+    /// assert_eq!(line.format(), "01234567 Alice        1970-01-01");
+    /// ```
+    pub fn format(&self) -> String {
+        // 作者は表示幅を揃えるために、切り詰めるか空白で埋める
+        let mut author = String::new();
+        let mut width = 0;
+        for c in self.author.chars() {
+            let char_width = c.width().unwrap_or(0);
+            if width + char_width > Self::AUTHOR_WIDTH {
+                break;
+            }
+            author.push(c);
+            width += char_width;
+        }
+        author.push_str(&" ".repeat(Self::AUTHOR_WIDTH - width));
+
+        let hash: String = self.hash.chars().take(8).collect();
+        format!("{} {} {}", hash, author, format_date(self.time))
+    }
+}
+
+/// UNIX時間の秒数を`YYYY-MM-DD`の形式に変換する
+fn format_date(time: i64) -> String {
+    // 1970-01-01からの日数を、グレゴリオ暦の年月日に変換する
+    let days = time.div_euclid(86400);
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// ファイルの各行を最後に変更したコミットを取得する
+/// # Arguments
+/// * `path` - ファイルのパス
+/// # Returns
+/// * `Option<Vec<BlameLine>>` - 行ごとのコミットの情報
+///   gitのリポジトリでない場合や、gitで管理していないファイルの場合はNone
+/// # Notes
+/// * 大きなリポジトリでは時間がかかるので、別のスレッドで呼び出す
+pub fn blame(path: &Path) -> Option<Vec<BlameLine>> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let file_name = path.file_name()?.to_str()?;
+
+    let output = run(dir, &["blame", "--porcelain", "--", file_name])?;
+    Some(parse_blame(&output))
+}
+
+/// `git blame --porcelain`の出力を解析する
+/// # Notes
+/// * 作者などの情報は、同じコミットが最初に現れたときにだけ出力される
+pub fn parse_blame(porcelain: &str) -> Vec<BlameLine> {
+    let mut commits: HashMap<String, BlameLine> = HashMap::new();
+    let mut result = Vec::new();
+    let mut current: Option<String> = None;
+
+    for line in porcelain.lines() {
+        // 行の内容はタブで始まり、1行の情報の終わりを表す
+        if line.starts_with('\t') {
+            if let Some(hash) = current.take() {
+                if let Some(commit) = commits.get(&hash) {
+                    result.push(commit.clone());
+                }
+            }
+            continue;
+        }
+
+        let Some(hash) = current.clone() else {
+            // 情報の最初の行は`ハッシュ 元の行番号 行番号 [行数]`
+            if let Some(hash) = line
+                .split(' ')
+                .next()
+                .filter(|h| h.len() >= 40 && h.chars().all(|c| c.is_ascii_hexdigit()))
+            {
+                commits
+                    .entry(hash.to_string())
+                    .or_insert_with(|| BlameLine {
+                        hash: hash.to_string(),
+                        author: String::new(),
+                        time: 0,
+                    });
+                current = Some(hash.to_string());
+            }
+            continue;
+        };
+
+        let Some(commit) = commits.get_mut(&hash) else {
+            continue;
+        };
+        if let Some(author) = line.strip_prefix("author ") {
+            commit.author = author.to_string();
+        } else if let Some(time) = line.strip_prefix("author-time ") {
+            commit.time += time.parse::<i64>().unwrap_or(0);
+        } else if let Some(tz) = line.strip_prefix("author-tz ") {
+            // `+0900`の形式のオフセットを秒に変換する
+            let sign = if tz.starts_with('-') { -1 } else { 1 };
+            let digits = tz.trim_start_matches(['+', '-']);
+            if let (Ok(hours), Ok(minutes)) = (
+                digits.get(..2).unwrap_or("").parse::<i64>(),
+                digits.get(2..).unwrap_or("").parse::<i64>(),
+            ) {
+                commit.time += sign * (hours * 3600 + minutes * 60);
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_diff() {
+        let diff = "\
+diff --git a/a.txt b/a.txt
+--- a/a.txt
++++ b/a.txt
+@@ -1 +1 @@
+-a
++A
+@@ -3,0 +4,2 @@
++x
++y
+@@ -5,2 +7,0 @@
+-d
+-e
+@@ -8 +8,2 @@
+-f
++F
++g
+";
+        assert_eq!(
+            parse_diff(diff, 10),
+            vec![
+                Some(DiffMarker::Modified),
+                None,
+                None,
+                Some(DiffMarker::Added),
+                Some(DiffMarker::Added),
+                None,
+                None,
+                Some(DiffMarker::Modified),
+                Some(DiffMarker::Added),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_blame() {
+        let a = "a".repeat(40);
+        let b = "b".repeat(40);
+        let porcelain = format!(
+            "{a} 1 1 2\nauthor Alice\nauthor-time 86400\nauthor-tz +0900\nsummary x\n\tone\n\
+             {a} 2 2\n\ttwo\n\
+             {b} 1 3 1\nauthor 山田太郎さんと鈴木\nauthor-time 951782400\nauthor-tz -0100\n\tthree\n"
+        );
+
+        let lines: Vec<String> = parse_blame(&porcelain).iter().map(|l| l.format()).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "aaaaaaaa Alice        1970-01-02",
+                "aaaaaaaa Alice        1970-01-02",
+                "bbbbbbbb 山田太郎さん 2000-02-28",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_status() {
+        let clean = "# branch.oid 0123456789abcdef\n# branch.head main\n";
+        assert_eq!(parse_status(clean).unwrap().format(), "main");
+
+        let dirty = format!("{}1 .M N... 100644 100644 100644 a b src/main.rs\n", clean);
+        assert_eq!(parse_status(&dirty).unwrap().format(), "main*");
+
+        let detached = "# branch.oid 0123456789abcdef\n# branch.head (detached)\n? new.txt\n";
+        assert_eq!(parse_status(detached).unwrap().format(), "01234567*");
+
+        assert_eq!(parse_status(""), None);
+    }
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_868_800), "2000-03-01");
+        assert_eq!(format_date(-1), "1969-12-31");
+    }
+
+    #[test]
+    /// ファイルの先頭や末尾の行を削除した場合
+    fn test_parse_diff_removed() {
+        assert_eq!(
+            parse_diff("@@ -1 +0,0 @@\n-a\n", 2),
+            vec![Some(DiffMarker::Removed), None]
+        );
+        assert_eq!(
+            parse_diff("@@ -3 +2,0 @@\n-c\n", 2),
+            vec![None, Some(DiffMarker::Removed)]
+        );
+    }
+}
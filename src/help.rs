@@ -0,0 +1,154 @@
+use crate::cell;
+use crate::keymap::{Action, KeySequence};
+use crate::layout::Rect;
+use crate::overlay::Overlay;
+use crate::screen;
+
+/// キーの割り当ての一覧を、エディタ領域に重ねて表示する
+pub struct Help {
+    /// 操作と、割り当てたキーの列
+    entries: Vec<(String, String)>,
+    /// 一番上に表示している行の位置
+    scroll: usize,
+}
+
+impl Help {
+    /// Helpを作成する
+    /// # Arguments
+    /// * `bindings` - 操作と、割り当てたキーの列
+    pub fn new(bindings: &[(Action, Vec<KeySequence>)]) -> Self {
+        Self {
+            entries: bindings
+                .iter()
+                .map(|(action, keys)| {
+                    let keys: Vec<String> = keys.iter().map(|keys| keys.to_string()).collect();
+                    (action.to_string(), keys.join(", "))
+                })
+                .collect(),
+            scroll: 0,
+        }
+    }
+
+    /// 各操作を表示する行を作成する
+    /// # Notes
+    /// * `操作 キー, キー`の形式で、キーの桁をそろえる
+    fn lines(&self) -> Vec<String> {
+        let ambiguous_wide = screen::ambiguous_wide();
+        let action_width = self
+            .entries
+            .iter()
+            .map(|(action, _)| cell::text_width(action, ambiguous_wide))
+            .max()
+            .unwrap_or(0);
+
+        self.entries
+            .iter()
+            .map(|(action, keys)| {
+                format!(
+                    "{}{}  {}",
+                    action,
+                    " ".repeat(action_width - cell::text_width(action, ambiguous_wide)),
+                    keys
+                )
+            })
+            .collect()
+    }
+
+    /// 上にスクロールする
+    /// # Arguments
+    /// * `lines` - スクロールする行数
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    /// 下にスクロールする
+    /// # Arguments
+    /// * `lines` - スクロールする行数
+    /// * `height` - 表示する領域の高さ
+    /// # Notes
+    /// * 最後の行が一番下に来るより先にはスクロールしない
+    pub fn scroll_down(&mut self, lines: usize, height: u16) {
+        let visible = self.entries.len().min((height as usize).saturating_sub(2));
+        self.scroll = (self.scroll + lines).min(self.entries.len() - visible);
+    }
+}
+
+impl Overlay for Help {
+    /// 表示する領域の中央に、枠で囲んだ一覧を表示する
+    /// # Arguments
+    /// * `area` - 表示する領域
+    /// # Notes
+    /// * 領域に収まらない場合は、スクロールした位置から表示する
+    fn print(&self, area: Rect) -> Option<Rect> {
+        let (x_start, y_start) = (area.x, area.y);
+        let lines = self.lines();
+        let width = area.width as usize;
+        let height = area.height as usize;
+        if width < 3 || height < 3 {
+            return None;
+        }
+
+        let ambiguous_wide = screen::ambiguous_wide();
+        let inner_width = lines
+            .iter()
+            .map(|line| cell::text_width(line, ambiguous_wide))
+            .max()
+            .unwrap_or(0)
+            .max(cell::text_width(" Keys (Esc to close) ", ambiguous_wide))
+            .min(width - 2);
+        let visible = lines.len().min(height - 2);
+        let x = x_start + ((width - inner_width - 2) / 2) as u16;
+        let y = y_start + ((height - visible - 2) / 2) as u16;
+        // 端末の高さを変えた場合も、最後の行が一番下に来るより先にはスクロールしない
+        let scroll = self.scroll.min(lines.len() - visible);
+
+        let title = cell::truncate(" Keys (Esc to close) ", inner_width, ambiguous_wide);
+        let top = format!(
+            "┌{}{}┐",
+            title,
+            "─".repeat(inner_width - cell::text_width(&title, ambiguous_wide))
+        );
+        screen::print_row(x, y, inner_width as u16 + 2, &top).unwrap();
+
+        for (row, line) in lines.iter().skip(scroll).take(visible).enumerate() {
+            let line = format!("│{}│", cell::pad(line, inner_width, ambiguous_wide));
+            screen::print_row(x, y + 1 + row as u16, inner_width as u16 + 2, &line).unwrap();
+        }
+
+        let bottom = format!("└{}┘", "─".repeat(inner_width));
+        screen::print_row(x, y + 1 + visible as u16, inner_width as u16 + 2, &bottom).unwrap();
+
+        Some(Rect {
+            x,
+            y,
+            width: inner_width as u16 + 2,
+            height: visible as u16 + 2,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines() {
+        let mut help = Help::new(&[
+            (
+                Action::Quit,
+                vec!["ctrl+w".parse().unwrap(), "q".parse().unwrap()],
+            ),
+            (Action::GoToTop, vec!["g g".parse().unwrap()]),
+        ]);
+        assert_eq!(help.lines(), vec!["quit       ctrl+w, q", "go_to_top  g g"]);
+
+        // 最後の行が一番下に来るまでスクロールする
+        help.scroll_down(5, 3);
+        assert_eq!(help.scroll, 1);
+        help.scroll_down(5, 10);
+        assert_eq!(help.scroll, 0);
+        help.scroll_down(1, 3);
+        help.scroll_up(5);
+        assert_eq!(help.scroll, 0);
+    }
+}
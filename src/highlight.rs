@@ -0,0 +1,295 @@
+use std::ops::Range;
+
+use tree_sitter::{InputEdit, Language, Node, Parser, Point, Tree};
+
+use crate::filetype::FileType;
+use crate::style::{Highlight, Style, Theme};
+
+/// tree-sitterを使った構文の強調表示
+/// 最初に文字列全体を構文解析し、編集した場合は変更のあった範囲だけを強調表示し直す
+pub struct Highlighter {
+    /// 構文解析するファイルの種類の名前
+    name: &'static str,
+    /// 強調表示に使うテーマ
+    theme: Theme,
+    /// 構文解析器
+    parser: Parser,
+    /// 構文木
+    tree: Option<Tree>,
+    /// 構文解析した文字列
+    source: String,
+    /// 強調表示する範囲(開始位置の昇順)
+    highlights: Vec<Highlight>,
+}
+
+impl Highlighter {
+    /// 言語を指定してHighlighterを作成する
+    fn new(name: &'static str, language: Language, theme: &Theme) -> Self {
+        let mut parser = Parser::new();
+        parser
+            .set_language(&language)
+            .expect("the grammar should be compatible with the tree-sitter library");
+
+        Self {
+            name,
+            theme: theme.clone(),
+            parser,
+            tree: None,
+            source: String::new(),
+            highlights: Vec::new(),
+        }
+    }
+
+    /// ファイルの種類から言語を判定して、Highlighterを作成する
+    /// # Arguments
+    /// * `file_type` - ファイルの種類
+    /// * `theme` - 構文の種類ごとの装飾を決めるテーマ
+    /// # Returns
+    /// * `Option<Highlighter>` - 対応している言語の場合はHighlighter、それ以外はNone
+    pub fn for_filetype(file_type: &FileType, theme: &Theme) -> Option<Self> {
+        let language: Language = match file_type.name {
+            "rust" => tree_sitter_rust::LANGUAGE.into(),
+            "json" => tree_sitter_json::LANGUAGE.into(),
+            _ => return None,
+        };
+
+        Some(Self::new(file_type.name, language, theme))
+    }
+
+    /// 同じファイルの種類とテーマで、同じ文字列を強調表示しているかを判定する
+    /// # Notes
+    /// * 設定を変更して表示し直す場合などに、構文解析し直さずに使い回せるかを確かめる
+    pub fn is_for(&self, file_type: &FileType, theme: &Theme, source: &str) -> bool {
+        self.name == file_type.name && &self.theme == theme && self.source == source
+    }
+
+    /// 文字列全体を構文解析して、強調表示する範囲を計算する
+    pub fn parse(&mut self, source: &str) {
+        self.source = source.to_string();
+        self.tree = self.parser.parse(&self.source, None);
+
+        self.highlights.clear();
+        if let Some(tree) = &self.tree {
+            Self::collect(
+                tree.root_node(),
+                &(0..self.source.len()),
+                &self.theme,
+                &mut self.highlights,
+            );
+        }
+    }
+
+    /// 文字列の一部を置き換えて、変更のあった範囲だけを強調表示し直す
+    /// # Arguments
+    /// * `start_byte` - 置き換える範囲の開始位置
+    /// * `old_end_byte` - 置き換える範囲の終了位置
+    /// * `new_text` - 置き換える文字列
+    pub fn edit(&mut self, start_byte: usize, old_end_byte: usize, new_text: &str) {
+        let Some(mut old_tree) = self.tree.take() else {
+            let mut source = self.source.clone();
+            source.replace_range(start_byte..old_end_byte, new_text);
+            self.parse(&source);
+            return;
+        };
+
+        let old_source = std::mem::take(&mut self.source);
+        let mut new_source = old_source.clone();
+        new_source.replace_range(start_byte..old_end_byte, new_text);
+        let new_end_byte = start_byte + new_text.len();
+
+        old_tree.edit(&InputEdit {
+            start_byte,
+            old_end_byte,
+            new_end_byte,
+            start_position: Self::point(&old_source, start_byte),
+            old_end_position: Self::point(&old_source, old_end_byte),
+            new_end_position: Self::point(&new_source, new_end_byte),
+        });
+
+        let Some(new_tree) = self.parser.parse(&new_source, Some(&old_tree)) else {
+            self.parse(&new_source);
+            return;
+        };
+
+        // 編集した範囲より後ろの強調表示は、位置をずらして使い回す
+        let delta = new_end_byte as isize - old_end_byte as isize;
+        let mut highlights: Vec<Highlight> = self
+            .highlights
+            .drain(..)
+            .filter(|h| h.range.end <= start_byte || h.range.start >= old_end_byte)
+            .map(|mut h| {
+                if h.range.start >= old_end_byte {
+                    h.range.start = (h.range.start as isize + delta) as usize;
+                    h.range.end = (h.range.end as isize + delta) as usize;
+                }
+                h
+            })
+            .collect();
+
+        // 構文木が変わった範囲と、編集した範囲を強調表示し直す
+        let mut ranges: Vec<Range<usize>> = old_tree
+            .changed_ranges(&new_tree)
+            .map(|range| range.start_byte..range.end_byte)
+            .collect();
+        ranges.push(start_byte..new_end_byte);
+
+        for range in &ranges {
+            // 構文木が変わった範囲と重なる強調表示は、ノードの境界が変わっている可能性があるので捨てる
+            highlights.retain(|h| h.range.end <= range.start || h.range.start >= range.end);
+        }
+        for range in &ranges {
+            let mut collected = Vec::new();
+            Self::collect(new_tree.root_node(), range, &self.theme, &mut collected);
+            for highlight in collected {
+                // 複数の範囲から同じノードを集めることがあるので、重複を除く
+                if !highlights.contains(&highlight) {
+                    highlights.push(highlight);
+                }
+            }
+        }
+        highlights.sort_by_key(|h| h.range.start);
+
+        self.highlights = highlights;
+        self.source = new_source;
+        self.tree = Some(new_tree);
+    }
+
+    /// 強調表示する範囲を取得する
+    pub fn highlights(&self) -> &[Highlight] {
+        &self.highlights
+    }
+
+    /// バイト単位の位置を、行と列の位置に変換する
+    fn point(source: &str, byte: usize) -> Point {
+        let before = &source[..byte];
+        let row = before.matches('\n').count();
+        let column = before.rfind('\n').map_or(byte, |i| byte - i - 1);
+        Point { row, column }
+    }
+
+    /// 構文木をたどって、範囲内の強調表示するノードを集める
+    /// # Arguments
+    /// * `node` - たどり始めるノード
+    /// * `range` - 集める範囲
+    /// * `theme` - 構文の種類ごとの装飾を決めるテーマ
+    /// * `highlights` - 集めた強調表示を追加するベクタ
+    fn collect(node: Node, range: &Range<usize>, theme: &Theme, highlights: &mut Vec<Highlight>) {
+        if node.end_byte() <= range.start || node.start_byte() >= range.end {
+            return;
+        }
+
+        if let Some(style) = Self::classify(&node, theme) {
+            if node.start_byte() < node.end_byte() {
+                highlights.push(Highlight {
+                    range: node.start_byte()..node.end_byte(),
+                    style,
+                });
+            }
+            return;
+        }
+
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            Self::collect(child, range, theme, highlights);
+        }
+    }
+
+    /// ノードの種類から、テーマの装飾を決める
+    fn classify(node: &Node, theme: &Theme) -> Option<Style> {
+        match node.kind() {
+            "line_comment" | "block_comment" | "comment" => Some(theme.comment),
+            "string_literal" | "raw_string_literal" | "char_literal" | "string" => {
+                Some(theme.string)
+            }
+            "integer_literal" | "float_literal" | "number" | "boolean_literal" | "true"
+            | "false" | "null" => Some(theme.number),
+            "type_identifier" | "primitive_type" => Some(theme.type_name),
+            // 名前のないノードのうち、英字だけからなるものはキーワード(`fn`や`let`など)
+            kind if !node.is_named()
+                && kind.len() > 1
+                && kind.chars().all(|c| c.is_ascii_lowercase() || c == '_') =>
+            {
+                Some(theme.keyword)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 強調表示する範囲の文字列を取得する
+    fn highlighted(highlighter: &Highlighter) -> Vec<&str> {
+        highlighter
+            .highlights()
+            .iter()
+            .map(|h| &highlighter.source[h.range.clone()])
+            .collect()
+    }
+
+    /// ファイルの種類の名前からHighlighterを作成する
+    fn for_name(name: &str) -> Option<Highlighter> {
+        Highlighter::for_filetype(&FileType::from_name(name).unwrap(), &Theme::default())
+    }
+
+    #[test]
+    fn test_for_filetype() {
+        assert!(for_name("rust").is_some());
+        assert!(for_name("json").is_some());
+        assert!(for_name("markdown").is_none());
+        assert!(for_name("make").is_none());
+    }
+
+    #[test]
+    fn test_parse() {
+        let mut highlighter = for_name("rust").unwrap();
+        highlighter.parse("fn main() {\n    let x = 1; // one\n}\n");
+
+        assert_eq!(highlighted(&highlighter), vec!["fn", "let", "1", "// one"]);
+    }
+
+    #[test]
+    /// 構文の種類ごとの装飾は、テーマで指定したものを使う
+    fn test_theme() {
+        let mut theme = Theme::default();
+        theme.keyword.bold = true;
+        theme.comment.italic = true;
+        let file_type = FileType::from_name("rust").unwrap();
+        let mut highlighter = Highlighter::for_filetype(&file_type, &theme).unwrap();
+        let source = "let x = 1; // one";
+        highlighter.parse(source);
+
+        let styles: Vec<Style> = highlighter.highlights().iter().map(|h| h.style).collect();
+        assert_eq!(styles, vec![theme.keyword, theme.number, theme.comment]);
+
+        // テーマが変わった場合は、使い回さずに構文解析し直す
+        assert!(highlighter.is_for(&file_type, &theme, source));
+        assert!(!highlighter.is_for(&file_type, &Theme::default(), source));
+        assert!(!highlighter.is_for(&file_type, &theme, "let x = 2;"));
+    }
+
+    #[test]
+    /// 編集した場合は、全体を構文解析し直した場合と同じ結果になる
+    fn test_edit() {
+        let mut highlighter = for_name("rust").unwrap();
+        highlighter.parse("fn main() {\n    let x = 1;\n}\n");
+
+        // `1`を文字列に置き換える
+        let start = highlighter.source.find('1').unwrap();
+        highlighter.edit(start, start + 1, "\"one\"");
+        assert_eq!(highlighted(&highlighter), vec!["fn", "let", "\"one\""]);
+
+        // 先頭にコメントを追加すると、後ろの強調表示の位置がずれる
+        highlighter.edit(0, 0, "// main\n");
+        assert_eq!(
+            highlighted(&highlighter),
+            vec!["// main", "fn", "let", "\"one\""]
+        );
+
+        let mut expected = for_name("rust").unwrap();
+        expected.parse(&highlighter.source.clone());
+        assert_eq!(highlighter.highlights(), expected.highlights());
+    }
+}
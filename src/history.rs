@@ -0,0 +1,155 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::marks::Marks;
+
+/// 最近開いたファイル
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct HistoryEntry {
+    /// ファイルの絶対パス
+    pub path: String,
+    /// 最後に一番上に表示していた行番号(1から始まる)
+    pub line: usize,
+    /// 最後の横方向のスクロール位置
+    pub column: u16,
+    /// ファイルの行に付けた印
+    #[serde(default, skip_serializing_if = "Marks::is_empty")]
+    pub marks: Marks,
+}
+
+/// 最近開いたファイルと、それぞれの最後の表示位置
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct History {
+    /// 最近開いたファイル(最近開いた順)
+    pub files: Vec<HistoryEntry>,
+}
+
+impl History {
+    /// 覚えておくファイルの最大数
+    const MAX_FILES: usize = 100;
+
+    /// 履歴を保存するファイルのパスを取得する
+    fn path() -> Option<PathBuf> {
+        crate::config::state_dir().map(|dir| dir.join("history.toml"))
+    }
+
+    /// 状態を保存するディレクトリの`history.toml`を読み込む
+    /// # Returns
+    /// * `History` - 読み込んだ履歴
+    /// # Notes
+    /// * 履歴がない場合や読み込めない場合は、空の履歴を返す(履歴がなくても表示には困らないため)
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|contents| toml::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// 状態を保存するディレクトリの`history.toml`に書き込む
+    /// # Returns
+    /// * `Result<(), std::io::Error>` - 書き込めなかった場合はエラー
+    pub fn save(&self) -> Result<(), std::io::Error> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let contents = toml::to_string(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, contents)
+    }
+
+    /// ファイルの最後の表示位置を取得する
+    /// # Arguments
+    /// * `file` - ファイルのパス(相対パスでもよい)
+    pub fn get(&self, file: &str) -> Option<&HistoryEntry> {
+        let path = absolute(file)?;
+        self.files.iter().find(|entry| entry.path == path)
+    }
+
+    /// ファイルを最近開いたファイルの先頭に移動して、最後の表示位置を覚える
+    /// # Arguments
+    /// * `file` - ファイルのパス(相対パスでもよい)
+    /// * `line` - 一番上に表示していた行番号(Noneの場合は前回の位置のままにする)
+    /// * `column` - 横方向のスクロール位置
+    /// * `marks` - ファイルの行に付けた印
+    /// # Notes
+    /// * 最大数を超えた場合は、最も前に開いたファイルから忘れる
+    pub fn record(&mut self, file: &str, line: Option<usize>, column: u16, marks: &Marks) {
+        let Some(path) = absolute(file) else {
+            return;
+        };
+
+        let previous = self.files.iter().position(|entry| entry.path == path);
+        let previous = previous.map(|i| self.files.remove(i));
+        let line = line.or(previous.map(|entry| entry.line)).unwrap_or(1);
+        self.files.insert(
+            0,
+            HistoryEntry {
+                path,
+                line,
+                column,
+                marks: marks.clone(),
+            },
+        );
+        self.files.truncate(Self::MAX_FILES);
+    }
+}
+
+/// ファイルの絶対パスを取得する
+/// # Notes
+/// * シンボリックリンクなどで同じファイルが別のパスにならないように、実際のパスにする
+/// * ファイルが存在しない場合はNone
+fn absolute(file: &str) -> Option<String> {
+    std::fs::canonicalize(Path::new(file))
+        .ok()
+        .map(|path| path.display().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let dir = std::env::temp_dir().join(format!("edoc-history-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let a = dir.join("a.txt").display().to_string();
+        let b = dir.join("b.txt").display().to_string();
+        std::fs::write(&a, "").unwrap();
+        std::fs::write(&b, "").unwrap();
+
+        let mut marks = Marks::default();
+        marks.set('a', 3);
+        marks.set('Z', 7);
+
+        let mut history = History::default();
+        history.record(&a, Some(10), 2, &Marks::default());
+        history.record(&b, Some(5), 0, &marks);
+        history.record(&a, None, 0, &Marks::default());
+        let missing = dir.join("missing").display().to_string();
+        history.record(&missing, Some(1), 0, &Marks::default());
+
+        assert_eq!(history.files.len(), 2);
+        assert_eq!(history.files[0].line, 10);
+        assert_eq!(history.get(&b).map(|entry| entry.line), Some(5));
+        assert_eq!(
+            history.get(&b).map(|entry| entry.marks.get('Z')),
+            Some(Some(7))
+        );
+
+        // 保存した形式から読み込み直せる
+        let contents = toml::to_string(&history).unwrap();
+        assert_eq!(toml::from_str::<History>(&contents).unwrap(), history);
+
+        // 印を保存していない履歴も読み込める
+        let old = format!("[[files]]\npath = {:?}\nline = 4\ncolumn = 0\n", a);
+        let old = toml::from_str::<History>(&old).unwrap();
+        assert!(old.files[0].marks.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,156 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use serde::{de::IntoDeserializer, Deserialize};
+
+/// コマンドを実行する時点
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Hook {
+    /// ファイルを開いたとき(コマンドの出力を開いたときは含まない)
+    OnOpen,
+    /// キーを押したとき
+    OnKey,
+    /// ファイルを書き込んだとき
+    OnSave,
+}
+
+impl fmt::Display for Hook {
+    /// `on_save`のような、設定ファイルで使う名前で表示する
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::OnOpen => "on_open",
+            Self::OnKey => "on_key",
+            Self::OnSave => "on_save",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Hook {
+    type Err = String;
+
+    /// `on_save`のような、設定ファイルで使う名前から作成する
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let deserializer: serde::de::value::StrDeserializer<serde::de::value::Error> =
+            s.into_deserializer();
+        Self::deserialize(deserializer).map_err(|e| e.to_string())
+    }
+}
+
+/// 時点ごとに実行するコマンドの登録
+/// # Notes
+/// * コマンドは`:`に続けて入力する文字列で、`:!cargo fmt`や`:42`のように入力したものとして実行する
+/// * コマンドの`{file}`は、開いたファイルや書き込んだファイルの名前に置き換える
+/// * `:!`や`:|`のようにシェルで実行するコマンドでは、名前を引用符で囲んでから置き換える
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Hooks {
+    /// 時点ごとの、登録した順のコマンド
+    commands: HashMap<Hook, Vec<String>>,
+}
+
+impl Hooks {
+    /// 設定ファイルの`[hooks]`のコマンドを登録したHooksを作成する
+    /// # Arguments
+    /// * `config` - 時点ごとのコマンド
+    pub fn new(config: &HashMap<Hook, Vec<String>>) -> Self {
+        Self {
+            commands: config.clone(),
+        }
+    }
+
+    /// コマンドを登録する
+    /// # Arguments
+    /// * `hook` - 実行する時点
+    /// * `command` - 実行するコマンド
+    /// # Notes
+    /// * 同じ時点に登録したコマンドは、登録した順に実行する
+    pub fn register(&mut self, hook: Hook, command: String) {
+        self.commands.entry(hook).or_default().push(command);
+    }
+
+    /// 時点に登録したコマンドを取得する
+    /// # Arguments
+    /// * `hook` - 実行する時点
+    /// * `file` - 開いたファイルや書き込んだファイルの名前(ない場合は空文字列に置き換える)
+    /// # Returns
+    /// * `Vec<String>` - `{file}`を置き換えたコマンド、登録した順に並べる
+    pub fn commands(&self, hook: Hook, file: Option<&str>) -> Vec<String> {
+        let file = file.unwrap_or_default();
+        self.commands
+            .get(&hook)
+            .into_iter()
+            .flatten()
+            .map(|command| {
+                if command.trim_start().starts_with(['!', '|']) {
+                    command.replace("{file}", &quote(file))
+                } else {
+                    command.replace("{file}", file)
+                }
+            })
+            .collect()
+    }
+}
+
+/// シェルに渡すために、ファイル名を引用符で囲む
+/// # Notes
+/// * ファイル名に`;`や空白などが含まれていても、1つの引数として渡す
+fn quote(file: &str) -> String {
+    format!("'{}'", file.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hooks() {
+        let config = HashMap::from([(Hook::OnSave, vec!["!rustfmt {file}".to_string()])]);
+        let mut hooks = Hooks::new(&config);
+        hooks.register(Hook::OnSave, "reload".to_string());
+        hooks.register(Hook::OnOpen, "set nowrap".to_string());
+
+        assert_eq!(
+            hooks.commands(Hook::OnSave, Some("src/main.rs")),
+            vec!["!rustfmt 'src/main.rs'".to_string(), "reload".to_string()]
+        );
+        assert_eq!(hooks.commands(Hook::OnOpen, None), vec!["set nowrap"]);
+        assert!(hooks.commands(Hook::OnKey, None).is_empty());
+
+        // 設定ファイルで使う名前で表示する
+        for hook in [Hook::OnOpen, Hook::OnKey, Hook::OnSave] {
+            assert_eq!(hook.to_string().parse(), Ok(hook));
+        }
+        assert!("save".parse::<Hook>().is_err());
+    }
+
+    #[test]
+    fn test_quote_file() {
+        let config = HashMap::from([(
+            Hook::OnOpen,
+            vec![
+                "!wc -l {file}".to_string(),
+                "|!grep x {file}".to_string(),
+                "e {file}.bak".to_string(),
+            ],
+        )]);
+        let hooks = Hooks::new(&config);
+
+        // シェルで実行するコマンドでは、ファイル名をコマンドとして解釈させない
+        assert_eq!(
+            hooks.commands(Hook::OnOpen, Some("x;rm -rf ~")),
+            vec![
+                "!wc -l 'x;rm -rf ~'".to_string(),
+                "|!grep x 'x;rm -rf ~'".to_string(),
+                "e x;rm -rf ~.bak".to_string(),
+            ]
+        );
+        assert_eq!(
+            hooks.commands(Hook::OnOpen, Some("it's.txt")),
+            vec![
+                r"!wc -l 'it'\''s.txt'".to_string(),
+                r"|!grep x 'it'\''s.txt'".to_string(),
+                "e it's.txt.bak".to_string(),
+            ]
+        );
+    }
+}
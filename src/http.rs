@@ -0,0 +1,141 @@
+use std::time::Duration;
+
+use crate::loading;
+
+/// 接続を待つ時間
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// URLから取得した内容の情報
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Remote {
+    /// 取得したURL
+    pub url: String,
+    /// 応答のContent-Type(ない場合はNone)
+    pub content_type: Option<String>,
+    /// 応答の本文の大きさ(バイト単位)
+    pub size: u64,
+}
+
+/// HTTPかHTTPSのURLか
+/// # Arguments
+/// * `file` - コマンドライン引数などで指定したファイル名
+pub fn is_url(file: &str) -> bool {
+    ["http://", "https://"].iter().any(|scheme| {
+        file.get(..scheme.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(scheme))
+    })
+}
+
+/// URLの内容を取得する
+/// # Arguments
+/// * `url` - 取得するURL
+/// * `show_progress` - 時間がかかる場合に、取得した大きさを表示する場合はtrue
+/// # Returns
+/// * `std::io::Result<(Remote, Vec<u8>)>` - 応答の情報と本文
+/// # Notes
+/// * 404などで取得できない場合は、状態コードを含むエラーにする(404と410は`NotFound`)
+/// * `Content-Encoding`で圧縮された本文は、展開してから返す
+/// * 環境変数`HTTPS_PROXY`などでプロキシを指定した場合は、プロキシを通す
+pub fn fetch(url: &str, show_progress: bool) -> std::io::Result<(Remote, Vec<u8>)> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(CONNECT_TIMEOUT)
+        .try_proxy_from_env(true)
+        .user_agent(concat!(
+            env!("CARGO_PKG_NAME"),
+            "/",
+            env!("CARGO_PKG_VERSION")
+        ))
+        .build();
+
+    let response = match agent.get(url).call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(code, response)) => {
+            let kind = match code {
+                404 | 410 => std::io::ErrorKind::NotFound,
+                _ => std::io::ErrorKind::Other,
+            };
+            return Err(std::io::Error::new(
+                kind,
+                format!("{} {}", code, response.status_text()),
+            ));
+        }
+        Err(ureq::Error::Transport(e)) => return Err(std::io::Error::other(e.to_string())),
+    };
+
+    let content_type = response.header("Content-Type").map(str::to_string);
+    let total = response
+        .header("Content-Length")
+        .and_then(|length| length.parse().ok());
+    let body = loading::read_all(response.into_reader(), url, total, show_progress)?;
+
+    let remote = Remote {
+        url: url.to_string(),
+        content_type,
+        size: body.len() as u64,
+    };
+    Ok((remote, body))
+}
+
+/// URLのパスの部分を取得する
+/// # Notes
+/// * 拡張子からファイルの種類や圧縮形式を判定するために使うので、クエリとフラグメントは取り除く
+pub fn path(url: &str) -> &str {
+    let url = url.split(['?', '#']).next().unwrap_or_default();
+    let after_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    after_scheme
+        .find('/')
+        .map_or("/", |index| &after_scheme[index..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, Write};
+
+    #[test]
+    fn test_fetch() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            for status in ["200 OK", "404 Not Found"] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+                    line.clear();
+                }
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Type: text/plain\r\nContent-Length: 6\r\nConnection: close\r\n\r\nhello\n",
+                    status
+                )
+                .unwrap();
+            }
+        });
+
+        let url = format!("http://{}/a.txt", address);
+        let (remote, body) = fetch(&url, false).unwrap();
+        assert_eq!(body, b"hello\n");
+        assert_eq!(remote.content_type.as_deref(), Some("text/plain"));
+        assert_eq!(remote.size, 6);
+
+        let e = fetch(&url, false).unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::NotFound);
+        assert_eq!(e.to_string(), "404 Not Found");
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_url() {
+        assert!(is_url("https://example.com/a.log"));
+        assert!(is_url("HTTP://example.com"));
+        assert!(!is_url("http.txt"));
+
+        assert_eq!(
+            path("https://example.com/logs/app.log.gz?x=1#top"),
+            "/logs/app.log.gz"
+        );
+        assert_eq!(path("https://example.com"), "/");
+    }
+}
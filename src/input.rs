@@ -0,0 +1,210 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+    time::Duration,
+};
+
+use crossterm::event::{self, Event};
+use tokio::sync::mpsc;
+
+/// 入力を読むスレッドが、止めるように頼まれたかを確認する間隔
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// 入力を読むスレッドから、画面を表示するループに送るイベント
+pub enum AppEvent {
+    /// 端末で押したキーや、端末の大きさの変更など
+    Terminal(Event),
+    /// 端末から読めなくなった(スレッドは終了する)
+    Error(std::io::Error),
+}
+
+/// 別のスレッドで端末から読んだイベントを受け取る
+/// # Notes
+/// * 表示に時間がかかっている間も入力を読み続けるので、押したキーが抜けない
+/// * `crossterm::event`の`poll`と`read`の代わりに使い、ほかの待ち時間などと一緒に待てるようにする
+pub struct Input {
+    /// 入力を読むスレッドから、イベントを受け取る
+    receiver: mpsc::UnboundedReceiver<AppEvent>,
+    /// 先に受け取って、まだ処理していないイベント
+    deferred: Option<Event>,
+    /// 入力を読むのを止めているか
+    paused: Arc<AtomicBool>,
+    /// 入力を読むスレッドが、端末から読んでいる間だけ持つロック
+    reading: Arc<Mutex<()>>,
+}
+
+/// 入力を読むのを止めている間だけ持つ
+/// # Notes
+/// * 破棄すると、また読み始める
+pub struct Paused<'a> {
+    paused: &'a AtomicBool,
+    _reading: MutexGuard<'a, ()>,
+}
+
+impl Drop for Paused<'_> {
+    fn drop(&mut self) {
+        self.paused.store(false, Ordering::Release);
+    }
+}
+
+impl Input {
+    /// 入力を読むスレッドを始める
+    /// # Notes
+    /// * 端末をRawモードにしてから呼び出す
+    pub fn spawn() -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let paused = Arc::new(AtomicBool::new(false));
+        let reading = Arc::new(Mutex::new(()));
+
+        let thread_paused = Arc::clone(&paused);
+        let thread_reading = Arc::clone(&reading);
+        std::thread::spawn(move || loop {
+            if thread_paused.load(Ordering::Acquire) {
+                std::thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+
+            // 止めるように頼まれたときに、端末から読んでいる途中で止めないようにロックを持つ
+            let event = {
+                let _reading = thread_reading
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner);
+                match event::poll(POLL_INTERVAL) {
+                    Ok(true) => event::read().map(AppEvent::Terminal),
+                    Ok(false) => continue,
+                    Err(e) => Err(e),
+                }
+            };
+            let stop = event.is_err();
+            let event = event.unwrap_or_else(AppEvent::Error);
+
+            // 受け取る側がなくなった場合や、読めなくなった場合は終了する
+            if sender.send(event).is_err() || stop {
+                break;
+            }
+        });
+
+        Self {
+            receiver,
+            deferred: None,
+            paused,
+            reading,
+        }
+    }
+
+    /// イベントが届くまで待つ
+    /// # Arguments
+    /// * `timeout` - 待つ時間
+    /// # Returns
+    /// * `std::io::Result<bool>` - 処理するイベントがある場合はtrue
+    /// # Notes
+    /// * 先に受け取ったイベントがある場合は、待たずにtrueを返す
+    pub async fn poll(&mut self, timeout: Duration) -> std::io::Result<bool> {
+        match tokio::time::timeout(timeout, self.wait()).await {
+            Ok(result) => result.map(|_| true),
+            Err(_) => Ok(false),
+        }
+    }
+
+    /// イベントが届くまで待つ
+    /// # Notes
+    /// * 受け取ったイベントは、`read`で読むまで残しておく
+    /// * 途中でやめても(`tokio::select!`でほかのものが先に終わった場合など)、イベントは失われない
+    pub async fn wait(&mut self) -> std::io::Result<()> {
+        if self.deferred.is_none() {
+            let event = self.receiver.recv().await.ok_or_else(Self::disconnected)?;
+            self.deferred = Some(Self::into_event(event)?);
+        }
+        Ok(())
+    }
+
+    /// イベントが届くまで待って、受け取る
+    pub async fn read(&mut self) -> std::io::Result<Event> {
+        self.wait().await?;
+        self.deferred.take().ok_or_else(Self::disconnected)
+    }
+
+    /// 受け取ったイベントを戻して、次に処理する
+    /// # Arguments
+    /// * `event` - `read`で受け取って、まだ処理していないイベント
+    pub fn unread(&mut self, event: Event) {
+        self.deferred = Some(event);
+    }
+
+    /// 入力を読むのを止める
+    /// # Returns
+    /// * `Paused` - 破棄するまで止めておく
+    /// # Notes
+    /// * エディタなどの外部のプログラムに端末を渡す間に、入力を横取りしないように止める
+    /// * 入力を読むスレッドが端末から読んでいる途中の場合は、読み終わるまで待つ
+    pub fn pause(&self) -> Paused<'_> {
+        self.paused.store(true, Ordering::Release);
+        Paused {
+            paused: &self.paused,
+            _reading: self.reading.lock().unwrap_or_else(PoisonError::into_inner),
+        }
+    }
+
+    /// 入力を読むスレッドから受け取ったものを、イベントかエラーにする
+    fn into_event(event: AppEvent) -> std::io::Result<Event> {
+        match event {
+            AppEvent::Terminal(event) => Ok(event),
+            AppEvent::Error(e) => Err(e),
+        }
+    }
+
+    /// 入力を読むスレッドが終了していた場合のエラー
+    fn disconnected() -> std::io::Error {
+        std::io::Error::new(std::io::ErrorKind::BrokenPipe, "input thread has stopped")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    fn key(c: char) -> Event {
+        Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+    }
+
+    #[tokio::test]
+    async fn test_input() {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let mut input = Input {
+            receiver,
+            deferred: None,
+            paused: Arc::new(AtomicBool::new(false)),
+            reading: Arc::new(Mutex::new(())),
+        };
+        assert!(!input.poll(Duration::ZERO).await.unwrap());
+
+        // 待っている間に届いたイベントは、読むまで残しておく
+        sender.send(AppEvent::Terminal(key('a'))).unwrap();
+        sender.send(AppEvent::Terminal(key('b'))).unwrap();
+        assert!(input.poll(Duration::ZERO).await.unwrap());
+        assert!(input.poll(Duration::ZERO).await.unwrap());
+        assert_eq!(input.read().await.unwrap(), key('a'));
+
+        // 戻したイベントは、次に届いたイベントより先に読む
+        let event = input.read().await.unwrap();
+        input.unread(event);
+        assert_eq!(input.read().await.unwrap(), key('b'));
+
+        sender
+            .send(AppEvent::Error(std::io::Error::other("error")))
+            .unwrap();
+        assert!(input.read().await.is_err());
+        drop(sender);
+        assert!(input.poll(Duration::ZERO).await.is_err());
+
+        // 止めている間は止めたことが分かり、破棄すると読み始める
+        {
+            let _paused = input.pause();
+            assert!(input.paused.load(Ordering::Acquire));
+        }
+        assert!(!input.paused.load(Ordering::Acquire));
+    }
+}
@@ -0,0 +1,379 @@
+use std::collections::HashSet;
+
+/// JSONの値
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    /// `null`、`true`、`false`、数値、文字列(元の文字列のまま保持する)
+    Scalar(String),
+    /// 配列
+    Array(Vec<Value>),
+    /// オブジェクト(キーは引用符を含めた元の文字列)
+    Object(Vec<(String, Value)>),
+}
+
+/// 整形した行の情報
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct LineInfo {
+    /// この行で始まるオブジェクトや配列の番号
+    opens: Option<usize>,
+    /// この行を含む最も内側のオブジェクトや配列の番号
+    enclosing: Option<usize>,
+}
+
+/// 整形したJSONを、オブジェクトや配列を折りたたんで表示する
+pub struct JsonView {
+    /// 構文解析したJSON
+    root: Value,
+    /// 折りたたんでいるオブジェクトや配列の番号(出現順に0から数える)
+    folded: HashSet<usize>,
+    /// 整形した文字列
+    text: String,
+    /// 整形した各行の情報
+    lines: Vec<LineInfo>,
+}
+
+impl JsonView {
+    /// JSONを構文解析して、JsonViewを作成する
+    /// # Arguments
+    /// * `source` - JSONの文字列
+    /// # Returns
+    /// * `Option<JsonView>` - オブジェクトか配列のJSONの場合はJsonView、それ以外はNone
+    pub fn parse(source: &str) -> Option<Self> {
+        let trimmed = source.trim_start_matches('\u{feff}').trim();
+        if !trimmed.starts_with(['{', '[']) {
+            return None;
+        }
+
+        let mut parser = Parser {
+            source: trimmed.as_bytes(),
+            position: 0,
+        };
+        let root = parser.value()?;
+        parser.skip_whitespace();
+        if parser.position != parser.source.len() {
+            return None;
+        }
+
+        let mut view = Self {
+            root,
+            folded: HashSet::new(),
+            text: String::new(),
+            lines: Vec::new(),
+        };
+        view.render();
+        Some(view)
+    }
+
+    /// 整形した文字列を取得する
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// 各行の折りたたみの状態を取得する
+    /// # Returns
+    /// * `Vec<Option<bool>>` - 折りたためない行はNone、折りたたんでいる場合は`Some(true)`
+    pub fn fold_markers(&self) -> Vec<Option<bool>> {
+        self.lines
+            .iter()
+            .map(|info| info.opens.map(|id| self.folded.contains(&id)))
+            .collect()
+    }
+
+    /// 行のオブジェクトや配列を折りたたむ、または展開する
+    /// # Arguments
+    /// * `line` - 整形した文字列の行の位置(0から始まる)
+    /// # Returns
+    /// * `Option<usize>` - 折りたたみを切り替えたオブジェクトや配列が始まる行の位置
+    /// # Notes
+    /// * 行で始まるオブジェクトや配列がない場合は、その行を含むオブジェクトや配列を折りたたむ
+    pub fn toggle(&mut self, line: usize) -> Option<usize> {
+        let info = *self.lines.get(line)?;
+        let id = info.opens.or(info.enclosing)?;
+        if !self.folded.remove(&id) {
+            self.folded.insert(id);
+        }
+        self.render();
+
+        self.lines.iter().position(|info| info.opens == Some(id))
+    }
+
+    /// 折りたたみの状態に合わせて整形し直す
+    fn render(&mut self) {
+        let mut renderer = Renderer {
+            folded: &self.folded,
+            text: String::new(),
+            lines: Vec::new(),
+            next_id: 0,
+            enclosing: Vec::new(),
+        };
+        renderer.value(&self.root, "", 0, "");
+        self.text = renderer.text;
+        self.lines = renderer.lines;
+    }
+}
+
+/// JSONを整形する
+struct Renderer<'a> {
+    /// 折りたたんでいるオブジェクトや配列の番号
+    folded: &'a HashSet<usize>,
+    /// 整形した文字列
+    text: String,
+    /// 整形した各行の情報
+    lines: Vec<LineInfo>,
+    /// 次に出現するオブジェクトや配列の番号
+    next_id: usize,
+    /// 出力中の行を含むオブジェクトや配列の番号
+    enclosing: Vec<usize>,
+}
+
+impl Renderer<'_> {
+    /// インデントの幅
+    const INDENT: usize = 2;
+
+    /// 1行を出力する
+    fn line(&mut self, depth: usize, text: &str, opens: Option<usize>) {
+        self.text.push_str(&" ".repeat(depth * Self::INDENT));
+        self.text.push_str(text);
+        self.text.push('\n');
+        self.lines.push(LineInfo {
+            opens,
+            enclosing: self.enclosing.last().copied(),
+        });
+    }
+
+    /// 値を出力する
+    /// # Arguments
+    /// * `value` - 値
+    /// * `key` - オブジェクトのキー(`"key": `の形式、配列の要素の場合は空文字列)
+    /// * `depth` - インデントの深さ
+    /// * `suffix` - 値の後ろに続ける文字列(`,`または空文字列)
+    fn value(&mut self, value: &Value, key: &str, depth: usize, suffix: &str) {
+        let (open, close, len) = match value {
+            Value::Scalar(s) => {
+                self.line(depth, &format!("{}{}{}", key, s, suffix), None);
+                return;
+            }
+            Value::Array(items) => ('[', ']', items.len()),
+            Value::Object(members) => ('{', '}', members.len()),
+        };
+
+        // 番号は折りたたんでいても、中身の分だけ進める
+        let id = self.next_id;
+        self.next_id += 1;
+        if len == 0 {
+            self.line(depth, &format!("{}{}{}{}", key, open, close, suffix), None);
+            return;
+        }
+        if self.folded.contains(&id) {
+            self.next_id += Self::count_containers(value) - 1;
+            let unit = match (open, len) {
+                ('[', 1) => "item",
+                ('[', _) => "items",
+                (_, 1) => "key",
+                _ => "keys",
+            };
+            let text = format!("{}{}…{}{} // {} {}", key, open, close, suffix, len, unit);
+            self.line(depth, &text, Some(id));
+            return;
+        }
+
+        self.line(depth, &format!("{}{}", key, open), Some(id));
+        self.enclosing.push(id);
+        match value {
+            Value::Array(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    let suffix = if i + 1 < len { "," } else { "" };
+                    self.value(item, "", depth + 1, suffix);
+                }
+            }
+            Value::Object(members) => {
+                for (i, (name, member)) in members.iter().enumerate() {
+                    let suffix = if i + 1 < len { "," } else { "" };
+                    self.value(member, &format!("{}: ", name), depth + 1, suffix);
+                }
+            }
+            Value::Scalar(_) => unreachable!(),
+        }
+        self.line(depth, &format!("{}{}", close, suffix), None);
+        self.enclosing.pop();
+    }
+
+    /// 値に含まれるオブジェクトと配列の数を数える(値自身を含む)
+    fn count_containers(value: &Value) -> usize {
+        match value {
+            Value::Scalar(_) => 0,
+            Value::Array(items) => 1 + items.iter().map(Self::count_containers).sum::<usize>(),
+            Value::Object(members) => {
+                1 + members
+                    .iter()
+                    .map(|(_, member)| Self::count_containers(member))
+                    .sum::<usize>()
+            }
+        }
+    }
+}
+
+/// JSONの構文解析器
+struct Parser<'a> {
+    /// JSONの文字列
+    source: &'a [u8],
+    /// 読み込んでいる位置
+    position: usize,
+}
+
+impl Parser<'_> {
+    /// 空白を読み飛ばす
+    fn skip_whitespace(&mut self) {
+        while self
+            .source
+            .get(self.position)
+            .is_some_and(|c| matches!(c, b' ' | b'\t' | b'\n' | b'\r'))
+        {
+            self.position += 1;
+        }
+    }
+
+    /// 次の文字を読み込む
+    fn expect(&mut self, c: u8) -> Option<()> {
+        self.skip_whitespace();
+        (self.source.get(self.position) == Some(&c)).then(|| self.position += 1)
+    }
+
+    /// 元の文字列の一部を取得する
+    fn slice(&self, start: usize) -> String {
+        String::from_utf8_lossy(&self.source[start..self.position]).into_owned()
+    }
+
+    /// 値を読み込む
+    fn value(&mut self) -> Option<Value> {
+        self.skip_whitespace();
+        match self.source.get(self.position)? {
+            b'{' => self.object(),
+            b'[' => self.array(),
+            b'"' => self.string().map(Value::Scalar),
+            _ => {
+                let start = self.position;
+                while self
+                    .source
+                    .get(self.position)
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || b"+-.".contains(c))
+                {
+                    self.position += 1;
+                }
+
+                let literal = self.slice(start);
+                let is_valid = matches!(literal.as_str(), "null" | "true" | "false")
+                    || literal.parse::<f64>().is_ok_and(|n| n.is_finite())
+                        && literal.starts_with(|c: char| c == '-' || c.is_ascii_digit());
+                is_valid.then_some(Value::Scalar(literal))
+            }
+        }
+    }
+
+    /// 文字列を引用符を含めて読み込む
+    fn string(&mut self) -> Option<String> {
+        self.expect(b'"')?;
+        let start = self.position - 1;
+        loop {
+            match self.source.get(self.position)? {
+                b'"' => break,
+                b'\\' => self.position += 2,
+                c if *c < 0x20 => return None,
+                _ => self.position += 1,
+            }
+        }
+        self.position += 1;
+
+        Some(self.slice(start))
+    }
+
+    /// 配列を読み込む
+    fn array(&mut self) -> Option<Value> {
+        self.expect(b'[')?;
+        let mut items = Vec::new();
+        if self.expect(b']').is_some() {
+            return Some(Value::Array(items));
+        }
+
+        loop {
+            items.push(self.value()?);
+            if self.expect(b']').is_some() {
+                return Some(Value::Array(items));
+            }
+            self.expect(b',')?;
+        }
+    }
+
+    /// オブジェクトを読み込む
+    fn object(&mut self) -> Option<Value> {
+        self.expect(b'{')?;
+        let mut members = Vec::new();
+        if self.expect(b'}').is_some() {
+            return Some(Value::Object(members));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(b':')?;
+            members.push((key, self.value()?));
+            if self.expect(b'}').is_some() {
+                return Some(Value::Object(members));
+            }
+            self.expect(b',')?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert!(JsonView::parse("{\"a\": [1, 2.5e3, -0.1]}").is_some());
+        assert!(JsonView::parse("[\"\\\"\", true, null, {}]").is_some());
+        assert!(JsonView::parse("1").is_none());
+        assert!(JsonView::parse("{\"a\": 1,}").is_none());
+        assert!(JsonView::parse("[1] 2").is_none());
+        assert!(JsonView::parse("[nan]").is_none());
+    }
+
+    #[test]
+    fn test_render() {
+        let view = JsonView::parse("{\"a\":[1,2],\"b\":{},\"c\":\"x\"}").unwrap();
+        assert_eq!(
+            view.text(),
+            "{\n  \"a\": [\n    1,\n    2\n  ],\n  \"b\": {},\n  \"c\": \"x\"\n}\n"
+        );
+        assert_eq!(
+            view.fold_markers(),
+            vec![Some(false), Some(false), None, None, None, None, None, None]
+        );
+    }
+
+    #[test]
+    fn test_toggle() {
+        let mut view = JsonView::parse("{\"a\":[1,[2]],\"b\":[3]}").unwrap();
+
+        // 配列が始まる行では、その配列を折りたたむ
+        assert_eq!(view.toggle(1), Some(1));
+        assert_eq!(
+            view.text(),
+            "{\n  \"a\": […], // 2 items\n  \"b\": [\n    3\n  ]\n}\n"
+        );
+        assert_eq!(view.fold_markers()[1], Some(true));
+
+        // 配列の要素の行では、それを含む配列を折りたたむ
+        assert_eq!(view.toggle(3), Some(2));
+        assert_eq!(
+            view.text(),
+            "{\n  \"a\": […], // 2 items\n  \"b\": […] // 1 item\n}\n"
+        );
+
+        // もう一度切り替えると展開する
+        assert_eq!(view.toggle(1), Some(1));
+        assert_eq!(view.toggle(0), Some(0));
+        assert_eq!(view.text(), "{…} // 2 keys\n");
+    }
+}
@@ -0,0 +1,101 @@
+use crate::layout::Pane;
+
+/// 先頭や末尾、指定した行への移動などの大きな移動をする前の位置を覚えておき、順に戻ったり進んだりする
+#[derive(Debug, Default)]
+pub struct JumpList {
+    /// 移動する前の位置(古い順)
+    entries: Vec<Pane>,
+    /// 戻ったり進んだりしている位置(`entries.len()`の場合は、一番新しい位置より後ろにいる)
+    index: usize,
+}
+
+impl JumpList {
+    /// 覚えておく位置の最大数
+    const MAX_ENTRIES: usize = 100;
+
+    /// 大きな移動をする前の位置を加える
+    /// # Arguments
+    /// * `position` - 移動する前の位置
+    /// # Notes
+    /// * 戻っている途中で加えた場合は、戻った位置より後ろの位置を忘れる
+    /// * 直前に加えた位置と同じ位置は加えない
+    pub fn push(&mut self, position: Pane) {
+        self.entries.truncate(self.index + 1);
+        if self.entries.last() != Some(&position) {
+            self.entries.push(position);
+        }
+        if self.entries.len() > Self::MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+        self.index = self.entries.len();
+    }
+
+    /// 1つ前の位置に戻る
+    /// # Arguments
+    /// * `current` - 今の位置
+    /// # Returns
+    /// * `Option<Pane>` - 戻る位置(それより前の位置がない場合はNone)
+    /// # Notes
+    /// * 一番新しい位置より後ろから戻る場合は、今の位置に進んで戻れるように、今の位置を加える
+    pub fn back(&mut self, current: Pane) -> Option<Pane> {
+        if self.index == self.entries.len() {
+            self.push(current);
+            self.index = self.entries.len() - 1;
+        }
+        if self.index == 0 {
+            return None;
+        }
+
+        self.index -= 1;
+        Some(self.entries[self.index])
+    }
+
+    /// 1つ後の位置に進む
+    /// # Returns
+    /// * `Option<Pane>` - 進む位置(戻っていない場合はNone)
+    pub fn forward(&mut self) -> Option<Pane> {
+        if self.index + 1 >= self.entries.len() {
+            return None;
+        }
+
+        self.index += 1;
+        Some(self.entries[self.index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jump_list() {
+        let a = Pane::new(0, 0, 0);
+        let b = Pane::new(0, 0, 10);
+        let c = Pane::new(1, 0, 5);
+        let d = Pane::new(0, 0, 20);
+
+        let mut jumps = JumpList::default();
+        assert_eq!(jumps.back(a), None);
+        assert_eq!(jumps.forward(), None);
+
+        let mut jumps = JumpList::default();
+        jumps.push(a);
+        jumps.push(b);
+        jumps.push(b);
+
+        // 今の位置に進んで戻れる
+        assert_eq!(jumps.back(c), Some(b));
+        assert_eq!(jumps.back(b), Some(a));
+        assert_eq!(jumps.back(a), None);
+        assert_eq!(jumps.forward(), Some(b));
+        assert_eq!(jumps.forward(), Some(c));
+        assert_eq!(jumps.forward(), None);
+
+        // 戻っている途中で加えた場合は、後ろの位置を忘れる
+        jumps.back(c);
+        jumps.back(b);
+        jumps.push(d);
+        assert_eq!(jumps.entries, vec![a, d]);
+        assert_eq!(jumps.forward(), None);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::cell;
+use crate::keymap::{Action, KeySequence};
+use crate::layout::Rect;
+use crate::overlay::Overlay;
+use crate::screen;
+
+/// 続けて押すキーを待っている間に、続けて押せるキーと割り当てた操作の一覧を、エディタ領域の下端に重ねて表示する
+pub struct KeyHint {
+    /// それまでに押したキーの列
+    prefix: String,
+    /// 続けて押せるキーの列と、割り当てた操作の名前
+    entries: Vec<(String, String)>,
+}
+
+impl KeyHint {
+    /// KeyHintを作成する
+    /// # Arguments
+    /// * `prefix` - それまでに押したキーの列
+    /// * `continuations` - 続けて押せるキーの列と、割り当てた操作
+    pub fn new(prefix: &KeySequence, continuations: &[(KeySequence, Action)]) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            entries: continuations
+                .iter()
+                .map(|(keys, action)| (keys.to_string(), action.to_string()))
+                .collect(),
+        }
+    }
+
+    /// 一覧の各行を作成する
+    /// # Arguments
+    /// * `width` - 一覧の横幅
+    /// # Notes
+    /// * `キー 操作`の形式で、横幅に収まるだけ列を並べ、上から下に順に詰める
+    fn lines(&self, width: usize) -> Vec<String> {
+        let ambiguous_wide = screen::ambiguous_wide();
+        let key_width = self
+            .entries
+            .iter()
+            .map(|(keys, _)| cell::text_width(keys, ambiguous_wide))
+            .max()
+            .unwrap_or(0);
+        let cells: Vec<String> = self
+            .entries
+            .iter()
+            .map(|(keys, action)| {
+                format!(
+                    "{}{} {}",
+                    keys,
+                    " ".repeat(key_width - cell::text_width(keys, ambiguous_wide)),
+                    action
+                )
+            })
+            .collect();
+        let cell_width = cells
+            .iter()
+            .map(|text| cell::text_width(text, ambiguous_wide))
+            .max()
+            .unwrap_or(0);
+
+        // 列の間には2文字の空白を入れる
+        let columns = ((width + 2) / (cell_width + 2)).max(1);
+        let rows = cells.len().div_ceil(columns);
+
+        (0..rows)
+            .map(|row| {
+                let line = cells
+                    .iter()
+                    .skip(row)
+                    .step_by(rows)
+                    .map(|text| cell::pad(text, cell_width, ambiguous_wide))
+                    .collect::<Vec<_>>()
+                    .join("  ");
+                cell::truncate(line.trim_end(), width, ambiguous_wide)
+            })
+            .collect()
+    }
+}
+
+impl Overlay for KeyHint {
+    /// 表示する領域の下端に、枠で囲んだ一覧を表示する
+    /// # Arguments
+    /// * `area` - 表示する領域
+    /// # Notes
+    /// * 領域に収まらない行は表示しない
+    fn print(&self, area: Rect) -> Option<Rect> {
+        let (x_start, y_start) = (area.x, area.y);
+        let width = area.width as usize;
+        let height = area.height as usize;
+        if width < 3 || height < 3 {
+            return None;
+        }
+
+        let inner_width = width - 2;
+        let lines = self.lines(inner_width);
+        let visible = lines.len().min(height - 2);
+        let y = y_start + (height - visible - 2) as u16;
+
+        let ambiguous_wide = screen::ambiguous_wide();
+        let title = cell::truncate(&format!(" {} ", self.prefix), inner_width, ambiguous_wide);
+        let top = format!(
+            "┌{}{}┐",
+            title,
+            "─".repeat(inner_width - cell::text_width(&title, ambiguous_wide))
+        );
+        screen::print_row(x_start, y, inner_width as u16 + 2, &top).unwrap();
+
+        for (row, line) in lines.iter().take(visible).enumerate() {
+            let line = format!("│{}│", cell::pad(line, inner_width, ambiguous_wide));
+            screen::print_row(x_start, y + 1 + row as u16, inner_width as u16 + 2, &line).unwrap();
+        }
+
+        let bottom = format!("└{}┘", "─".repeat(inner_width));
+        screen::print_row(
+            x_start,
+            y + 1 + visible as u16,
+            inner_width as u16 + 2,
+            &bottom,
+        )
+        .unwrap();
+
+        Some(Rect {
+            x: x_start,
+            y,
+            width: width as u16,
+            height: visible as u16 + 2,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines() {
+        let hint = KeyHint::new(
+            &"ctrl+x".parse().unwrap(),
+            &[
+                ("0".parse().unwrap(), Action::ClosePane),
+                ("2".parse().unwrap(), Action::SplitHorizontal),
+                ("ctrl+c".parse().unwrap(), Action::Quit),
+            ],
+        );
+
+        // 横幅に収まるだけ列を並べ、上から下に順に詰める
+        assert_eq!(
+            hint.lines(60),
+            vec![
+                "0      close_pane        ctrl+c quit",
+                "2      split_horizontal"
+            ]
+        );
+        assert_eq!(
+            hint.lines(20),
+            vec!["0      close_pane", "2      split_horizon", "ctrl+c quit"]
+        );
+    }
+}
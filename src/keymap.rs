@@ -0,0 +1,886 @@
+use std::{collections::HashMap, fmt, str::FromStr, time::Duration};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use serde::{de::IntoDeserializer, Deserialize, Deserializer};
+
+/// 続けて押すキーを待つ時間
+/// 過ぎた場合は、それまでに押したキーに割り当てた操作を行う
+pub const SEQUENCE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// キーに割り当てる操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// 終了する
+    Quit,
+    /// 上にスクロールする
+    ScrollUp,
+    /// 下にスクロールする
+    ScrollDown,
+    /// 左にスクロールする
+    ScrollLeft,
+    /// 右にスクロールする
+    ScrollRight,
+    /// 1画面分下にスクロールする
+    PageDown,
+    /// 1画面分上にスクロールする
+    PageUp,
+    /// 半画面分下にスクロールする
+    HalfPageDown,
+    /// 半画面分上にスクロールする
+    HalfPageUp,
+    /// 先頭に移動する
+    GoToTop,
+    /// 末尾に移動する
+    GoToBottom,
+    /// `--pattern`で指定した文字列を含む次の行に移動する
+    SearchNext,
+    /// `--pattern`で指定した文字列を含む前の行に移動する
+    SearchPrevious,
+    /// コマンドラインを開く(`:n`や`:42`など)
+    Command,
+    /// 次のファイルを表示する
+    NextFile,
+    /// 前のファイルを表示する
+    PreviousFile,
+    /// 開いているファイルの一覧を表示する
+    BufferList,
+    /// カレントディレクトリの下にあるファイルを探して開く
+    FindFile,
+    /// 最近開いたファイルから探して開く
+    RecentFiles,
+    /// ファイルツリーの表示と操作を切り替える
+    ToggleFileTree,
+    /// エディタ領域を上下に分割する
+    SplitHorizontal,
+    /// エディタ領域を左右に分割する
+    SplitVertical,
+    /// 操作する領域を閉じる
+    ClosePane,
+    /// 操作する領域を切り替える
+    FocusNextPane,
+    /// ほかの領域も同じだけスクロールするかを切り替える
+    ToggleScrollLock,
+    /// 上下に分割した領域の境界線を上に動かす
+    ResizePaneUp,
+    /// 上下に分割した領域の境界線を下に動かす
+    ResizePaneDown,
+    /// 左右に分割した領域の境界線を左に動かす
+    ResizePaneLeft,
+    /// 左右に分割した領域の境界線を右に動かす
+    ResizePaneRight,
+    /// 表示しているファイルを新しいタブページで開く
+    NewTab,
+    /// 表示しているタブページを閉じる
+    CloseTab,
+    /// 次のタブページを表示する
+    NextTab,
+    /// 前のタブページを表示する
+    PreviousTab,
+    /// 空白文字の表示を切り替える
+    ToggleWhitespace,
+    /// git blameの表示を切り替える
+    ToggleBlame,
+    /// Markdownの装飾した表示と元の文字列の表示を切り替える
+    ToggleMarkdown,
+    /// JSONを整形した表示と元の文字列の表示を切り替える
+    ToggleJson,
+    /// 整形したJSONの一番上の行のオブジェクトや配列を折りたたむ/展開する
+    ToggleFold,
+    /// 次の改ページに移動する
+    NextPageBreak,
+    /// 前の改ページに移動する
+    PreviousPageBreak,
+    /// 差分を左右に並べる表示と切り替える
+    ToggleSideBySide,
+    /// 次の差分のまとまりに移動する
+    NextHunk,
+    /// 前の差分のまとまりに移動する
+    PreviousHunk,
+    /// 差分の次のファイルに移動する
+    NextDiffFile,
+    /// 差分の前のファイルに移動する
+    PreviousDiffFile,
+    /// 設定ファイルを読み込み直す
+    ReloadConfig,
+    /// 続けて押す文字のレジスタに、押したキーの記録を始める(記録している場合は終える)
+    RecordMacro,
+    /// 続けて押す文字のレジスタに記録したキーを再生する(数字を先に押すと、その回数だけ繰り返す)
+    PlayMacro,
+    /// キーの割り当ての一覧を表示する
+    Help,
+    /// 続けて押す文字の名前で、一番上に表示している行に印を付ける
+    SetMark,
+    /// 続けて押す文字の名前の印を付けた行に移動する
+    JumpToMark,
+    /// 行番号の左に印を表示するかを切り替える
+    ToggleMarks,
+    /// 先頭や末尾への移動などの大きな移動をする前の位置に戻る
+    JumpBack,
+    /// 戻る前の位置に進む
+    JumpForward,
+    /// 一番上の行の括弧に対応する括弧の行に移動する
+    MatchingBracket,
+    /// 次の単語の先頭に移動する
+    NextWord,
+    /// 前の単語の先頭に移動する
+    PreviousWord,
+    /// 次の単語の末尾に移動する
+    NextWordEnd,
+    /// 1文字左に移動する
+    CursorLeft,
+    /// 1文字右に移動する
+    CursorRight,
+    /// 上の行に移動する
+    CursorUp,
+    /// 下の行に移動する
+    CursorDown,
+    /// 操作する位置がある行を強調表示するかを切り替える
+    ToggleCursorLine,
+    /// 操作する位置から選択を始める(選択している場合はやめる)
+    ToggleSelection,
+    /// 操作する位置から矩形の選択を始める(矩形で選択している場合はやめる)
+    ToggleBlockSelection,
+    /// 選択をやめる
+    ClearSelection,
+    /// 選択している文字列をクリップボードにコピーする
+    Yank,
+    /// 操作する位置の行をクリップボードにコピーする
+    YankLine,
+    /// 次にコピーする先のレジスタを、続けて押す文字で選ぶ
+    SelectRegister,
+    /// 選択している文字列かファイル全体を通すコマンドを、コマンドラインで入力する
+    Pipe,
+    /// 書き込むファイルの名前を、コマンドラインで入力する
+    Save,
+    /// エディタでファイルを編集し、終了したら読み込み直す
+    OpenEditor,
+    /// ファイルの末尾を追いかけて、追加された内容を読み込み続ける
+    Follow,
+    /// ファイルを読み込み直す
+    Reload,
+    /// 既定の割り当てを取り消す
+    #[serde(rename = "none")]
+    Unbound,
+}
+
+impl fmt::Display for Action {
+    /// `focus_next_pane`のような、設定ファイルで使う名前で表示する
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if *self == Self::Unbound {
+            return write!(f, "none");
+        }
+
+        // 列挙子の名前を、大文字の前で区切って小文字にする
+        for (i, c) in format!("{:?}", self).chars().enumerate() {
+            if c.is_uppercase() && i > 0 {
+                write!(f, "_")?;
+            }
+            write!(f, "{}", c.to_ascii_lowercase())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    /// `focus_next_pane`のような、設定ファイルで使う名前から作成する
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let deserializer: serde::de::value::StrDeserializer<serde::de::value::Error> =
+            s.into_deserializer();
+        Self::deserialize(deserializer).map_err(|e| e.to_string())
+    }
+}
+
+/// 既定の割り当てに重ねる、ほかのページャーやエディタに合わせた割り当て
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum KeymapPreset {
+    /// Only the default bindings.
+    #[default]
+    Default,
+    /// less: q to quit, j/k, space/b, d/u, g/G and n/N.
+    Less,
+    /// Vim: j/k/h/l, Ctrl+F/B, Ctrl+D/U, g/G, n/N and Ctrl+O/Tab.
+    Vim,
+    /// Emacs: Ctrl+N/P/F/B, Ctrl+V/Alt+V, Alt+</Alt+> and Ctrl+S/R.
+    Emacs,
+}
+
+impl KeymapPreset {
+    /// 既定の割り当てに重ねる割り当てを取得する
+    fn bindings(self) -> &'static [(&'static str, Action)] {
+        match self {
+            Self::Default => &[],
+            Self::Less => &[
+                ("q", Action::Quit),
+                ("Q", Action::Quit),
+                ("j", Action::ScrollDown),
+                ("e", Action::ScrollDown),
+                ("ctrl+n", Action::ScrollDown),
+                ("ctrl+e", Action::ScrollDown),
+                ("k", Action::ScrollUp),
+                ("y", Action::ScrollUp),
+                ("ctrl+y", Action::ScrollUp),
+                ("ctrl+k", Action::ScrollUp),
+                ("space", Action::PageDown),
+                ("f", Action::PageDown),
+                ("ctrl+f", Action::PageDown),
+                ("ctrl+v", Action::PageDown),
+                ("pagedown", Action::PageDown),
+                ("b", Action::PageUp),
+                ("ctrl+b", Action::PageUp),
+                ("pageup", Action::PageUp),
+                ("d", Action::HalfPageDown),
+                ("ctrl+d", Action::HalfPageDown),
+                ("u", Action::HalfPageUp),
+                ("ctrl+u", Action::HalfPageUp),
+                ("g", Action::GoToTop),
+                ("<", Action::GoToTop),
+                ("home", Action::GoToTop),
+                ("G", Action::GoToBottom),
+                (">", Action::GoToBottom),
+                ("end", Action::GoToBottom),
+                ("n", Action::SearchNext),
+                ("N", Action::SearchPrevious),
+                ("v", Action::OpenEditor),
+            ],
+            Self::Vim => &[
+                ("Z Z", Action::Quit),
+                ("Z Q", Action::Quit),
+                ("j", Action::ScrollDown),
+                ("ctrl+e", Action::ScrollDown),
+                ("k", Action::ScrollUp),
+                ("ctrl+y", Action::ScrollUp),
+                ("h", Action::ScrollLeft),
+                ("l", Action::ScrollRight),
+                ("ctrl+f", Action::PageDown),
+                ("ctrl+b", Action::PageUp),
+                ("ctrl+d", Action::HalfPageDown),
+                ("ctrl+u", Action::HalfPageUp),
+                ("g g", Action::GoToTop),
+                ("G", Action::GoToBottom),
+                ("n", Action::SearchNext),
+                ("N", Action::SearchPrevious),
+                ("ctrl+o", Action::JumpBack),
+                ("tab", Action::JumpForward),
+                ("ctrl+w s", Action::SplitHorizontal),
+                ("ctrl+w v", Action::SplitVertical),
+                ("ctrl+w q", Action::ClosePane),
+                ("ctrl+w w", Action::FocusNextPane),
+            ],
+            Self::Emacs => &[
+                ("ctrl+x ctrl+c", Action::Quit),
+                ("ctrl+x 2", Action::SplitHorizontal),
+                ("ctrl+x 3", Action::SplitVertical),
+                ("ctrl+x 0", Action::ClosePane),
+                ("ctrl+x o", Action::FocusNextPane),
+                ("ctrl+n", Action::ScrollDown),
+                ("ctrl+p", Action::ScrollUp),
+                ("ctrl+f", Action::ScrollRight),
+                ("ctrl+b", Action::ScrollLeft),
+                ("ctrl+v", Action::PageDown),
+                ("alt+v", Action::PageUp),
+                ("alt+<", Action::GoToTop),
+                ("alt+>", Action::GoToBottom),
+                ("ctrl+s", Action::SearchNext),
+                ("ctrl+r", Action::SearchPrevious),
+            ],
+        }
+    }
+}
+
+/// 修飾キーとキーの組み合わせ
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    /// キー
+    pub code: KeyCode,
+    /// 修飾キー
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    /// KeyChordを作成する
+    /// # Arguments
+    /// * `code` - キー
+    /// * `modifiers` - 修飾キー
+    /// # Notes
+    /// * 文字とShift + Tabキーは、Shiftキーを押したかがキーに含まれるので、Shiftキーを取り除く
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let modifiers = match code {
+            KeyCode::Char(_) | KeyCode::BackTab => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+
+        Self { code, modifiers }
+    }
+}
+
+impl From<&KeyEvent> for KeyChord {
+    fn from(key: &KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = String;
+
+    /// `ctrl+w`や`alt+shift+up`のような文字列から作成する
+    /// # Notes
+    /// * 修飾キーとキーの名前は大文字と小文字を区別しない(1文字のキーは区別する)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // `+`キーを指定できるように、最後の`+`の直後が空の場合は`+`をキーとする
+        let (prefix, key) = match s.strip_suffix("++") {
+            Some(prefix) => (prefix, "+"),
+            None if s == "+" => ("", "+"),
+            None => s.rsplit_once('+').unwrap_or(("", s)),
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in prefix.split('+').filter(|modifier| !modifier.is_empty()) {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("{}: Unknown modifier key", s)),
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_lowercase().as_str() {
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "enter" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "space" => KeyCode::Char(' '),
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "delete" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(format!("{}: Unknown key", s)),
+                },
+            },
+        };
+
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyChord {
+    /// `ctrl+w`のような、設定ファイルで使う形式で表示する
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "ctrl+"),
+            (KeyModifiers::ALT, "alt+"),
+            (KeyModifiers::SHIFT, "shift+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}", name)?;
+            }
+        }
+
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            KeyCode::BackTab => write!(f, "shift+tab"),
+            KeyCode::PageUp => write!(f, "pageup"),
+            KeyCode::PageDown => write!(f, "pagedown"),
+            code => write!(f, "{}", format!("{:?}", code).to_lowercase()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyChord {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// 続けて押すキーの列(`g g`や`ctrl+k ctrl+w`など)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeySequence(pub Vec<KeyChord>);
+
+impl FromStr for KeySequence {
+    type Err = String;
+
+    /// 空白で区切ったキーの列から作成する
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let chords = s
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Vec<KeyChord>, _>>()?;
+        if chords.is_empty() {
+            return Err("Empty key sequence".to_string());
+        }
+
+        Ok(Self(chords))
+    }
+}
+
+impl fmt::Display for KeySequence {
+    /// `ctrl+k ctrl+w`のような、設定ファイルで使う形式で表示する
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, chord) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", chord)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'de> Deserialize<'de> for KeySequence {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// 押したキーの列に割り当てた操作を探した結果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lookup {
+    /// 操作が割り当てられている
+    Action(Action),
+    /// コマンド(`:`に続けて入力する文字列)が割り当てられている
+    Command(String),
+    /// 続けて押すキーによって操作が決まる
+    Pending,
+    /// 操作が割り当てられていない
+    Unbound,
+}
+
+/// キーと操作の対応
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    /// キーの列に割り当てた操作
+    bindings: HashMap<Vec<KeyChord>, Action>,
+    /// キーの列に割り当てたコマンド(`:`に続けて入力する文字列)
+    commands: HashMap<Vec<KeyChord>, String>,
+}
+
+impl Default for Keymap {
+    /// 既定の割り当てのKeymapを作成する
+    fn default() -> Self {
+        let bindings = [
+            ("ctrl+w", Action::Quit),
+            ("up", Action::ScrollUp),
+            ("down", Action::ScrollDown),
+            ("left", Action::CursorLeft),
+            ("right", Action::CursorRight),
+            ("shift+up", Action::CursorUp),
+            ("shift+down", Action::CursorDown),
+            ("shift+left", Action::ScrollLeft),
+            ("shift+right", Action::ScrollRight),
+            ("alt+c", Action::ToggleCursorLine),
+            ("v", Action::ToggleSelection),
+            ("ctrl+v", Action::ToggleBlockSelection),
+            ("esc", Action::ClearSelection),
+            ("y", Action::Yank),
+            ("y y", Action::YankLine),
+            ("\"", Action::SelectRegister),
+            ("|", Action::Pipe),
+            ("s", Action::Save),
+            ("ctrl+e", Action::OpenEditor),
+            ("F", Action::Follow),
+            ("R", Action::Reload),
+            (":", Action::Command),
+            ("ctrl+p", Action::FindFile),
+            ("alt+e", Action::ToggleFileTree),
+            ("alt+h", Action::SplitHorizontal),
+            ("alt+v", Action::SplitVertical),
+            ("alt+q", Action::ClosePane),
+            ("alt+o", Action::FocusNextPane),
+            ("alt+l", Action::ToggleScrollLock),
+            ("alt+up", Action::ResizePaneUp),
+            ("alt+down", Action::ResizePaneDown),
+            ("alt+left", Action::ResizePaneLeft),
+            ("alt+right", Action::ResizePaneRight),
+            ("alt+t", Action::NewTab),
+            ("alt+x", Action::CloseTab),
+            ("tab", Action::NextTab),
+            ("shift+tab", Action::PreviousTab),
+            ("alt+w", Action::ToggleWhitespace),
+            ("alt+b", Action::ToggleBlame),
+            ("alt+m", Action::ToggleMarkdown),
+            ("alt+j", Action::ToggleJson),
+            ("enter", Action::ToggleFold),
+            ("]", Action::NextPageBreak),
+            ("[", Action::PreviousPageBreak),
+            ("alt+s", Action::ToggleSideBySide),
+            ("}", Action::NextHunk),
+            ("{", Action::PreviousHunk),
+            (")", Action::NextDiffFile),
+            ("(", Action::PreviousDiffFile),
+            ("alt+r", Action::ReloadConfig),
+            ("q", Action::RecordMacro),
+            ("@", Action::PlayMacro),
+            ("?", Action::Help),
+            ("f1", Action::Help),
+            ("m", Action::SetMark),
+            ("'", Action::JumpToMark),
+            ("alt+'", Action::ToggleMarks),
+            ("ctrl+o", Action::JumpBack),
+            ("alt+i", Action::JumpForward),
+            ("%", Action::MatchingBracket),
+            ("w", Action::NextWord),
+            ("ctrl+right", Action::NextWord),
+            ("b", Action::PreviousWord),
+            ("ctrl+left", Action::PreviousWord),
+            ("e", Action::NextWordEnd),
+        ]
+        .into_iter()
+        .map(|(keys, action)| {
+            let KeySequence(keys) = keys.parse().unwrap();
+            (keys, action)
+        })
+        .collect();
+
+        Self {
+            bindings,
+            commands: HashMap::new(),
+        }
+    }
+}
+
+impl Keymap {
+    /// 既定の割り当てに、プリセットの割り当てと設定ファイルの割り当てを順に上書きしたKeymapを作成する
+    /// # Arguments
+    /// * `preset` - 既定の割り当てに重ねるプリセット
+    /// * `custom` - 設定ファイルの`[keymap]`の割り当て
+    /// # Notes
+    /// * `none`を割り当てたキーは、既定の割り当てやプリセットの割り当てを取り消す
+    /// * プリセットで割り当てたキーから始まる、既定の長いキーの列は取り消す
+    ///   (lessの`y`が、既定の`y y`を待たずにすぐ動くように)
+    pub fn new(preset: KeymapPreset, custom: &HashMap<KeySequence, Action>) -> Self {
+        let mut keymap = Self::default();
+        let preset_bindings: Vec<(Vec<KeyChord>, Action)> = preset
+            .bindings()
+            .iter()
+            .map(|&(keys, action)| (keys.parse::<KeySequence>().unwrap().0, action))
+            .collect();
+        keymap.bindings.retain(|sequence, _| {
+            !preset_bindings
+                .iter()
+                .any(|(keys, _)| sequence.len() > keys.len() && sequence.starts_with(keys))
+        });
+        keymap.bindings.extend(preset_bindings);
+        for (keys, &action) in custom {
+            if action == Action::Unbound {
+                keymap.bindings.remove(&keys.0);
+            } else {
+                keymap.bindings.insert(keys.0.clone(), action);
+            }
+        }
+
+        keymap
+    }
+
+    /// キーに割り当てた操作を取得する
+    /// # Arguments
+    /// * `key` - 押したキー
+    /// # Notes
+    /// * 続けて押すキーは待たず、1つのキーだけの割り当てを探す
+    pub fn action(&self, key: &KeyEvent) -> Option<Action> {
+        self.get(&[KeyChord::from(key)])
+    }
+
+    /// キーの列に割り当てた操作を取得する
+    /// # Arguments
+    /// * `keys` - 押したキーの列
+    pub fn get(&self, keys: &[KeyChord]) -> Option<Action> {
+        self.bindings.get(keys).copied()
+    }
+
+    /// キーの列にコマンドを割り当てる
+    /// # Arguments
+    /// * `keys` - 割り当てるキーの列
+    /// * `command` - `:`に続けて入力する文字列(`set wrap`やプラグインで登録したコマンドなど)
+    /// # Notes
+    /// * 同じキーの列に割り当てた操作は取り消す
+    pub fn bind_command(&mut self, keys: &KeySequence, command: &str) {
+        self.bindings.remove(&keys.0);
+        self.commands.insert(keys.0.clone(), command.to_string());
+    }
+
+    /// キーの列に割り当てたコマンドを取得する
+    /// # Arguments
+    /// * `keys` - 押したキーの列
+    pub fn command(&self, keys: &[KeyChord]) -> Option<&str> {
+        self.commands.get(keys).map(String::as_str)
+    }
+
+    /// 操作ごとに、割り当てたキーの列を取得する
+    /// # Returns
+    /// * `Vec<(Action, Vec<KeySequence>)>` - 操作と割り当てたキーの列、操作を定義した順とキーの列の順に並べる
+    pub fn bindings(&self) -> Vec<(Action, Vec<KeySequence>)> {
+        let mut actions: Vec<(Action, Vec<KeySequence>)> = Vec::new();
+        for (keys, &action) in &self.bindings {
+            let sequence = KeySequence(keys.clone());
+            match actions.iter_mut().find(|(a, _)| *a == action) {
+                Some((_, sequences)) => sequences.push(sequence),
+                None => actions.push((action, vec![sequence])),
+            }
+        }
+
+        actions.sort_by_key(|(action, _)| *action);
+        for (_, sequences) in actions.iter_mut() {
+            sequences.sort_by_key(|sequence| sequence.to_string());
+        }
+
+        actions
+    }
+
+    /// キーの列に続けて押せるキーの列と、割り当てた操作を取得する
+    /// # Arguments
+    /// * `keys` - 押したキーの列
+    /// # Returns
+    /// * `Vec<(KeySequence, Action)>` - 続けて押すキーの列(押したキーの列を除く)と操作、キーの列の順に並べる
+    pub fn continuations(&self, keys: &[KeyChord]) -> Vec<(KeySequence, Action)> {
+        let mut continuations: Vec<(KeySequence, Action)> = self
+            .bindings
+            .iter()
+            .filter(|(sequence, _)| sequence.len() > keys.len() && sequence.starts_with(keys))
+            .map(|(sequence, &action)| (KeySequence(sequence[keys.len()..].to_vec()), action))
+            .collect();
+        continuations.sort_by_key(|(sequence, _)| sequence.to_string());
+
+        continuations
+    }
+
+    /// キーの列に割り当てた操作を探す
+    /// # Arguments
+    /// * `keys` - 押したキーの列
+    /// # Returns
+    /// * `Lookup` - 割り当てた操作、または続けて押すキーを待つか
+    /// # Notes
+    /// * より長いキーの列の先頭と一致する場合は、キーの列自体に操作が割り当てられていても続けて押すキーを待つ
+    ///   (待ち時間を過ぎた場合は`get`で取得した操作を行う)
+    pub fn lookup(&self, keys: &[KeyChord]) -> Lookup {
+        let is_prefix = self
+            .bindings
+            .keys()
+            .chain(self.commands.keys())
+            .any(|sequence| sequence.len() > keys.len() && sequence.starts_with(keys));
+        if is_prefix {
+            return Lookup::Pending;
+        }
+
+        if let Some(command) = self.command(keys) {
+            return Lookup::Command(command.to_string());
+        }
+        match self.get(keys) {
+            Some(action) => Lookup::Action(action),
+            None => Lookup::Unbound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_chord() {
+        assert_eq!(
+            "ctrl+w".parse(),
+            Ok(KeyChord::new(KeyCode::Char('w'), KeyModifiers::CONTROL))
+        );
+        assert_eq!(
+            "Alt+Shift+Up".parse(),
+            Ok(KeyChord::new(
+                KeyCode::Up,
+                KeyModifiers::ALT | KeyModifiers::SHIFT
+            ))
+        );
+        assert_eq!(
+            "ctrl++".parse(),
+            Ok(KeyChord::new(KeyCode::Char('+'), KeyModifiers::CONTROL))
+        );
+        assert_eq!(
+            "shift+tab".parse(),
+            Ok(KeyChord::new(KeyCode::BackTab, KeyModifiers::NONE))
+        );
+        assert_eq!(
+            "f5".parse(),
+            Ok(KeyChord::new(KeyCode::F(5), KeyModifiers::NONE))
+        );
+        assert!("hyper+a".parse::<KeyChord>().is_err());
+        assert!("f13".parse::<KeyChord>().is_err());
+
+        // 設定ファイルで使う形式で表示する
+        for s in ["ctrl+w", "alt+up", "shift+tab", "space", "f5", ":"] {
+            assert_eq!(s.parse::<KeyChord>().unwrap().to_string(), s);
+        }
+    }
+
+    #[test]
+    fn test_action_name() {
+        // 設定ファイルで使う名前で表示する
+        for action in [
+            Action::Quit,
+            Action::FocusNextPane,
+            Action::GoToTop,
+            Action::Unbound,
+        ] {
+            assert_eq!(action.to_string().parse(), Ok(action));
+        }
+        assert_eq!(Action::ToggleJson.to_string(), "toggle_json");
+        assert!("toggleJson".parse::<Action>().is_err());
+    }
+
+    #[test]
+    fn test_key_sequence() {
+        let chord = |s: &str| s.parse::<KeyChord>().unwrap();
+        assert_eq!(
+            " ctrl+k  ctrl+w ".parse(),
+            Ok(KeySequence(vec![chord("ctrl+k"), chord("ctrl+w")]))
+        );
+        assert_eq!("g g".parse::<KeySequence>().unwrap().to_string(), "g g");
+        assert!("".parse::<KeySequence>().is_err());
+        assert!("g hyper+g".parse::<KeySequence>().is_err());
+    }
+
+    #[test]
+    fn test_lookup() {
+        let chord = |s: &str| s.parse::<KeyChord>().unwrap();
+        let custom = HashMap::from([
+            ("ctrl+k ctrl+w".parse().unwrap(), Action::ClosePane),
+            ("ctrl+w w".parse().unwrap(), Action::FocusNextPane),
+        ]);
+        let keymap = Keymap::new(KeymapPreset::Default, &custom);
+
+        assert_eq!(keymap.lookup(&[chord("ctrl+k")]), Lookup::Pending);
+        assert_eq!(
+            keymap.lookup(&[chord("ctrl+k"), chord("ctrl+w")]),
+            Lookup::Action(Action::ClosePane)
+        );
+        assert_eq!(
+            keymap.lookup(&[chord("ctrl+k"), chord("x")]),
+            Lookup::Unbound
+        );
+        assert_eq!(
+            keymap.lookup(&[chord("down")]),
+            Lookup::Action(Action::ScrollDown)
+        );
+
+        // 長いキーの列の先頭と一致する場合は、割り当てがあっても続けて押すキーを待つ
+        assert_eq!(keymap.lookup(&[chord("ctrl+w")]), Lookup::Pending);
+        assert_eq!(keymap.get(&[chord("ctrl+w")]), Some(Action::Quit));
+
+        assert_eq!(
+            keymap.continuations(&[chord("ctrl+k")]),
+            vec![("ctrl+w".parse().unwrap(), Action::ClosePane)]
+        );
+        assert!(keymap.continuations(&[chord("down")]).is_empty());
+
+        // 操作ごとに、割り当てたキーの列をまとめる
+        let bindings = keymap.bindings();
+        assert_eq!(bindings[0], (Action::Quit, vec!["ctrl+w".parse().unwrap()]));
+        assert!(bindings.contains(&(
+            Action::ClosePane,
+            vec!["alt+q".parse().unwrap(), "ctrl+k ctrl+w".parse().unwrap()]
+        )));
+
+        // コマンドを割り当てたキーの列は、同じキーの列の操作より優先する
+        let mut keymap = keymap;
+        keymap.bind_command(&"ctrl+k ctrl+w".parse().unwrap(), "set wrap");
+        keymap.bind_command(&"g t".parse().unwrap(), "hello");
+        assert_eq!(
+            keymap.lookup(&[chord("ctrl+k"), chord("ctrl+w")]),
+            Lookup::Command("set wrap".to_string())
+        );
+        assert_eq!(keymap.lookup(&[chord("g")]), Lookup::Pending);
+        assert_eq!(keymap.command(&[chord("g"), chord("t")]), Some("hello"));
+        assert_eq!(keymap.get(&[chord("ctrl+k"), chord("ctrl+w")]), None);
+    }
+
+    #[test]
+    fn test_keymap() {
+        let custom = HashMap::from([
+            ("q".parse().unwrap(), Action::Quit),
+            ("ctrl+w".parse().unwrap(), Action::Unbound),
+        ]);
+        let keymap = Keymap::new(KeymapPreset::Default, &custom);
+
+        let key = |code, modifiers| KeyEvent::new(code, modifiers);
+        assert_eq!(
+            keymap.action(&key(KeyCode::Char('q'), KeyModifiers::NONE)),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            keymap.action(&key(KeyCode::Char('w'), KeyModifiers::CONTROL)),
+            None
+        );
+        // 端末によってはShiftキーを押したことが伝わるが、文字に含まれるので区別しない
+        assert_eq!(
+            keymap.action(&key(KeyCode::Char('}'), KeyModifiers::SHIFT)),
+            Some(Action::NextHunk)
+        );
+        assert_eq!(
+            keymap.action(&key(KeyCode::BackTab, KeyModifiers::SHIFT)),
+            Some(Action::PreviousTab)
+        );
+    }
+
+    #[test]
+    fn test_keymap_preset() {
+        let key = |code, modifiers| KeyEvent::new(code, modifiers);
+
+        // プリセットの割り当てをすべて読み込める
+        for preset in [KeymapPreset::Less, KeymapPreset::Vim, KeymapPreset::Emacs] {
+            for &(key, _) in preset.bindings() {
+                assert!(key.parse::<KeySequence>().is_ok(), "{}", key);
+            }
+        }
+
+        let keymap = Keymap::new(KeymapPreset::Less, &HashMap::new());
+        assert_eq!(
+            keymap.action(&key(KeyCode::Char('q'), KeyModifiers::NONE)),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            keymap.action(&key(KeyCode::Char('G'), KeyModifiers::SHIFT)),
+            Some(Action::GoToBottom)
+        );
+        // プリセットにない割り当ては既定のまま
+        assert_eq!(
+            keymap.action(&key(KeyCode::Char('w'), KeyModifiers::CONTROL)),
+            Some(Action::Quit)
+        );
+        // プリセットで割り当てたキーは、既定の長いキーの列を待たない
+        let y = [KeyChord::new(KeyCode::Char('y'), KeyModifiers::NONE)];
+        assert_eq!(keymap.lookup(&y), Lookup::Action(Action::ScrollUp));
+        assert_eq!(Keymap::default().lookup(&y), Lookup::Pending);
+
+        // 設定ファイルの割り当てはプリセットより優先する
+        let custom = HashMap::from([("ctrl+p".parse().unwrap(), Action::FindFile)]);
+        let keymap = Keymap::new(KeymapPreset::Emacs, &custom);
+        assert_eq!(
+            keymap.action(&key(KeyCode::Char('p'), KeyModifiers::CONTROL)),
+            Some(Action::FindFile)
+        );
+        assert_eq!(
+            keymap.action(&key(KeyCode::Char('<'), KeyModifiers::ALT)),
+            Some(Action::GoToTop)
+        );
+    }
+}
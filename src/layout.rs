@@ -0,0 +1,576 @@
+use serde::{Deserialize, Serialize};
+
+use crate::screen;
+use crate::style::{ColorDepth, Style};
+
+/// 画面上の矩形の領域
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    /// 開始位置(X座標)
+    pub x: u16,
+    /// 開始位置(Y座標)
+    pub y: u16,
+    /// 横幅
+    pub width: u16,
+    /// 縦幅
+    pub height: u16,
+}
+
+impl Rect {
+    /// 2つの領域をどちらも含む、最小の領域を求める
+    pub fn union(&self, other: &Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rect {
+            x,
+            y,
+            width: (self.x + self.width).max(other.x + other.width) - x,
+            height: (self.y + self.height).max(other.y + other.height) - y,
+        }
+    }
+
+    /// 2つの領域が重なる部分を求める
+    /// # Returns
+    /// * `Option<Rect>` - 重なる部分(重ならない場合はNone)
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        if right <= x || bottom <= y {
+            return None;
+        }
+
+        Some(Rect {
+            x,
+            y,
+            width: right - x,
+            height: bottom - y,
+        })
+    }
+
+    /// 上から指定した高さの領域と、残りの領域に分ける
+    /// # Arguments
+    /// * `height` - 上の領域の高さ(領域の高さより大きい場合は、領域の高さにする)
+    /// # Returns
+    /// * `(Rect, Rect)` - 上の領域と、残りの領域
+    pub fn split_top(&self, height: u16) -> (Rect, Rect) {
+        let height = height.min(self.height);
+        (
+            Rect { height, ..*self },
+            Rect {
+                y: self.y + height,
+                height: self.height - height,
+                ..*self
+            },
+        )
+    }
+
+    /// 下から指定した高さの領域と、残りの領域に分ける
+    /// # Arguments
+    /// * `height` - 下の領域の高さ(領域の高さより大きい場合は、領域の高さにする)
+    /// # Returns
+    /// * `(Rect, Rect)` - 残りの領域と、下の領域
+    pub fn split_bottom(&self, height: u16) -> (Rect, Rect) {
+        self.split_top(self.height.saturating_sub(height))
+    }
+
+    /// 左から指定した幅の領域と、残りの領域に分ける
+    /// # Arguments
+    /// * `width` - 左の領域の幅(領域の幅より大きい場合は、領域の幅にする)
+    /// # Returns
+    /// * `(Rect, Rect)` - 左の領域と、残りの領域
+    pub fn split_left(&self, width: u16) -> (Rect, Rect) {
+        let width = width.min(self.width);
+        (
+            Rect { width, ..*self },
+            Rect {
+                x: self.x + width,
+                width: self.width - width,
+                ..*self
+            },
+        )
+    }
+}
+
+/// 端末の画面を、表示するものごとに分けた領域
+/// # Notes
+/// * 上から順に、タブバー、ファイルツリーとエディタ領域、ステータスバーを並べる
+/// * 表示していないものの領域は、幅か高さが0になる
+/// * 端末の大きさや、表示するものが変わったときに計算し直す
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Regions {
+    /// タブバー
+    pub tab_bar: Rect,
+    /// 左側に表示するファイルツリー(エディタ領域との境界線を含む)
+    pub sidebar: Rect,
+    /// エディタ領域(分割した場合は、分割した領域すべて)
+    pub editor: Rect,
+    /// ステータスバー
+    pub status_bar: Rect,
+}
+
+impl Regions {
+    /// 端末の画面を分ける
+    /// # Arguments
+    /// * `screen` - 端末の画面全体
+    /// * `tab_bar_height` - タブバーの高さ(表示しない場合は0)
+    /// * `sidebar_width` - ファイルツリーの幅(表示しない場合は0)
+    /// * `status_bar_height` - ステータスバーの高さ
+    /// # Notes
+    /// * 端末が小さい場合は、ステータスバー、タブバーの順に場所を取り、残りをエディタ領域にする
+    pub fn new(
+        screen: Rect,
+        tab_bar_height: u16,
+        sidebar_width: u16,
+        status_bar_height: u16,
+    ) -> Self {
+        let (main, status_bar) = screen.split_bottom(status_bar_height);
+        let (tab_bar, main) = main.split_top(tab_bar_height);
+        let (sidebar, editor) = main.split_left(sidebar_width);
+        Self {
+            tab_bar,
+            sidebar,
+            editor,
+            status_bar,
+        }
+    }
+
+    /// 一覧やダイアログを重ねて表示する領域
+    /// # Notes
+    /// * ファイルツリーと、分割したすべての領域に重ねて表示する
+    pub fn overlay(&self) -> Rect {
+        self.sidebar.union(&self.editor)
+    }
+}
+
+/// エディタ領域を分割する向き
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitDirection {
+    /// 上下に分割する
+    Horizontal,
+    /// 左右に分割する
+    Vertical,
+}
+
+/// 分割したエディタ領域のひとつ
+/// 同じファイルを複数の領域に表示できるように、スクロール位置は領域ごとに保持する
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pane {
+    /// 表示しているファイルの位置
+    pub buffer: usize,
+    /// カーソルの横位置
+    pub cursor_x: u16,
+    /// カーソルの縦位置
+    pub cursor_y: usize,
+}
+
+impl Pane {
+    /// Paneを作成する
+    /// # Arguments
+    /// * `buffer` - 表示するファイルの位置
+    /// * `cursor_x` - カーソルの横位置
+    /// * `cursor_y` - カーソルの縦位置
+    pub fn new(buffer: usize, cursor_x: u16, cursor_y: usize) -> Self {
+        Self {
+            buffer,
+            cursor_x,
+            cursor_y,
+        }
+    }
+}
+
+/// タブページごとの、エディタ領域の分割の状態
+pub struct Layout {
+    /// 分割した領域(左または上から順に、最大2つ)
+    pub panes: Vec<Pane>,
+    /// 操作する領域の位置
+    pub focused: usize,
+    /// 分割する向き
+    pub direction: SplitDirection,
+    /// 最初の領域が占める割合(%)
+    pub ratio: u16,
+    /// 操作する領域をスクロールしたときに、ほかの領域も同じだけスクロールするか
+    pub scroll_lock: bool,
+}
+
+impl Layout {
+    /// 分割できる領域の最大数
+    const MAX_PANES: usize = 2;
+    /// 最初の領域が占める割合の最小値(%)
+    const MIN_RATIO: u16 = 10;
+    /// 最初の領域が占める割合の最大値(%)
+    const MAX_RATIO: u16 = 90;
+
+    /// 分割していないLayoutを作成する
+    /// # Arguments
+    /// * `pane` - エディタ領域全体に表示する領域
+    pub fn new(pane: Pane) -> Self {
+        Self {
+            panes: vec![pane],
+            focused: 0,
+            direction: SplitDirection::Vertical,
+            ratio: 50,
+            scroll_lock: false,
+        }
+    }
+
+    /// 操作する領域を取得する
+    pub fn focused_pane(&self) -> &Pane {
+        &self.panes[self.focused]
+    }
+
+    /// 操作する領域を変更できるように取得する
+    pub fn focused_pane_mut(&mut self) -> &mut Pane {
+        &mut self.panes[self.focused]
+    }
+
+    /// 操作する領域の位置と大きさを計算する
+    /// # Arguments
+    /// * `area` - エディタ領域全体
+    pub fn focused_rect(&self, area: Rect) -> Rect {
+        self.rects(area)[self.focused]
+    }
+
+    /// 操作する領域を分割して、同じファイルを表示する領域を追加する
+    /// # Arguments
+    /// * `direction` - 分割する向き
+    /// # Returns
+    /// * `bool` - 分割した場合はtrue、すでに分割している場合はfalse
+    /// # Notes
+    /// * 追加した領域を操作する領域にする
+    pub fn split(&mut self, direction: SplitDirection) -> bool {
+        if self.panes.len() >= Self::MAX_PANES {
+            return false;
+        }
+
+        let pane = *self.focused_pane();
+        self.panes.insert(self.focused + 1, pane);
+        self.focused += 1;
+        self.direction = direction;
+        self.ratio = 50;
+        true
+    }
+
+    /// 操作する領域を閉じる
+    /// # Returns
+    /// * `bool` - 閉じた場合はtrue、分割していない場合はfalse
+    /// # Notes
+    /// * 領域がひとつになるので、スクロールの連動も解除する
+    pub fn close(&mut self) -> bool {
+        if self.panes.len() == 1 {
+            return false;
+        }
+
+        self.panes.remove(self.focused);
+        self.focused = self.focused.min(self.panes.len() - 1);
+        self.scroll_lock = false;
+        true
+    }
+
+    /// 操作する領域以外の領域をスクロールする
+    /// # Arguments
+    /// * `dx` - 横にスクロールする量
+    /// * `dy` - 縦にスクロールする量
+    /// # Notes
+    /// * スクロールできる範囲は表示するときに決まるので、ここでは0未満にならないようにだけする
+    pub fn scroll_others(&mut self, dx: i32, dy: i32) {
+        let focused = self.focused;
+        for (i, pane) in self.panes.iter_mut().enumerate() {
+            if i == focused {
+                continue;
+            }
+            pane.cursor_x = (pane.cursor_x as i32 + dx).clamp(0, u16::MAX as i32) as u16;
+            pane.cursor_y = pane.cursor_y.saturating_add_signed(dy as isize);
+        }
+    }
+
+    /// 次の領域を操作する領域にする
+    pub fn focus_next(&mut self) {
+        self.focused = (self.focused + 1) % self.panes.len();
+    }
+
+    /// 領域の境界線を動かす
+    /// # Arguments
+    /// * `delta` - 最初の領域が占める割合の変化量(%)
+    pub fn resize(&mut self, delta: i16) {
+        self.ratio = self
+            .ratio
+            .saturating_add_signed(delta)
+            .clamp(Self::MIN_RATIO, Self::MAX_RATIO);
+    }
+
+    /// 各領域の位置と大きさを計算する
+    /// # Arguments
+    /// * `area` - エディタ領域全体
+    /// # Returns
+    /// * `Vec<Rect>` - `panes`と同じ順番の、各領域の位置と大きさ
+    /// # Notes
+    /// * 領域の間には、幅1の境界線を表示する
+    /// * どの領域も幅と高さが1以上になるようにする
+    pub fn rects(&self, area: Rect) -> Vec<Rect> {
+        if self.panes.len() == 1 {
+            return vec![area];
+        }
+
+        let size = match self.direction {
+            SplitDirection::Horizontal => area.height,
+            SplitDirection::Vertical => area.width,
+        };
+        let available = size.saturating_sub(1);
+        let first = (available as u32 * self.ratio as u32 / 100) as u16;
+        let first = first.clamp(1, available.saturating_sub(1).max(1));
+        let second = available.saturating_sub(first).max(1);
+
+        match self.direction {
+            SplitDirection::Horizontal => vec![
+                Rect {
+                    height: first,
+                    ..area
+                },
+                Rect {
+                    y: area.y + first + 1,
+                    height: second,
+                    ..area
+                },
+            ],
+            SplitDirection::Vertical => vec![
+                Rect {
+                    width: first,
+                    ..area
+                },
+                Rect {
+                    x: area.x + first + 1,
+                    width: second,
+                    ..area
+                },
+            ],
+        }
+    }
+
+    /// 領域の間に境界線を表示する
+    /// # Arguments
+    /// * `area` - エディタ領域全体
+    /// * `style` - 境界線の装飾
+    /// * `color_depth` - 端末で表示できる色数
+    pub fn print_separator(&self, area: Rect, style: &Style, color_depth: ColorDepth) {
+        let rects = self.rects(area);
+        let Some(first) = rects.first().filter(|_| rects.len() > 1) else {
+            return;
+        };
+
+        match self.direction {
+            SplitDirection::Horizontal => {
+                let line = style.paint(&"─".repeat(area.width as usize), color_depth);
+                screen::print_row(area.x, first.y + first.height, area.width, &line).unwrap();
+            }
+            SplitDirection::Vertical => {
+                let line = style.paint("│", color_depth);
+                for y in area.y..area.y + area.height {
+                    screen::print_row(first.x + first.width, y, 1, &line).unwrap();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AREA: Rect = Rect {
+        x: 0,
+        y: 1,
+        width: 81,
+        height: 21,
+    };
+
+    #[test]
+    fn test_union_and_intersection() {
+        let a = Rect {
+            x: 2,
+            y: 3,
+            width: 10,
+            height: 4,
+        };
+        let b = Rect {
+            x: 8,
+            y: 1,
+            width: 10,
+            height: 4,
+        };
+        assert_eq!(
+            a.union(&b),
+            Rect {
+                x: 2,
+                y: 1,
+                width: 16,
+                height: 6
+            }
+        );
+        assert_eq!(
+            a.intersection(&b),
+            Some(Rect {
+                x: 8,
+                y: 3,
+                width: 4,
+                height: 2
+            })
+        );
+
+        // 接しているだけの領域は重ならない
+        let c = Rect {
+            x: 12,
+            y: 3,
+            width: 5,
+            height: 4,
+        };
+        assert_eq!(a.intersection(&c), None);
+    }
+
+    #[test]
+    fn test_regions() {
+        let screen = Rect {
+            x: 0,
+            y: 0,
+            width: 80,
+            height: 24,
+        };
+        let regions = Regions::new(screen, 1, 20, 1);
+        assert_eq!(
+            regions.tab_bar,
+            Rect {
+                x: 0,
+                y: 0,
+                width: 80,
+                height: 1
+            }
+        );
+        assert_eq!(
+            regions.sidebar,
+            Rect {
+                x: 0,
+                y: 1,
+                width: 20,
+                height: 22
+            }
+        );
+        assert_eq!(
+            regions.editor,
+            Rect {
+                x: 20,
+                y: 1,
+                width: 60,
+                height: 22
+            }
+        );
+        assert_eq!(
+            regions.status_bar,
+            Rect {
+                x: 0,
+                y: 23,
+                width: 80,
+                height: 1
+            }
+        );
+        assert_eq!(
+            regions.overlay(),
+            Rect {
+                x: 0,
+                y: 1,
+                width: 80,
+                height: 22
+            }
+        );
+
+        // 端末が小さい場合は、ステータスバーを優先してエディタ領域を0にする
+        let regions = Regions::new(
+            Rect {
+                height: 1,
+                ..screen
+            },
+            1,
+            0,
+            1,
+        );
+        assert_eq!(regions.status_bar.y, 0);
+        assert_eq!(regions.tab_bar.height, 0);
+        assert_eq!(regions.editor.height, 0);
+    }
+
+    #[test]
+    fn test_split_and_close() {
+        let mut layout = Layout::new(Pane::new(0, 0, 5));
+        assert_eq!(layout.rects(AREA), vec![AREA]);
+
+        assert!(layout.split(SplitDirection::Vertical));
+        assert!(!layout.split(SplitDirection::Horizontal));
+        assert_eq!(layout.panes.len(), 2);
+        assert_eq!(layout.focused, 1);
+        assert_eq!(layout.focused_pane().cursor_y, 5);
+
+        layout.focus_next();
+        assert_eq!(layout.focused, 0);
+        assert!(layout.close());
+        assert!(!layout.close());
+        assert_eq!(layout.panes.len(), 1);
+    }
+
+    #[test]
+    fn test_scroll_others() {
+        let mut layout = Layout::new(Pane::new(0, 0, 3));
+        layout.split(SplitDirection::Horizontal);
+        layout.scroll_others(2, -5);
+        assert_eq!(layout.panes[0], Pane::new(0, 2, 0));
+        assert_eq!(layout.panes[1], Pane::new(0, 0, 3));
+
+        layout.scroll_lock = true;
+        layout.close();
+        assert!(!layout.scroll_lock);
+    }
+
+    #[test]
+    fn test_rects() {
+        let mut layout = Layout::new(Pane::new(0, 0, 0));
+        layout.split(SplitDirection::Vertical);
+        assert_eq!(
+            layout.rects(AREA),
+            vec![
+                Rect {
+                    x: 0,
+                    y: 1,
+                    width: 40,
+                    height: 21
+                },
+                Rect {
+                    x: 41,
+                    y: 1,
+                    width: 40,
+                    height: 21
+                },
+            ]
+        );
+
+        layout.direction = SplitDirection::Horizontal;
+        layout.resize(-100);
+        assert_eq!(layout.ratio, 10);
+        assert_eq!(
+            layout.rects(AREA),
+            vec![
+                Rect {
+                    x: 0,
+                    y: 1,
+                    width: 81,
+                    height: 2
+                },
+                Rect {
+                    x: 0,
+                    y: 4,
+                    width: 81,
+                    height: 18
+                },
+            ]
+        );
+    }
+}
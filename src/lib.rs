@@ -0,0 +1,35 @@
+//! ファイルの内容を折り返して端末に表示する部分と、開いたファイルを扱う部分
+//! `edoc`のコマンドはこのライブラリを使って作られていて、ほかのツールからも使える
+
+pub mod backend;
+pub mod bracket;
+pub mod buffer;
+pub mod cell;
+pub mod config;
+pub mod contents;
+pub mod decompress;
+pub mod diff;
+pub mod editorconfig;
+pub mod file_tree;
+pub mod filetype;
+pub mod git;
+#[cfg(feature = "tree-sitter")]
+pub mod highlight;
+pub mod hooks;
+pub mod http;
+pub mod json;
+pub mod keymap;
+pub mod layout;
+pub mod loading;
+pub mod mapped;
+pub mod markdown;
+pub mod marks;
+pub mod overlay;
+pub mod pager;
+pub mod prompt;
+pub mod screen;
+pub mod ssh;
+pub mod status_bar;
+pub mod style;
+pub mod tab_bar;
+pub mod word;
@@ -0,0 +1,175 @@
+use std::{
+    io::{Read, Write},
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{Clear, ClearType},
+};
+
+/// 読み込みに時間がかかる場合だけ表示するように、表示を始めるまで待つ時間
+const DELAY: Duration = Duration::from_millis(200);
+/// 表示を更新する間隔
+const INTERVAL: Duration = Duration::from_millis(100);
+/// 一度に読み込む大きさ
+const CHUNK_SIZE: usize = 1 << 20;
+/// 読み込んでいることを表す回転する記号
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+/// 読み込んだ大きさを、端末の行に表示する
+struct Progress {
+    /// 読み込んでいるものの名前
+    name: String,
+    /// 読み込む全体の大きさ(パイプなどで分からない場合はNone)
+    total: Option<u64>,
+    /// 読み込み始めた時刻
+    started_at: Instant,
+    /// 最後に表示した時刻(まだ表示していない場合はNone)
+    printed_at: Option<Instant>,
+    /// 表示した回数(回転する記号に使う)
+    frame: usize,
+}
+
+impl Progress {
+    /// 読み込んだ大きさを表示する
+    /// # Arguments
+    /// * `read` - 読み込んだ大きさ
+    /// # Notes
+    /// * すぐに読み終わる場合に画面がちらつかないように、読み込み始めてしばらくしてから表示する
+    fn update(&mut self, read: u64) {
+        let now = Instant::now();
+        if now.duration_since(self.started_at) < DELAY
+            || self
+                .printed_at
+                .is_some_and(|printed_at| now.duration_since(printed_at) < INTERVAL)
+        {
+            return;
+        }
+        self.printed_at = Some(now);
+        self.frame += 1;
+
+        let text = format!(
+            "{} Loading {}: {}",
+            SPINNER[self.frame % SPINNER.len()],
+            self.name,
+            format_progress(read, self.total)
+        );
+        let mut stdout = std::io::stdout();
+        let _ = queue!(
+            stdout,
+            Print("\r"),
+            Clear(ClearType::CurrentLine),
+            SetAttribute(Attribute::Reverse),
+            Print(text),
+            SetAttribute(Attribute::Reset)
+        );
+        let _ = stdout.flush();
+    }
+
+    /// 表示した場合は、表示を消す
+    fn finish(&self) {
+        if self.printed_at.is_some() {
+            let mut stdout = std::io::stdout();
+            let _ = queue!(stdout, Print("\r"), Clear(ClearType::CurrentLine));
+            let _ = stdout.flush();
+        }
+    }
+}
+
+/// 最後まで読み込む
+/// # Arguments
+/// * `reader` - 読み込むファイルや標準入力
+/// * `name` - 読み込んでいるものの名前
+/// * `total` - 読み込む全体の大きさ(分からない場合はNone)
+/// * `show_progress` - 時間がかかる場合に、読み込んだ大きさを表示する場合はtrue
+/// # Returns
+/// * `std::io::Result<Vec<u8>>` - 読み込んだ内容
+/// # Notes
+/// * 表示は端末の今の行に重ねて、読み終わったら消す(画面を切り替える前に使う)
+pub fn read_all(
+    mut reader: impl Read,
+    name: &str,
+    total: Option<u64>,
+    show_progress: bool,
+) -> std::io::Result<Vec<u8>> {
+    let mut progress = show_progress.then(|| Progress {
+        name: name.to_string(),
+        total,
+        started_at: Instant::now(),
+        printed_at: None,
+        frame: 0,
+    });
+
+    let mut contents = Vec::with_capacity(total.unwrap_or(0) as usize);
+    let mut chunk = vec![0; CHUNK_SIZE];
+    let result = loop {
+        match reader.read(&mut chunk) {
+            Ok(0) => break Ok(()),
+            Ok(n) => contents.extend_from_slice(&chunk[..n]),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => break Err(e),
+        }
+        if let Some(progress) = progress.as_mut() {
+            progress.update(contents.len() as u64);
+        }
+    };
+
+    if let Some(progress) = &progress {
+        progress.finish();
+    }
+    result.map(|_| contents)
+}
+
+/// 読み込んだ大きさを表す文字列を作成する
+/// # Arguments
+/// * `read` - 読み込んだ大きさ
+/// * `total` - 読み込む全体の大きさ(分からない場合はNone)
+fn format_progress(read: u64, total: Option<u64>) -> String {
+    match total.filter(|&total| total > 0) {
+        Some(total) => format!(
+            "{} / {} ({}%)",
+            format_bytes(read),
+            format_bytes(total),
+            read.min(total) * 100 / total
+        ),
+        None => format_bytes(read),
+    }
+}
+
+/// バイト数を、読みやすい単位を付けた文字列にする
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_all() {
+        let input = vec![b'a'; CHUNK_SIZE + 10];
+        assert_eq!(read_all(&input[..], "a", None, false).unwrap(), input);
+
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(300 << 20), "300.0 MiB");
+        assert_eq!(
+            format_progress(5 << 20, Some(10 << 20)),
+            "5.0 MiB / 10.0 MiB (50%)"
+        );
+        assert_eq!(format_progress(2048, None), "2.0 KiB");
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::{HashMap, VecDeque};
+
+use crossterm::event::KeyEvent;
+
+/// 押したキーをレジスタに記録し、あとで同じ順番に押したものとして再生する
+#[derive(Debug, Default)]
+pub struct Macros {
+    /// レジスタごとに記録したキー
+    registers: HashMap<char, Vec<KeyEvent>>,
+    /// 記録しているレジスタと、記録したキー(記録していない場合はNone)
+    recording: Option<(char, Vec<KeyEvent>)>,
+    /// 再生するキー
+    queue: VecDeque<KeyEvent>,
+}
+
+impl Macros {
+    /// レジスタに使える文字か
+    /// # Arguments
+    /// * `register` - レジスタの名前
+    pub fn is_register(register: char) -> bool {
+        register.is_ascii_alphanumeric()
+    }
+
+    /// 記録しているレジスタを取得する
+    /// # Returns
+    /// * `Option<char>` - 記録しているレジスタ(記録していない場合はNone)
+    pub fn recording(&self) -> Option<char> {
+        self.recording.as_ref().map(|(register, _)| *register)
+    }
+
+    /// レジスタへの記録を始める
+    /// # Arguments
+    /// * `register` - 記録するレジスタ
+    /// # Notes
+    /// * 記録を終えたときに、レジスタに記録していたキーを置き換える
+    pub fn start_recording(&mut self, register: char) {
+        self.recording = Some((register, Vec::new()));
+    }
+
+    /// レジスタへの記録を終える
+    pub fn stop_recording(&mut self) {
+        if let Some((register, keys)) = self.recording.take() {
+            self.registers.insert(register, keys);
+        }
+    }
+
+    /// 記録している場合は、押したキーを加える
+    /// # Arguments
+    /// * `key` - 押したキー
+    pub fn record(&mut self, key: KeyEvent) {
+        if let Some((_, keys)) = self.recording.as_mut() {
+            keys.push(key);
+        }
+    }
+
+    /// レジスタに記録したキーを、指定した回数だけ再生する
+    /// # Arguments
+    /// * `register` - 再生するレジスタ
+    /// * `count` - 繰り返す回数
+    /// # Returns
+    /// * `bool` - レジスタに記録したキーがある場合はtrue、それ以外はfalse
+    /// # Notes
+    /// * 再生している途中の場合は、残りのキーより先に再生する
+    pub fn play(&mut self, register: char, count: usize) -> bool {
+        let Some(keys) = self
+            .registers
+            .get(&register)
+            .filter(|keys| !keys.is_empty())
+        else {
+            return false;
+        };
+
+        for _ in 0..count {
+            for key in keys.iter().rev() {
+                self.queue.push_front(*key);
+            }
+        }
+
+        true
+    }
+
+    /// 再生しているか
+    pub fn is_playing(&self) -> bool {
+        !self.queue.is_empty()
+    }
+
+    /// 次に再生するキーを取得する
+    /// # Returns
+    /// * `Option<KeyEvent>` - 次に再生するキー(再生し終えた場合はNone)
+    pub fn next_key(&mut self) -> Option<KeyEvent> {
+        self.queue.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    fn key(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_macros() {
+        let mut macros = Macros::default();
+        assert!(!macros.play('a', 1));
+
+        // 記録していない間のキーは加えない
+        macros.record(key('x'));
+        macros.start_recording('a');
+        assert_eq!(macros.recording(), Some('a'));
+        macros.record(key('j'));
+        macros.record(key('n'));
+        macros.stop_recording();
+        assert_eq!(macros.recording(), None);
+        macros.record(key('y'));
+
+        assert!(macros.play('a', 2));
+        assert!(macros.is_playing());
+        assert_eq!(macros.next_key(), Some(key('j')));
+
+        // 再生している途中で再生したキーは、残りのキーより先に再生する
+        assert!(macros.play('a', 1));
+        let keys: Vec<KeyEvent> = std::iter::from_fn(|| macros.next_key()).collect();
+        assert_eq!(keys, vec![key('j'), key('n'), key('n'), key('j'), key('n')]);
+        assert!(!macros.is_playing());
+
+        assert!(Macros::is_register('a'));
+        assert!(!Macros::is_register('@'));
+    }
+}
@@ -1,14 +1,14 @@
 use std::{
-    io::{stdout, Read, Write},
-    time::Duration,
+    io::{stdout, Write},
+    time::Instant,
 };
 
 use clap::Parser;
 
 use crossterm::{
-    cursor::{Hide, Show},
-    event::{poll, read, Event, KeyCode, KeyEvent, KeyModifiers},
+    cursor::{Hide, MoveTo, Show},
     execute, queue,
+    style::Print,
     terminal::{
         self, disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
     },
@@ -18,220 +18,1120 @@ extern crate unicode_width;
 
 use clap::CommandFactory;
 
-mod contents;
-mod status_bar;
+mod buffer_list;
+mod clipboard;
+mod command_line;
+mod editor;
+mod env_args;
+mod exit_code;
+mod finder;
+mod help;
+mod history;
+mod input;
+mod jump_list;
+mod key_hint;
+mod macros;
+mod plugin;
+mod registers;
+mod session;
+mod shell;
+mod watcher;
 
-fn main() -> std::io::Result<()> {
-    let args = Args::parse();
+// 表示する部分とファイルを扱う部分はライブラリにあるので、ほかのモジュールからもcrate::で使えるようにする
+use edoc_core::{
+    backend, buffer, cell, config, contents, decompress, diff, file_tree, git, hooks, http, keymap,
+    layout, loading, mapped, marks, overlay, prompt, screen, ssh, status_bar, style, tab_bar, word,
+};
+
+fn main() {
+    // 入力やファイルの変更、待ち時間などをまとめて待てるように、非同期のランタイムで動かす
+    // 端末は1つだけで、同時に表示するものもないので、1つのスレッドで動かす
+    let result = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .and_then(|runtime| runtime.block_on(run()));
+
+    // 失敗した理由ごとの終了コードで終了する
+    match result {
+        Ok(()) => exit_code::ExitCode::Success.exit(),
+        Err(e) => {
+            eprintln!("{}", e);
+            exit_code::ExitCode::from(&e).exit();
+        }
+    }
+}
+
+async fn run() -> std::io::Result<()> {
+    // 環境変数EDOCで指定した既定のオプションを、コマンドライン引数より前に加える
+    let env = std::env::var(env_args::VAR_NAME).ok();
+    let args = match env_args::merge(std::env::args_os().collect(), env.as_deref()) {
+        Ok(args) => match Args::try_parse_from(args) {
+            Ok(args) => args,
+            // --helpや--versionの場合は、表示して正常に終了する
+            Err(e) if !e.use_stderr() => {
+                e.print()?;
+                exit_code::ExitCode::Success.exit();
+            }
+            Err(e) => {
+                e.print()?;
+                exit_code::ExitCode::Usage.exit();
+            }
+        },
+        Err(e) => {
+            eprintln!("{}", e);
+            exit_code::ExitCode::Usage.exit();
+        }
+    };
+
+    // 出力先が端末でない場合は、画面を切り替えずに内容をそのまま出力する
+    let is_terminal = atty::is(atty::Stream::Stdout);
 
     // 端末のサイズを取得する
-    let (mut term_width, mut term_height) = terminal::size()?;
+    // 端末がなくても出力できるように、端末以外に出力する場合は既定のサイズを使う
+    let (term_width, term_height) = match screen::size() {
+        Ok(size) => size,
+        Err(_) if !is_terminal => (80, 24),
+        Err(e) => return Err(e),
+    };
 
-    let original_contents = match get_contents(args.file.clone()) {
-        Ok(contents) => contents,
+    let (config, theme) = match load_config(args.theme.as_deref()) {
+        Ok(loaded) => loaded,
         Err(e) => {
-            // 標準入力がなく、ファイルを指定していない場合はヘルプを表示するため、標準エラー出力には何も出力しない
-            if (e.kind() == std::io::ErrorKind::Other) && (e.to_string() == "No input file") {
-            } else {
+            eprintln!("{}", e);
+            exit_code::ExitCode::Config.exit();
+        }
+    };
+    // 設定ファイルを変更したかを確認するために、読み込んだときの更新日時を覚えておく
+    let config_modified_time = config::Config::modified_time();
+
+    // --diffを指定した場合は、2つのファイルの名前と内容を読み込んでおく
+    let diff_files = match args.diff.as_deref() {
+        Some([old, new]) => match (
+            get_contents(Some(old.clone()), true),
+            get_contents(Some(new.clone()), true),
+        ) {
+            (Ok(old_contents), Ok(new_contents)) => {
+                Some([(old.clone(), old_contents), (new.clone(), new_contents)])
+            }
+            (Err(e), _) | (_, Err(e)) => {
                 eprintln!("{}", e);
+                exit_code::ExitCode::from(&e).exit();
             }
+        },
+        _ => None,
+    };
 
-            std::process::exit(1);
+    // --sessionを指定し、ファイルを指定していない場合は、前回終了したときの状態に戻す
+    // ファイルを指定した場合は、そのファイルで新しく始めて、終了するときに上書きする
+    let session = match args.session.as_deref().map(session::Session::load) {
+        Some(Ok(session)) => session.filter(|_| args.files.is_empty()),
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            exit_code::ExitCode::Config.exit();
         }
+        None => None,
+    };
+
+    // ファイルを指定していない場合は、標準入力を読み込む
+    let files = match &session {
+        Some(session) => session.files.iter().cloned().map(Some).collect(),
+        None if args.files.is_empty() => vec![None],
+        None => args.files.iter().cloned().map(Some).collect(),
+    };
+    // ディレクトリを指定した場合は、内容の代わりにNoneを入れておき、中身の一覧を表示する
+    // URLやSSHのパスを指定した場合は、取得した場所も入れておき、ステータスバーに表示する
+    // 大きなファイルは、内容の代わりにメモリマップで開いたファイルを入れておき、表示する範囲だけを読み取る
+    // (端末に表示しない場合は、すべて出力するので読み込む)
+    // (末尾を追いかける場合は、ログのローテーションなどで切り詰められることが多いので読み込む)
+    let mut file_contents = Vec::new();
+    if diff_files.is_none() {
+        for file in files {
+            if file
+                .as_deref()
+                .is_some_and(|file| std::path::Path::new(file).is_dir())
+            {
+                file_contents.push((file, None, None, None));
+                continue;
+            }
+            if let Some(mapped) = file
+                .as_deref()
+                .filter(|_| is_terminal && !args.follow)
+                .and_then(mapped::MappedFile::open)
+            {
+                file_contents.push((file, None, None, Some(mapped)));
+                continue;
+            }
+
+            let contents = match file
+                .as_deref()
+                .and_then(|file| get_remote_contents(file, true))
+            {
+                Some(result) => result.map(|(origin, contents)| (contents, Some(origin))),
+                None => get_contents(file.clone(), true).map(|contents| (contents, None)),
+            };
+            match contents {
+                Ok((contents, origin)) => file_contents.push((file, Some(contents), origin, None)),
+                Err(e) => {
+                    // 標準入力がなく、ファイルを指定していない場合はヘルプを表示するため、標準エラー出力には何も出力しない
+                    if (e.kind() == std::io::ErrorKind::InvalidInput)
+                        && (e.to_string() == "No input file")
+                    {
+                    } else {
+                        eprintln!("{}", e);
+                    }
+
+                    exit_code::ExitCode::from(&e).exit();
+                }
+            }
+        }
+    }
+
+    // 最近開いたファイルと、それぞれの最後の表示位置
+    let history = history::History::load();
+
+    // 色を使う場合は、端末で表示できる色数に合わせて装飾する
+    let color_depth = if use_color(args.color) {
+        style::ColorDepth::detect()
+    } else {
+        style::ColorDepth::None
+    };
+
+    // タブバーとファイルツリーは、開くファイルが決まってから表示するかを決める
+    let regions = screen_regions(None, term_width, term_height, 1);
+    let mut status_bar = status_bar::StatusBar::new(
+        regions.status_bar.width,
+        regions.status_bar.height,
+        regions.status_bar.x,
+        regions.status_bar.y,
+    );
+    status_bar.color_depth = color_depth;
+    status_bar.style = theme.status_bar;
+    status_bar.error_style = theme.error;
+    status_bar.layout = config.status_bar.items.clone();
+
+    let status_bar_encoding =
+        status_bar::StatusBarItem::new("encoding".to_string(), "UTF-8".to_string());
+
+    status_bar.add_item(status_bar_encoding);
+
+    // 長い行を折り返すかどうかは、コマンドライン引数、設定ファイルの順に優先する
+    let wrap = if args.chop_long_lines {
+        Some(false)
+    } else if args.wrap {
+        Some(true)
+    } else {
+        None
     };
 
-    queue!(stdout(), EnterAlternateScreen)?;
+    // すべてのファイルに共通する表示の設定
+    // タブ幅などのファイルごとに決まる設定は、ファイルを開くときに決める
+    let settings = buffer::Settings {
+        options: contents::DisplayOptions {
+            tab_width: 8,
+            raw_control_chars: args.raw_control_chars,
+            show_whitespace: false,
+            squeeze_blank: args.squeeze_blank,
+            chop_long_lines: !wrap.or(config.wrap).unwrap_or(true),
+            strip_ansi: args.strip_ansi,
+            color_depth,
+            theme,
+            ambiguous_wide: args.ambiguous_wide || is_cjk_locale(),
+            highlights: Default::default(),
+            fold_markers: Default::default(),
+            diff_markers: Default::default(),
+            mark_markers: Default::default(),
+            annotations: Default::default(),
+            sticky_headers: Vec::new(),
+            line_number_offset: 0,
+        },
+        tab_width: args.tab_width,
+        wrap,
+        config,
+    };
+    screen::set_ambiguous_wide(settings.options.ambiguous_wide);
+
+    // ファイルツリーの一番上のディレクトリは、最初に指定したディレクトリにする
+    let directory = file_contents
+        .iter()
+        .find(|(_, contents, _, mapped)| contents.is_none() && mapped.is_none())
+        .and_then(|(file, _, _, _)| file.clone());
+
+    let mut buffers: Vec<buffer::Buffer> = match diff_files {
+        Some(files) => vec![buffer::Buffer::open_diff(
+            files,
+            args.side_by_side,
+            term_width,
+            &settings,
+        )],
+        None => file_contents
+            .into_iter()
+            .map(
+                |(file, contents, origin, mapped)| match (file, contents, origin, mapped) {
+                    (file, _, _, Some(mapped)) => {
+                        buffer::Buffer::open_mapped(file.unwrap_or_default(), mapped, &settings)
+                    }
+                    (_, Some(contents), Some(origin), None) => {
+                        buffer::Buffer::open_remote(origin, contents, &settings)
+                    }
+                    (file, Some(contents), None, None) => {
+                        buffer::Buffer::open(file, contents, &settings)
+                    }
+                    (file, None, _, None) => {
+                        buffer::Buffer::open_directory(file.unwrap_or_default(), &settings)
+                    }
+                },
+            )
+            .collect(),
+    };
+
+    // 出力先が端末でない場合や、-Fを指定し1つのファイルが1画面に収まる場合は、
+    // 画面を切り替えずにそのまま出力して終了する
+    if !is_terminal
+        || (args.quit_if_one_screen
+            && buffers.len() == 1
+            && directory.is_none()
+            && buffers[0].fits(regions.editor.width, regions.editor.height))
+    {
+        let mut stdout = stdout();
+        for buffer in &buffers {
+            stdout.write_all(buffer.plain_text(args.line_numbers).as_bytes())?;
+        }
+        stdout.flush()?;
+        return Ok(());
+    }
+
+    // --no-alt-screenを指定した場合は、終了した後も最後の表示が端末に残るように、今の画面に表示する
+    // 今の画面に表示されている内容は、消さずにスクロールバックに送っておく
+    let alternate_screen = !args.no_alt_screen;
+    if alternate_screen {
+        queue!(stdout(), EnterAlternateScreen)?;
+    } else {
+        queue!(stdout(), Print("\n".repeat(term_height as usize)))?;
+    }
     enable_raw_mode()?;
 
+    // 同期出力に対応している端末にだけ、書きかけの画面が見えないように出力をまとめるエスケープシーケンスを送る
+    let synchronized_update = backend::query_synchronized_update();
+    screen::set_backend(Box::new(backend::CrosstermBackend {
+        synchronized_update,
+    }));
+
     queue!(stdout(), Hide)?;
 
     let default_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |panic_info| {
+        if synchronized_update {
+            queue!(stdout(), terminal::EndSynchronizedUpdate).unwrap();
+        }
         queue!(stdout(), Show).unwrap();
         disable_raw_mode().unwrap();
-        queue!(stdout(), LeaveAlternateScreen).unwrap();
+        if alternate_screen {
+            queue!(stdout(), LeaveAlternateScreen).unwrap();
+        }
         stdout().flush().unwrap();
         default_hook(panic_info);
     }));
 
     execute!(stdout(), terminal::Clear(terminal::ClearType::All))?;
-    let status_bar_height = 1;
-    let status_bar_width = term_width;
+    screen::invalidate_all();
 
-    let mut status_bar = status_bar::StatusBar::new(
-        status_bar_width,
-        status_bar_height,
-        0,
-        term_height - status_bar_height,
+    let mut current = 0;
+
+    // タブページごとに、エディタ領域の分割と各領域で表示しているファイルの位置を保持する
+    let mut tabs = vec![layout::Layout::new(layout::Pane::new(current, 0, 0))];
+    let mut current_tab = 0;
+    if let Some(session) = &session {
+        tabs = session.tabs.iter().map(restore_layout).collect();
+        current_tab = session.current_tab;
+        current = tabs[current_tab].focused_pane().buffer;
+    }
+    update_status_items(&mut status_bar, &buffers, current);
+    let mut tab_bar = tab_bar::TabBar::new(term_width, 0);
+    tab_bar.style = settings.options.theme.tab_bar;
+    tab_bar.current_style = settings.options.theme.tab_bar_current;
+    tab_bar.color_depth = color_depth;
+
+    // キーと操作の対応(プリセットと設定ファイルの割り当てで既定の割り当てを上書きする)
+    let mut keymap = keymap::Keymap::new(
+        args.keymap
+            .or(settings.config.keymap_preset)
+            .unwrap_or_default(),
+        &settings.config.keymap,
     );
 
-    let status_bar_encoding =
-        status_bar::StatusBarItem::new("encoding".to_string(), "UTF-8".to_string());
+    // 設定ディレクトリのプラグインを読み込んで、プラグインで割り当てたキーの列を加える
+    // 読み込めないプラグインがある場合は、ほかのプラグインだけを使ってステータスバーで知らせる
+    let (mut plugins, plugin_errors) = plugin::Plugins::load();
+    plugins.bind_keys(&mut keymap);
+    if !plugin_errors.is_empty() {
+        status_bar.set_message(prompt::Message::error(plugin_errors.join(", ")));
+    }
 
-    status_bar.add_item(status_bar_encoding);
+    // ファイルを開いたときや書き込んだときなどに実行する、設定ファイルとプラグインで登録したコマンド
+    let mut hooks = hooks::Hooks::new(&settings.config.hooks);
+    plugins.register_hooks(&mut hooks);
+
+    // フックやプラグインで実行するコマンドは、入力を待たずに順に実行する
+    let mut queued_commands = std::collections::VecDeque::new();
+    apply_plugin_effects(&mut plugins, &mut status_bar, &mut queued_commands);
+
+    // nキーやNキーで検索する文字列
+    let search_pattern = args.pattern.clone();
+
+    // 操作しているファイルの末尾を追いかけているか(Ctrl+Cでやめる)
+    let follow = args.follow && buffers[current].file.is_some();
+
+    // エディタ領域の左に表示するファイルツリー(展開の状態は、隠しても保持する)
+    // ディレクトリを指定した場合は、最初からファイルツリーでその中のファイルを選べるようにする
+    let mut file_tree = file_tree::FileTree::new(std::path::PathBuf::from(
+        directory.as_deref().unwrap_or("."),
+    ));
+    file_tree.selection_style = settings.options.theme.selection;
+    file_tree.separator_style = settings.options.theme.separator;
+    file_tree.color_depth = color_depth;
+    file_tree.visible = directory.is_some();
+    file_tree.focused = directory.is_some();
 
     // エディタ領域に表示する文字列を取得する
-    let cursor_x = 0;
-    let mut cursor_y = 0;
-    let mut editor_height = term_height - status_bar_height;
-    let mut contents = contents::Contents::new(
-        original_contents.clone(),
+    // タブページが2つ以上ある場合は、エディタ領域はタブバーの下から始まる
+    let regions = screen_regions(Some(&file_tree), term_width, term_height, tabs.len());
+    let area = regions.editor;
+
+    // セッションを元に戻す場合は、各領域を保存したときの位置から表示する
+    // それ以外は、以前に開いたことがあるファイルを最後に表示していた位置から表示する
+    match &session {
+        Some(session) => {
+            for (layout, tab) in tabs.iter_mut().zip(&session.tabs) {
+                restore_pane_positions(&mut buffers, layout, tab, area);
+            }
+            let pane = *tabs[current_tab].focused_pane();
+            buffers[current].cursor_x = pane.cursor_x;
+            buffers[current].cursor_y = pane.cursor_y;
+        }
+        None => {
+            for buffer in buffers.iter_mut() {
+                restore_position(buffer, &history, area);
+            }
+        }
+    }
+
+    // 以前に開いたことがあるファイルは、前に付けた印に戻れるようにする
+    for buffer in buffers.iter_mut() {
+        restore_marks(buffer, &history);
+    }
+
+    // 検索する文字列が指定されている場合は、最初に見つかった行から表示する
+    if let Some(pattern) = &args.pattern {
+        let rect = tabs[current_tab].focused_rect(area);
+        let mut contents = buffers[current].view(rect.x, rect.y, rect.width, rect.height);
+        contents.scroll_to_match(pattern);
+        buffers[current].cursor_y = contents.cursor_y;
+    }
+
+    // 末尾を追いかける場合は、末尾から表示する
+    if follow {
+        buffers[current].move_window(true, &settings, term_width);
+        buffers[current].cursor_y = usize::MAX;
+        update_follow_item(&mut status_bar, follow);
+    }
+
+    if tabs.len() > 1 {
+        print_tab_bar(&tab_bar, &buffers, &tabs, current_tab);
+    }
+    if file_tree.visible {
+        file_tree.print(regions.sidebar);
+    }
+    save_cursor(&mut tabs[current_tab], &buffers[current]);
+    print_panes(
+        &mut buffers,
+        &mut tabs[current_tab],
+        area,
+        &settings.options,
+    )?;
+    update_pane_item(&mut status_bar, &tabs[current_tab]);
+
+    status_bar.print(&buffers[current]);
+    screen::flush()?;
+
+    let mut editor = editor::Editor {
+        args,
         term_width,
-        editor_height,
-        0,
-        0,
-        cursor_x,
-        cursor_y,
-    );
+        term_height,
+        config_modified_time,
+        status_bar,
+        settings,
+        buffers,
+        current,
+        tabs,
+        current_tab,
+        tab_bar,
+        keymap,
+        plugins,
+        hooks,
+        queued_commands,
+        opened_buffers: 0,
+        search_pattern,
+        reload: false,
+        file_watcher: watcher::FileWatcher::new(),
+        changed_files: std::collections::HashSet::new(),
+        follow,
+        command_line: None,
+        pending: Vec::new(),
+        pending_since: Instant::now(),
+        macros: macros::Macros::default(),
+        register_action: None,
+        count: None,
+        jumps: jump_list::JumpList::default(),
+        clipboard: clipboard::Clipboard::default(),
+        registers: registers::Registers::default(),
+        yank_register: None,
+        buffer_list: None,
+        help: None,
+        confirm: None,
+        overlays: overlay::OverlayLayer::default(),
+        finder: None,
+        file_tree,
+        history,
+        // 表示に時間がかかっている間も押したキーが抜けないように、入力は別のスレッドで読む
+        input: input::Input::spawn(),
+        color_depth,
+        alternate_screen,
+    };
+    editor.run().await?;
 
-    let status_bar_line = status_bar::StatusBarItem::new(
-        "line".to_string(),
-        "ln ".to_string() + (cursor_y + 1).to_string().as_str(),
-    );
-    status_bar.add_item(status_bar_line);
+    screen::end_frame()?;
+    queue!(stdout(), Show)?;
 
-    contents.print()?;
-    status_bar.print();
-    stdout().flush()?;
+    disable_raw_mode()?;
 
-    loop {
-        let event = read()?;
+    // 今の画面に表示している場合は、表示を残したまま、ステータスバーの行を空けてシェルに戻る
+    if editor.alternate_screen {
+        queue!(stdout(), LeaveAlternateScreen)?;
+    } else {
+        queue!(
+            stdout(),
+            MoveTo(0, editor.status_bar.y_start),
+            terminal::Clear(terminal::ClearType::CurrentLine)
+        )?;
+    }
+
+    screen::flush()?;
+
+    // 次に開いたときに同じ位置から表示できるように、開いていたファイルの表示位置を保存する
+    let area = screen_regions(
+        Some(&editor.file_tree),
+        editor.term_width,
+        editor.term_height,
+        editor.tabs.len(),
+    )
+    .editor;
+    record_history(&mut editor.history, &editor.buffers, editor.current, area);
+    if let Err(e) = editor.history.save() {
+        eprintln!("{}", e);
+    }
 
-        // イベントを読み捨てるため、pollを呼び出す
-        while poll(Duration::from_secs(0))? {
-            let _ = read()?;
+    // --sessionを指定した場合は、次に同じ名前で起動したときに元に戻せるように保存する
+    if let Some(name) = &editor.args.session {
+        save_cursor(
+            &mut editor.tabs[editor.current_tab],
+            &editor.buffers[editor.current],
+        );
+        let session = capture_session(&mut editor.buffers, &editor.tabs, editor.current_tab, area);
+        if let Err(e) = session.save(name) {
+            eprintln!("{}", e);
         }
+    }
 
-        // Ctrl + W で抜ける
-        match event {
-            Event::Key(KeyEvent {
-                code: KeyCode::Char('w'),
-                modifiers: KeyModifiers::CONTROL,
-                kind: _,
-                state: _,
-            }) => {
-                break;
-            }
-            // Upキーでカーソルを上に移動する
-            Event::Key(KeyEvent {
-                code: KeyCode::Up,
-                modifiers: _,
-                kind: _,
-                state: _,
-            }) => {
-                cursor_y = if cursor_y == 0 { 0 } else { cursor_y - 1 };
-
-                let mut contents = contents::Contents::new(
-                    original_contents.clone(),
-                    term_width,
-                    editor_height,
-                    0,
-                    0,
-                    cursor_x,
-                    cursor_y,
-                );
-                contents.print()?;
-
-                // 表示するときに再計算されるので、cursor_yを更新する
-                cursor_y = contents.cursor_y;
-
-                let status_bar_line = status_bar::StatusBarItem::new(
-                    "line".to_string(),
-                    "ln ".to_string() + (cursor_y + 1).to_string().as_str(),
-                );
-                status_bar.add_item(status_bar_line);
-
-                status_bar.print();
-                stdout().flush()?;
-            }
+    Ok(())
+}
 
-            // Downキーでカーソルを下に移動する
-            Event::Key(KeyEvent {
-                code: KeyCode::Down,
-                modifiers: _,
-                kind: _,
-                state: _,
-            }) => {
-                cursor_y += 1;
-
-                let mut contents = contents::Contents::new(
-                    original_contents.clone(),
-                    term_width,
-                    editor_height,
-                    0,
-                    0,
-                    cursor_x,
-                    cursor_y,
-                );
-                contents.print()?;
-
-                // 表示するときに再計算されるので、cursor_yを更新する
-                cursor_y = contents.cursor_y;
-
-                let status_bar_line = status_bar::StatusBarItem::new(
-                    "line".to_string(),
-                    "ln ".to_string() + (cursor_y + 1).to_string().as_str(),
-                );
-                status_bar.add_item(status_bar_line);
-
-                status_bar.print();
-                stdout().flush()?;
+/// 表示するファイルに合わせて、ステータスバーのファイルに関する項目を入れ替える
+/// # Arguments
+/// * `status_bar` - ステータスバー
+/// * `buffers` - 開いているファイル
+/// * `current` - 表示するファイルの位置
+/// # Notes
+/// * 複数のファイルを開いている場合は、何番目のファイルを表示しているかも表示する
+/// * 行番号の項目は取り除くので、表示した後に追加する
+fn update_status_items(
+    status_bar: &mut status_bar::StatusBar,
+    buffers: &[buffer::Buffer],
+    current: usize,
+) {
+    for name in [
+        "filetype",
+        "indent",
+        "eol",
+        "git",
+        "blame",
+        "line",
+        "col",
+        "selection",
+    ] {
+        status_bar.remove_item(name);
+    }
+
+    if buffers.len() > 1 {
+        let status_bar_file = status_bar::StatusBarItem::new(
+            "file".to_string(),
+            format!("file {}/{}", current + 1, buffers.len()),
+        );
+        status_bar.add_item(status_bar_file);
+    }
+
+    let buffer = &buffers[current];
+    for item in &buffer.status_items {
+        status_bar.add_item(item.clone());
+    }
+    if buffer.blame_receiver.is_some() {
+        let status_bar_blame =
+            status_bar::StatusBarItem::new("blame".to_string(), "blame: loading".to_string());
+        status_bar.add_item(status_bar_blame);
+    }
+
+    // 行番号と桁と選択しているかは、表示するときに操作しているファイルから求める
+    // ファイルごとの項目の後ろに表示するように、取り除いてから加え直す
+    status_bar.add_item(status_bar::StatusBarItem::provider(
+        "line".to_string(),
+        |buffer| Some(format!("ln {}", buffer.cursor_y + 1)),
+    ));
+    status_bar.add_item(status_bar::StatusBarItem::provider(
+        "col".to_string(),
+        |buffer| Some(format!("col {}", buffer.caret_column() + 1)),
+    ));
+    status_bar.add_item(status_bar::StatusBarItem::provider(
+        "selection".to_string(),
+        |buffer| {
+            buffer
+                .selection_mode()
+                .map(|block| if block { "visual block" } else { "visual" }.to_string())
+        },
+    ));
+}
+
+/// ステータスバーに、ファイルの末尾を追いかけているかを表示する
+/// # Arguments
+/// * `status_bar` - ステータスバー
+/// * `follow` - 末尾を追いかけている場合はtrue
+fn update_follow_item(status_bar: &mut status_bar::StatusBar, follow: bool) {
+    if follow {
+        let status_bar_follow = status_bar::StatusBarItem::new(
+            "follow".to_string(),
+            "following (Ctrl+C to stop)".to_string(),
+        );
+        status_bar.add_item(status_bar_follow);
+    } else {
+        status_bar.remove_item("follow");
+    }
+}
+
+/// 入力したコマンドを解釈する
+/// # Arguments
+/// * `line` - `:`に続けて入力した文字列
+/// * `plugins` - コマンドを登録したプラグイン
+/// # Returns
+/// * `Result<command_line::Command, String>` - 解釈したコマンド(解釈できない場合はエラーメッセージ)
+/// # Notes
+/// * 組み込みのコマンドにない名前の場合は、プラグインで登録したコマンドから探す
+fn parse_command(line: &str, plugins: &plugin::Plugins) -> Result<command_line::Command, String> {
+    command_line::parse(line).or_else(|e| plugins.parse_command(line).ok_or(e))
+}
+
+/// プラグインが行った操作を、ステータスバーに反映する
+/// # Arguments
+/// * `plugins` - 操作を行ったプラグイン
+/// * `status_bar` - 反映するステータスバー
+/// * `queued_commands` - 実行を待つコマンド(プラグインで実行するコマンドを加える)
+/// # Returns
+/// * `bool` - 反映する操作があった場合はtrue
+/// # Notes
+/// * 表示し直すのは呼び出した側で行う
+fn apply_plugin_effects(
+    plugins: &mut plugin::Plugins,
+    status_bar: &mut status_bar::StatusBar,
+    queued_commands: &mut std::collections::VecDeque<String>,
+) -> bool {
+    let effects = plugins.take_effects();
+    let applied = !effects.is_empty();
+    for effect in effects {
+        match effect {
+            plugin::Effect::Message { text, is_error } => {
+                status_bar.set_message(if is_error {
+                    prompt::Message::error(text)
+                } else {
+                    prompt::Message::info(text)
+                });
             }
-            // RightキーとLeftキーでX軸方向でカーソルを移動する機能は未実装
-            // 理由: 今は必ずおりたたみ表示になるので、X軸方向でカーソルを移動する機能は不要
-            Event::FocusGained => todo!(),
-            Event::FocusLost => todo!(),
-            Event::Mouse(_) => todo!(),
-            Event::Paste(_) => todo!(),
-            Event::Resize(columns, rows) => {
-                term_width = columns;
-                term_height = rows;
-                editor_height = term_height - status_bar_height;
-
-                let mut contents = contents::Contents::new(
-                    original_contents.clone(),
-                    term_width,
-                    editor_height,
-                    0,
-                    0,
-                    cursor_x,
-                    cursor_y,
-                );
-
-                status_bar.width = term_width;
-                status_bar.y_start = term_height - status_bar_height;
-
-                contents.print()?;
-
-                // 表示するときに再計算されるので、cursor_yを更新する
-                cursor_y = contents.cursor_y;
-
-                let status_bar_line = status_bar::StatusBarItem::new(
-                    "line".to_string(),
-                    "ln ".to_string() + (cursor_y + 1).to_string().as_str(),
-                );
-                status_bar.add_item(status_bar_line);
-
-                status_bar.print();
-                stdout().flush()?;
+            plugin::Effect::SetStatus { name, value } => {
+                status_bar.add_item(status_bar::StatusBarItem::new(name, value));
             }
-            _ => {}
+            plugin::Effect::RemoveStatus(name) => status_bar.remove_item(&name),
+            plugin::Effect::Execute(command) => queued_commands.push_back(command),
         }
     }
 
-    queue!(stdout(), Show)?;
+    applied
+}
 
-    disable_raw_mode()?;
+/// プラグインに渡す、表示しているファイルの状態
+fn plugin_context(buffer: &buffer::Buffer) -> plugin::Context {
+    plugin::Context {
+        file: buffer.file.clone(),
+        line_number: buffer.cursor_y + 1,
+    }
+}
 
-    queue!(stdout(), LeaveAlternateScreen)?;
+/// フックを実行する
+/// # Arguments
+/// * `hook` - 実行する時点
+/// * `argument` - 開いたファイルや書き込んだファイルの名前、押したキー
+/// * `buffer` - 操作しているバッファ
+/// * `hooks` - 設定ファイルとプラグインで登録したコマンド
+/// * `plugins` - 関数を登録したプラグイン
+/// * `status_bar` - プラグインが行った操作を反映するステータスバー
+/// * `queued_commands` - 実行を待つコマンド
+/// # Returns
+/// * `bool` - ステータスバーを変更した場合はtrue
+/// # Notes
+/// * 登録したコマンドは、実行を待つコマンドに加えて、入力を待つ前に順に実行する
+/// * コマンドの`{file}`は、開いたファイルや書き込んだファイルの名前(キーを押したときは操作しているファイルの名前)に置き換える
+fn run_hook(
+    hook: hooks::Hook,
+    argument: &str,
+    buffer: &buffer::Buffer,
+    hooks: &hooks::Hooks,
+    plugins: &mut plugin::Plugins,
+    status_bar: &mut status_bar::StatusBar,
+    queued_commands: &mut std::collections::VecDeque<String>,
+) -> bool {
+    let file = match hook {
+        hooks::Hook::OnKey => buffer.file.as_deref(),
+        _ => Some(argument),
+    };
+    queued_commands.extend(hooks.commands(hook, file));
+
+    let result = plugins.run_hook(hook, argument, plugin_context(buffer));
+    let applied = apply_plugin_effects(plugins, status_bar, queued_commands);
+    match result {
+        Ok(()) => applied,
+        Err(e) => {
+            status_bar.set_message(prompt::Message::error(e));
+            true
+        }
+    }
+}
+
+/// 設定ファイルと、使うテーマを読み込む
+/// # Arguments
+/// * `theme` - コマンドライン引数で指定したテーマ
+/// # Returns
+/// * `Result<(config::Config, style::Theme), std::io::Error>` - 読み込んだ設定とテーマ
+/// # Notes
+/// * コマンドライン引数でテーマを指定していない場合は、設定ファイルのテーマを使う
+fn load_config(theme: Option<&str>) -> Result<(config::Config, style::Theme), std::io::Error> {
+    let config = config::Config::load()?;
+    let theme_name = theme.or(config.theme.as_deref()).unwrap_or("default");
+    let theme = style::Theme::load(theme_name)?;
+
+    Ok((config, theme))
+}
+
+/// ファイルを開く
+/// # Arguments
+/// * `buffers` - 開いているファイル
+/// * `path` - 開くファイルのパス
+/// * `settings` - すべてのファイルに共通する表示の設定
+/// * `history` - 最近開いたファイルの履歴
+/// * `rect` - ファイルを表示する領域
+/// # Returns
+/// * `Option<usize>` - 開いたファイルの`buffers`での位置(読み込めなかった場合はNone)
+/// # Notes
+/// * すでに開いているファイルの場合は、読み込み直さずにそのファイルの位置を返す
+/// * 以前に開いたことがあるファイルの場合は、最後に表示していた位置から表示する
+fn open_file(
+    buffers: &mut Vec<buffer::Buffer>,
+    path: &std::path::Path,
+    settings: &buffer::Settings,
+    history: &history::History,
+    rect: layout::Rect,
+) -> Option<usize> {
+    // URLやSSHのパスの場合は、同じ場所を開いていなければ取得する
+    let file = path.display().to_string();
+    let opened = buffers.iter().position(|buffer| {
+        buffer
+            .origin
+            .as_ref()
+            .is_some_and(|origin| origin.name() == file)
+    });
+    if opened.is_some() {
+        return opened;
+    }
+    if let Some(result) = get_remote_contents(&file, false) {
+        let (origin, contents) = result.ok()?;
+        buffers.push(buffer::Buffer::open_remote(origin, contents, settings));
+        return Some(buffers.len() - 1);
+    }
+
+    let canonical = std::fs::canonicalize(path).ok();
+    let opened = buffers.iter().position(|buffer| {
+        buffer
+            .file
+            .as_ref()
+            .is_some_and(|file| std::fs::canonicalize(file).ok() == canonical)
+    });
+    if opened.is_some() {
+        return opened;
+    }
+
+    // 大きなファイルは、すべて読み込まずにメモリマップで開く
+    let mut buffer = match mapped::MappedFile::open(&file) {
+        Some(mapped) => buffer::Buffer::open_mapped(file, mapped, settings),
+        None => {
+            let contents = get_contents(Some(file.clone()), false).ok()?;
+            buffer::Buffer::open(Some(file), contents, settings)
+        }
+    };
+    restore_position(&mut buffer, history, rect);
+    restore_marks(&mut buffer, history);
+    buffers.push(buffer);
+
+    Some(buffers.len() - 1)
+}
+
+/// 以前に開いたことがあるファイルの場合は、最後に表示していた位置までスクロールする
+/// # Arguments
+/// * `buffer` - 開いたファイル
+/// * `history` - 最近開いたファイルの履歴
+/// * `rect` - ファイルを表示する領域
+fn restore_position(buffer: &mut buffer::Buffer, history: &history::History, rect: layout::Rect) {
+    let Some(entry) = buffer.file.as_deref().and_then(|file| history.get(file)) else {
+        return;
+    };
+
+    buffer.scroll_to_line(entry.line, rect.width, rect.height);
+    buffer.cursor_x = entry.column;
+}
+
+/// 以前に開いたことがあるファイルの場合は、前に付けた印を元に戻す
+/// # Arguments
+/// * `buffer` - 開いたファイル
+/// * `history` - 最近開いたファイルの履歴
+fn restore_marks(buffer: &mut buffer::Buffer, history: &history::History) {
+    let Some(entry) = buffer.file.as_deref().and_then(|file| history.get(file)) else {
+        return;
+    };
+
+    buffer.marks = entry.marks.clone();
+    buffer.update_mark_markers();
+}
+
+/// 保存したタブページから、エディタ領域の分割を作成する
+/// # Arguments
+/// * `tab` - 保存したタブページ
+/// # Notes
+/// * ファイルは保存した順に開くので、`Session::files`での位置をそのまま`buffers`での位置として使う
+/// * 縦のスクロール位置は表示する領域の大きさで変わるので、`restore_pane_positions`で設定する
+fn restore_layout(tab: &session::SessionTab) -> layout::Layout {
+    layout::Layout {
+        panes: tab
+            .panes
+            .iter()
+            .map(|pane| layout::Pane::new(pane.file, pane.column, 0))
+            .collect(),
+        focused: tab.focused,
+        direction: tab.direction,
+        ratio: tab.ratio,
+        scroll_lock: tab.scroll_lock,
+    }
+}
+
+/// 各領域を、保存したときに一番上に表示していた行までスクロールする
+/// # Arguments
+/// * `buffers` - 開いているファイル
+/// * `layout` - 元に戻したタブページのエディタ領域の分割
+/// * `tab` - 保存したタブページ
+/// * `area` - エディタ領域全体
+fn restore_pane_positions(
+    buffers: &mut [buffer::Buffer],
+    layout: &mut layout::Layout,
+    tab: &session::SessionTab,
+    area: layout::Rect,
+) {
+    let rects = layout.rects(area);
+    for ((pane, saved), rect) in layout.panes.iter_mut().zip(&tab.panes).zip(rects) {
+        let buffer = &mut buffers[pane.buffer];
+        buffer.cursor_x = pane.cursor_x;
+        buffer.cursor_y = 0;
+        buffer.scroll_to_line(saved.line, rect.width, rect.height);
+        pane.cursor_y = buffer.cursor_y;
+    }
+}
+
+/// 開いているファイルと、タブページや分割した領域の状態を保存できる形にする
+/// # Arguments
+/// * `buffers` - 開いているファイル
+/// * `tabs` - タブページごとのエディタ領域の分割
+/// * `current_tab` - 表示しているタブページの位置
+/// * `area` - エディタ領域全体
+/// # Notes
+/// * 標準入力は開き直せないので保存せず、標準入力を表示している領域も保存しない
+/// * 各領域の一番上の行番号を求めるために、`buffers`のスクロール位置を変更する
+fn capture_session(
+    buffers: &mut [buffer::Buffer],
+    tabs: &[layout::Layout],
+    current_tab: usize,
+    area: layout::Rect,
+) -> session::Session {
+    let mut session = session::Session {
+        files: buffers
+            .iter()
+            .map(|buffer| {
+                buffer
+                    .file
+                    .as_deref()
+                    .and_then(|file| std::fs::canonicalize(file).ok())
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_default()
+            })
+            .collect(),
+        tabs: Vec::new(),
+        current_tab,
+    };
+
+    for layout in tabs {
+        let rects = layout.rects(area);
+        let panes = layout
+            .panes
+            .iter()
+            .zip(rects)
+            .map(|(pane, rect)| {
+                let buffer = &mut buffers[pane.buffer];
+                buffer.cursor_x = pane.cursor_x;
+                buffer.cursor_y = pane.cursor_y;
+                session::SessionPane {
+                    file: pane.buffer,
+                    line: buffer.top_line_number(rect.width, rect.height).unwrap_or(1),
+                    column: pane.cursor_x,
+                }
+            })
+            .collect();
+        session.tabs.push(session::SessionTab {
+            panes,
+            focused: layout.focused,
+            direction: layout.direction,
+            ratio: layout.ratio,
+            scroll_lock: layout.scroll_lock,
+        });
+    }
+    session.retain_files(|file| !file.is_empty());
+
+    session
+}
+
+/// 開いていたファイルと、それぞれの最後の表示位置を履歴に記録する
+/// # Arguments
+/// * `history` - 最近開いたファイルの履歴
+/// * `buffers` - 開いているファイル
+/// * `current` - 表示しているファイルの位置
+/// * `area` - エディタ領域全体
+/// # Notes
+/// * 表示しているファイルが最近開いたファイルの先頭になるように、最後に記録する
+/// * 標準入力やディレクトリは記録しない
+fn record_history(
+    history: &mut history::History,
+    buffers: &[buffer::Buffer],
+    current: usize,
+    area: layout::Rect,
+) {
+    let order = (0..buffers.len())
+        .filter(|&i| i != current)
+        .chain([current]);
+    for i in order {
+        let buffer = &buffers[i];
+        let Some(file) = buffer
+            .file
+            .as_deref()
+            .filter(|file| std::path::Path::new(file).is_file())
+        else {
+            continue;
+        };
+
+        history.record(
+            file,
+            buffer.top_line_number(area.width, area.height),
+            buffer.cursor_x,
+            &buffer.marks,
+        );
+    }
+}
+
+/// 端末の画面を、タブバー、ファイルツリー、エディタ領域、ステータスバーの領域に分ける
+/// # Arguments
+/// * `file_tree` - ファイルツリー(まだ作成していない場合はNone)
+/// * `term_width` - 端末の横幅
+/// * `term_height` - 端末の高さ
+/// * `tab_count` - タブページの数
+/// # Notes
+/// * タブバーは、タブページが2つ以上ある場合だけ表示する
+fn screen_regions(
+    file_tree: Option<&file_tree::FileTree>,
+    term_width: u16,
+    term_height: u16,
+    tab_count: usize,
+) -> layout::Regions {
+    let screen = layout::Rect {
+        x: 0,
+        y: 0,
+        width: term_width,
+        height: term_height,
+    };
+    let sidebar_width = file_tree.map_or(0, |file_tree| file_tree.width(term_width));
+    layout::Regions::new(
+        screen,
+        u16::from(tab_count > 1),
+        sidebar_width,
+        status_bar::HEIGHT,
+    )
+}
+
+/// エディタ領域を分割している場合は、ステータスバーに操作している領域の位置を表示する
+/// # Arguments
+/// * `status_bar` - ステータスバー
+/// * `layout` - 表示しているタブページのエディタ領域の分割
+fn update_pane_item(status_bar: &mut status_bar::StatusBar, layout: &layout::Layout) {
+    status_bar.remove_item("pane");
+
+    if layout.panes.len() > 1 {
+        let status_bar_pane = status_bar::StatusBarItem::new(
+            "pane".to_string(),
+            format!(
+                "pane {}/{}{}",
+                layout.focused + 1,
+                layout.panes.len(),
+                if layout.scroll_lock {
+                    " (scroll lock)"
+                } else {
+                    ""
+                }
+            ),
+        );
+        status_bar.add_item(status_bar_pane);
+    }
+}
+
+/// 操作する領域に、表示したファイルのスクロール位置を保存する
+/// # Arguments
+/// * `layout` - 表示しているタブページのエディタ領域の分割
+/// * `buffer` - 操作する領域で表示しているファイル
+fn save_cursor(layout: &mut layout::Layout, buffer: &buffer::Buffer) {
+    let pane = layout.focused_pane_mut();
+    pane.cursor_x = buffer.cursor_x;
+    pane.cursor_y = buffer.cursor_y;
+}
+
+/// 分割したすべての領域に、それぞれのファイルの内容を表示する
+/// # Arguments
+/// * `buffers` - 開いているファイル
+/// * `layout` - 表示しているタブページのエディタ領域の分割
+/// * `area` - エディタ領域全体
+/// * `options` - 表示に関する設定(境界線の装飾と色数に使う)
+/// # Notes
+/// * 各領域のスクロール位置は、表示するときに再計算した位置に更新する
+/// * ファイルのスクロール位置が操作する領域のものになるように、操作する領域を最後に表示する
+fn print_panes(
+    buffers: &mut [buffer::Buffer],
+    layout: &mut layout::Layout,
+    area: layout::Rect,
+    options: &contents::DisplayOptions,
+) -> std::io::Result<()> {
+    let rects = layout.rects(area);
+    let focused = layout.focused;
+    let order = (0..layout.panes.len())
+        .filter(|&i| i != focused)
+        .chain([focused]);
+
+    for i in order {
+        let pane = &mut layout.panes[i];
+        let buffer = &mut buffers[pane.buffer];
+        buffer.cursor_x = pane.cursor_x;
+        buffer.cursor_y = pane.cursor_y;
+
+        let rect = rects[i];
+        let mut contents = buffer.view(rect.x, rect.y, rect.width, rect.height);
+        contents.print()?;
+
+        // 表示するときに再計算されるので、cursor_xとcursor_yを更新する
+        buffer.cursor_x = contents.cursor_x;
+        buffer.cursor_y = contents.cursor_y;
+        pane.cursor_x = contents.cursor_x;
+        pane.cursor_y = contents.cursor_y;
+    }
+
+    layout.print_separator(area, &options.theme.separator, options.color_depth);
+
+    Ok(())
+}
+
+/// 重ねて表示した一覧などを閉じたときに、隠れていた部分だけを表示し直す
+/// # Arguments
+/// * `buffers` - 開いているファイル
+/// * `layout` - 表示しているタブページのエディタ領域の分割
+/// * `file_tree` - ファイルツリー
+/// * `regions` - 端末の画面を分けた領域
+/// * `region` - 隠れていた部分
+/// * `options` - 表示に関する設定
+/// # Notes
+/// * 隠れていた部分と重なる領域だけを、重なる行だけ表示し直す
+/// * ファイルツリーと境界線は、隠れていた部分と重なる場合はすべて表示し直す
+fn restore_region(
+    buffers: &mut [buffer::Buffer],
+    layout: &mut layout::Layout,
+    file_tree: &file_tree::FileTree,
+    regions: layout::Regions,
+    region: layout::Rect,
+    options: &contents::DisplayOptions,
+) -> std::io::Result<()> {
+    let area = regions.editor;
+    if file_tree.visible && region.intersection(&regions.sidebar).is_some() {
+        file_tree.print(regions.sidebar);
+    }
+
+    let rects = layout.rects(area);
+    for (pane, rect) in layout.panes.iter_mut().zip(rects) {
+        let Some(damaged) = rect.intersection(&region) else {
+            continue;
+        };
+
+        let buffer = &mut buffers[pane.buffer];
+        buffer.cursor_x = pane.cursor_x;
+        buffer.cursor_y = pane.cursor_y;
+
+        let mut contents = buffer.view(rect.x, rect.y, rect.width, rect.height);
+        let top = damaged.y - rect.y;
+        contents.print_rows(top..top + damaged.height)?;
+
+        // 表示するときに再計算されるので、cursor_xとcursor_yを更新する
+        buffer.cursor_x = contents.cursor_x;
+        buffer.cursor_y = contents.cursor_y;
+        pane.cursor_x = contents.cursor_x;
+        pane.cursor_y = contents.cursor_y;
+    }
+
+    // 同じファイルを複数の領域に表示している場合があるので、ファイルの位置を操作する領域の位置にそろえる
+    let pane = *layout.focused_pane();
+    buffers[pane.buffer].cursor_x = pane.cursor_x;
+    buffers[pane.buffer].cursor_y = pane.cursor_y;
+
+    layout.print_separator(area, &options.theme.separator, options.color_depth);
 
-    stdout().flush()?;
     Ok(())
 }
 
 /// ファイルの内容を取得する
 /// # Arguments
 /// * `file` - ファイル名
+/// * `show_progress` - 読み込みに時間がかかる場合に、読み込んだ大きさを表示する場合はtrue
+///   (画面を切り替える前に、端末に表示する場合だけ使う)
 /// # Returns
 /// * `Result<String, std::io::Error>` - ファイルの内容を取得できた場合は、`Ok(String)`を返す
 /// # Examples
@@ -250,20 +1150,29 @@ fn main() -> std::io::Result<()> {
 /// | `Some(file)` | 存在しない         | あり/なし | エラーを表示して終了する |
 /// | `None`       |                    | あり      | 標準入力の内容           |
 /// | `None`       |                    | なし      | エラーを表示して終了する |
-fn get_contents(file: Option<String>) -> Result<String, std::io::Error> {
-    let mut contents = String::new();
-    match file {
+fn get_contents(file: Option<String>, show_progress: bool) -> Result<String, std::io::Error> {
+    let show_progress = show_progress && atty::is(atty::Stream::Stdout);
+    let contents = match file {
         Some(file) => {
-            // ファイルが存在しない場合は、エラーを表示して終了する
-            match std::fs::read_to_string(&file) {
-                Ok(file_contents) => contents = file_contents,
-                Err(_) => {
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::NotFound,
-                        format!("{}: No such file or directory", file),
-                    ));
+            // 終了コードで理由を区別できるように、エラーの種類は残したままファイル名を付ける
+            let read = std::fs::File::open(&file).and_then(|reader| {
+                let total = reader.metadata().ok().map(|metadata| metadata.len());
+                loading::read_all(reader, &file, total, show_progress)
+            });
+            let contents = read.map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => {
+                    std::io::Error::new(e.kind(), format!("{}: No such file or directory", file))
                 }
-            }
+                _ => std::io::Error::new(e.kind(), format!("{}: {}", file, e)),
+            })?;
+            let contents = decompress::decompress(Some(&file), contents)
+                .map_err(|e| std::io::Error::new(e.kind(), format!("{}: {}", file, e)))?;
+            String::from_utf8(contents).map_err(|_| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("{}: Not valid UTF-8", file),
+                )
+            })?
         }
         None => {
             if atty::is(atty::Stream::Stdin) {
@@ -271,17 +1180,140 @@ fn get_contents(file: Option<String>) -> Result<String, std::io::Error> {
                 // 装飾付きの文字でヘルプを表示したいので、ここで`print_help`を呼び出す
                 args.print_help().unwrap();
                 return Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
+                    std::io::ErrorKind::InvalidInput,
                     "No input file",
                 ));
             } else {
-                std::io::stdin().read_to_string(&mut contents)?;
+                let contents = loading::read_all(
+                    std::io::stdin().lock(),
+                    "standard input",
+                    None,
+                    show_progress,
+                )?;
+                let contents = decompress::decompress(None, contents)
+                    .map_err(|e| std::io::Error::new(e.kind(), format!("standard input: {}", e)))?;
+                String::from_utf8(contents).map_err(|_| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "standard input: Not valid UTF-8",
+                    )
+                })?
             }
         }
     };
     Ok(contents)
 }
 
+/// URLやSSHのパスの内容を取得する
+/// # Arguments
+/// * `file` - URLか`[user@]host:path`の形式のパス
+/// * `show_progress` - 取得に時間がかかる場合に、取得した大きさを表示する場合はtrue
+///   (画面を切り替える前に、端末に表示する場合だけ使う)
+/// # Returns
+/// * `Option<Result<(buffer::Origin, String), std::io::Error>>` - 取得した場所と内容
+///   (URLでもSSHのパスでもない場合はNone)
+/// # Notes
+/// * ファイルと同じように、パスが圧縮形式の拡張子の場合などは展開する
+/// * SSHのパスは、画面を切り替える前だけパスワードなどの入力を求める
+fn get_remote_contents(
+    file: &str,
+    show_progress: bool,
+) -> Option<Result<(buffer::Origin, String), std::io::Error>> {
+    let origin = if http::is_url(file) {
+        None
+    } else {
+        Some(ssh::Location::parse(file)?)
+    };
+
+    let show_progress = show_progress && atty::is(atty::Stream::Stdout);
+    let with_file = |e: std::io::Error| std::io::Error::new(e.kind(), format!("{}: {}", file, e));
+    let fetched = match origin {
+        Some(location) => {
+            ssh::read(&location, show_progress).map(|body| (buffer::Origin::Ssh(location), body))
+        }
+        None => http::fetch(file, show_progress)
+            .map(|(remote, body)| (buffer::Origin::Url(remote), body)),
+    };
+
+    Some(fetched.map_err(with_file).and_then(|(origin, body)| {
+        let body = decompress::decompress(Some(&origin.name()), body).map_err(with_file)?;
+        let contents = String::from_utf8(body).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{}: Not valid UTF-8", file),
+            )
+        })?;
+        Ok((origin, contents))
+    }))
+}
+
+/// タブページが2つ以上ある場合は、タブバーを表示する
+/// # Arguments
+/// * `tab_bar` - タブバー
+/// * `buffers` - 開いているファイル
+/// * `tabs` - 各タブページのエディタ領域の分割
+/// * `current_tab` - 表示しているタブページの位置
+fn print_tab_bar(
+    tab_bar: &tab_bar::TabBar,
+    buffers: &[buffer::Buffer],
+    tabs: &[layout::Layout],
+    current_tab: usize,
+) {
+    if tabs.len() > 1 {
+        let names: Vec<String> = tabs
+            .iter()
+            .map(|layout| buffers[layout.focused_pane().buffer].name())
+            .collect();
+        tab_bar.print(&names, current_tab);
+    }
+}
+
+/// ロケールが日本語、中国語、韓国語のいずれかであるかを判定する
+/// # Returns
+/// * `bool` - CJKのロケールの場合はtrue、それ以外はfalse
+/// # Notes
+/// * 環境変数は`LC_ALL`、`LC_CTYPE`、`LANG`の順に参照し、最初に空でない値を使う
+fn is_cjk_locale() -> bool {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+
+    ["ja", "zh", "ko"]
+        .iter()
+        .any(|language| locale.starts_with(language))
+}
+
+/// 色を使うかどうかの設定
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum ColorMode {
+    /// Use colors when writing to a terminal and NO_COLOR is not set.
+    Auto,
+    /// Always use colors.
+    Always,
+    /// Never use colors.
+    Never,
+}
+
+/// 色を使うかどうかを判定する
+/// # Arguments
+/// * `mode` - 色を使うかどうかの設定
+/// # Returns
+/// * `bool` - 色を使う場合はtrue、それ以外はfalse
+/// # Notes
+/// * 環境変数`NO_COLOR`は空でない値が設定されている場合のみ有効とする(https://no-color.org/)
+fn use_color(mode: ColorMode) -> bool {
+    match mode {
+        ColorMode::Always => true,
+        ColorMode::Never => false,
+        ColorMode::Auto => {
+            let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+            !no_color && atty::is(atty::Stream::Stdout)
+        }
+    }
+}
+
 #[derive(Debug, Parser)]
 #[clap(
     name = env!("CARGO_PKG_NAME"),
@@ -289,9 +1321,92 @@ fn get_contents(file: Option<String>) -> Result<String, std::io::Error> {
     author = env!("CARGO_PKG_AUTHORS"),
     about = env!("CARGO_PKG_DESCRIPTION"),
     arg_required_else_help = false,
+    args_override_self = true,
+    after_help = "Press ? or F1 while viewing to list the current key bindings.\n\n\
+                  Default options can be set in the EDOC environment variable, e.g. EDOC=\"--no-wrap --theme default\". Options given on the command line override them.\n\n\
+                  Exit status: 0 on success, 1 if a file is not found, 2 if a file is not valid UTF-8, \
+                  3 for other read or terminal errors, 4 if the config file, theme or session cannot be loaded, \
+                  and 64 for invalid arguments or no input.",
 )]
 struct Args {
-    /// File to print. If no FILE is specified, read standard input.
+    /// Files to print: paths, directories, compressed files, http(s) URLs or [user@]host:path. If no FILE is specified, read standard input.
     #[clap()]
-    file: Option<String>,
+    files: Vec<String>,
+
+    /// Show the differences between OLD and NEW with line numbers from both files.
+    #[clap(long, num_args = 2, value_names = ["OLD", "NEW"], conflicts_with = "files")]
+    diff: Option<Vec<String>>,
+
+    /// Show the --diff output with the two files side by side. Toggle with Alt+S.
+    #[clap(long, requires = "diff")]
+    side_by_side: bool,
+
+    /// Number of columns between tab stops. Defaults to the detected file type's width, then tab_width in config.toml, or 8.
+    #[clap(long, value_parser = clap::value_parser!(u16).range(1..))]
+    tab_width: Option<u16>,
+
+    /// Output control characters as-is instead of showing them as ^M or <U+009B>.
+    #[clap(short, long)]
+    raw_control_chars: bool,
+
+    /// Squeeze multiple adjacent blank lines into one.
+    #[clap(short, long)]
+    squeeze_blank: bool,
+
+    /// Chop long lines instead of wrapping them. Use Left/Right to scroll horizontally.
+    #[clap(short = 'S', long, visible_alias = "no-wrap", overrides_with = "wrap")]
+    chop_long_lines: bool,
+
+    /// Wrap long lines even if config.toml sets wrap = false.
+    #[clap(long, overrides_with = "chop_long_lines")]
+    wrap: bool,
+
+    /// Remove all ANSI escape sequences from the input before displaying it.
+    #[clap(long)]
+    strip_ansi: bool,
+
+    /// When to use colors and other styling. NO_COLOR disables them in auto mode.
+    #[clap(long, value_enum, default_value_t = ColorMode::Auto)]
+    color: ColorMode,
+
+    /// Theme name looked up as ~/.config/edoc/themes/NAME.toml, or a path to a theme file.
+    /// Defaults to theme in config.toml, or "default".
+    #[clap(long)]
+    theme: Option<String>,
+
+    /// Treat East Asian ambiguous-width characters as wide. Enabled automatically in CJK locales.
+    #[clap(long)]
+    ambiguous_wide: bool,
+
+    /// Key binding preset layered over the defaults. Defaults to keymap_preset in config.toml.
+    /// Keys in the [keymap] table of config.toml take precedence over the preset.
+    #[clap(long, value_enum)]
+    keymap: Option<keymap::KeymapPreset>,
+
+    /// Save the open files, tab pages, splits and positions as NAME on quit.
+    /// Starting again with the same NAME and no FILE restores them.
+    #[clap(long, value_name = "NAME", conflicts_with = "diff")]
+    session: Option<String>,
+
+    /// Keep reading data appended to the file and scroll to the end, like tail -f. Press Ctrl+C to stop following.
+    #[clap(long)]
+    follow: bool,
+
+    /// Print the input and exit without starting the full-screen view if it fits on one screen after wrapping.
+    #[clap(short = 'F', long)]
+    quit_if_one_screen: bool,
+
+    /// Keep the last screen in the terminal after quitting by drawing on the main screen instead of the alternate screen.
+    #[clap(short = 'X', long)]
+    no_alt_screen: bool,
+
+    /// Prefix each line with its line number when printing instead of starting the full-screen view,
+    /// such as when the output is redirected to a file or a pipe.
+    #[clap(short = 'N', long)]
+    line_numbers: bool,
+
+    /// Start at the first line containing PATTERN. Composed and decomposed characters match each other.
+    /// The less, vim and emacs keymaps jump to the next and previous match.
+    #[clap(short, long)]
+    pattern: Option<String>,
 }
@@ -0,0 +1,496 @@
+use std::{
+    ops::Range,
+    sync::{mpsc, Arc},
+};
+
+use crate::decompress;
+
+/// メモリマップで開くファイルの大きさの下限(これより小さいファイルはすべて読み込む)
+const THRESHOLD: u64 = 64 << 20;
+/// 表示する範囲に、一番上の行より前と後ろにそれぞれ含める行数
+const HALF_LINES: usize = 1024;
+/// 表示する範囲に、一番上の行より前と後ろにそれぞれ含める大きさ(バイト単位、後ろには少なくとも1行含める)
+const HALF_BYTES: usize = 128 << 10;
+/// 索引に先頭の位置を入れる行の間隔(この行数より多くは数えずに、行の先頭の位置を求められる)
+const INDEX_STEP: usize = 1024;
+/// 索引を作るときに、作り終えた分を受け渡す大きさ(バイト単位)
+const INDEX_CHUNK: usize = 1 << 20;
+
+/// メモリマップで開いた大きなファイルと、そのうち文字列にして表示する範囲
+/// # Notes
+/// * ファイル全体を文字列にせず、表示している位置の前後だけをUTF-8として読み取る
+/// * UTF-8として読み取れないバイト列は、置換文字(U+FFFD)にする
+/// * 行の先頭の位置の索引を別のスレッドで作り、作り終えた後は行番号から位置をすぐに求める
+/// * 切り詰められたファイルのマップした範囲を読み取ると異常終了(SIGBUS)するので、読み取る前に大きさを確かめる
+pub struct MappedFile {
+    /// 開いたファイル(大きさを確かめたり、マップし直したりするために使う)
+    file: std::fs::File,
+    /// ファイルの内容(索引を作るスレッドと共有する)
+    map: Arc<memmap2::Mmap>,
+    /// マップした内容のうち、読み取ってよい大きさ(バイト単位、ファイルが小さくなった場合はマップより小さい)
+    len: usize,
+    /// 表示する範囲(バイト単位、行の先頭から行の末尾まで)
+    range: Range<usize>,
+    /// 表示する範囲より前にある行数
+    first_line: usize,
+    /// `INDEX_STEP`行ごとの、行の先頭の位置(バイト単位、最初は0)
+    index: Vec<usize>,
+    /// 索引を作り終えた位置(バイト単位)
+    indexed: usize,
+    /// 索引を作っているスレッドから、作り終えた分の索引と位置を受け取る(作り終えた場合はNone)
+    indexer: Option<mpsc::Receiver<(Vec<usize>, usize)>>,
+}
+
+impl MappedFile {
+    /// 大きなファイルの場合は、メモリマップで開く
+    /// # Arguments
+    /// * `file` - ファイル名
+    /// # Returns
+    /// * `Option<MappedFile>` - 開いたファイル(小さいファイルや圧縮されたファイル、開けない場合はNone)
+    /// # Notes
+    /// * Noneの場合はすべて読み込む(開けない理由は、読み込むときのエラーで知らせる)
+    pub fn open(file: &str) -> Option<Self> {
+        let reader = std::fs::File::open(file).ok()?;
+        let metadata = reader.metadata().ok()?;
+        if !metadata.is_file() || metadata.len() < THRESHOLD {
+            return None;
+        }
+
+        // SAFETY: 開いている間に他のプロセスがファイルを切り詰めると、その後ろを読み取るときに異常終了する
+        // 読み取る前に毎回ファイルの大きさを確かめ、切り詰められた後ろは読み取らない
+        let map = unsafe { memmap2::Mmap::map(&reader) }.ok()?;
+        if decompress::Format::detect(Some(file), &map).is_some() {
+            return None;
+        }
+        Self::new(reader, map)
+    }
+
+    /// メモリマップしたファイルの索引を作り始めて、先頭から表示する
+    /// # Notes
+    /// * 索引を作り終えるのを待たずに、最初の画面を表示できる
+    fn new(file: std::fs::File, map: memmap2::Mmap) -> Option<Self> {
+        let map = Arc::new(map);
+        let mut mapped = Self {
+            indexer: Some(spawn_indexer(file.try_clone().ok()?, Arc::clone(&map))),
+            len: map.len(),
+            file,
+            map,
+            range: 0..0,
+            first_line: 0,
+            index: vec![0],
+            indexed: 0,
+        };
+        mapped.center(0);
+
+        Some(mapped)
+    }
+
+    /// ファイル全体の大きさ(バイト単位)
+    pub fn size(&self) -> u64 {
+        self.available() as u64
+    }
+
+    /// ファイル全体の内容
+    /// # Notes
+    /// * 切り詰められている場合は、今の大きさまで
+    pub fn bytes(&self) -> &[u8] {
+        &self.map[..self.available()]
+    }
+
+    /// 表示する範囲の先頭の位置(バイト単位)
+    pub fn start(&self) -> usize {
+        self.range.start
+    }
+
+    /// 表示する範囲より前にある行数(表示する行番号に足す)
+    pub fn first_line(&self) -> usize {
+        self.first_line
+    }
+
+    /// 表示する範囲を文字列にする
+    /// # Notes
+    /// * 切り詰められている場合は、今の大きさまで
+    pub fn text(&self) -> String {
+        let available = self.available();
+        let range = self.range.start.min(available)..self.range.end.min(available);
+        String::from_utf8_lossy(&self.map[range]).into_owned()
+    }
+
+    /// マップした内容のうち、今のファイルの大きさまでの読み取ってよい大きさ
+    fn available(&self) -> usize {
+        let len = self.file.metadata().map_or(0, |metadata| metadata.len());
+        self.len.min(usize::try_from(len).unwrap_or(usize::MAX))
+    }
+
+    /// マップした内容のうち、読み取ってよい部分
+    fn data(&self) -> &[u8] {
+        &self.map[..self.len]
+    }
+
+    /// ファイルの大きさが変わっていた場合は、マップし直す
+    /// # Notes
+    /// * 切り詰められたファイルの後ろを読み取らないように、マップした内容を読み取る前に呼び出す
+    /// * 小さくなった場合は内容が入れ替わっている(ログのローテーションなど)かもしれないので、索引を作り直す
+    /// * マップし直せない場合は、今の大きさまでしか読み取らない
+    fn refresh(&mut self) {
+        let len = self.file.metadata().map_or(0, |metadata| metadata.len());
+        let len = usize::try_from(len).unwrap_or(usize::MAX);
+        if len == self.len {
+            return;
+        }
+
+        // SAFETY: openと同じく、読み取る前に毎回大きさを確かめる
+        if let Ok(map) = unsafe { memmap2::Mmap::map(&self.file) } {
+            self.map = Arc::new(map);
+        }
+        let shrunk = len < self.len;
+        self.len = len.min(self.map.len());
+        if shrunk {
+            self.index = vec![0];
+            self.indexed = 0;
+            self.indexer = self
+                .file
+                .try_clone()
+                .ok()
+                .map(|file| spawn_indexer(file, Arc::clone(&self.map)));
+            self.center(self.line_start_before(self.range.start));
+        }
+    }
+
+    /// 一番上の行が表示する範囲の端に近づいた場合は、一番上の行が中央に来るように表示する範囲を移す
+    /// # Arguments
+    /// * `top_line` - 一番上に表示している行の、表示する範囲での行番号(1から始まる)
+    /// # Returns
+    /// * `Option<usize>` - 移した場合は、移した後の範囲での一番上の行の行番号
+    /// # Notes
+    /// * 範囲の前後の4分の1に入った場合に移すので、少しずつスクロールしても範囲を移すことは少ない
+    pub fn shift(&mut self, top_line: usize) -> Option<usize> {
+        self.refresh();
+        let lines = self.window_line_count();
+        let near_start = top_line <= lines / 4 && self.range.start > 0;
+        let near_end = top_line * 4 >= lines * 3 && self.range.end < self.len;
+        if !near_start && !near_end {
+            return None;
+        }
+
+        let top = self.line_start(top_line);
+        let line = self.first_line + top_line;
+        let range = self.range.clone();
+        self.center(top);
+        if self.range == range {
+            return None;
+        }
+        Some(line - self.first_line)
+    }
+
+    /// ファイルの先頭か末尾を表示する範囲に移す
+    /// # Arguments
+    /// * `to_end` - 末尾に移す場合はtrue、先頭に移す場合はfalse
+    /// # Returns
+    /// * `bool` - 表示する範囲が変わった場合はtrue
+    /// # Notes
+    /// * 末尾の行番号は、索引を作り終えていない場合は作り終えるまで待って求める
+    pub fn move_to(&mut self, to_end: bool) -> bool {
+        self.refresh();
+        let top = if to_end { self.last_line_start() } else { 0 };
+
+        let range = self.range.clone();
+        self.center(top);
+        self.range != range
+    }
+
+    /// 指定した位置を含む行の前後を、表示する範囲にする
+    /// # Arguments
+    /// * `position` - 位置(バイト単位、ファイルより大きい場合は末尾)
+    /// # Notes
+    /// * 開き直したファイルで、前と同じ位置を表示するために使う
+    pub fn move_near(&mut self, position: usize) {
+        self.refresh();
+        self.center(self.line_start_before(position));
+    }
+
+    /// 指定した位置を含む行の先頭の位置(バイト単位、ファイルより大きい場合は最後の行)
+    fn line_start_before(&self, position: usize) -> usize {
+        let position = position.min(self.len);
+        self.data()[..position]
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |index| index + 1)
+    }
+
+    /// 指定した行の前後を表示する範囲にする
+    /// # Arguments
+    /// * `line_number` - ファイルでの行番号(1から始まる、ファイルの行数より大きい場合は最後の行)
+    /// # Returns
+    /// * `usize` - 表示する範囲での、指定した行の行番号
+    /// # Notes
+    /// * 索引を作り終えている場合は、索引の行から数えるだけなので、すぐに移せる
+    pub fn move_to_line(&mut self, line_number: usize) -> usize {
+        self.refresh();
+        let top = self.line_offset(line_number.max(1) - 1);
+        let top = if top < self.len {
+            top
+        } else {
+            self.last_line_start()
+        };
+        self.center(top);
+        self.line_at(top) + 1 - self.first_line
+    }
+
+    /// ファイル全体の行数
+    /// # Notes
+    /// * 索引を作り終えていない場合は、作り終えるまで待つ
+    pub fn line_count(&mut self) -> usize {
+        self.refresh();
+        let lines = self.line_at(self.len);
+        lines + usize::from(self.data().last().is_some_and(|&b| b != b'\n'))
+    }
+
+    /// 指定した行の前後を表示する範囲にする
+    /// # Arguments
+    /// * `top` - 中央にする行の先頭の位置(バイト単位)
+    fn center(&mut self, top: usize) {
+        let mut start = top;
+        for _ in 0..HALF_LINES {
+            if start == 0 {
+                break;
+            }
+            let previous = self.data()[..start - 1]
+                .iter()
+                .rposition(|&b| b == b'\n')
+                .map_or(0, |index| index + 1);
+            if top - previous > HALF_BYTES {
+                break;
+            }
+            start = previous;
+        }
+
+        let mut end = top;
+        for count in 0..HALF_LINES {
+            if end == self.len {
+                break;
+            }
+            let next = self.data()[end..]
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(self.len, |index| end + index + 1);
+            if count > 0 && next - top > HALF_BYTES {
+                break;
+            }
+            end = next;
+        }
+
+        self.first_line = self.line_at(start);
+        self.range = start..end;
+    }
+
+    /// 指定した位置より前にある行数
+    /// # Notes
+    /// * 索引を作り終えていない位置の場合は、その位置まで作り終えるのを待つ
+    fn line_at(&mut self, position: usize) -> usize {
+        self.update_index(|mapped| mapped.indexed < position);
+        let step = self.index.partition_point(|&offset| offset <= position) - 1;
+        step * INDEX_STEP + count_lines(&self.data()[self.index[step]..position])
+    }
+
+    /// 指定した行の先頭の位置(バイト単位、ファイルの行数より大きい場合はファイルの大きさ)
+    /// # Arguments
+    /// * `line` - 前にある行数(0から始まる)
+    fn line_offset(&mut self, line: usize) -> usize {
+        self.update_index(|mapped| {
+            mapped.indexed < mapped.len && line / INDEX_STEP >= mapped.index.len()
+        });
+        let step = (line / INDEX_STEP).min(self.index.len() - 1);
+        let mut offset = self.index[step];
+        for _ in step * INDEX_STEP..line {
+            match self.data()[offset..].iter().position(|&b| b == b'\n') {
+                Some(index) => offset += index + 1,
+                None => return self.len,
+            }
+        }
+        offset
+    }
+
+    /// 最後の行の先頭の位置(最後の改行の後は行ではないので、その前の行)
+    fn last_line_start(&self) -> usize {
+        let end = self.len.saturating_sub(1);
+        self.data()[..end]
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |index| index + 1)
+    }
+
+    /// 索引を作っているスレッドから、作り終えた分を受け取る
+    /// # Arguments
+    /// * `wait` - trueを返す間は、スレッドが作り終えるのを待つ
+    fn update_index(&mut self, wait: impl Fn(&Self) -> bool) {
+        let Some(indexer) = self.indexer.take() else {
+            return;
+        };
+        loop {
+            let received = if wait(self) {
+                indexer.recv().map_err(|_| mpsc::TryRecvError::Disconnected)
+            } else {
+                indexer.try_recv()
+            };
+            match received {
+                // 小さくなる前のファイルの索引は使わない
+                Ok((offsets, indexed)) => {
+                    self.index
+                        .extend(offsets.into_iter().filter(|&offset| offset <= self.len));
+                    self.indexed = indexed.min(self.len);
+                }
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => return,
+            }
+        }
+        self.indexer = Some(indexer);
+    }
+
+    /// 表示する範囲の行数
+    fn window_line_count(&self) -> usize {
+        let text = &self.data()[self.range.clone()];
+        count_lines(text) + usize::from(text.last().is_some_and(|&b| b != b'\n'))
+    }
+
+    /// 表示する範囲での行の、ファイルでの先頭の位置(バイト単位)
+    /// # Arguments
+    /// * `line` - 表示する範囲での行番号(1から始まる)
+    fn line_start(&self, line: usize) -> usize {
+        if line <= 1 {
+            return self.range.start;
+        }
+        self.data()[self.range.clone()]
+            .iter()
+            .enumerate()
+            .filter(|(_, &b)| b == b'\n')
+            .nth(line - 2)
+            .map_or(self.range.end, |(index, _)| self.range.start + index + 1)
+    }
+}
+
+/// 改行の数を数える
+fn count_lines(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|&&b| b == b'\n').count()
+}
+
+/// 行の先頭の位置の索引を、別のスレッドで作り始める
+/// # Arguments
+/// * `file` - マップしたファイル(大きさを確かめるために使う)
+/// * `map` - ファイルの内容
+/// # Returns
+/// * `mpsc::Receiver<(Vec<usize>, usize)>` - `INDEX_CHUNK`ごとに、追加する索引と作り終えた位置を受け取る
+/// # Notes
+/// * ファイルを閉じて受け取る側がなくなった場合は、途中でやめる
+/// * 切り詰められたファイルの後ろを読み取らないように、読み取る前に大きさを確かめ、小さくなっていた場合はやめる
+fn spawn_indexer(
+    file: std::fs::File,
+    map: Arc<memmap2::Mmap>,
+) -> mpsc::Receiver<(Vec<usize>, usize)> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut lines = 0;
+        for start in (0..map.len()).step_by(INDEX_CHUNK) {
+            let end = (start + INDEX_CHUNK).min(map.len());
+            let len = file.metadata().map_or(0, |metadata| metadata.len());
+            if usize::try_from(len).unwrap_or(usize::MAX) < end {
+                return;
+            }
+            let chunk = &map[start..end];
+            let mut offsets = Vec::new();
+            for (position, _) in chunk.iter().enumerate().filter(|(_, &b)| b == b'\n') {
+                lines += 1;
+                if lines % INDEX_STEP == 0 {
+                    offsets.push(start + position + 1);
+                }
+            }
+            if sender.send((offsets, start + chunk.len())).is_err() {
+                return;
+            }
+        }
+    });
+
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_move() {
+        let path = std::env::temp_dir().join(format!("edoc-mapped-{}.txt", std::process::id()));
+        let mut file = std::fs::File::create(&path).unwrap();
+        for line in 1..=20000 {
+            writeln!(file, "line{}", line).unwrap();
+        }
+        drop(file);
+
+        let file = std::fs::File::open(&path).unwrap();
+        let map = unsafe { memmap2::Mmap::map(&file).unwrap() };
+        let mut mapped = MappedFile::new(file, map).unwrap();
+        assert_eq!(mapped.first_line(), 0);
+        assert_eq!(mapped.window_line_count(), HALF_LINES);
+        assert!(mapped.text().starts_with("line1\n"));
+
+        // 範囲の中ほどでは移さず、末尾に近づいたら一番上の行が中央に来るように移す
+        assert_eq!(mapped.shift(100), None);
+        assert_eq!(mapped.shift(800), Some(800));
+        assert_eq!(mapped.first_line(), 0);
+        assert_eq!(mapped.shift(1500), Some(HALF_LINES + 1));
+        assert_eq!(mapped.first_line(), 1499 - HALF_LINES);
+        assert!(mapped
+            .text()
+            .starts_with(&format!("line{}\n", 1500 - HALF_LINES)));
+
+        assert!(mapped.move_to(true));
+        assert_eq!(mapped.first_line(), 19999 - HALF_LINES);
+        assert!(mapped.text().ends_with("line20000\n"));
+        assert!(mapped.move_to(false));
+        assert_eq!(mapped.first_line(), 0);
+
+        // 索引を作り終えた後は、索引の行から数えて移す
+        assert_eq!(mapped.line_count(), 20000);
+        assert_eq!(mapped.index.len(), 20000 / INDEX_STEP + 1);
+        assert_eq!(mapped.move_to_line(15000), HALF_LINES + 1);
+        assert_eq!(mapped.first_line(), 14999 - HALF_LINES);
+        assert!(mapped.text().contains("\nline15000\n"));
+        assert_eq!(mapped.move_to_line(99999), HALF_LINES + 1);
+        assert!(mapped.text().ends_with("line20000\n"));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    /// 切り詰められた場合は、マップし直して今の大きさまでしか読み取らない
+    fn test_truncate() {
+        let path = std::env::temp_dir().join(format!("edoc-truncate-{}.txt", std::process::id()));
+        let mut file = std::fs::File::create(&path).unwrap();
+        for line in 1..=20000 {
+            writeln!(file, "line{}", line).unwrap();
+        }
+        drop(file);
+
+        let file = std::fs::File::open(&path).unwrap();
+        let map = unsafe { memmap2::Mmap::map(&file).unwrap() };
+        let mut mapped = MappedFile::new(file, map).unwrap();
+        assert_eq!(mapped.line_count(), 20000);
+        assert!(mapped.move_to(true));
+
+        // ログのローテーション(copytruncate)のように、切り詰めてから書き足す
+        let mut file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(0).unwrap();
+        file.write_all(b"new1\nnew2\n").unwrap();
+        drop(file);
+
+        assert_eq!(mapped.size(), 10);
+        assert_eq!(mapped.text(), "");
+        assert_eq!(mapped.line_count(), 2);
+        assert_eq!(mapped.first_line(), 0);
+        assert_eq!(mapped.text(), "new1\nnew2\n");
+        assert_eq!(mapped.bytes(), b"new1\nnew2\n");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
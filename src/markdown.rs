@@ -0,0 +1,232 @@
+use crate::style::{ColorDepth, Style, Theme};
+
+/// Markdownを端末で読みやすいように装飾する
+/// # Arguments
+/// * `source` - Markdownの文字列
+/// * `theme` - 見出しやコードの装飾
+/// * `depth` - 端末で表示できる色数
+/// # Returns
+/// * `String` - 装飾した文字列
+/// # Notes
+/// * 見出しは記号(`#`)を取り除いて装飾する
+/// * 箇条書きの記号(`-`、`*`、`+`)は`•`に置き換える
+/// * フェンスで囲まれたコードブロックはフェンスの行を取り除き、中身はそのまま装飾する
+/// * 強調(`*em*`、`**strong**`)とインラインコード(`` `code` ``)を装飾する
+pub fn render(source: &str, theme: &Theme, depth: ColorDepth) -> String {
+    let renderer = Renderer { theme, depth };
+    let mut result = String::new();
+
+    // コードブロックの中の場合は、開始したフェンスの文字列
+    let mut fence: Option<String> = None;
+    for line in source.lines() {
+        let trimmed = line.trim_start();
+
+        if let Some(open) = &fence {
+            if trimmed.starts_with(open.as_str()) && trimmed.trim_end() == open {
+                fence = None;
+            } else {
+                result.push_str(&theme.code.paint(line, depth));
+                result.push('\n');
+            }
+            continue;
+        }
+
+        if let Some(open) = fence_marker(trimmed) {
+            fence = Some(open);
+            continue;
+        }
+
+        result.push_str(&renderer.block(line));
+        result.push('\n');
+    }
+
+    result
+}
+
+/// 行がコードブロックのフェンス(```や~~~)の場合は、フェンスの文字列を取得する
+fn fence_marker(line: &str) -> Option<String> {
+    let c = line.chars().next().filter(|&c| c == '`' || c == '~')?;
+    let count = line.chars().take_while(|&x| x == c).count();
+    (count >= 3).then(|| c.to_string().repeat(count))
+}
+
+/// Markdownの装飾に使う設定
+struct Renderer<'a> {
+    /// 見出しやコードの装飾
+    theme: &'a Theme,
+    /// 端末で表示できる色数
+    depth: ColorDepth,
+}
+
+impl Renderer<'_> {
+    /// コードブロック以外の1行を装飾する
+    fn block(&self, line: &str) -> String {
+        let indent = &line[..line.len() - line.trim_start().len()];
+        let body = &line[indent.len()..];
+
+        // 見出し
+        let level = body.chars().take_while(|&c| c == '#').count();
+        if (1..=6).contains(&level) {
+            let rest = &body[level..];
+            if rest.is_empty() || rest.starts_with(' ') {
+                let text = rest.trim().trim_end_matches('#').trim_end();
+                let mut style = self.theme.heading;
+                style.underline |= level == 1;
+                return format!("{}{}", indent, style.paint(&self.inline(text), self.depth));
+            }
+        }
+
+        // 箇条書き
+        for marker in ["- ", "* ", "+ "] {
+            if let Some(rest) = body.strip_prefix(marker) {
+                return format!("{}• {}", indent, self.inline(rest));
+            }
+        }
+
+        format!("{}{}", indent, self.inline(body))
+    }
+
+    /// 強調とインラインコードを装飾する
+    fn inline(&self, text: &str) -> String {
+        let mut result = String::new();
+        let mut rest = text;
+
+        while let Some(c) = rest.chars().next() {
+            // バックスラッシュでエスケープした記号はそのまま出力する
+            if c == '\\' {
+                if let Some(escaped) = rest[1..]
+                    .chars()
+                    .next()
+                    .filter(|c| c.is_ascii_punctuation())
+                {
+                    result.push(escaped);
+                    rest = &rest[1 + escaped.len_utf8()..];
+                    continue;
+                }
+            }
+
+            if c == '`' {
+                let ticks = &rest[..rest.chars().take_while(|&c| c == '`').count()];
+                if let Some(end) = rest[ticks.len()..].find(ticks) {
+                    let code = &rest[ticks.len()..ticks.len() + end];
+                    result.push_str(&self.theme.code.paint(code.trim(), self.depth));
+                    rest = &rest[ticks.len() * 2 + end..];
+                    continue;
+                }
+                result.push_str(ticks);
+                rest = &rest[ticks.len()..];
+                continue;
+            }
+
+            if c == '*' || c == '_' {
+                let (delimiter, style) = if rest[1..].starts_with(c) {
+                    (
+                        &rest[..2],
+                        Style {
+                            bold: true,
+                            ..Default::default()
+                        },
+                    )
+                } else {
+                    (
+                        &rest[..1],
+                        Style {
+                            italic: true,
+                            ..Default::default()
+                        },
+                    )
+                };
+
+                if let Some(end) = Self::closing(&result, &rest[delimiter.len()..], delimiter) {
+                    let inner = &rest[delimiter.len()..delimiter.len() + end];
+                    result.push_str(&style.paint(&self.inline(inner), self.depth));
+                    rest = &rest[delimiter.len() * 2 + end..];
+                    continue;
+                }
+                result.push_str(delimiter);
+                rest = &rest[delimiter.len()..];
+                continue;
+            }
+
+            result.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+
+        result
+    }
+
+    /// 強調の終わりの記号の位置を探す
+    /// # Arguments
+    /// * `before` - 強調の開始記号より前の文字列
+    /// * `after` - 強調の開始記号より後ろの文字列
+    /// * `delimiter` - 強調の記号
+    /// # Returns
+    /// * `Option<usize>` - `after`での終わりの記号の位置
+    /// # Notes
+    /// * 空白に接する記号や、`snake_case`のように単語の途中にある`_`は強調として扱わない
+    fn closing(before: &str, after: &str, delimiter: &str) -> Option<usize> {
+        if after.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let is_underscore = delimiter.starts_with('_');
+        if is_underscore && before.ends_with(char::is_alphanumeric) {
+            return None;
+        }
+
+        after.match_indices(delimiter).map(|(i, _)| i).find(|&i| {
+            let inner = &after[..i];
+            let next = &after[i + delimiter.len()..];
+            // `***`のような連続した記号の途中では閉じない
+            !(inner.is_empty()
+                || inner.ends_with(char::is_whitespace)
+                || next.starts_with(&delimiter[..1])
+                || (is_underscore && next.starts_with(char::is_alphanumeric)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 装飾しない場合の結果を取得する
+    fn render_plain(source: &str) -> String {
+        render(source, &Theme::default(), ColorDepth::None)
+    }
+
+    #[test]
+    fn test_render_blocks() {
+        let source = "# Title #\n\n- one\n  * two\n```rust\nlet x = 1;\n```\n#hashtag\n";
+        assert_eq!(
+            render_plain(source),
+            "Title\n\n• one\n  • two\nlet x = 1;\n#hashtag\n"
+        );
+    }
+
+    #[test]
+    fn test_render_inline() {
+        assert_eq!(render_plain("a **b** *c* `d` e"), "a b c d e\n");
+        assert_eq!(render_plain("snake_case_name"), "snake_case_name\n");
+        assert_eq!(render_plain("2 * 3 * 4"), "2 * 3 * 4\n");
+        assert_eq!(render_plain(r"\*not\*"), "*not*\n");
+    }
+
+    #[test]
+    fn test_render_styles() {
+        let depth = ColorDepth::Ansi16;
+        let theme = Theme::default();
+        assert_eq!(
+            render("a *b* **c**", &theme, depth),
+            "a \x1b[3mb\x1b[23m \x1b[1mc\x1b[22m\n"
+        );
+        assert_eq!(
+            render("## h", &theme, depth),
+            format!("{}\n", theme.heading.paint("h", depth))
+        );
+        // コードブロックの中は強調しない
+        assert_eq!(
+            render("```\n*a*\n```", &theme, depth),
+            format!("{}\n", theme.code.paint("*a*", depth))
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// ファイルの行に付けた印
+/// `m`に続けて押した文字で印を付け、`'`に続けて押した文字で印を付けた行に戻る
+/// 次に開いたときも同じ行に戻れるように、最近開いたファイルの履歴に保存する
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(from = "BTreeMap<String, usize>", into = "BTreeMap<String, usize>")]
+pub struct Marks {
+    /// 印の名前と、印を付けた行番号(1から始まる)
+    marks: BTreeMap<char, usize>,
+}
+
+impl From<BTreeMap<String, usize>> for Marks {
+    /// 保存した印を読み込む
+    /// # Notes
+    /// * TOMLのキーは文字列なので、印の名前として使えない文字列は読み飛ばす
+    fn from(saved: BTreeMap<String, usize>) -> Self {
+        let marks = saved
+            .into_iter()
+            .filter_map(|(name, line_number)| {
+                let mut chars = name.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(mark), None) if Self::is_mark(mark) => Some((mark, line_number)),
+                    _ => None,
+                }
+            })
+            .collect();
+
+        Self { marks }
+    }
+}
+
+impl From<Marks> for BTreeMap<String, usize> {
+    /// 印の名前を文字列にして保存する
+    fn from(marks: Marks) -> Self {
+        marks
+            .marks
+            .into_iter()
+            .map(|(mark, line_number)| (mark.to_string(), line_number))
+            .collect()
+    }
+}
+
+impl Marks {
+    /// 印の名前に使える文字か
+    /// # Arguments
+    /// * `mark` - 印の名前
+    pub fn is_mark(mark: char) -> bool {
+        mark.is_ascii_alphabetic()
+    }
+
+    /// 行に印を付ける
+    /// # Arguments
+    /// * `mark` - 印の名前
+    /// * `line_number` - 印を付ける行番号(1から始まる)
+    /// # Notes
+    /// * 同じ名前の印を付けていた場合は、付け直す
+    pub fn set(&mut self, mark: char, line_number: usize) {
+        self.marks.insert(mark, line_number);
+    }
+
+    /// 印を付けていないか
+    pub fn is_empty(&self) -> bool {
+        self.marks.is_empty()
+    }
+
+    /// 印を付けた行番号を取得する
+    /// # Arguments
+    /// * `mark` - 印の名前
+    /// # Returns
+    /// * `Option<usize>` - 印を付けた行番号(印を付けていない場合はNone)
+    pub fn get(&self, mark: char) -> Option<usize> {
+        self.marks.get(&mark).copied()
+    }
+
+    /// 行番号の左に表示する、行ごとの印を作成する
+    /// # Arguments
+    /// * `line_count` - ファイルの行数
+    /// # Returns
+    /// * `Vec<Option<char>>` - 行ごとの印(印を付けていない行はNone)
+    /// # Notes
+    /// * 同じ行に複数の印を付けた場合は、名前の順で最初の印を表示する
+    pub fn markers(&self, line_count: usize) -> Vec<Option<char>> {
+        let mut markers = vec![None; line_count];
+        for (&mark, &line_number) in self.marks.iter().rev() {
+            if let Some(marker) = line_number
+                .checked_sub(1)
+                .and_then(|index| markers.get_mut(index))
+            {
+                *marker = Some(mark);
+            }
+        }
+
+        markers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_marks() {
+        let mut marks = Marks::default();
+        assert_eq!(marks.get('a'), None);
+
+        marks.set('b', 2);
+        marks.set('a', 2);
+        marks.set('c', 9);
+        marks.set('c', 3);
+        assert_eq!(marks.get('c'), Some(3));
+
+        // 同じ行の印は名前の順で最初の印を表示し、ファイルより後ろの行の印は表示しない
+        marks.set('z', 10);
+        assert_eq!(marks.markers(4), vec![None, Some('a'), Some('c'), None]);
+
+        // 印の名前として使えない文字列で保存した印は読み飛ばす
+        let saved = BTreeMap::from([("a".to_string(), 1), ("ab".to_string(), 2)]);
+        assert_eq!(Marks::from(saved).markers(2), vec![Some('a'), None]);
+
+        assert!(Marks::is_mark('A'));
+        assert!(!Marks::is_mark('1'));
+    }
+}
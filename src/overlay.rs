@@ -0,0 +1,236 @@
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::cell;
+use crate::layout::Rect;
+use crate::screen;
+
+/// 一覧を重ねる順番
+pub const LIST_LAYER: u8 = 1;
+
+/// ダイアログを重ねる順番(一覧より上に表示する)
+pub const DIALOG_LAYER: u8 = 2;
+
+/// エディタ領域に重ねて表示する一覧やダイアログ
+pub trait Overlay {
+    /// 表示する領域に重ねて表示する
+    /// # Arguments
+    /// * `area` - 表示する領域
+    /// # Returns
+    /// * `Option<Rect>` - 表示した部分(領域が小さすぎて表示しなかった場合はNone)
+    fn print(&self, area: Rect) -> Option<Rect>;
+
+    /// 重ねる順番(大きいほど上に表示する)
+    /// # Notes
+    /// * 一覧などの上に確認のダイアログを表示する場合など、同時に表示したときにどちらを上にするかを決める
+    fn z_index(&self) -> u8 {
+        LIST_LAYER
+    }
+}
+
+/// 重ねて表示したものを、エディタ領域などとは別の層に表示する
+/// # Notes
+/// * 下に隠れた部分を覚えておき、閉じたときに呼び出し側でその部分を表示し直せるようにする
+#[derive(Debug, Default)]
+pub struct OverlayLayer {
+    /// 重ねて表示したことで隠れた部分(隠れた部分がない場合はNone)
+    damaged: Option<Rect>,
+}
+
+impl OverlayLayer {
+    /// 重ねて表示し、隠れた部分に加える
+    /// # Arguments
+    /// * `overlay` - 重ねて表示するもの
+    /// * `area` - 表示する領域
+    /// # Notes
+    /// * 同じ順番の層に前に表示したものは消してから表示するので、小さくなった場合も前の表示が残らない
+    /// * 表示し直すたびに大きさが変わる場合も、それまでに隠れた部分をすべて覚えておく
+    pub fn show(&mut self, overlay: &dyn Overlay, area: Rect) {
+        let z_index = overlay.z_index();
+        screen::clear_layer(z_index);
+        if let Some(rect) = screen::with_layer(z_index, || overlay.print(area)) {
+            self.damaged = Some(match self.damaged {
+                Some(damaged) => damaged.union(&rect),
+                None => rect,
+            });
+        }
+    }
+
+    /// 隠れた部分を取り出す
+    /// # Returns
+    /// * `Option<Rect>` - 隠れた部分(隠れた部分がない場合はNone)
+    /// # Notes
+    /// * 重ねて表示したものはすべて消す
+    /// * 下に隠れていた内容はそのまま見えるようになるが、隠れている間に変わった部分は呼び出し側で表示し直す
+    pub fn take_damaged(&mut self) -> Option<Rect> {
+        screen::clear_layers();
+        self.damaged.take()
+    }
+}
+
+/// はいかいいえで答える確認のダイアログ
+pub struct Confirm {
+    /// 確認する内容
+    message: String,
+}
+
+impl Confirm {
+    /// ダイアログのタイトル
+    const TITLE: &'static str = " Confirm ";
+    /// 確認する内容の後ろに表示する、答え方の説明
+    const CHOICES: &'static str = " [y/n]";
+
+    /// Confirmを作成する
+    /// # Arguments
+    /// * `message` - 確認する内容
+    pub fn new(message: String) -> Self {
+        Self {
+            message: message.replace('\n', " "),
+        }
+    }
+
+    /// 押したキーを答えとして解釈する
+    /// # Arguments
+    /// * `key` - 押したキー
+    /// # Returns
+    /// * `Option<bool>` - はいの場合はtrue、いいえの場合はfalse(答えではないキーの場合はNone)
+    /// # Notes
+    /// * Escキーはいいえとして扱う
+    pub fn answer(&self, key: &KeyEvent) -> Option<bool> {
+        match key.code {
+            KeyCode::Char('y' | 'Y') => Some(true),
+            KeyCode::Char('n' | 'N') | KeyCode::Esc => Some(false),
+            _ => None,
+        }
+    }
+
+    /// 表示する領域の中央に、ダイアログを表示する部分を求める
+    fn rect(&self, area: Rect) -> Option<Rect> {
+        if area.width < 3 || area.height < 3 {
+            return None;
+        }
+
+        let ambiguous_wide = screen::ambiguous_wide();
+        let inner_width = (cell::text_width(&self.message, ambiguous_wide)
+            + cell::text_width(Self::CHOICES, ambiguous_wide))
+        .max(cell::text_width(Self::TITLE, ambiguous_wide))
+        .min(area.width as usize - 2) as u16;
+
+        Some(Rect {
+            x: area.x + (area.width - inner_width - 2) / 2,
+            y: area.y + (area.height - 3) / 2,
+            width: inner_width + 2,
+            height: 3,
+        })
+    }
+}
+
+impl Overlay for Confirm {
+    fn z_index(&self) -> u8 {
+        DIALOG_LAYER
+    }
+
+    fn print(&self, area: Rect) -> Option<Rect> {
+        let rect = self.rect(area)?;
+        let inner_width = rect.width as usize - 2;
+
+        let ambiguous_wide = screen::ambiguous_wide();
+        let title = cell::truncate(Self::TITLE, inner_width, ambiguous_wide);
+        let line = format!("{}{}", self.message, Self::CHOICES);
+        let lines = [
+            format!(
+                "┌{}{}┐",
+                title,
+                "─".repeat(inner_width - cell::text_width(&title, ambiguous_wide))
+            ),
+            format!("│{}│", cell::pad(&line, inner_width, ambiguous_wide)),
+            format!("└{}┘", "─".repeat(inner_width)),
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            screen::print_row(rect.x, rect.y + i as u16, rect.width, line).unwrap();
+        }
+
+        Some(rect)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crossterm::event::KeyModifiers;
+
+    const AREA: Rect = Rect {
+        x: 0,
+        y: 1,
+        width: 40,
+        height: 10,
+    };
+
+    /// 指定した部分に表示したことにする
+    struct Fixed(Rect);
+
+    impl Overlay for Fixed {
+        fn print(&self, _area: Rect) -> Option<Rect> {
+            Some(self.0)
+        }
+    }
+
+    #[test]
+    fn test_overlay_layer() {
+        let mut layer = OverlayLayer::default();
+        assert_eq!(layer.take_damaged(), None);
+
+        // 表示し直すたびに、隠れた部分を広げる
+        let first = Rect {
+            x: 5,
+            y: 3,
+            width: 10,
+            height: 2,
+        };
+        let second = Rect {
+            x: 8,
+            y: 2,
+            width: 4,
+            height: 6,
+        };
+        layer.show(&Fixed(first), AREA);
+        layer.show(&Fixed(second), AREA);
+        assert_eq!(
+            layer.take_damaged(),
+            Some(Rect {
+                x: 5,
+                y: 2,
+                width: 10,
+                height: 6
+            })
+        );
+        assert_eq!(layer.take_damaged(), None);
+    }
+
+    #[test]
+    fn test_confirm() {
+        let confirm = Confirm::new("Overwrite?".to_string());
+        let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+        assert_eq!(confirm.answer(&key('y')), Some(true));
+        assert_eq!(confirm.answer(&key('N')), Some(false));
+        assert_eq!(
+            confirm.answer(&KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)),
+            Some(false)
+        );
+        assert_eq!(confirm.answer(&key('x')), None);
+
+        // 表示する領域の中央に表示し、収まらない場合は領域の横幅に合わせる
+        assert_eq!(
+            confirm.rect(AREA),
+            Some(Rect {
+                x: 11,
+                y: 4,
+                width: 18,
+                height: 3
+            })
+        );
+        let long = Confirm::new("x".repeat(100));
+        assert_eq!(long.rect(AREA).map(|rect| rect.width), Some(40));
+        assert_eq!(confirm.rect(Rect { height: 2, ..AREA }), None);
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+
+use crossterm::event::KeyEvent;
+
+use crate::{
+    buffer::{Buffer, Settings},
+    config,
+    contents::DisplayOptions,
+    keymap::{Action, Keymap, KeymapPreset},
+    layout::Rect,
+    screen,
+};
+
+/// ほかの端末のアプリケーションに組み込んで使う、内容をスクロールして表示する部分
+/// # Notes
+/// * 表示する内容を渡し、押したキーを送って、表示する領域を指定して表示させる
+/// * 表示は`screen`を通して出力するので、`screen::set_backend`で出力する先を差し替えられる
+/// * ファイルを開いたり保存したりはせず、スクロールだけを扱う
+pub struct Pager {
+    /// 表示している内容
+    buffer: Buffer,
+    /// 内容を開くときに使う設定
+    settings: Settings,
+    /// キーの割り当て
+    keymap: Keymap,
+    /// 最後に表示した領域(1画面分スクロールする行数に使う)
+    rect: Rect,
+}
+
+impl Pager {
+    /// Pagerを作成する
+    /// # Arguments
+    /// * `name` - ファイルの種類を判定するための名前(ない場合はNone)
+    /// * `contents` - 表示する内容
+    /// # Notes
+    /// * キーの割り当ては、lessと同じ割り当てにする
+    pub fn new(name: Option<String>, contents: String) -> Self {
+        let settings = Settings {
+            options: DisplayOptions::default(),
+            tab_width: None,
+            wrap: None,
+            config: config::Config::default(),
+        };
+        Self {
+            buffer: Buffer::open(name, contents, &settings),
+            settings,
+            keymap: Keymap::new(KeymapPreset::Less, &HashMap::new()),
+            rect: Rect {
+                x: 0,
+                y: 0,
+                width: 80,
+                height: 24,
+            },
+        }
+    }
+
+    /// 表示の設定を変える
+    /// # Arguments
+    /// * `options` - 行番号や折り返しなどの表示の設定
+    pub fn set_options(&mut self, options: DisplayOptions) {
+        self.settings.options = options;
+        self.buffer.reload_settings(&self.settings, self.rect.width);
+    }
+
+    /// キーの割り当てを変える
+    /// # Arguments
+    /// * `keymap` - キーの割り当て
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    /// 表示する内容を置き換える
+    /// # Arguments
+    /// * `contents` - 新しく表示する内容
+    /// # Notes
+    /// * ファイルの種類を判定し直して、先頭から表示する
+    pub fn set_contents(&mut self, contents: String) {
+        self.buffer = Buffer::open(self.buffer.file.clone(), contents, &self.settings);
+    }
+
+    /// 表示している内容の末尾に加える
+    /// # Arguments
+    /// * `text` - 加える文字列
+    /// # Notes
+    /// * コマンドの出力を少しずつ受け取る場合などに使い、スクロールした位置はそのままにする
+    pub fn append(&mut self, text: &str) {
+        self.buffer.append_contents(text);
+    }
+
+    /// 押したキーに割り当てた操作を行う
+    /// # Arguments
+    /// * `key` - 押したキー
+    /// # Returns
+    /// * `bool` - 操作を行った場合はtrue、スクロール以外の操作や割り当てのないキーの場合はfalse
+    /// # Notes
+    /// * 表示し直すのは`render`を呼び出したとき
+    pub fn handle_key(&mut self, key: &KeyEvent) -> bool {
+        let Some(action) = self.keymap.action(key) else {
+            return false;
+        };
+        self.perform(action)
+    }
+
+    /// スクロールする操作を行う
+    /// # Arguments
+    /// * `action` - 行う操作
+    /// # Returns
+    /// * `bool` - 操作を行った場合はtrue、スクロール以外の操作の場合はfalse
+    pub fn perform(&mut self, action: Action) -> bool {
+        let page = self.rect.height.max(1) as usize;
+        let half_page = (self.rect.height / 2).max(1) as usize;
+        let buffer = &mut self.buffer;
+        match action {
+            Action::ScrollUp => buffer.cursor_y = buffer.cursor_y.saturating_sub(1),
+            Action::ScrollDown => buffer.cursor_y = buffer.cursor_y.saturating_add(1),
+            Action::PageUp => buffer.cursor_y = buffer.cursor_y.saturating_sub(page),
+            Action::PageDown => buffer.cursor_y = buffer.cursor_y.saturating_add(page),
+            Action::HalfPageUp => buffer.cursor_y = buffer.cursor_y.saturating_sub(half_page),
+            Action::HalfPageDown => buffer.cursor_y = buffer.cursor_y.saturating_add(half_page),
+            Action::GoToTop => buffer.cursor_y = 0,
+            // 表示するときに最後の行が表示できる位置まで戻される
+            Action::GoToBottom => buffer.cursor_y = usize::MAX,
+            // 折り返して表示する場合は横にスクロールする必要がないので、何もしない
+            Action::ScrollLeft | Action::ScrollRight if !buffer.options.chop_long_lines => {}
+            Action::ScrollLeft => buffer.cursor_x = buffer.cursor_x.saturating_sub(1),
+            Action::ScrollRight => buffer.cursor_x = buffer.cursor_x.saturating_add(1),
+            _ => return false,
+        }
+        true
+    }
+
+    /// 一番上に表示している行の行番号(1から始まる)
+    pub fn top_line_number(&self) -> usize {
+        self.buffer
+            .top_line_number(self.rect.width, self.rect.height)
+            .unwrap_or(1)
+    }
+
+    /// 指定した領域に表示する
+    /// # Arguments
+    /// * `rect` - 表示する領域
+    /// # Notes
+    /// * 前に表示した内容から変わった行だけを出力する
+    pub fn render(&mut self, rect: Rect) -> std::io::Result<()> {
+        self.rect = rect;
+        let mut contents = self.buffer.view(rect.x, rect.y, rect.width, rect.height);
+        contents.print()?;
+
+        // 表示するときに再計算されるので、cursor_xとcursor_yを更新する
+        self.buffer.cursor_x = contents.cursor_x;
+        self.buffer.cursor_y = contents.cursor_y;
+        Ok(())
+    }
+
+    /// 指定した大きさで表示した内容を、文字列として取得する
+    /// # Arguments
+    /// * `width` - 表示する横幅
+    /// * `height` - 表示する高さ
+    /// # Returns
+    /// * `std::io::Result<String>` - 表示した内容(行ごとに改行で区切り、装飾は含まない)
+    pub fn render_to_string(&mut self, width: u16, height: u16) -> std::io::Result<String> {
+        let rect = Rect {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        };
+        screen::render_to_string(width, height, || self.render(rect))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    fn key(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_pager() {
+        let lines: Vec<String> = (1..=10).map(|n| format!("line{}", n)).collect();
+        let mut pager = Pager::new(None, lines[..5].join("\n") + "\n");
+        assert_eq!(
+            pager.render_to_string(10, 3).unwrap(),
+            "1 line1\n2 line2\n3 line3"
+        );
+
+        // 押したキーに割り当てた操作でスクロールし、表示するときに最後の行が表示できる位置まで戻す
+        assert!(pager.handle_key(&key('j')));
+        assert!(pager.handle_key(&key('G')));
+        assert!(!pager.handle_key(&key('q')));
+        assert!(!pager.handle_key(&key('!')));
+        assert_eq!(
+            pager.render_to_string(10, 3).unwrap(),
+            "3 line3\n4 line4\n5 line5"
+        );
+        assert_eq!(pager.top_line_number(), 3);
+
+        // 加えた内容は、スクロールした位置のまま表示する
+        pager.append(&(lines[5..].join("\n") + "\n"));
+        assert!(pager.handle_key(&key(' ')));
+        assert_eq!(
+            pager.render_to_string(10, 3).unwrap(),
+            " 6 line6\n 7 line7\n 8 line8"
+        );
+
+        pager.set_contents("a\n".to_string());
+        assert_eq!(pager.render_to_string(10, 3).unwrap(), "1 a\n\n");
+    }
+}
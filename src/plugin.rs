@@ -0,0 +1,561 @@
+use std::{cell::RefCell, collections::BTreeSet, path::Path, rc::Rc};
+
+use rhai::{Dynamic, Engine, EvalAltResult, FnPtr, AST};
+
+use crate::command_line::{self, Command};
+use crate::config;
+use crate::hooks::{Hook, Hooks};
+use crate::keymap::{KeySequence, Keymap};
+
+/// スクリプトが1回の実行で行える処理の数
+/// 終わらないループなどで、画面が操作できなくならないように制限する
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// プラグインが行った、画面に反映する操作
+/// # Notes
+/// * スクリプトを実行している間は画面を操作せず、実行した後で`take_effects`で受け取って反映する
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Effect {
+    /// ステータスバーにメッセージを表示する(`message`、`error`、`print`)
+    Message {
+        /// 表示する文字列
+        text: String,
+        /// エラーを知らせるメッセージか
+        is_error: bool,
+    },
+    /// ステータスバーの項目を加えるか、上書きする(`set_status`)
+    SetStatus {
+        /// 項目名
+        name: String,
+        /// 項目の値
+        value: String,
+    },
+    /// ステータスバーの項目を取り除く(`remove_status`)
+    RemoveStatus(String),
+    /// コマンドを、コマンドラインで入力した場合と同じように実行する(`execute`)
+    Execute(String),
+}
+
+/// コマンドを実行したときの、表示しているファイルの状態
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Context {
+    /// 表示しているファイルの名前(標準入力などの場合はNone)
+    pub file: Option<String>,
+    /// 一番上に表示している行の行番号(1から始まる)
+    pub line_number: usize,
+}
+
+/// プラグインで登録したコマンド
+struct PluginCommand {
+    /// コマンドの名前
+    name: String,
+    /// 実行する関数
+    callback: FnPtr,
+    /// 関数を定義したスクリプト(`Plugins::scripts`の位置)
+    script: usize,
+}
+
+/// プラグインで登録した、時点ごとに実行する関数
+struct HookCallback {
+    /// 実行する時点
+    hook: Hook,
+    /// 実行する関数
+    callback: FnPtr,
+    /// 関数を定義したスクリプト(`Plugins::scripts`の位置)
+    script: usize,
+}
+
+/// スクリプトから呼び出す関数と共有する状態
+#[derive(Default)]
+struct State {
+    /// 登録したコマンド
+    commands: Vec<PluginCommand>,
+    /// 時点ごとに実行する関数
+    hook_callbacks: Vec<HookCallback>,
+    /// 時点ごとに実行するコマンド
+    hook_commands: Vec<(Hook, String)>,
+    /// キーの列と、割り当てたコマンド
+    bindings: Vec<(KeySequence, String)>,
+    /// 加えたステータスバーの項目の名前
+    status_items: BTreeSet<String>,
+    /// まだ画面に反映していない操作
+    effects: Vec<Effect>,
+    /// コマンドを実行したときの、表示しているファイルの状態
+    context: Context,
+    /// 読み込んでいるスクリプト(`Plugins::scripts`の位置)
+    script: usize,
+}
+
+/// 設定ディレクトリの`plugins/*.rhai`を読み込んで、スクリプトで機能を加える
+/// # Notes
+/// * スクリプトは[Rhai](https://rhai.rs)で書き、ファイル名の順に読み込む
+/// * スクリプトから呼び出せる関数は次のとおりで、名前と引数は変えない
+///   * `register_command(name, callback)` - `:name`で実行するコマンドを登録する(`callback`は名前に続けて入力した文字列を受け取る)
+///   * `bind_key(keys, command)` - キーの列にコマンドを割り当てる(`bind_key("g h", "hello")`など、組み込みのコマンドも割り当てられる)
+///   * `set_status(name, value)`、`remove_status(name)` - ステータスバーの項目を加える、取り除く
+///   * `message(text)`、`error(text)` - ステータスバーにメッセージを表示する(`print`は`message`と同じ)
+///   * `execute(command)` - コマンドを、コマンドラインで入力した場合と同じように実行する
+///   * `on(hook, callback)` - `on_open`、`on_key`、`on_save`の時点で実行する関数を登録する
+///     (`callback`は開いたファイルや書き込んだファイルの名前、押したキーを受け取る。関数の代わりにコマンドも登録できる)
+///   * `file_name()`、`line_number()` - 表示しているファイルの名前と、一番上に表示している行の行番号
+pub struct Plugins {
+    /// スクリプトを実行する
+    engine: Engine,
+    /// 読み込んだスクリプト
+    scripts: Vec<AST>,
+    /// スクリプトから呼び出す関数と共有する状態
+    state: Rc<RefCell<State>>,
+}
+
+impl Default for Plugins {
+    /// スクリプトを読み込んでいないPluginsを作成する
+    fn default() -> Self {
+        let state = Rc::new(RefCell::new(State::default()));
+        Self {
+            engine: Self::engine(&state),
+            scripts: Vec::new(),
+            state,
+        }
+    }
+}
+
+impl Plugins {
+    /// 設定ディレクトリの`plugins`にあるスクリプトを読み込む
+    /// # Returns
+    /// * `(Plugins, Vec<String>)` - 読み込んだプラグインと、読み込めなかったスクリプトのエラーメッセージ
+    /// # Notes
+    /// * 読み込めないスクリプトがあっても、ほかのスクリプトは読み込む
+    /// * `plugins`がない場合は、何も読み込まない
+    pub fn load() -> (Self, Vec<String>) {
+        let mut plugins = Self::default();
+        let Some(dir) = config::config_dir().map(|dir| dir.join("plugins")) else {
+            return (plugins, Vec::new());
+        };
+
+        let mut paths: Vec<_> = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|extension| extension == "rhai")
+                })
+                .collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return (plugins, vec![format!("{}: {}", dir.display(), e)]),
+        };
+        paths.sort();
+
+        let errors = paths
+            .iter()
+            .filter_map(|path| plugins.load_file(path).err())
+            .collect();
+
+        (plugins, errors)
+    }
+
+    /// スクリプトのファイルを読み込む
+    fn load_file(&mut self, path: &Path) -> Result<(), String> {
+        let source =
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        self.add_script(&source)
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// スクリプトを読み込んで、一番上の処理(コマンドの登録など)を実行する
+    /// # Arguments
+    /// * `source` - スクリプト
+    fn add_script(&mut self, source: &str) -> Result<(), String> {
+        let ast = self.engine.compile(source).map_err(|e| e.to_string())?;
+        self.state.borrow_mut().script = self.scripts.len();
+        self.engine.run_ast(&ast).map_err(|e| e.to_string())?;
+        self.scripts.push(ast);
+
+        Ok(())
+    }
+
+    /// スクリプトを実行するEngineを作成して、スクリプトから呼び出す関数を登録する
+    fn engine(state: &Rc<RefCell<State>>) -> Engine {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        // 標準出力に出力すると画面が崩れるので、printはメッセージとして表示し、debugは無視する
+        let shared = Rc::clone(state);
+        engine.on_print(move |text| {
+            shared.borrow_mut().effects.push(Effect::Message {
+                text: text.to_string(),
+                is_error: false,
+            });
+        });
+        engine.on_debug(|_, _, _| {});
+
+        let shared = Rc::clone(state);
+        engine.register_fn(
+            "register_command",
+            move |name: &str, callback: FnPtr| -> Result<(), Box<EvalAltResult>> {
+                if name.is_empty() || name.contains(char::is_whitespace) {
+                    return Err(format!("{}: Invalid command name", name).into());
+                }
+
+                let mut state = shared.borrow_mut();
+                let script = state.script;
+                state.commands.retain(|command| command.name != name);
+                state.commands.push(PluginCommand {
+                    name: name.to_string(),
+                    callback,
+                    script,
+                });
+                Ok(())
+            },
+        );
+
+        let shared = Rc::clone(state);
+        engine.register_fn(
+            "bind_key",
+            move |keys: &str, command: &str| -> Result<(), Box<EvalAltResult>> {
+                let keys: KeySequence = keys.parse().map_err(|e: String| e)?;
+                let mut state = shared.borrow_mut();
+                state.bindings.retain(|(bound, _)| *bound != keys);
+                state.bindings.push((keys, command.to_string()));
+                Ok(())
+            },
+        );
+
+        let shared = Rc::clone(state);
+        engine.register_fn("set_status", move |name: &str, value: &str| {
+            let mut state = shared.borrow_mut();
+            state.status_items.insert(name.to_string());
+            state.effects.push(Effect::SetStatus {
+                name: name.to_string(),
+                value: value.to_string(),
+            });
+        });
+
+        let shared = Rc::clone(state);
+        engine.register_fn("remove_status", move |name: &str| {
+            let mut state = shared.borrow_mut();
+            state.status_items.remove(name);
+            state.effects.push(Effect::RemoveStatus(name.to_string()));
+        });
+
+        for (function, is_error) in [("message", false), ("error", true)] {
+            let shared = Rc::clone(state);
+            engine.register_fn(function, move |text: &str| {
+                shared.borrow_mut().effects.push(Effect::Message {
+                    text: text.to_string(),
+                    is_error,
+                });
+            });
+        }
+
+        let shared = Rc::clone(state);
+        engine.register_fn("execute", move |command: &str| {
+            shared
+                .borrow_mut()
+                .effects
+                .push(Effect::Execute(command.to_string()));
+        });
+
+        let shared = Rc::clone(state);
+        engine.register_fn(
+            "on",
+            move |hook: &str, callback: FnPtr| -> Result<(), Box<EvalAltResult>> {
+                let hook: Hook = hook.parse()?;
+                let mut state = shared.borrow_mut();
+                let script = state.script;
+                state.hook_callbacks.push(HookCallback {
+                    hook,
+                    callback,
+                    script,
+                });
+                Ok(())
+            },
+        );
+
+        let shared = Rc::clone(state);
+        engine.register_fn(
+            "on",
+            move |hook: &str, command: &str| -> Result<(), Box<EvalAltResult>> {
+                let hook: Hook = hook.parse()?;
+                shared
+                    .borrow_mut()
+                    .hook_commands
+                    .push((hook, command.to_string()));
+                Ok(())
+            },
+        );
+
+        let shared = Rc::clone(state);
+        engine.register_fn("file_name", move || {
+            shared.borrow().context.file.clone().unwrap_or_default()
+        });
+
+        let shared = Rc::clone(state);
+        engine.register_fn("line_number", move || {
+            shared.borrow().context.line_number as rhai::INT
+        });
+
+        engine
+    }
+
+    /// 入力したコマンドが、プラグインで登録したコマンドの場合に解釈する
+    /// # Arguments
+    /// * `line` - `:`に続けて入力した文字列
+    /// # Returns
+    /// * `Option<Command>` - 登録したコマンド(登録していない名前の場合はNone)
+    pub fn parse_command(&self, line: &str) -> Option<Command> {
+        let (name, argument) = command_line::split_name(line);
+        self.state
+            .borrow()
+            .commands
+            .iter()
+            .any(|command| command.name == name)
+            .then(|| Command::Plugin {
+                name: name.to_string(),
+                argument: argument.map(str::to_string),
+            })
+    }
+
+    /// プラグインで登録したコマンドを実行する
+    /// # Arguments
+    /// * `name` - 登録したコマンドの名前
+    /// * `argument` - 名前に続けて入力した文字列
+    /// * `context` - 表示しているファイルの状態
+    /// # Returns
+    /// * `Result<(), String>` - 実行できなかった場合はエラーメッセージ
+    /// # Notes
+    /// * 画面に反映する操作は、`take_effects`で受け取る
+    pub fn run_command(
+        &mut self,
+        name: &str,
+        argument: &str,
+        context: Context,
+    ) -> Result<(), String> {
+        let (callback, script) = {
+            let mut state = self.state.borrow_mut();
+            state.context = context;
+            let command = state
+                .commands
+                .iter()
+                .find(|command| command.name == name)
+                .ok_or_else(|| format!("{}: Unknown command", name))?;
+            (command.callback.clone(), command.script)
+        };
+
+        self.call(&callback, script, argument)
+            .map_err(|e| format!("{}: {}", name, e))
+    }
+
+    /// プラグインで登録した、時点ごとに実行する関数を実行する
+    /// # Arguments
+    /// * `hook` - 実行する時点
+    /// * `argument` - 開いたファイルや書き込んだファイルの名前、押したキー
+    /// * `context` - 表示しているファイルの状態
+    /// # Returns
+    /// * `Result<(), String>` - 実行できなかった関数がある場合はエラーメッセージ
+    /// # Notes
+    /// * 実行できない関数があっても、ほかの関数は実行する
+    pub fn run_hook(&mut self, hook: Hook, argument: &str, context: Context) -> Result<(), String> {
+        let callbacks: Vec<(FnPtr, usize)> = {
+            let mut state = self.state.borrow_mut();
+            state.context = context;
+            state
+                .hook_callbacks
+                .iter()
+                .filter(|callback| callback.hook == hook)
+                .map(|callback| (callback.callback.clone(), callback.script))
+                .collect()
+        };
+
+        let errors: Vec<String> = callbacks
+            .iter()
+            .filter_map(|(callback, script)| self.call(callback, *script, argument).err())
+            .map(|e| format!("{}: {}", hook, e))
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join(", "))
+        }
+    }
+
+    /// スクリプトで定義した関数を実行する
+    /// # Arguments
+    /// * `callback` - 実行する関数
+    /// * `script` - 関数を定義したスクリプト(`scripts`の位置)
+    /// * `argument` - 関数に渡す文字列
+    fn call(
+        &self,
+        callback: &FnPtr,
+        script: usize,
+        argument: &str,
+    ) -> Result<(), Box<EvalAltResult>> {
+        callback
+            .call::<Dynamic>(&self.engine, &self.scripts[script], (argument.to_string(),))
+            .map(|_| ())
+    }
+
+    /// プラグインで登録した、時点ごとに実行するコマンドを加える
+    /// # Arguments
+    /// * `hooks` - 加える登録
+    /// # Notes
+    /// * 設定ファイルを読み込み直して登録を作り直した場合は、もう一度加える
+    pub fn register_hooks(&self, hooks: &mut Hooks) {
+        for (hook, command) in &self.state.borrow().hook_commands {
+            hooks.register(*hook, command.clone());
+        }
+    }
+
+    /// プラグインで割り当てたキーの列を、キーの割り当てに加える
+    /// # Arguments
+    /// * `keymap` - 加えるキーの割り当て
+    /// # Notes
+    /// * 設定ファイルを読み込み直してキーの割り当てを作り直した場合は、もう一度加える
+    pub fn bind_keys(&self, keymap: &mut Keymap) {
+        for (keys, command) in &self.state.borrow().bindings {
+            keymap.bind_command(keys, command);
+        }
+    }
+
+    /// プラグインで加えたステータスバーの項目の名前
+    /// # Notes
+    /// * プラグインを読み込み直す前に、加えた項目を取り除くために使う
+    pub fn status_items(&self) -> Vec<String> {
+        self.state.borrow().status_items.iter().cloned().collect()
+    }
+
+    /// まだ画面に反映していない操作を受け取る
+    pub fn take_effects(&mut self) -> Vec<Effect> {
+        std::mem::take(&mut self.state.borrow_mut().effects)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::{KeyChord, KeymapPreset, Lookup};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_plugins() {
+        let mut plugins = Plugins::default();
+        plugins
+            .add_script(
+                r#"
+                register_command("greet", |name| {
+                    message(`Hello, ${name} (${file_name()}:${line_number()})`);
+                    set_status("greet", name);
+                });
+                bind_key("g h", "greet world");
+                print("loaded");
+                "#,
+            )
+            .unwrap();
+        plugins
+            .add_script(r#"register_command("fail", |arg| { throw "oops"; });"#)
+            .unwrap();
+        assert_eq!(
+            plugins.take_effects(),
+            vec![Effect::Message {
+                text: "loaded".to_string(),
+                is_error: false
+            }]
+        );
+
+        // 登録したコマンドの名前だけを解釈する
+        assert_eq!(
+            plugins.parse_command(" greet  you "),
+            Some(Command::Plugin {
+                name: "greet".to_string(),
+                argument: Some("you".to_string())
+            })
+        );
+        assert_eq!(plugins.parse_command("unknown"), None);
+
+        let context = Context {
+            file: Some("a.txt".to_string()),
+            line_number: 3,
+        };
+        plugins
+            .run_command("greet", "you", context.clone())
+            .unwrap();
+        assert_eq!(
+            plugins.take_effects(),
+            vec![
+                Effect::Message {
+                    text: "Hello, you (a.txt:3)".to_string(),
+                    is_error: false
+                },
+                Effect::SetStatus {
+                    name: "greet".to_string(),
+                    value: "you".to_string()
+                }
+            ]
+        );
+        assert_eq!(plugins.status_items(), vec!["greet".to_string()]);
+        assert!(plugins.run_command("fail", "", context.clone()).is_err());
+        assert!(plugins.run_command("none", "", context).is_err());
+
+        // 割り当てたキーの列は、コマンドとしてキーの割り当てに加える
+        let mut keymap = Keymap::new(KeymapPreset::Default, &HashMap::new());
+        plugins.bind_keys(&mut keymap);
+        let keys: Vec<KeyChord> = "g h".parse::<KeySequence>().unwrap().0;
+        assert_eq!(
+            keymap.lookup(&keys),
+            Lookup::Command("greet world".to_string())
+        );
+    }
+
+    #[test]
+    fn test_hooks() {
+        let mut plugins = Plugins::default();
+        plugins
+            .add_script(
+                r#"
+                on("on_open", |file| execute(`mark a`));
+                on("on_key", |key| if key == "x" { throw "no x"; });
+                on("on_save", "!rustfmt {file}");
+                "#,
+            )
+            .unwrap();
+
+        // 関数は実行し、コマンドは登録に加える
+        plugins
+            .run_hook(Hook::OnOpen, "a.txt", Context::default())
+            .unwrap();
+        assert_eq!(
+            plugins.take_effects(),
+            vec![Effect::Execute("mark a".to_string())]
+        );
+        assert!(plugins
+            .run_hook(Hook::OnKey, "j", Context::default())
+            .is_ok());
+        assert!(plugins
+            .run_hook(Hook::OnKey, "x", Context::default())
+            .is_err());
+
+        let mut hooks = Hooks::default();
+        plugins.register_hooks(&mut hooks);
+        assert_eq!(
+            hooks.commands(Hook::OnSave, Some("a.rs")),
+            vec!["!rustfmt 'a.rs'".to_string()]
+        );
+
+        assert!(plugins.add_script(r#"on("on_close", "quit");"#).is_err());
+    }
+
+    #[test]
+    fn test_plugins_error() {
+        let mut plugins = Plugins::default();
+        assert!(plugins.add_script("let x = ;").is_err());
+        assert!(plugins
+            .add_script(r#"register_command("two words", |arg| {});"#)
+            .is_err());
+        assert!(plugins
+            .add_script(r#"bind_key("hyper+x", "quit");"#)
+            .is_err());
+
+        // 終わらないループは途中でやめる
+        assert!(plugins.add_script("loop {}").is_err());
+    }
+}
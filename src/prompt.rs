@@ -0,0 +1,308 @@
+use std::time::{Duration, Instant};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::screen;
+use crate::status_bar::StatusBar;
+
+/// メッセージを表示し続ける時間
+pub const MESSAGE_DURATION: Duration = Duration::from_secs(3);
+
+/// ステータスバーの行で文字列を入力する
+/// # Notes
+/// * 入力している間は、ステータスバーの項目の代わりに、先頭の文字と入力した文字列を表示する
+pub struct Prompt {
+    /// 入力した文字列の前に表示する文字列(`:`など)
+    prefix: String,
+    /// 入力した文字列
+    input: String,
+    /// カーソルの位置(文字数)
+    cursor: usize,
+}
+
+/// 押したキーによって、入力がどうなったか
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptEvent {
+    /// Enterキーで入力を確定した
+    Submit(String),
+    /// Escキーなどで入力をやめた
+    Cancel,
+    /// 入力した文字列かカーソルの位置が変わった
+    Changed,
+    /// 入力には使わないキーを押した
+    Ignored,
+}
+
+impl Prompt {
+    /// Promptを作成する
+    /// # Arguments
+    /// * `prefix` - 入力した文字列の前に表示する文字列
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            input: String::new(),
+            cursor: 0,
+        }
+    }
+
+    /// 入力した状態から始める
+    /// # Arguments
+    /// * `input` - 入力した状態にする文字列(カーソルは末尾に置く)
+    pub fn with_input(mut self, input: &str) -> Self {
+        self.input = input.to_string();
+        self.cursor = input.chars().count();
+        self
+    }
+
+    /// 入力した文字列を取得する
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// カーソルの位置を、入力した文字列の中の位置(バイト数)に変換する
+    fn byte_index(&self, cursor: usize) -> usize {
+        self.input
+            .char_indices()
+            .nth(cursor)
+            .map_or(self.input.len(), |(index, _)| index)
+    }
+
+    /// 押したキーで、文字列を編集する
+    /// # Arguments
+    /// * `key` - 押したキー
+    /// # Returns
+    /// * `PromptEvent` - 入力がどうなったか
+    /// # Notes
+    /// * 何も入力していない状態のBackspaceキーでも入力をやめる
+    /// * Ctrl+Uでカーソルより前をすべて、Ctrl+Wでカーソルの前の単語を削除する
+    pub fn handle_key(&mut self, key: &KeyEvent) -> PromptEvent {
+        let length = self.input.chars().count();
+
+        match (key.code, key.modifiers) {
+            (KeyCode::Enter, _) => return PromptEvent::Submit(self.input.clone()),
+            (KeyCode::Esc, _) => return PromptEvent::Cancel,
+            (KeyCode::Char('c'), KeyModifiers::CONTROL) => return PromptEvent::Cancel,
+            (KeyCode::Backspace, _) if self.input.is_empty() => return PromptEvent::Cancel,
+            (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                let index = self.byte_index(self.cursor);
+                self.input.insert(index, c);
+                self.cursor += 1;
+            }
+            (KeyCode::Backspace, _) if self.cursor > 0 => {
+                self.cursor -= 1;
+                let index = self.byte_index(self.cursor);
+                self.input.remove(index);
+            }
+            (KeyCode::Delete, _) if self.cursor < length => {
+                let index = self.byte_index(self.cursor);
+                self.input.remove(index);
+            }
+            (KeyCode::Left, _) if self.cursor > 0 => self.cursor -= 1,
+            (KeyCode::Right, _) if self.cursor < length => self.cursor += 1,
+            (KeyCode::Home, _) | (KeyCode::Char('a'), KeyModifiers::CONTROL) => self.cursor = 0,
+            (KeyCode::End, _) | (KeyCode::Char('e'), KeyModifiers::CONTROL) => self.cursor = length,
+            (KeyCode::Char('u'), KeyModifiers::CONTROL) => {
+                let index = self.byte_index(self.cursor);
+                self.input.replace_range(..index, "");
+                self.cursor = 0;
+            }
+            (KeyCode::Char('w'), KeyModifiers::CONTROL) => {
+                // カーソルの前の空白と、その前の空白以外の文字を削除する
+                let before: Vec<char> = self.input.chars().take(self.cursor).collect();
+                let spaces = before
+                    .iter()
+                    .rev()
+                    .take_while(|c| c.is_whitespace())
+                    .count();
+                let word = before
+                    .iter()
+                    .rev()
+                    .skip(spaces)
+                    .take_while(|c| !c.is_whitespace())
+                    .count();
+                let start = self.byte_index(self.cursor - spaces - word);
+                let end = self.byte_index(self.cursor);
+                self.input.replace_range(start..end, "");
+                self.cursor -= spaces + word;
+            }
+            _ => return PromptEvent::Ignored,
+        }
+
+        PromptEvent::Changed
+    }
+
+    /// 横幅に収まるように、表示する文字列とカーソルの位置を求める
+    /// # Arguments
+    /// * `width` - 表示する横幅
+    /// # Returns
+    /// * `(String, usize)` - 表示する文字列と、カーソルを表示する桁
+    /// # Notes
+    /// * カーソルが横幅に収まらない場合は、収まるまで先頭の文字を表示しない
+    fn visible(&self, width: usize) -> (String, usize) {
+        let before = &self.input[..self.byte_index(self.cursor)];
+        let mut column = self.prefix.width() + before.width();
+
+        let mut chars = self.prefix.chars().chain(self.input.chars());
+        while column >= width {
+            match chars.next() {
+                Some(c) => column -= c.width().unwrap_or(0),
+                None => break,
+            }
+        }
+
+        let mut text = String::new();
+        let mut total = 0;
+        for c in chars {
+            total += c.width().unwrap_or(0);
+            if total > width {
+                break;
+            }
+            text.push(c);
+        }
+
+        (text, column)
+    }
+
+    /// ステータスバーの行に、入力した文字列とカーソルを表示する
+    /// # Arguments
+    /// * `status_bar` - 入力に使うステータスバー
+    /// # Notes
+    /// * 入力をやめたときは、呼び出し側でカーソルを隠す
+    pub fn print(&self, status_bar: &StatusBar) {
+        let (text, column) = self.visible(status_bar.width as usize);
+        status_bar.print_text(&text);
+
+        screen::set_cursor(Some((
+            status_bar.x_start + column as u16,
+            status_bar.y_start,
+        )));
+    }
+}
+
+/// ステータスバーの項目の代わりに、一定の時間だけ表示するメッセージ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    /// 表示する文字列
+    pub text: String,
+    /// エラーを知らせるメッセージか(エラーの場合は、エラーの装飾で表示する)
+    pub is_error: bool,
+    /// 表示をやめる時刻
+    pub expires_at: Instant,
+}
+
+impl Message {
+    /// 知らせるだけのメッセージを作成する
+    /// # Arguments
+    /// * `text` - 表示する文字列
+    pub fn info(text: String) -> Self {
+        Self {
+            text,
+            is_error: false,
+            expires_at: Instant::now() + MESSAGE_DURATION,
+        }
+    }
+
+    /// エラーを知らせるメッセージを作成する
+    /// # Arguments
+    /// * `text` - 表示する文字列
+    pub fn error(text: String) -> Self {
+        Self {
+            is_error: true,
+            ..Self::info(text)
+        }
+    }
+
+    /// 表示をやめる時刻を過ぎたか
+    pub fn is_expired(&self) -> bool {
+        Instant::now() >= self.expires_at
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn ctrl(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    fn type_str(prompt: &mut Prompt, s: &str) {
+        for c in s.chars() {
+            assert_eq!(
+                prompt.handle_key(&key(KeyCode::Char(c))),
+                PromptEvent::Changed
+            );
+        }
+    }
+
+    #[test]
+    fn test_handle_key() {
+        let mut prompt = Prompt::new(":");
+        type_str(&mut prompt, "set wrp");
+
+        // カーソルの位置に文字を挿入する
+        prompt.handle_key(&key(KeyCode::Left));
+        type_str(&mut prompt, "a");
+        assert_eq!(prompt.input(), "set wrap");
+        assert_eq!(prompt.cursor, 7);
+
+        prompt.handle_key(&key(KeyCode::Home));
+        assert_eq!(
+            prompt.handle_key(&key(KeyCode::Backspace)),
+            PromptEvent::Ignored
+        );
+        prompt.handle_key(&key(KeyCode::Delete));
+        assert_eq!(prompt.input(), "et wrap");
+
+        // カーソルの前の単語を削除する
+        prompt.handle_key(&key(KeyCode::End));
+        prompt.handle_key(&ctrl('w'));
+        assert_eq!(prompt.input(), "et ");
+        prompt.handle_key(&ctrl('w'));
+        assert_eq!(prompt.input(), "");
+
+        // マルチバイト文字も1文字ずつ扱う
+        type_str(&mut prompt, "e あい");
+        prompt.handle_key(&key(KeyCode::Backspace));
+        prompt.handle_key(&key(KeyCode::Left));
+        prompt.handle_key(&ctrl('u'));
+        assert_eq!(prompt.input(), "あ");
+        assert_eq!(prompt.cursor, 0);
+
+        assert_eq!(
+            prompt.handle_key(&key(KeyCode::Enter)),
+            PromptEvent::Submit("あ".to_string())
+        );
+        assert_eq!(prompt.handle_key(&key(KeyCode::Esc)), PromptEvent::Cancel);
+        prompt.handle_key(&key(KeyCode::Delete));
+        assert_eq!(
+            prompt.handle_key(&key(KeyCode::Backspace)),
+            PromptEvent::Cancel
+        );
+
+        // 入力した状態から始める
+        let mut prompt = Prompt::new(":").with_input("|あ");
+        type_str(&mut prompt, "x");
+        assert_eq!(prompt.input(), "|あx");
+    }
+
+    #[test]
+    fn test_visible() {
+        let mut prompt = Prompt::new(":");
+        type_str(&mut prompt, "abcdef");
+        assert_eq!(prompt.visible(10), (":abcdef".to_string(), 7));
+
+        // カーソルが横幅に収まるまで、先頭の文字を表示しない
+        assert_eq!(prompt.visible(5), ("cdef".to_string(), 4));
+
+        // カーソルより後ろは、横幅に収まらない文字を表示しない
+        prompt.handle_key(&key(KeyCode::Home));
+        assert_eq!(prompt.visible(5), (":abcd".to_string(), 1));
+    }
+}
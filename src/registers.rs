@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+/// コピーした文字列を名前を付けて保存しておき、複数の文字列を使い分けられるようにする
+#[derive(Debug, Default)]
+pub struct Registers {
+    /// レジスタごとの文字列(名前は小文字)
+    registers: HashMap<char, String>,
+}
+
+impl Registers {
+    /// レジスタを選ばずにコピーした場合にも、最後にコピーした文字列を保存するレジスタ
+    pub const UNNAMED: char = '"';
+
+    /// レジスタに使える文字か
+    /// # Arguments
+    /// * `register` - レジスタの名前
+    /// # Notes
+    /// * 英字はレジスタの名前で、大文字の場合は小文字のレジスタに追加する
+    /// * `+`と`*`はクリップボードを表す
+    pub fn is_register(register: char) -> bool {
+        register.is_ascii_alphabetic() || Self::is_clipboard(register)
+    }
+
+    /// クリップボードを表すレジスタか
+    /// # Arguments
+    /// * `register` - レジスタの名前
+    pub fn is_clipboard(register: char) -> bool {
+        matches!(register, '+' | '*')
+    }
+
+    /// コピーした文字列をレジスタに保存する
+    /// # Arguments
+    /// * `register` - 保存するレジスタ(Noneの場合は名前のないレジスタだけに保存する)
+    /// * `text` - コピーした文字列
+    /// # Returns
+    /// * `&str` - 保存したレジスタの文字列(大文字のレジスタに追加した場合は、追加した後の文字列)
+    /// # Notes
+    /// * 名前のないレジスタには、保存したレジスタと同じ文字列を保存する
+    pub fn yank(&mut self, register: Option<char>, text: &str) -> &str {
+        let contents = match register.filter(|c| c.is_ascii_alphabetic()) {
+            Some(c) if c.is_ascii_uppercase() => {
+                let contents = self.registers.entry(c.to_ascii_lowercase()).or_default();
+                contents.push_str(text);
+                contents.clone()
+            }
+            Some(c) => {
+                self.registers.insert(c, text.to_string());
+                text.to_string()
+            }
+            None => text.to_string(),
+        };
+
+        self.registers
+            .entry(Self::UNNAMED)
+            .or_default()
+            .clone_from(&contents);
+        &self.registers[&Self::UNNAMED]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_yank() {
+        assert!(Registers::is_register('a'));
+        assert!(Registers::is_register('+'));
+        assert!(!Registers::is_register('1'));
+
+        let mut registers = Registers::default();
+        assert_eq!(registers.yank(None, "x\n"), "x\n");
+        assert_eq!(registers.yank(Some('a'), "a\n"), "a\n");
+
+        // 大文字のレジスタは、小文字のレジスタに追加する
+        assert_eq!(registers.yank(Some('A'), "b\n"), "a\nb\n");
+        assert_eq!(registers.registers[&'a'], "a\nb\n");
+        assert_eq!(registers.registers[&Registers::UNNAMED], "a\nb\n");
+
+        // クリップボードを表すレジスタは、名前のないレジスタだけに保存する
+        registers.yank(Some('+'), "c");
+        assert_eq!(registers.registers.len(), 2);
+        assert_eq!(registers.registers[&Registers::UNNAMED], "c");
+    }
+}
@@ -0,0 +1,562 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    backend::{Backend, CrosstermBackend, MemoryBackend},
+    cell::{self, Cell, CellStyle, HYPERLINK_END},
+    layout::Rect,
+};
+
+/// 続けて入力した場合に、画面を更新する最短の間隔(1秒に60回ほど)
+pub const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// 端末の大きさを続けて変えている間に、大きさが決まったとみなすまで待つ時間
+pub const RESIZE_SETTLE_TIME: Duration = Duration::from_millis(50);
+
+/// 重ねて表示するものがない、一番下の層
+/// エディタ領域やステータスバーなどは、この層に出力する
+pub const BASE_LAYER: u8 = 0;
+
+/// 表示するものが出力した内容と重ねて表示するものを組み合わせて、端末に出力する
+/// # Notes
+/// * 出力した内容は文字ごとに覚えておき、端末に表示している内容と異なる部分だけを出力する
+/// * 重ねて表示するものは層ごとに覚えておき、層を消すと下に隠れていた内容がそのまま見えるようになる
+#[derive(Default)]
+struct Compositor {
+    /// 横幅
+    width: u16,
+    /// 高さ
+    height: u16,
+    /// 一番下の層に出力した内容
+    base: Vec<Vec<Cell>>,
+    /// 重ねて表示する層(番号が大きいほど上に表示する)ごとの、出力した部分
+    layers: BTreeMap<u8, Vec<Vec<Option<Cell>>>>,
+    /// 端末に表示している内容(分からない部分はNone)
+    flushed: Vec<Vec<Option<Cell>>>,
+    /// 端末のカーソルを表示する位置(隠す場合はNone)
+    cursor: Option<(u16, u16)>,
+    /// 出力する先から大きさを取得したか
+    sized: bool,
+}
+
+impl Compositor {
+    /// 大きさを変えて、出力した内容をすべて忘れる
+    fn resize(&mut self, width: u16, height: u16) {
+        self.width = width;
+        self.height = height;
+        self.base = vec![vec![Cell::default(); width as usize]; height as usize];
+        self.layers.clear();
+        self.flushed = vec![vec![None; width as usize]; height as usize];
+        self.sized = true;
+    }
+
+    /// 層の1行の一部に出力する
+    /// # Returns
+    /// * `bool` - 出力した内容が前と異なる場合はtrue
+    fn write(&mut self, layer: u8, x: u16, y: u16, width: u16, text: &str) -> bool {
+        if y >= self.height || x >= self.width {
+            return false;
+        }
+        let width = width.min(self.width - x) as usize;
+        let mut cells = cell::parse(text, AMBIGUOUS_WIDE.get());
+
+        // 範囲に収まらない部分は出力せず、範囲の残りは空白で埋める
+        cells.truncate(width);
+        if cells.last().is_some_and(|cell| cell.width == 2) {
+            cells.pop();
+        }
+        cells.resize(width, Cell::default());
+
+        let range = x as usize..x as usize + width;
+        if layer == BASE_LAYER {
+            let row = &mut self.base[y as usize][range];
+            let changed = row != cells.as_slice();
+            row.clone_from_slice(&cells);
+            return changed;
+        }
+
+        let (columns, rows) = (self.width as usize, self.height as usize);
+        let grid = self
+            .layers
+            .entry(layer)
+            .or_insert_with(|| vec![vec![None; columns]; rows]);
+        let row = &mut grid[y as usize][range];
+        let changed = row
+            .iter()
+            .zip(&cells)
+            .any(|(old, new)| old.as_ref() != Some(new));
+        for (old, new) in row.iter_mut().zip(cells) {
+            *old = Some(new);
+        }
+        changed
+    }
+
+    /// 1行分の、すべての層を重ねた内容を求める
+    /// # Notes
+    /// * 重ねたことで左右の半分が隠れた全角文字は、残った半分を空白にする
+    fn compose_row(&self, y: usize) -> Vec<Cell> {
+        let mut row = self.base[y].clone();
+        for grid in self.layers.values() {
+            for (cell, layer_cell) in row.iter_mut().zip(&grid[y]) {
+                if let Some(layer_cell) = layer_cell {
+                    cell.clone_from(layer_cell);
+                }
+            }
+        }
+
+        let mut x = 0;
+        while x < row.len() {
+            let paired = row[x].width == 2 && row.get(x + 1).is_some_and(Cell::is_continuation);
+            if paired {
+                x += 2;
+                continue;
+            }
+            if row[x].width != 1 {
+                row[x] = Cell {
+                    style: row[x].style.clone(),
+                    ..Cell::default()
+                };
+            }
+            x += 1;
+        }
+        row
+    }
+
+    /// すべての層を重ねた内容のうち、端末に表示している内容と異なる部分だけを出力する
+    fn present(&mut self, backend: &mut dyn Backend) -> std::io::Result<()> {
+        let mut style: Option<CellStyle> = None;
+        for y in 0..self.height as usize {
+            let row = self.compose_row(y);
+            let mut position = None;
+            let mut text = String::new();
+            let mut x = 0;
+            while x < row.len() {
+                let cell = &row[x];
+                let span = (cell.width as usize).max(1);
+                let columns = x..x + span;
+                if row[columns.clone()]
+                    .iter()
+                    .zip(&self.flushed[y][columns.clone()])
+                    .all(|(cell, flushed)| flushed.as_ref() == Some(cell))
+                {
+                    x += span;
+                    continue;
+                }
+
+                // 続けて出力できない位置に移る場合は、それまでの文字列を出力してから移る
+                if position != Some(x) {
+                    backend.print(&std::mem::take(&mut text))?;
+                    backend.move_to(x as u16, y as u16)?;
+                }
+                if style.as_ref() != Some(&cell.style) {
+                    let previous = style.as_ref().and_then(CellStyle::hyperlink);
+                    text.push_str(&cell.style.sgr());
+                    if previous != cell.style.hyperlink() {
+                        text.push_str(cell.style.hyperlink().unwrap_or(HYPERLINK_END));
+                    }
+                    style = Some(cell.style.clone());
+                }
+                text.push_str(&cell.symbol);
+                for column in columns {
+                    self.flushed[y][column] = Some(row[column].clone());
+                }
+                x += span;
+                position = Some(x);
+            }
+            backend.print(&text)?;
+        }
+
+        // 端末で続けて出力するものに、装飾やハイパーリンクが残らないようにする
+        if let Some(style) = style.filter(|style| *style != CellStyle::default()) {
+            let end = if style.hyperlink().is_some() {
+                HYPERLINK_END
+            } else {
+                ""
+            };
+            backend.print(&format!("{}{}", CellStyle::default().sgr(), end))?;
+        }
+        backend.set_cursor(self.cursor)
+    }
+
+    /// 行の範囲を上下にずらす
+    fn shift(&mut self, y_start: u16, height: u16, delta: i32) {
+        let start = (y_start as usize).min(self.height as usize);
+        let end = (y_start as usize + height as usize).min(self.height as usize);
+        let count = (delta.unsigned_abs() as usize).min(end - start);
+        let blank = vec![Cell::default(); self.width as usize];
+        shift_rows(&mut self.base[start..end], delta, count, &blank);
+
+        // 端末でも同じようにずれて、新しく見えるようになった行は空になる
+        let blank: Vec<Option<Cell>> = blank.into_iter().map(Some).collect();
+        shift_rows(&mut self.flushed[start..end], delta, count, &blank);
+    }
+}
+
+/// 行を上下にずらし、新しく見えるようになった行を空にする
+fn shift_rows<T: Clone>(rows: &mut [Vec<T>], delta: i32, count: usize, blank: &[T]) {
+    let len = rows.len();
+    let cleared = if delta > 0 {
+        rows.rotate_left(count);
+        len - count..len
+    } else {
+        rows.rotate_right(count);
+        0..count
+    };
+    for row in &mut rows[cleared] {
+        row.clone_from_slice(blank);
+    }
+}
+
+thread_local! {
+    /// 端末に表示する内容
+    /// 端末は1つだけなので、表示するものがそれぞれ引数で受け取らずに使えるように、ここで覚えておく
+    static COMPOSITOR: RefCell<Compositor> = RefCell::new(Compositor::default());
+    /// 画面を出力する先
+    static BACKEND: RefCell<Box<dyn Backend>> = RefCell::new(Box::new(CrosstermBackend::default()));
+    /// 出力する層
+    static LAYER: std::cell::Cell<u8> = const { std::cell::Cell::new(BASE_LAYER) };
+    /// 東アジアの文字幅が曖昧な文字を全角として扱うか
+    static AMBIGUOUS_WIDE: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+    /// 画面の更新を始めてから、終えていないか
+    static IN_FRAME: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+    /// 最後に画面の更新を終えた時刻
+    static LAST_FRAME: std::cell::Cell<Option<Instant>> = const { std::cell::Cell::new(None) };
+}
+
+/// 画面を出力する先を差し替える
+/// # Arguments
+/// * `backend` - 新しく出力する先
+/// # Returns
+/// * `Box<dyn Backend>` - 今まで出力していた先
+/// # Notes
+/// * 出力した内容は出力する先ごとに異なるので、すべて忘れる
+pub fn set_backend(backend: Box<dyn Backend>) -> Box<dyn Backend> {
+    let previous = BACKEND.replace(backend);
+    invalidate_all();
+    previous
+}
+
+/// 端末の代わりにメモリ上に表示して、表示した内容を文字列として取得する
+/// # Arguments
+/// * `width` - 表示する横幅
+/// * `height` - 表示する高さ
+/// * `draw` - `Contents`や`StatusBar`などを表示する
+/// # Returns
+/// * `std::io::Result<String>` - 表示した内容(行ごとに改行で区切り、装飾は含まない)
+/// # Notes
+/// * 端末がなくても、表示した結果をそのまま見比べるテストを書けるようにする
+/// * 表示し終えたら、元の出力する先に戻す
+pub fn render_to_string(
+    width: u16,
+    height: u16,
+    draw: impl FnOnce() -> std::io::Result<()>,
+) -> std::io::Result<String> {
+    let backend = MemoryBackend::new(width, height);
+    let previous = set_backend(Box::new(backend.clone()));
+    let result = draw().and_then(|_| flush());
+    set_backend(previous);
+    result.map(|_| backend.to_string())
+}
+
+/// 画面を出力する先を使う
+fn with_backend<T>(f: impl FnOnce(&mut dyn Backend) -> T) -> T {
+    BACKEND.with(|backend| f(backend.borrow_mut().as_mut()))
+}
+
+/// 端末に表示する内容を使う
+/// # Notes
+/// * 最初に使うときに、出力する先から大きさを取得する
+fn with_compositor<T>(f: impl FnOnce(&mut Compositor) -> T) -> T {
+    COMPOSITOR.with_borrow_mut(|compositor| {
+        if !compositor.sized {
+            let (width, height) = size().unwrap_or_default();
+            compositor.resize(width, height);
+        }
+        f(compositor)
+    })
+}
+
+/// 端末の大きさ
+/// # Returns
+/// * `std::io::Result<(u16, u16)>` - 横幅と高さ
+pub fn size() -> std::io::Result<(u16, u16)> {
+    with_backend(|backend| backend.size())
+}
+
+/// 東アジアの文字幅が曖昧な文字を、全角として扱うかを設定する
+/// # Notes
+/// * 出力した文字列を文字ごとに分けるときに、`Contents`と同じ表示幅になるようにする
+pub fn set_ambiguous_wide(ambiguous_wide: bool) {
+    AMBIGUOUS_WIDE.set(ambiguous_wide);
+}
+
+/// 東アジアの文字幅が曖昧な文字を、全角として扱うか
+/// # Notes
+/// * 一覧などを重ねて表示するときに、`cell::truncate`などで同じ表示幅で数えるために使う
+pub fn ambiguous_wide() -> bool {
+    AMBIGUOUS_WIDE.get()
+}
+
+/// 画面の更新を始める
+/// # Notes
+/// * 出力する先が対応している場合は、`end_frame`までの出力をまとめて表示するので、書きかけの画面が見えない
+/// * すでに更新を始めている場合は、何もしない
+pub fn begin_frame() -> std::io::Result<()> {
+    if !IN_FRAME.replace(true) {
+        with_backend(|backend| backend.begin_update())?;
+    }
+    Ok(())
+}
+
+/// 画面の更新を終えて、まとめて表示させる
+/// # Notes
+/// * 入力を待つ前や、外部のプログラムに端末を渡す前に呼び出す
+pub fn end_frame() -> std::io::Result<()> {
+    present()?;
+    if IN_FRAME.replace(false) {
+        with_backend(|backend| backend.end_update())?;
+        LAST_FRAME.set(Some(Instant::now()));
+    }
+    with_backend(|backend| backend.flush())
+}
+
+/// ここまでに出力した内容を、端末に表示させる
+/// # Notes
+/// * 画面の更新を始めている場合は、`end_frame`を呼び出すまで表示されない場合もある
+pub fn flush() -> std::io::Result<()> {
+    present()?;
+    with_backend(|backend| backend.flush())
+}
+
+/// すべての層を重ねた内容のうち、端末に表示している内容と異なる部分を出力する
+fn present() -> std::io::Result<()> {
+    with_compositor(|compositor| with_backend(|backend| compositor.present(backend)))
+}
+
+/// 次に画面を更新してよい時刻
+/// # Notes
+/// * 最後に画面の更新を終えてから`FRAME_INTERVAL`が過ぎるまでは、続けて入力したものをまとめて1回で表示する
+pub fn next_frame_at() -> Instant {
+    LAST_FRAME
+        .get()
+        .map_or_else(Instant::now, |last| last + FRAME_INTERVAL)
+}
+
+/// 端末の1行の一部に出力する
+/// # Arguments
+/// * `x` - 開始位置(X座標)
+/// * `y` - 行(Y座標)
+/// * `width` - 出力する部分の横幅
+/// * `text` - 出力する文字列
+/// # Returns
+/// * `bool` - 出力した内容が前と異なる場合はtrue
+/// # Notes
+/// * すぐには端末に出力せず、`flush`か`end_frame`で、表示している内容から変わった文字だけを出力する
+/// * `width`の範囲を消してから出力するので、`text`が短い場合は残りは空白になる
+/// * `with_layer`の中で呼び出した場合は、その層に出力する
+pub fn print_row(x: u16, y: u16, width: u16, text: &str) -> std::io::Result<bool> {
+    Ok(with_compositor(|compositor| {
+        compositor.write(LAYER.get(), x, y, width, text)
+    }))
+}
+
+/// 指定した層に出力する
+/// # Arguments
+/// * `layer` - 出力する層(番号が大きいほど上に表示する)
+/// * `draw` - `print_row`で出力する
+/// # Notes
+/// * 一覧やダイアログなどを、ほかの表示を消さずに重ねて表示するために使う
+pub fn with_layer<T>(layer: u8, draw: impl FnOnce() -> T) -> T {
+    let previous = LAYER.replace(layer);
+    let result = draw();
+    LAYER.set(previous);
+    result
+}
+
+/// 重ねて表示した層を消す
+/// # Arguments
+/// * `layer` - 消す層
+/// # Notes
+/// * 下に隠れていた内容は、表示し直さなくても次に出力するときに見えるようになる
+pub fn clear_layer(layer: u8) {
+    with_compositor(|compositor| compositor.layers.remove(&layer));
+}
+
+/// 重ねて表示した層をすべて消す
+pub fn clear_layers() {
+    with_compositor(|compositor| compositor.layers.clear());
+}
+
+/// 端末のカーソルを表示する位置を設定する
+/// # Arguments
+/// * `position` - 表示する位置(隠す場合はNone)
+/// # Notes
+/// * 出力した内容を表示した後に、カーソルを移す
+pub fn set_cursor(position: Option<(u16, u16)>) {
+    with_compositor(|compositor| compositor.cursor = position);
+}
+
+/// 指定した部分に表示している内容を忘れる
+/// # Arguments
+/// * `rect` - 忘れる部分
+/// # Notes
+/// * `print_row`を使わずに端末に出力した場合に呼び出し、次は必ず出力されるようにする
+pub fn invalidate(rect: Rect) {
+    with_compositor(|compositor| {
+        let rows = compositor.flushed.iter_mut().skip(rect.y as usize);
+        for row in rows.take(rect.height as usize) {
+            let cells = row.iter_mut().skip(rect.x as usize);
+            cells
+                .take(rect.width as usize)
+                .for_each(|cell| *cell = None);
+        }
+    });
+}
+
+/// 出力した内容をすべて忘れる
+/// # Notes
+/// * 画面を消した場合や、端末の大きさが変わった場合に呼び出す
+/// * 出力する先から大きさを取得し直して、すべての層を空にする
+pub fn invalidate_all() {
+    let (width, height) = size().unwrap_or_default();
+    COMPOSITOR.with_borrow_mut(|compositor| compositor.resize(width, height));
+}
+
+/// 端末の行の範囲を上下にずらす
+/// # Arguments
+/// * `y_start` - ずらす範囲の一番上の行(Y座標)
+/// * `height` - ずらす範囲の行数
+/// * `delta` - ずらす行数(正の場合は上に、負の場合は下にずらす)
+/// # Notes
+/// * 端末のスクロール領域を設定してずらすので、範囲の行は端末の横幅すべてがずれる
+/// * 出力した内容も一緒にずらすので、新しく見えるようになった行だけを出力し直せばよい
+pub fn scroll_rows(y_start: u16, height: u16, delta: i32) -> std::io::Result<()> {
+    if delta == 0 || delta.unsigned_abs() >= height as u32 {
+        return Ok(());
+    }
+
+    // まだ出力していない内容があると、端末と一緒にずらせないので、先に出力する
+    present()?;
+    with_backend(|backend| backend.scroll(y_start, height, delta))?;
+    with_compositor(|compositor| compositor.shift(y_start, height, delta));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    /// 出力した内容を記録する出力先
+    struct Recorder(Rc<RefCell<Vec<String>>>);
+
+    impl Backend for Recorder {
+        fn move_to(&mut self, x: u16, y: u16) -> std::io::Result<()> {
+            self.0.borrow_mut().push(format!("move {} {}", x, y));
+            Ok(())
+        }
+
+        fn print(&mut self, text: &str) -> std::io::Result<()> {
+            if !text.is_empty() {
+                self.0.borrow_mut().push(text.to_string());
+            }
+            Ok(())
+        }
+
+        fn size(&self) -> std::io::Result<(u16, u16)> {
+            Ok((8, 4))
+        }
+
+        fn scroll(&mut self, y_start: u16, height: u16, delta: i32) -> std::io::Result<()> {
+            self.0
+                .borrow_mut()
+                .push(format!("scroll {} {} {}", y_start, height, delta));
+            Ok(())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_backend() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let previous = set_backend(Box::new(Recorder(Rc::clone(&log))));
+        assert_eq!(size().unwrap(), (8, 4));
+
+        // 最初はすべて出力し、次からは表示している内容から変わった文字だけを出力する
+        assert!(print_row(2, 1, 3, "ab").unwrap());
+        flush().unwrap();
+        log.borrow_mut().clear();
+        assert!(!print_row(2, 1, 3, "ab").unwrap());
+        assert!(print_row(2, 1, 3, "a\x1b[1mc\x1b[22m").unwrap());
+        flush().unwrap();
+        assert_eq!(*log.borrow(), ["move 3 1", "\x1b[0;1mc", "\x1b[0m"]);
+
+        // ずらした行は端末でもずれているので、新しく見えるようになった行だけを出力する
+        log.borrow_mut().clear();
+        scroll_rows(0, 4, 1).unwrap();
+        scroll_rows(0, 4, 4).unwrap();
+        print_row(2, 0, 3, "ac").unwrap();
+        print_row(0, 3, 1, "x").unwrap();
+        flush().unwrap();
+        assert_eq!(
+            *log.borrow(),
+            ["scroll 0 4 1", "move 3 0", "\x1b[0mc", "move 0 3", "x"]
+        );
+
+        set_backend(previous);
+    }
+
+    #[test]
+    fn test_shift() {
+        let mut compositor = Compositor::default();
+        compositor.resize(10, 4);
+        for y in 0..4 {
+            compositor.write(BASE_LAYER, 0, y, 10, &y.to_string());
+        }
+
+        // 1行目から3行目を上にずらすと、2行目と3行目が1行上に移り、3行目は新しく見える行になる
+        compositor.shift(1, 3, 1);
+        assert!(!compositor.write(BASE_LAYER, 0, 0, 10, "0"));
+        assert!(!compositor.write(BASE_LAYER, 0, 1, 10, "2"));
+        assert!(!compositor.write(BASE_LAYER, 0, 2, 10, "3"));
+        assert!(compositor.write(BASE_LAYER, 0, 3, 10, "3"));
+
+        compositor.shift(0, 4, -2);
+        assert!(compositor.write(BASE_LAYER, 0, 0, 10, "0"));
+        assert!(!compositor.write(BASE_LAYER, 0, 2, 10, "0"));
+        assert!(!compositor.write(BASE_LAYER, 0, 3, 10, "2"));
+    }
+
+    #[test]
+    fn test_layers() {
+        let screen = render_to_string(6, 2, || {
+            print_row(0, 0, 6, "abcdef")?;
+            print_row(0, 1, 6, "あいう")?;
+            with_layer(1, || print_row(1, 0, 2, "XY"))?;
+
+            // 全角文字の半分だけが隠れた場合は、残った半分を空白にする
+            with_layer(2, || print_row(1, 1, 2, "Z"))?;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(screen, "aXYdef\n Z  う");
+
+        let screen = render_to_string(6, 1, || {
+            print_row(0, 0, 6, "abcdef")?;
+            with_layer(1, || print_row(1, 0, 2, "XY"))?;
+            flush()?;
+
+            // 層を消すと、下に隠れていた内容が見える
+            clear_layer(1);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(screen, "abcdef");
+    }
+}
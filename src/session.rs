@@ -0,0 +1,219 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::layout::SplitDirection;
+
+/// 分割した領域のひとつで表示していたファイルと位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SessionPane {
+    /// 表示していたファイルの`Session::files`での位置
+    pub file: usize,
+    /// 一番上に表示していた行番号(1から始まる)
+    pub line: usize,
+    /// 横方向のスクロール位置
+    pub column: u16,
+}
+
+/// タブページごとの、エディタ領域の分割の状態
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SessionTab {
+    /// 分割した領域(左または上から順に)
+    pub panes: Vec<SessionPane>,
+    /// 操作していた領域の位置
+    pub focused: usize,
+    /// 分割する向き
+    pub direction: SplitDirection,
+    /// 最初の領域が占める割合(%)
+    pub ratio: u16,
+    /// スクロールを連動させていたか
+    pub scroll_lock: bool,
+}
+
+/// 開いていたファイルと、タブページや分割した領域の状態
+/// `--session NAME`で終了するときに保存し、次に同じ名前で起動したときに元に戻す
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Session {
+    /// 開いていたファイルの絶対パス(開いた順)
+    pub files: Vec<String>,
+    /// タブページ
+    pub tabs: Vec<SessionTab>,
+    /// 表示していたタブページの位置
+    pub current_tab: usize,
+}
+
+impl Session {
+    /// セッションを保存するファイルのパスを取得する
+    /// # Arguments
+    /// * `name` - セッションの名前
+    /// # Notes
+    /// * 状態を保存するディレクトリの外に書き込まないように、パスの区切りを含む名前はエラーにする
+    fn path(name: &str) -> Result<PathBuf, std::io::Error> {
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{}: Invalid session name", name),
+            ));
+        }
+
+        let dir = crate::config::state_dir().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Could not determine the state directory",
+            )
+        })?;
+
+        Ok(dir.join("sessions").join(format!("{}.toml", name)))
+    }
+
+    /// 状態を保存するディレクトリの`sessions/NAME.toml`を読み込む
+    /// # Arguments
+    /// * `name` - セッションの名前
+    /// # Returns
+    /// * `Result<Option<Session>, std::io::Error>` - 読み込んだセッション(まだ保存していない場合はNone)
+    /// # Notes
+    /// * 保存したあとに削除されたファイルは取り除く
+    pub fn load(name: &str) -> Result<Option<Self>, std::io::Error> {
+        let path = Self::path(name)?;
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let mut session: Self = toml::from_str(&contents).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })?;
+        session.retain_files(|file| Path::new(file).exists());
+
+        Ok(Some(session).filter(|session| !session.files.is_empty()))
+    }
+
+    /// 状態を保存するディレクトリの`sessions/NAME.toml`に書き込む
+    /// # Arguments
+    /// * `name` - セッションの名前
+    /// # Returns
+    /// * `Result<(), std::io::Error>` - 書き込めなかった場合はエラー
+    pub fn save(&self, name: &str) -> Result<(), std::io::Error> {
+        let path = Self::path(name)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let contents = toml::to_string(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, contents)
+    }
+
+    /// 条件に合うファイルだけを残して、領域が表示するファイルの位置を付け直す
+    /// # Arguments
+    /// * `keep` - 残すファイルの条件
+    /// # Notes
+    /// * 残さなかったファイルを表示していた領域は閉じ、領域がなくなったタブページも閉じる
+    /// * 操作していた領域や表示していたタブページが範囲外になった場合は、最後のものにする
+    /// * タブページがなくなった場合は、最初のファイルを表示するタブページを作る
+    pub fn retain_files(&mut self, keep: impl Fn(&str) -> bool) {
+        let mut positions = Vec::new();
+        let mut files = Vec::new();
+        for file in std::mem::take(&mut self.files) {
+            let kept = keep(&file);
+            positions.push(kept.then_some(files.len()));
+            if kept {
+                files.push(file);
+            }
+        }
+        self.files = files;
+
+        for tab in self.tabs.iter_mut() {
+            tab.panes = tab
+                .panes
+                .iter()
+                .filter_map(|pane| {
+                    let file = positions.get(pane.file).copied().flatten()?;
+                    Some(SessionPane { file, ..*pane })
+                })
+                .collect();
+            tab.focused = tab.focused.min(tab.panes.len().saturating_sub(1));
+        }
+        self.tabs.retain(|tab| !tab.panes.is_empty());
+
+        if self.tabs.is_empty() && !self.files.is_empty() {
+            self.tabs.push(SessionTab {
+                panes: vec![SessionPane {
+                    file: 0,
+                    line: 1,
+                    column: 0,
+                }],
+                focused: 0,
+                direction: SplitDirection::Vertical,
+                ratio: 50,
+                scroll_lock: false,
+            });
+        }
+        self.current_tab = self.current_tab.min(self.tabs.len().saturating_sub(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pane(file: usize, line: usize) -> SessionPane {
+        SessionPane {
+            file,
+            line,
+            column: 0,
+        }
+    }
+
+    #[test]
+    fn test_retain_files() {
+        let mut session = Session {
+            files: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            tabs: vec![
+                SessionTab {
+                    panes: vec![pane(0, 10), pane(2, 20)],
+                    focused: 1,
+                    direction: SplitDirection::Horizontal,
+                    ratio: 30,
+                    scroll_lock: true,
+                },
+                SessionTab {
+                    panes: vec![pane(1, 5)],
+                    focused: 0,
+                    direction: SplitDirection::Vertical,
+                    ratio: 50,
+                    scroll_lock: false,
+                },
+            ],
+            current_tab: 1,
+        };
+
+        // 保存した形式から読み込み直せる
+        let contents = toml::to_string(&session).unwrap();
+        assert_eq!(toml::from_str::<Session>(&contents).unwrap(), session);
+
+        session.retain_files(|file| file != "b");
+        assert_eq!(session.files, vec!["a", "c"]);
+        assert_eq!(session.tabs.len(), 1);
+        assert_eq!(session.tabs[0].panes, vec![pane(0, 10), pane(1, 20)]);
+        assert_eq!(session.current_tab, 0);
+
+        session.retain_files(|file| file != "a");
+        assert_eq!(session.tabs[0].panes, vec![pane(0, 20)]);
+        assert_eq!(session.tabs[0].focused, 0);
+
+        session.retain_files(|_| false);
+        assert_eq!(session, Session::default());
+    }
+
+    #[test]
+    fn test_path() {
+        assert!(Session::path("").is_err());
+        assert!(Session::path("../review").is_err());
+        assert!(Session::path(".hidden").is_err());
+    }
+}
@@ -0,0 +1,166 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// シェルで実行したコマンドの結果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Output {
+    /// 標準出力
+    pub stdout: String,
+    /// 標準エラー出力
+    pub stderr: String,
+    /// コマンドが成功したか
+    pub success: bool,
+}
+
+impl Output {
+    /// 標準出力と標準エラー出力をつなげた文字列を取得する
+    /// # Notes
+    /// * 標準エラー出力は、標準出力の後ろに改行で区切って置く
+    pub fn combined(&self) -> String {
+        if self.stderr.is_empty() {
+            return self.stdout.clone();
+        }
+        if self.stdout.is_empty() || self.stdout.ends_with('\n') {
+            return format!("{}{}", self.stdout, self.stderr);
+        }
+        format!("{}\n{}", self.stdout, self.stderr)
+    }
+
+    /// コマンドが失敗した理由を表す文字列を取得する
+    /// # Notes
+    /// * 標準エラー出力の最初の行を使い、何も出力していない場合は終了したことだけを伝える
+    pub fn error_message(&self) -> String {
+        self.stderr
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .map_or_else(|| "Command failed".to_string(), str::to_string)
+    }
+}
+
+/// シェルでコマンドを実行して、出力を取得する
+/// # Arguments
+/// * `command` - 実行するコマンド(パイプやリダイレクトも使える)
+/// * `input` - コマンドの標準入力に渡す文字列(Noneの場合は何も渡さない)
+/// # Returns
+/// * `std::io::Result<Output>` - コマンドの出力(シェルを起動できない場合はエラー)
+/// # Notes
+/// * 端末の表示を崩さないように、標準出力と標準エラー出力はすべて受け取る
+pub fn run(command: &str, input: Option<&str>) -> std::io::Result<Output> {
+    let mut child = shell_command(command)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // 出力を読みながら書き込まないと、パイプがいっぱいになって止まるので別のスレッドで書き込む
+    let writer = match (child.stdin.take(), input) {
+        (Some(mut stdin), Some(input)) => {
+            let input = input.to_string();
+            // 出力を最後まで読まずに終了するコマンド(headなど)もあるので、書き込めなくても気にしない
+            Some(std::thread::spawn(move || {
+                let _ = stdin.write_all(input.as_bytes());
+            }))
+        }
+        _ => None,
+    };
+
+    let output = child.wait_with_output()?;
+    if let Some(writer) = writer {
+        let _ = writer.join();
+    }
+
+    Ok(Output {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        success: output.status.success(),
+    })
+}
+
+/// 環境変数で指定したエディタで、ファイルを指定した行から編集する
+/// # Arguments
+/// * `file` - 編集するファイル
+/// * `line_number` - 開く行番号(1から始まる)
+/// # Returns
+/// * `std::io::Result<bool>` - エディタが成功した場合はtrue(エディタを起動できない場合はエラー)
+/// # Notes
+/// * 端末を使うエディタが多いので、標準入出力はそのまま渡して終了するまで待つ
+/// * 呼び出す前に、端末を通常の状態に戻しておく必要がある
+pub fn edit(file: &str, line_number: usize) -> std::io::Result<bool> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    let args = editor_args(&editor, file, line_number);
+
+    let status = Command::new(&args[0]).args(&args[1..]).status()?;
+    Ok(status.success())
+}
+
+/// エディタを起動するコマンドライン引数を作成する
+/// # Arguments
+/// * `editor` - エディタのコマンド(`code --wait`のように引数を含んでもよい)
+/// * `file` - 編集するファイル
+/// * `line_number` - 開く行番号(1から始まる)
+/// # Notes
+/// * 多くのエディタが対応している`+行番号`で、開く行を指定する
+fn editor_args(editor: &str, file: &str, line_number: usize) -> Vec<String> {
+    editor
+        .split_whitespace()
+        .map(str::to_string)
+        .chain([format!("+{}", line_number), file.to_string()])
+        .collect()
+}
+
+/// OSのシェルでコマンドを実行するCommandを作成する
+fn shell_command(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").arg(command);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command);
+        shell
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run() {
+        let output = run("sort", Some("b\na\n")).unwrap();
+        assert_eq!(output.stdout, "a\nb\n");
+        assert!(output.success);
+
+        let output = run("echo oops >&2; exit 3", None).unwrap();
+        assert!(!output.success);
+        assert_eq!(output.error_message(), "oops");
+        assert_eq!(output.combined(), "oops\n");
+        assert_eq!(
+            run("printf a; echo b >&2", None).unwrap().combined(),
+            "a\nb\n"
+        );
+        assert_eq!(
+            run("exit 1", None).unwrap().error_message(),
+            "Command failed"
+        );
+    }
+
+    #[test]
+    fn test_editor_args() {
+        assert_eq!(editor_args("vi", "a.txt", 3), ["vi", "+3", "a.txt"]);
+        assert_eq!(
+            editor_args(" code  --wait ", "a b.txt", 1),
+            ["code", "--wait", "+1", "a b.txt"]
+        );
+    }
+}
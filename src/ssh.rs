@@ -0,0 +1,213 @@
+use std::{
+    fmt,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use crate::{http, loading};
+
+/// `user@host:path`の形式で指定した、SSHで接続するホストのファイル
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    /// 接続するホスト(`user@`を含む場合もある)
+    pub host: String,
+    /// ホストでのファイルのパス(相対パスはホームディレクトリから)
+    pub path: String,
+}
+
+impl Location {
+    /// `[user@]host:path`の形式の引数から作成する
+    /// # Arguments
+    /// * `arg` - コマンドライン引数などで指定したファイル名
+    /// # Returns
+    /// * `Option<Location>` - SSHのパスの場合はLocation、それ以外はNone
+    /// # Notes
+    /// * `scp`と同じように、最初の`:`より前に`/`がない場合をSSHのパスとする
+    /// * 同じ名前のローカルのファイルがある場合や、URL、Windowsのドライブ文字(`C:`)の場合は、SSHのパスとしない
+    /// * IPv6アドレスは`[::1]:path`のように括弧で囲む
+    pub fn parse(arg: &str) -> Option<Self> {
+        if http::is_url(arg) || std::path::Path::new(arg).exists() {
+            return None;
+        }
+
+        let (host, path) = match arg.find("]:") {
+            Some(index) if arg.starts_with('[') || arg.contains("@[") => {
+                (&arg[..index + 1], &arg[index + 2..])
+            }
+            _ => arg.split_once(':')?,
+        };
+        if host.chars().count() < 2
+            || path.is_empty()
+            || host.contains('/')
+            || host.contains(char::is_whitespace)
+        {
+            return None;
+        }
+
+        Some(Self {
+            host: host.to_string(),
+            path: path.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Location {
+    /// 指定したときと同じ`host:path`の形式で表示する
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.path)
+    }
+}
+
+/// ホストのファイルを読み込む
+/// # Arguments
+/// * `location` - 読み込むファイル
+/// * `interactive` - パスワードなどの入力を求めてもよい場合はtrue(画面を切り替える前だけ)
+/// # Returns
+/// * `std::io::Result<Vec<u8>>` - ファイルの内容
+/// # Notes
+/// * ファイルがない場合は`NotFound`、接続できない場合などはsshのエラーメッセージのエラーにする
+pub fn read(location: &Location, interactive: bool) -> std::io::Result<Vec<u8>> {
+    let mut child = ssh_command(
+        location,
+        interactive,
+        &format!("cat -- {}", quote(&location.path)),
+    )
+    .stdin(Stdio::null())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()?;
+
+    let name = location.to_string();
+    let contents = match child.stdout.take() {
+        Some(stdout) => loading::read_all(stdout, &name, None, interactive)?,
+        None => Vec::new(),
+    };
+    let output = child.wait_with_output()?;
+    check_status(&output)?;
+
+    Ok(contents)
+}
+
+/// ホストのファイルに書き込む
+/// # Arguments
+/// * `location` - 書き込むファイル
+/// * `contents` - 書き込む内容
+pub fn write(location: &Location, contents: &[u8]) -> std::io::Result<()> {
+    let mut child = ssh_command(location, false, &format!("cat > {}", quote(&location.path)))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // 接続できずに書き込めなかった場合は、sshのエラーメッセージを伝える
+    let written = match child.stdin.take() {
+        Some(mut stdin) => stdin.write_all(contents),
+        None => Ok(()),
+    };
+    let output = child.wait_with_output()?;
+    check_status(&output)?;
+    written
+}
+
+/// ホストにファイルがあるか
+/// # Arguments
+/// * `location` - 確認するファイル
+/// # Notes
+/// * 接続できない場合は、あるかどうか分からないのでエラーにする
+pub fn exists(location: &Location) -> std::io::Result<bool> {
+    let output = ssh_command(
+        location,
+        false,
+        &format!("test -e {}", quote(&location.path)),
+    )
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::piped())
+    .output()?;
+
+    match output.status.code() {
+        Some(0) => Ok(true),
+        Some(1) => Ok(false),
+        _ => check_status(&output).map(|_| false),
+    }
+}
+
+/// ホストでコマンドを実行するsshのCommandを作成する
+/// # Arguments
+/// * `location` - 接続するホストのファイル
+/// * `interactive` - パスワードなどの入力を求めてもよい場合はtrue
+/// * `command` - ホストで実行するコマンド
+/// # Notes
+/// * 画面を表示している間は入力を求められないので、鍵などで接続できない場合はすぐに失敗させる
+/// * sshはIPv6アドレスを括弧で囲まずに指定するので、括弧は取り除く
+fn ssh_command(location: &Location, interactive: bool, command: &str) -> Command {
+    let mut ssh = Command::new("ssh");
+    if !interactive {
+        ssh.args(["-o", "BatchMode=yes"]);
+    }
+    let host = location.host.replace(['[', ']'], "");
+    ssh.args(["--", host.as_str(), command]);
+    ssh
+}
+
+/// sshの終了状態から、失敗した場合はエラーを作成する
+fn check_status(output: &std::process::Output) -> std::io::Result<()> {
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let message = stderr
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or("ssh failed")
+        .to_string();
+    let kind = if message.contains("No such file or directory") {
+        std::io::ErrorKind::NotFound
+    } else {
+        std::io::ErrorKind::Other
+    };
+    Err(std::io::Error::new(kind, message))
+}
+
+/// ホストのシェルに渡すために、パスを引用符で囲む
+/// # Notes
+/// * ホームディレクトリを表す先頭の`~/`は、シェルが展開できるように囲まない
+fn quote(path: &str) -> String {
+    let (home, path) = match path.strip_prefix("~/") {
+        Some(path) => ("~/", path),
+        None => ("", path),
+    };
+    format!("{}'{}'", home, path.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let location = Location::parse("user@example.com:/var/log/app.log").unwrap();
+        assert_eq!(location.host, "user@example.com");
+        assert_eq!(location.path, "/var/log/app.log");
+        assert_eq!(location.to_string(), "user@example.com:/var/log/app.log");
+
+        let location = Location::parse("user@[::1]:notes.md").unwrap();
+        assert_eq!(location.host, "user@[::1]");
+        assert_eq!(location.path, "notes.md");
+
+        assert!(Location::parse("server:~/a.txt").is_some());
+        assert!(Location::parse("src/a:b.txt").is_none());
+        assert!(Location::parse("C:\\a.txt").is_none());
+        assert!(Location::parse("host:").is_none());
+        assert!(Location::parse("https://example.com/a.txt").is_none());
+        assert!(Location::parse("a.txt").is_none());
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("/var/log/app.log"), "'/var/log/app.log'");
+        assert_eq!(quote("it's.txt"), r"'it'\''s.txt'");
+        assert_eq!(quote("~/a b.txt"), "~/'a b.txt'");
+    }
+}
@@ -1,27 +1,72 @@
-use std::io::stdout;
+use unicode_width::UnicodeWidthStr;
 
-use crossterm::{
-    cursor::MoveTo,
-    queue,
-    style::{Attribute, Print, ResetColor},
-    QueueableCommand,
-};
+use std::{rc::Rc, time::Instant};
+
+use crate::buffer::Buffer;
+use crate::prompt::Message;
+use crate::screen;
+use crate::style::{ColorDepth, Style};
+
+/// ステータスバーの高さ
+pub const HEIGHT: u16 = 1;
+
+/// 表示するときに、操作しているファイルから項目の値を求める関数
+/// 値がない場合はNoneを返し、項目を表示しない
+pub type Provider = Rc<dyn Fn(&Buffer) -> Option<String>>;
+
+/// ステータスバーの項目の値
+#[derive(Clone)]
+enum ItemValue {
+    /// 決まった文字列
+    Text(String),
+    /// 表示するときに求める値
+    Provider(Provider),
+}
 
 /// ステータスバーの項目
+#[derive(Clone)]
 pub struct StatusBarItem {
     /// 項目名
     name: String,
     /// 項目の値
-    value: String,
+    value: ItemValue,
 }
 
 impl StatusBarItem {
     /// StatusBarItemを作成する
-    pub fn new(name: String, mut value: String) -> Self {
-        // 表示できるのは一行のみなので、改行を全て" "(スペース)に置換する
-        value = value.replace('\n', " ");
+    pub fn new(name: String, value: String) -> Self {
+        Self {
+            name,
+            value: ItemValue::Text(value),
+        }
+    }
+
+    /// 表示するときに値を求める項目を作成する
+    /// # Arguments
+    /// * `name` - 項目名
+    /// * `provider` - 操作しているファイルから、項目の値を求める関数
+    /// # Notes
+    /// * 行番号のような、操作するたびに変わる値を、変わるたびに項目を作り直さずに表示するために使う
+    pub fn provider(name: String, provider: impl Fn(&Buffer) -> Option<String> + 'static) -> Self {
+        Self {
+            name,
+            value: ItemValue::Provider(Rc::new(provider)),
+        }
+    }
+
+    /// 項目の値を取得する
+    /// # Arguments
+    /// * `buffer` - 操作しているファイル
+    /// # Returns
+    /// * `Option<String>` - 項目の値(表示しない場合はNone)
+    pub fn value(&self, buffer: &Buffer) -> Option<String> {
+        let value = match &self.value {
+            ItemValue::Text(value) => Some(value.clone()),
+            ItemValue::Provider(provider) => provider(buffer),
+        };
 
-        Self { name, value }
+        // 表示できるのは一行のみなので、改行を全て" "(スペース)に置換する
+        value.map(|value| value.replace('\n', " "))
     }
 }
 
@@ -29,6 +74,8 @@ impl StatusBarItem {
 pub struct StatusBar {
     /// ステータスバーの項目
     items: Vec<StatusBarItem>,
+    /// 表示する項目の名前と順番(Noneの場合は、すべての項目を追加した順に表示する)
+    pub layout: Option<Vec<String>>,
     /// 幅
     pub width: u16,
     /// 高さ
@@ -37,16 +84,35 @@ pub struct StatusBar {
     pub x_start: u16,
     /// 開始位置(Y座標)
     pub y_start: u16,
+    /// 装飾
+    pub style: Style,
+    /// エラーを知らせるメッセージの装飾
+    pub error_style: Style,
+    /// 項目の代わりに表示するメッセージ(表示していない場合はNone)
+    message: Option<Message>,
+    /// 端末で表示できる色数(`ColorDepth::None`の場合は装飾しない)
+    pub color_depth: ColorDepth,
 }
 
 impl StatusBar {
     pub fn new(width: u16, height: u16, x_start: u16, y_start: u16) -> Self {
         Self {
             items: vec![],
+            layout: None,
             width,
             height,
             x_start,
             y_start,
+            style: Style {
+                reverse: true,
+                ..Default::default()
+            },
+            error_style: Style {
+                reverse: true,
+                ..Default::default()
+            },
+            message: None,
+            color_depth: ColorDepth::Ansi16,
         }
     }
 
@@ -62,40 +128,164 @@ impl StatusBar {
         self.items.push(item);
     }
 
-    pub fn print(&self) {
-        stdout().queue(MoveTo(0, self.y_start)).unwrap();
+    /// 項目を取り除く
+    /// # Arguments
+    /// * `name` - 項目名
+    pub fn remove_item(&mut self, name: &str) {
+        self.items.retain(|item| item.name != name);
+    }
 
-        // ステータスバーの文字色と背景色を反転する
-        queue!(stdout(), Print(Attribute::Reverse)).unwrap();
-        // 1行すべてを背景色で塗りつぶす
-        queue!(stdout(), Print(" ".repeat(self.width as usize))).unwrap();
+    /// 項目の代わりに、メッセージを表示するようにする
+    /// # Arguments
+    /// * `message` - 表示するメッセージ
+    /// # Notes
+    /// * 前に表示していたメッセージは、表示をやめる時刻より前でも置き換える
+    pub fn set_message(&mut self, message: Message) {
+        self.message = Some(message);
+    }
+
+    /// 表示しているメッセージの、表示をやめる時刻を取得する
+    pub fn message_expires_at(&self) -> Option<Instant> {
+        self.message.as_ref().map(|message| message.expires_at)
+    }
+
+    /// 表示をやめる時刻を過ぎたメッセージを取り除く
+    /// # Returns
+    /// * `bool` - メッセージを取り除いた場合はtrue、それ以外はfalse
+    pub fn clear_expired_message(&mut self) -> bool {
+        if self.message.as_ref().is_some_and(Message::is_expired) {
+            self.message = None;
+            return true;
+        }
 
-        // カーソルが移動したので、行の先頭に移動する
-        stdout().queue(MoveTo(0, self.y_start)).unwrap();
+        false
+    }
+
+    /// 表示する項目を、表示する順番に取得する
+    /// # Notes
+    /// * 表示する項目を指定している場合は、指定していない項目は表示しない
+    fn visible_items(&self) -> Vec<&StatusBarItem> {
+        match &self.layout {
+            Some(layout) => layout
+                .iter()
+                .filter_map(|name| self.items.iter().find(|item| &item.name == name))
+                .collect(),
+            None => self.items.iter().collect(),
+        }
+    }
+
+    /// ステータスバーを表示する
+    /// # Arguments
+    /// * `buffer` - 操作しているファイル(表示するときに値を求める項目に使う)
+    pub fn print(&self, buffer: &Buffer) {
+        // メッセージを表示している間は、項目の代わりにメッセージを表示する
+        if let Some(message) = &self.message {
+            let style = if message.is_error {
+                self.error_style
+            } else {
+                self.style
+            };
+            self.print_styled(&message.text.replace('\n', " "), style);
+            return;
+        }
 
         // ステータスバーの項目を表示する
         // 項目の間には" "を表示する
-        self.items.iter().for_each(|item| {
-            queue!(stdout(), Print(item.value.as_str())).unwrap();
+        let text = self
+            .visible_items()
+            .iter()
+            .filter_map(|item| item.value(buffer))
+            .collect::<Vec<_>>()
+            .join(" ");
 
-            // 最後の項目以外は" "を表示する
-            if item.name != self.items.last().unwrap().name.as_str() {
-                queue!(stdout(), Print(" ")).unwrap();
-            }
-        });
+        self.print_text(&text);
+    }
+
+    /// ステータスバーの項目の代わりに、入力中のコマンドなどの文字列を表示する
+    /// # Arguments
+    /// * `text` - 表示する文字列
+    pub fn print_text(&self, text: &str) {
+        self.print_styled(text, self.style);
+    }
+
+    /// 指定した装飾で、ステータスバーに文字列を表示する
+    fn print_styled(&self, text: &str, style: Style) {
+        // ステータスバーの領域すべてを背景色で塗りつぶすように、空白で埋める
+        for y in 0..self.height {
+            let line = if y == 0 { text } else { "" };
+            let padding = (self.width as usize).saturating_sub(line.width());
 
-        // ステータスバーの背景色をリセットする
-        queue!(stdout(), ResetColor).unwrap();
+            // 項目が変わっていない場合は、ちらつかないように出力しない
+            screen::print_row(
+                self.x_start,
+                self.y_start + y,
+                self.width,
+                &style.paint(
+                    &format!("{}{}", line, " ".repeat(padding)),
+                    self.color_depth,
+                ),
+            )
+            .unwrap();
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::buffer::Settings;
+
+    fn buffer(contents: &str) -> Buffer {
+        let settings = Settings {
+            options: crate::contents::DisplayOptions::default(),
+            tab_width: None,
+            wrap: None,
+            config: crate::config::Config::default(),
+        };
+        Buffer::open(None, contents.to_string(), &settings)
+    }
+
+    #[test]
+    fn test_print() {
+        let mut status_bar = StatusBar::new(12, 2, 0, 1);
+        status_bar.add_item(StatusBarItem::new("a".to_string(), "UTF-8".to_string()));
+        status_bar.add_item(StatusBarItem::new("b".to_string(), "ln 1".to_string()));
+
+        let mut buffer = buffer("a\nb\n");
+
+        // 端末の代わりにメモリ上に表示して、表示した内容をそのまま見比べる
+        let screen = crate::screen::render_to_string(12, 3, || {
+            status_bar.print(&buffer);
+            Ok(())
+        });
+        assert_eq!(screen.unwrap(), "\nUTF-8 ln 1\n");
+
+        // 表示するときに値を求める項目は、表示するたびに操作しているファイルから求める
+        // 値がない項目は表示しない
+        status_bar.add_item(StatusBarItem::provider("b".to_string(), |buffer| {
+            Some(format!("ln {}", buffer.cursor_y + 1))
+        }));
+        status_bar.add_item(StatusBarItem::provider("c".to_string(), |_| None));
+        buffer.cursor_y = 1;
+        let screen = crate::screen::render_to_string(12, 3, || {
+            status_bar.print(&buffer);
+            Ok(())
+        });
+        assert_eq!(screen.unwrap(), "\nUTF-8 ln 2\n");
+
+        status_bar.set_message(Message::error("error".to_string()));
+        let screen = crate::screen::render_to_string(12, 3, || {
+            status_bar.print(&buffer);
+            Ok(())
+        });
+        assert_eq!(screen.unwrap(), "\nerror\n");
+    }
 
     #[test]
     fn test_status_bar() {
         let mut status_bar = StatusBar::new(10, 1, 0, 0);
+        let buffer = buffer("");
+        let value = |item: &StatusBarItem| item.value(&buffer).unwrap();
 
         // 項目を追加することができるか確認する
         let item1 = StatusBarItem::new("item1".to_string(), "value1".to_string());
@@ -106,9 +296,9 @@ mod tests {
 
         assert_eq!(status_bar.items.len(), 2);
         assert_eq!(status_bar.items[0].name, "item1");
-        assert_eq!(status_bar.items[0].value, "value1");
+        assert_eq!(value(&status_bar.items[0]), "value1");
         assert_eq!(status_bar.items[1].name, "item2");
-        assert_eq!(status_bar.items[1].value, "value2");
+        assert_eq!(value(&status_bar.items[1]), "value2");
 
         // 過去に同じ名前の項目がある場合は上書きするか確認する
         let item3 = StatusBarItem::new("item1".to_string(), "value3".to_string());
@@ -116,9 +306,9 @@ mod tests {
 
         assert_eq!(status_bar.items.len(), 2);
         assert_eq!(status_bar.items[0].name, "item1");
-        assert_eq!(status_bar.items[0].value, "value3");
+        assert_eq!(value(&status_bar.items[0]), "value3");
         assert_eq!(status_bar.items[1].name, "item2");
-        assert_eq!(status_bar.items[1].value, "value2");
+        assert_eq!(value(&status_bar.items[1]), "value2");
 
         // 表示できるのは一行のみなので、改行を全て" "(スペース)に置換していることを確認する
         let item4 = StatusBarItem::new("item4".to_string(), "value4\nvalue4".to_string());
@@ -126,10 +316,56 @@ mod tests {
 
         assert_eq!(status_bar.items.len(), 3);
         assert_eq!(status_bar.items[0].name, "item1");
-        assert_eq!(status_bar.items[0].value, "value3");
+        assert_eq!(value(&status_bar.items[0]), "value3");
         assert_eq!(status_bar.items[1].name, "item2");
-        assert_eq!(status_bar.items[1].value, "value2");
+        assert_eq!(value(&status_bar.items[1]), "value2");
         assert_eq!(status_bar.items[2].name, "item4");
-        assert_eq!(status_bar.items[2].value, "value4 value4");
+        assert_eq!(value(&status_bar.items[2]), "value4 value4");
+
+        // 項目を取り除くことができるか確認する
+        status_bar.remove_item("item2");
+        status_bar.remove_item("unknown");
+
+        assert_eq!(status_bar.items.len(), 2);
+        assert_eq!(status_bar.items[0].name, "item1");
+        assert_eq!(status_bar.items[1].name, "item4");
+    }
+
+    #[test]
+    fn test_layout() {
+        let mut status_bar = StatusBar::new(10, 1, 0, 0);
+        for name in ["encoding", "file", "line"] {
+            status_bar.add_item(StatusBarItem::new(name.to_string(), name.to_string()));
+        }
+
+        // 指定した順番で、指定した項目だけを表示する
+        status_bar.layout = Some(vec![
+            "line".to_string(),
+            "unknown".to_string(),
+            "encoding".to_string(),
+        ]);
+        let names: Vec<&str> = status_bar
+            .visible_items()
+            .iter()
+            .map(|item| item.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["line", "encoding"]);
+    }
+
+    #[test]
+    fn test_message() {
+        let mut status_bar = StatusBar::new(10, 1, 0, 0);
+        assert_eq!(status_bar.message_expires_at(), None);
+
+        // 表示をやめる時刻を過ぎるまでは取り除かない
+        status_bar.set_message(Message::info("saved".to_string()));
+        assert!(!status_bar.clear_expired_message());
+        assert!(status_bar.message_expires_at().is_some());
+
+        let mut message = Message::error("failed".to_string());
+        message.expires_at = Instant::now();
+        status_bar.set_message(message);
+        assert!(status_bar.clear_expired_message());
+        assert_eq!(status_bar.message_expires_at(), None);
     }
 }
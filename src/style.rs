@@ -0,0 +1,890 @@
+use std::{ops::Range, path::PathBuf};
+
+use serde::Deserialize;
+
+/// 色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Color {
+    /// RGBで指定する色
+    Rgb(u8, u8, u8),
+    /// 256色のパレットの番号で指定する色(0から15は端末の基本色)
+    Indexed(u8),
+}
+
+/// 端末で表示できる色数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorDepth {
+    /// 色も装飾も使わない
+    None,
+    /// 16色
+    Ansi16,
+    /// 256色
+    Ansi256,
+    /// 1677万色(トゥルーカラー)
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// 環境変数から端末で表示できる色数を推測する
+    /// # Returns
+    /// * `ColorDepth` - 端末で表示できる色数
+    /// # Notes
+    /// * `COLORTERM`が`truecolor`または`24bit`の場合はトゥルーカラー
+    /// * `TERM`に`256color`が含まれる場合は256色
+    /// * それ以外は16色
+    pub fn detect() -> Self {
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        let term = std::env::var("TERM").unwrap_or_default();
+        Self::from_env(&colorterm, &term)
+    }
+
+    /// 環境変数の値から端末で表示できる色数を推測する
+    fn from_env(colorterm: &str, term: &str) -> Self {
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            Self::TrueColor
+        } else if term.contains("256color") {
+            Self::Ansi256
+        } else {
+            Self::Ansi16
+        }
+    }
+}
+
+/// 16色の基本色のRGB値(xtermの既定値)
+const ANSI16_PALETTE: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// 256色のパレットのうち、6x6x6の色立方体の各成分の値
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// 16色の基本色の名前
+const ANSI16_NAMES: [&str; 16] = [
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "bright_black",
+    "bright_red",
+    "bright_green",
+    "bright_yellow",
+    "bright_blue",
+    "bright_magenta",
+    "bright_cyan",
+    "bright_white",
+];
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    /// 文字列から色を作成する
+    /// `#rrggbb`形式、256色のパレットの番号(`"208"`など)、16色の基本色の名前(`"red"`など)に対応する
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if let Some(hex) = value.strip_prefix('#') {
+            let component = |i: usize| {
+                hex.get(i..i + 2)
+                    .and_then(|c| u8::from_str_radix(c, 16).ok())
+            };
+            return match (hex.len(), component(0), component(2), component(4)) {
+                (6, Some(r), Some(g), Some(b)) => Ok(Self::Rgb(r, g, b)),
+                _ => Err(format!("invalid color: {}", value)),
+            };
+        }
+
+        if let Ok(i) = value.parse::<u8>() {
+            return Ok(Self::Indexed(i));
+        }
+
+        ANSI16_NAMES
+            .iter()
+            .position(|name| *name == value)
+            .map(|i| Self::Indexed(i as u8))
+            .ok_or_else(|| format!("invalid color: {}", value))
+    }
+}
+
+impl Color {
+    /// 色をRGB値に変換する
+    fn to_rgb(self) -> (u8, u8, u8) {
+        match self {
+            Self::Rgb(r, g, b) => (r, g, b),
+            Self::Indexed(i @ 0..=15) => ANSI16_PALETTE[i as usize],
+            Self::Indexed(i @ 16..=231) => {
+                let i = i - 16;
+                (
+                    CUBE_LEVELS[(i / 36) as usize],
+                    CUBE_LEVELS[(i / 6 % 6) as usize],
+                    CUBE_LEVELS[(i % 6) as usize],
+                )
+            }
+            Self::Indexed(i) => {
+                let level = 8 + (i - 232) * 10;
+                (level, level, level)
+            }
+        }
+    }
+
+    /// 256色のパレットの番号に変換する
+    fn to_ansi256(self) -> u8 {
+        let (r, g, b) = match self {
+            Self::Indexed(i) => return i,
+            Self::Rgb(r, g, b) => (r, g, b),
+        };
+
+        // 無彩色はグレースケールの部分を使う
+        if r == g && g == b {
+            return match r {
+                0..=7 => 16,
+                249..=255 => 231,
+                _ => 232 + ((r as u16 - 8) * 24 / 247) as u8,
+            };
+        }
+
+        let level = |v: u8| -> u8 {
+            CUBE_LEVELS
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, &l)| (l as i16 - v as i16).abs())
+                .map(|(i, _)| i as u8)
+                .unwrap_or(0)
+        };
+        16 + 36 * level(r) + 6 * level(g) + level(b)
+    }
+
+    /// 16色の基本色の番号に変換する
+    fn to_ansi16(self) -> u8 {
+        if let Self::Indexed(i @ 0..=15) = self {
+            return i;
+        }
+
+        let (r, g, b) = self.to_rgb();
+        let distance = |(pr, pg, pb): (u8, u8, u8)| {
+            let dr = pr as i32 - r as i32;
+            let dg = pg as i32 - g as i32;
+            let db = pb as i32 - b as i32;
+            dr * dr + dg * dg + db * db
+        };
+        ANSI16_PALETTE
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, &color)| distance(color))
+            .map(|(i, _)| i as u8)
+            .unwrap_or(0)
+    }
+
+    /// 色を指定するSGRのパラメータを取得する
+    /// # Arguments
+    /// * `depth` - 端末で表示できる色数
+    /// * `is_background` - 背景色の場合はtrue、文字色の場合はfalse
+    fn sgr_parameter(self, depth: ColorDepth, is_background: bool) -> Option<String> {
+        let base = if is_background { 40 } else { 30 };
+        match depth {
+            ColorDepth::None => None,
+            ColorDepth::TrueColor => {
+                let (r, g, b) = self.to_rgb();
+                Some(format!("{};2;{};{};{}", base + 8, r, g, b))
+            }
+            ColorDepth::Ansi256 => Some(format!("{};5;{}", base + 8, self.to_ansi256())),
+            ColorDepth::Ansi16 => {
+                let i = self.to_ansi16();
+                if i < 8 {
+                    Some((base + i as u16).to_string())
+                } else {
+                    // 明るい色は90番台(背景色は100番台)を使う
+                    Some((base + 60 + (i - 8) as u16).to_string())
+                }
+            }
+        }
+    }
+}
+
+/// 文字の装飾
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Style {
+    /// 文字色
+    pub foreground: Option<Color>,
+    /// 背景色
+    pub background: Option<Color>,
+    /// 太字
+    pub bold: bool,
+    /// 薄い色
+    pub dim: bool,
+    /// 斜体
+    pub italic: bool,
+    /// 下線
+    pub underline: bool,
+    /// 文字色と背景色の反転
+    pub reverse: bool,
+}
+
+impl Style {
+    /// 装飾に別の装飾を重ねる
+    /// # Arguments
+    /// * `other` - 上に重ねる装飾
+    /// # Returns
+    /// * `Style` - `other`で指定した色や属性を加えた装飾(`other`で指定していない色はそのまま)
+    pub fn merge(&self, other: &Style) -> Style {
+        Style {
+            foreground: other.foreground.or(self.foreground),
+            background: other.background.or(self.background),
+            bold: self.bold || other.bold,
+            dim: self.dim || other.dim,
+            italic: self.italic || other.italic,
+            underline: self.underline || other.underline,
+            reverse: self.reverse || other.reverse,
+        }
+    }
+
+    /// 装飾を開始するエスケープシーケンスを取得する
+    /// # Arguments
+    /// * `depth` - 端末で表示できる色数
+    /// # Returns
+    /// * `String` - 装飾を開始するエスケープシーケンス(装飾しない場合は空文字列)
+    pub fn start(&self, depth: ColorDepth) -> String {
+        if depth == ColorDepth::None {
+            return String::new();
+        }
+
+        let mut parameters = Vec::new();
+        if self.bold {
+            parameters.push("1".to_string());
+        }
+        if self.dim {
+            parameters.push("2".to_string());
+        }
+        if self.italic {
+            parameters.push("3".to_string());
+        }
+        if self.underline {
+            parameters.push("4".to_string());
+        }
+        if self.reverse {
+            parameters.push("7".to_string());
+        }
+        if let Some(parameter) = self
+            .foreground
+            .and_then(|color| color.sgr_parameter(depth, false))
+        {
+            parameters.push(parameter);
+        }
+        if let Some(parameter) = self
+            .background
+            .and_then(|color| color.sgr_parameter(depth, true))
+        {
+            parameters.push(parameter);
+        }
+
+        if parameters.is_empty() {
+            String::new()
+        } else {
+            format!("\x1b[{}m", parameters.join(";"))
+        }
+    }
+
+    /// 装飾を終了するエスケープシーケンスを取得する
+    /// 元の文字列の装飾を崩さないように、リセットではなくこの装飾で指定した属性だけを元に戻す
+    /// # Arguments
+    /// * `depth` - 端末で表示できる色数
+    /// # Returns
+    /// * `String` - 装飾を終了するエスケープシーケンス(装飾しない場合は空文字列)
+    pub fn end(&self, depth: ColorDepth) -> String {
+        if depth == ColorDepth::None {
+            return String::new();
+        }
+
+        let mut parameters = Vec::new();
+        if self.bold || self.dim {
+            parameters.push("22");
+        }
+        if self.italic {
+            parameters.push("23");
+        }
+        if self.underline {
+            parameters.push("24");
+        }
+        if self.reverse {
+            parameters.push("27");
+        }
+        if self.foreground.is_some() {
+            parameters.push("39");
+        }
+        if self.background.is_some() {
+            parameters.push("49");
+        }
+
+        if parameters.is_empty() {
+            String::new()
+        } else {
+            format!("\x1b[{}m", parameters.join(";"))
+        }
+    }
+
+    /// 文字列を装飾する
+    /// # Arguments
+    /// * `s` - 装飾する文字列
+    /// * `depth` - 端末で表示できる色数
+    /// # Returns
+    /// * `String` - 装飾した文字列
+    /// # Examples
+    /// ```
+    /// let style = Style { dim: true, ..Default::default() };
+    /// assert_eq!(style.paint("abc", ColorDepth::Ansi16), "\x1b[2mabc\x1b[22m");
+    /// ```
+    pub fn paint(&self, s: &str, depth: ColorDepth) -> String {
+        format!("{}{}{}", self.start(depth), s, self.end(depth))
+    }
+}
+
+/// 文字列の一部の装飾
+/// 構文の強調表示などに使う
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Highlight {
+    /// 装飾する範囲(元の文字列でのバイト単位の位置)
+    pub range: Range<usize>,
+    /// 装飾
+    pub style: Style,
+}
+
+impl Highlight {
+    /// 強調表示に別の強調表示を重ねる
+    /// # Arguments
+    /// * `base` - 下になる強調表示(開始位置の昇順)
+    /// * `overlays` - 上に重ねる強調表示(開始位置の昇順で、互いに重ならない)
+    /// # Returns
+    /// * `Vec<Highlight>` - 重なる部分は`overlays`の装飾を使った、互いに重ならない強調表示(開始位置の昇順)
+    pub fn overlay(base: Vec<Highlight>, overlays: Vec<Highlight>) -> Vec<Highlight> {
+        if overlays.is_empty() {
+            return base;
+        }
+
+        let mut result = Vec::new();
+        for highlight in base {
+            // 重なる部分を除いた残りを追加する
+            let mut start = highlight.range.start;
+            let first = overlays.partition_point(|o| o.range.end <= start);
+            for overlay in overlays[first..]
+                .iter()
+                .take_while(|o| o.range.start < highlight.range.end)
+            {
+                if start < overlay.range.start {
+                    result.push(Highlight {
+                        range: start..overlay.range.start,
+                        style: highlight.style,
+                    });
+                }
+                start = start.max(overlay.range.end);
+            }
+            if start < highlight.range.end {
+                result.push(Highlight {
+                    range: start..highlight.range.end,
+                    style: highlight.style,
+                });
+            }
+        }
+        result.extend(overlays);
+        result.sort_by_key(|h| h.range.start);
+
+        result
+    }
+
+    /// 範囲の中の強調表示に装飾を重ね、強調表示のない部分にはその装飾を付ける
+    /// # Arguments
+    /// * `base` - 強調表示(開始位置の昇順で、互いに重ならない)
+    /// * `range` - 装飾を重ねる範囲
+    /// * `style` - 重ねる装飾
+    /// # Returns
+    /// * `Vec<Highlight>` - 範囲の中を隙間なく装飾した、互いに重ならない強調表示(開始位置の昇順)
+    /// # Notes
+    /// * `overlay`と異なり、範囲の中の強調表示の色や属性を残す
+    pub fn fill(base: Vec<Highlight>, range: Range<usize>, style: Style) -> Vec<Highlight> {
+        if range.is_empty() {
+            return base;
+        }
+
+        let mut result = Vec::new();
+        // 範囲の中で、まだ装飾していない部分の開始位置
+        let mut position = range.start;
+        for highlight in base {
+            if highlight.range.end <= range.start || highlight.range.start >= range.end {
+                result.push(highlight);
+                continue;
+            }
+
+            let start = highlight.range.start.max(range.start);
+            let end = highlight.range.end.min(range.end);
+            if highlight.range.start < start {
+                result.push(Highlight {
+                    range: highlight.range.start..start,
+                    style: highlight.style,
+                });
+            }
+            if position < start {
+                result.push(Highlight {
+                    range: position..start,
+                    style,
+                });
+            }
+            result.push(Highlight {
+                range: start..end,
+                style: highlight.style.merge(&style),
+            });
+            if end < highlight.range.end {
+                result.push(Highlight {
+                    range: end..highlight.range.end,
+                    style: highlight.style,
+                });
+            }
+            position = end;
+        }
+        if position < range.end {
+            result.push(Highlight {
+                range: position..range.end,
+                style,
+            });
+        }
+        result.sort_by_key(|h| h.range.start);
+
+        result
+    }
+}
+
+/// 画面の各部分の装飾
+/// テーマファイルで指定しなかった部分は、既定のテーマの装飾を使う
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    /// 行番号
+    pub line_number: Style,
+    /// 空白文字や制御文字を表す記号
+    pub special_char: Style,
+    /// 改ページを表す罫線
+    pub page_break: Style,
+    /// コメント(構文解析をしない場合は行コメントだけ)
+    pub comment: Style,
+    /// 構文解析した文字列や文字
+    pub string: Style,
+    /// 構文解析した数値や真偽値
+    pub number: Style,
+    /// 構文解析した型の名前
+    pub type_name: Style,
+    /// 構文解析したキーワード(`fn`や`let`など)
+    pub keyword: Style,
+    /// 強調表示する行末の空白
+    pub trailing_whitespace: Style,
+    /// Markdownの見出し
+    pub heading: Style,
+    /// Markdownのコード
+    pub code: Style,
+    /// 追加した行の記号
+    pub diff_added: Style,
+    /// 変更した行の記号
+    pub diff_modified: Style,
+    /// 削除した行の記号
+    pub diff_removed: Style,
+    /// 行に付けた印の名前
+    pub mark: Style,
+    /// 差分で追加した単語
+    pub diff_added_word: Style,
+    /// 差分で削除した単語
+    pub diff_removed_word: Style,
+    /// git blameなどの行の注釈
+    pub annotation: Style,
+    /// 一覧で選択している項目
+    pub selection: Style,
+    /// ファイルを探すときに、入力した文字と一致した文字
+    pub finder_match: Style,
+    /// 対応する括弧
+    pub matching_bracket: Style,
+    /// 操作する位置がある行
+    pub cursor_line: Style,
+    /// タブバー
+    pub tab_bar: Style,
+    /// タブバーの、表示しているタブページ
+    pub tab_bar_current: Style,
+    /// 分割したエディタ領域の境界線
+    pub separator: Style,
+    /// ステータスバー
+    pub status_bar: Style,
+    /// ステータスバーに表示する、エラーを知らせるメッセージ
+    pub error: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        let dim = Style {
+            dim: true,
+            ..Default::default()
+        };
+
+        Self {
+            line_number: dim,
+            special_char: dim,
+            page_break: dim,
+            comment: dim,
+            string: Style {
+                foreground: Some(Color::Indexed(2)),
+                ..Default::default()
+            },
+            number: Style {
+                foreground: Some(Color::Indexed(3)),
+                ..Default::default()
+            },
+            type_name: Style {
+                foreground: Some(Color::Indexed(6)),
+                ..Default::default()
+            },
+            keyword: Style {
+                foreground: Some(Color::Indexed(5)),
+                ..Default::default()
+            },
+            trailing_whitespace: Style {
+                background: Some(Color::Indexed(1)),
+                ..Default::default()
+            },
+            heading: Style {
+                bold: true,
+                ..Default::default()
+            },
+            code: Style {
+                foreground: Some(Color::Indexed(6)),
+                ..Default::default()
+            },
+            diff_added: Style {
+                foreground: Some(Color::Indexed(2)),
+                ..Default::default()
+            },
+            diff_modified: Style {
+                foreground: Some(Color::Indexed(3)),
+                ..Default::default()
+            },
+            diff_removed: Style {
+                foreground: Some(Color::Indexed(1)),
+                ..Default::default()
+            },
+            mark: Style {
+                bold: true,
+                foreground: Some(Color::Indexed(5)),
+                ..Default::default()
+            },
+            diff_added_word: Style {
+                foreground: Some(Color::Indexed(2)),
+                reverse: true,
+                ..Default::default()
+            },
+            diff_removed_word: Style {
+                foreground: Some(Color::Indexed(1)),
+                reverse: true,
+                ..Default::default()
+            },
+            annotation: dim,
+            selection: Style {
+                reverse: true,
+                ..Default::default()
+            },
+            finder_match: Style {
+                bold: true,
+                foreground: Some(Color::Indexed(3)),
+                ..Default::default()
+            },
+            matching_bracket: Style {
+                bold: true,
+                reverse: true,
+                ..Default::default()
+            },
+            cursor_line: Style {
+                underline: true,
+                ..Default::default()
+            },
+            tab_bar: Style {
+                reverse: true,
+                ..Default::default()
+            },
+            tab_bar_current: Style {
+                bold: true,
+                ..Default::default()
+            },
+            separator: dim,
+            status_bar: Style {
+                reverse: true,
+                ..Default::default()
+            },
+            error: Style {
+                bold: true,
+                foreground: Some(Color::Indexed(7)),
+                background: Some(Color::Indexed(1)),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl Theme {
+    /// 名前またはパスを指定してテーマを読み込む
+    /// # Arguments
+    /// * `name` - テーマの名前、またはテーマファイルのパス
+    /// # Returns
+    /// * `Result<Theme, std::io::Error>` - 読み込んだテーマ
+    /// # Notes
+    /// * `default`は組み込みの既定のテーマ
+    /// * 名前の場合は、設定ディレクトリの`themes/<name>.toml`を読み込む
+    /// * `/`を含むか`.toml`で終わる場合は、パスとして扱う
+    pub fn load(name: &str) -> Result<Self, std::io::Error> {
+        if name == "default" {
+            return Ok(Self::default());
+        }
+
+        let path = if name.contains('/') || name.ends_with(".toml") {
+            PathBuf::from(name)
+        } else {
+            crate::config::config_dir()
+                .ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::NotFound,
+                        "Could not determine the configuration directory",
+                    )
+                })?
+                .join("themes")
+                .join(format!("{}.toml", name))
+        };
+
+        let contents = std::fs::read_to_string(&path).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{}: No such theme", path.display()),
+            )
+        })?;
+
+        Self::parse(&contents).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })
+    }
+
+    /// TOML形式の文字列からテーマを作成する
+    fn parse(contents: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_depth_from_env() {
+        assert_eq!(
+            ColorDepth::from_env("truecolor", "xterm"),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(
+            ColorDepth::from_env("", "xterm-256color"),
+            ColorDepth::Ansi256
+        );
+        assert_eq!(ColorDepth::from_env("", "xterm"), ColorDepth::Ansi16);
+    }
+
+    #[test]
+    /// 端末で表示できる色数に合わせて、色を減らす
+    fn test_color_downgrade() {
+        let orange = Color::Rgb(255, 135, 0);
+        assert_eq!(
+            orange.sgr_parameter(ColorDepth::TrueColor, false),
+            Some("38;2;255;135;0".to_string())
+        );
+        assert_eq!(
+            orange.sgr_parameter(ColorDepth::Ansi256, false),
+            Some("38;5;208".to_string())
+        );
+        // 最も近い基本色は明るい黄色ではなく黄色
+        assert_eq!(
+            orange.sgr_parameter(ColorDepth::Ansi16, true),
+            Some("43".to_string())
+        );
+
+        // グレースケール
+        assert_eq!(Color::Rgb(128, 128, 128).to_ansi256(), 243);
+
+        // 基本色はそのまま使う
+        assert_eq!(
+            Color::Indexed(9).sgr_parameter(ColorDepth::Ansi16, false),
+            Some("91".to_string())
+        );
+        assert_eq!(
+            Color::Indexed(9).sgr_parameter(ColorDepth::None, false),
+            None
+        );
+    }
+
+    #[test]
+    fn test_color_try_from() {
+        assert_eq!(
+            Color::try_from("#ff8700".to_string()),
+            Ok(Color::Rgb(255, 135, 0))
+        );
+        assert_eq!(Color::try_from("208".to_string()), Ok(Color::Indexed(208)));
+        assert_eq!(
+            Color::try_from("bright_red".to_string()),
+            Ok(Color::Indexed(9))
+        );
+        assert!(Color::try_from("#ff87".to_string()).is_err());
+        assert!(Color::try_from("orange".to_string()).is_err());
+    }
+
+    #[test]
+    /// 指定しなかった部分は既定のテーマの装飾を使う
+    fn test_theme_parse() {
+        let theme = Theme::parse(
+            r##"
+            [line_number]
+            foreground = "#808080"
+
+            [status_bar]
+            foreground = "black"
+            background = "yellow"
+            bold = true
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(
+            theme.line_number,
+            Style {
+                foreground: Some(Color::Rgb(128, 128, 128)),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            theme.status_bar,
+            Style {
+                foreground: Some(Color::Indexed(0)),
+                background: Some(Color::Indexed(3)),
+                bold: true,
+                ..Default::default()
+            }
+        );
+        assert_eq!(theme.page_break, Theme::default().page_break);
+
+        assert!(Theme::parse("[line_number]\nforeground = \"orange\"").is_err());
+    }
+
+    #[test]
+    fn test_highlight_overlay() {
+        let highlight = |range: Range<usize>, bold: bool| Highlight {
+            range,
+            style: Style {
+                bold,
+                ..Default::default()
+            },
+        };
+
+        let base = vec![highlight(0..10, false), highlight(12..14, false)];
+        let overlays = vec![highlight(2..4, true), highlight(8..13, true)];
+        assert_eq!(
+            Highlight::overlay(base, overlays),
+            vec![
+                highlight(0..2, false),
+                highlight(2..4, true),
+                highlight(4..8, false),
+                highlight(8..13, true),
+                highlight(13..14, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_highlight_fill() {
+        let bold = Style {
+            bold: true,
+            ..Default::default()
+        };
+        let underline = Style {
+            underline: true,
+            ..Default::default()
+        };
+        let highlight = |range: Range<usize>, style: Style| Highlight { range, style };
+
+        // 範囲の中の強調表示には装飾を重ね、隙間には装飾を付ける
+        let base = vec![
+            highlight(0..3, bold),
+            highlight(5..6, bold),
+            highlight(9..12, bold),
+        ];
+        assert_eq!(
+            Highlight::fill(base, 2..10, underline),
+            vec![
+                highlight(0..2, bold),
+                highlight(2..3, bold.merge(&underline)),
+                highlight(3..5, underline),
+                highlight(5..6, bold.merge(&underline)),
+                highlight(6..9, underline),
+                highlight(9..10, bold.merge(&underline)),
+                highlight(10..12, bold),
+            ]
+        );
+        assert_eq!(
+            Highlight::fill(Vec::new(), 1..2, underline),
+            vec![highlight(1..2, underline)]
+        );
+
+        // 重ねる装飾で指定していない色は、元の色のまま
+        let red = Style {
+            foreground: Some(Color::Indexed(1)),
+            ..Default::default()
+        };
+        let background = Style {
+            background: Some(Color::Indexed(8)),
+            ..Default::default()
+        };
+        assert_eq!(
+            red.merge(&background),
+            Style {
+                foreground: Some(Color::Indexed(1)),
+                background: Some(Color::Indexed(8)),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_style_paint() {
+        let style = Style {
+            bold: true,
+            foreground: Some(Color::Indexed(1)),
+            background: Some(Color::Rgb(0, 0, 0)),
+            ..Default::default()
+        };
+        assert_eq!(
+            style.paint("abc", ColorDepth::Ansi256),
+            "\x1b[1;38;5;1;48;5;16mabc\x1b[22;39;49m"
+        );
+
+        // 色を使わない場合は装飾しない
+        assert_eq!(style.paint("abc", ColorDepth::None), "abc");
+
+        // 装飾を指定しない場合はそのまま
+        assert_eq!(Style::default().paint("abc", ColorDepth::TrueColor), "abc");
+    }
+}
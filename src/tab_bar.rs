@@ -0,0 +1,105 @@
+use unicode_width::UnicodeWidthStr;
+
+use crate::screen;
+use crate::style::{ColorDepth, Style};
+
+/// タブページの一覧を表示するタブバー
+pub struct TabBar {
+    /// 幅
+    pub width: u16,
+    /// 開始位置(Y座標)
+    pub y_start: u16,
+    /// 装飾
+    pub style: Style,
+    /// 表示しているタブページの装飾
+    pub current_style: Style,
+    /// 端末で表示できる色数(`ColorDepth::None`の場合は装飾しない)
+    pub color_depth: ColorDepth,
+}
+
+impl TabBar {
+    pub fn new(width: u16, y_start: u16) -> Self {
+        Self {
+            width,
+            y_start,
+            style: Style {
+                reverse: true,
+                ..Default::default()
+            },
+            current_style: Style {
+                bold: true,
+                ..Default::default()
+            },
+            color_depth: ColorDepth::Ansi16,
+        }
+    }
+
+    /// 各タブページの見出しを作成する
+    /// # Arguments
+    /// * `names` - 各タブページで表示しているファイルの名前
+    /// # Returns
+    /// * `Vec<String>` - ` 1:main.rs `のような、番号とファイル名の見出し
+    fn labels(names: &[String]) -> Vec<String> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| format!(" {}:{} ", i + 1, name))
+            .collect()
+    }
+
+    /// タブバーを表示する
+    /// # Arguments
+    /// * `names` - 各タブページで表示しているファイルの名前
+    /// * `current` - 表示しているタブページの位置
+    /// # Notes
+    /// * 幅に収まらない場合は、表示しているタブページが見えるように左側のタブページを省略する
+    pub fn print(&self, names: &[String], current: usize) {
+        let labels = Self::labels(names);
+        let width = self.width as usize;
+
+        // 表示しているタブページの右端が収まるまで、左側のタブページを省略する
+        let mut first = 0;
+        while first < current
+            && labels[first..=current]
+                .iter()
+                .map(|label| label.width())
+                .sum::<usize>()
+                > width
+        {
+            first += 1;
+        }
+
+        let mut text = String::new();
+        let mut used = 0;
+        for (i, label) in labels.iter().enumerate().skip(first) {
+            if used + label.width() > width {
+                break;
+            }
+            used += label.width();
+            let style = if i == current {
+                self.current_style
+            } else {
+                self.style
+            };
+            text.push_str(&style.paint(label, self.color_depth));
+        }
+        text.push_str(
+            &self
+                .style
+                .paint(&" ".repeat(width - used), self.color_depth),
+        );
+
+        screen::print_row(0, self.y_start, self.width, &text).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labels() {
+        let names = vec!["main.rs".to_string(), "[stdin]".to_string()];
+        assert_eq!(TabBar::labels(&names), vec![" 1:main.rs ", " 2:[stdin] "]);
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use notify::{RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+
+/// 開いているファイルを監視して、変更されたファイルを知らせる
+pub struct FileWatcher {
+    /// ファイルの変更を監視する(監視を始められない環境ではNone)
+    watcher: Option<notify::RecommendedWatcher>,
+    /// 監視しているディレクトリで変更されたファイルのパスを受け取る
+    receiver: mpsc::UnboundedReceiver<PathBuf>,
+    /// 監視しているファイル(開いたときの名前のまま)
+    files: Vec<String>,
+    /// 監視しているファイルの実際のパス
+    paths: HashSet<PathBuf>,
+    /// 監視しているディレクトリ
+    dirs: HashSet<PathBuf>,
+}
+
+impl FileWatcher {
+    /// FileWatcherを作成する
+    /// # Notes
+    /// * 監視を始められない環境では、何も知らせないFileWatcherを作成する
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            if event.kind.is_access() {
+                return;
+            }
+            for path in event.paths {
+                let _ = sender.send(path);
+            }
+        })
+        .ok();
+
+        Self {
+            watcher,
+            receiver,
+            files: Vec::new(),
+            paths: HashSet::new(),
+            dirs: HashSet::new(),
+        }
+    }
+
+    /// 監視するファイルを、開いているファイルに合わせる
+    /// # Arguments
+    /// * `files` - 開いているファイル
+    /// # Notes
+    /// * 多くのエディタは別のファイルに書き込んでから名前を変えて保存するので、ファイルを含むディレクトリを監視する
+    /// * 開いているファイルが変わっていない場合は何もしない
+    pub fn sync(&mut self, files: Vec<String>) {
+        if files == self.files {
+            return;
+        }
+        let Some(watcher) = self.watcher.as_mut() else {
+            return;
+        };
+
+        self.paths = files
+            .iter()
+            .filter_map(|file| std::fs::canonicalize(file).ok())
+            .filter(|path| path.is_file())
+            .collect();
+        let dirs: HashSet<PathBuf> = self
+            .paths
+            .iter()
+            .filter_map(|path| path.parent().map(Path::to_path_buf))
+            .collect();
+        for dir in self.dirs.difference(&dirs) {
+            let _ = watcher.unwatch(dir);
+        }
+        for dir in dirs.difference(&self.dirs) {
+            let _ = watcher.watch(dir, RecursiveMode::NonRecursive);
+        }
+        self.dirs = dirs;
+        self.files = files;
+    }
+
+    /// 監視しているファイルがあるか
+    pub fn is_watching(&self) -> bool {
+        !self.paths.is_empty()
+    }
+
+    /// 前に確認してから変更された、監視しているファイルを取得する
+    /// # Returns
+    /// * `HashSet<PathBuf>` - 変更されたファイルの実際のパス
+    pub fn changed(&mut self) -> HashSet<PathBuf> {
+        std::iter::from_fn(|| self.receiver.try_recv().ok())
+            .filter(|path| self.paths.contains(path))
+            .collect()
+    }
+
+    /// 監視しているファイルが変更されるまで待つ
+    /// # Returns
+    /// * `HashSet<PathBuf>` - 変更されたファイルの実際のパス
+    /// # Notes
+    /// * 監視しているディレクトリのほかのファイルだけが変更された場合は、待ち続ける
+    /// * 監視を始められない環境では、いつまでも待つ
+    pub async fn next_change(&mut self) -> HashSet<PathBuf> {
+        loop {
+            let Some(path) = self.receiver.recv().await else {
+                return std::future::pending().await;
+            };
+            let mut changed = self.changed();
+            if self.paths.contains(&path) {
+                changed.insert(path);
+            }
+            if !changed.is_empty() {
+                return changed;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_changed() {
+        let dir = std::env::temp_dir().join(format!("edoc-watcher-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let a = dir.join("a.txt");
+        let b = dir.join("b.txt");
+        std::fs::write(&a, "a").unwrap();
+        std::fs::write(&b, "b").unwrap();
+
+        let mut watcher = FileWatcher::new();
+        watcher.sync(vec![a.display().to_string()]);
+        assert!(watcher.is_watching());
+
+        // 監視しているファイルの変更だけを知らせる
+        std::fs::write(&b, "bb").unwrap();
+        std::fs::write(&a, "aa").unwrap();
+        let a = std::fs::canonicalize(&a).unwrap();
+        let start = std::time::Instant::now();
+        let mut changed = HashSet::new();
+        while changed.is_empty() && start.elapsed() < std::time::Duration::from_secs(5) {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            changed = watcher.changed();
+        }
+        assert_eq!(changed, HashSet::from([a.clone()]));
+
+        // 変更されるまで待つ場合も、監視しているファイルの変更だけを知らせる
+        std::fs::write(&b, "bbb").unwrap();
+        std::fs::write(&a, "aaa").unwrap();
+        let changed =
+            tokio::time::timeout(std::time::Duration::from_secs(5), watcher.next_change())
+                .await
+                .unwrap();
+        assert_eq!(changed, HashSet::from([a]));
+
+        watcher.sync(Vec::new());
+        assert!(!watcher.is_watching());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
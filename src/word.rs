@@ -0,0 +1,117 @@
+use std::borrow::Cow;
+
+use ropey::Rope;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Unicodeの単語の区切り(UAX #29)で区切った部分のうち、空白以外を単語として扱う
+fn is_word(segment: &str) -> bool {
+    !segment.chars().all(char::is_whitespace)
+}
+
+/// 行の開始位置と、改行文字を含む行を取得する
+/// # Arguments
+/// * `s` - 文字列
+/// * `line_index` - 行の位置(0から始まる)
+/// # Returns
+/// * `(usize, Cow<str>)` - 文字列での行の開始位置(バイト単位)と、改行文字を含む行
+/// # Notes
+/// * 改行の前後は必ず単語の区切りになる(UAX #29)ので、行ごとに区切っても改行をまたいで区切った場合と同じになる
+fn line(s: &Rope, line_index: usize) -> (usize, Cow<'_, str>) {
+    (s.line_to_byte(line_index), s.line(line_index).into())
+}
+
+/// 次の単語の先頭の位置を探す
+/// # Arguments
+/// * `s` - 文字列
+/// * `position` - 探し始める位置(バイト単位)
+/// # Returns
+/// * `Option<usize>` - 次の単語の先頭の位置(バイト単位、次の単語がない場合はNone)
+/// # Notes
+/// * 単語の途中から探す場合は、その単語の残りを飛ばす
+pub fn next_word_start(s: &Rope, position: usize) -> Option<usize> {
+    (s.byte_to_line(position)..s.len_lines()).find_map(|line_index| {
+        // 探し始める行は、探し始める位置から区切る
+        let (start, line) = line(s, line_index);
+        let offset = position.saturating_sub(start);
+        line[offset..]
+            .split_word_bound_indices()
+            .map(|(index, segment)| (start + offset + index, segment))
+            .find(|&(index, segment)| index > position && is_word(segment))
+            .map(|(index, _)| index)
+    })
+}
+
+/// 前の単語の先頭の位置を探す
+/// # Arguments
+/// * `s` - 文字列
+/// * `position` - 探し始める位置(バイト単位)
+/// # Returns
+/// * `Option<usize>` - 前の単語の先頭の位置(バイト単位、前の単語がない場合はNone)
+/// # Notes
+/// * 単語の途中から探す場合は、その単語の先頭の位置を返す
+pub fn previous_word_start(s: &Rope, position: usize) -> Option<usize> {
+    (0..=s.byte_to_line(position)).rev().find_map(|line_index| {
+        // 探し始める行は、探し始める位置までを区切る
+        let (start, line) = line(s, line_index);
+        let end = (position - start).min(line.len());
+        line[..end]
+            .split_word_bound_indices()
+            .rev()
+            .find(|&(_, segment)| is_word(segment))
+            .map(|(index, _)| start + index)
+    })
+}
+
+/// 次の単語の末尾(最後の文字の先頭)の位置を探す
+/// # Arguments
+/// * `s` - 文字列
+/// * `position` - 探し始める位置(バイト単位)
+/// # Returns
+/// * `Option<usize>` - 次の単語の末尾の位置(バイト単位、次の単語がない場合はNone)
+/// # Notes
+/// * 単語の末尾より前から探す場合は、その単語の末尾の位置を返す
+pub fn next_word_end(s: &Rope, position: usize) -> Option<usize> {
+    (s.byte_to_line(position)..s.len_lines()).find_map(|line_index| {
+        // 探し始める行は、探し始める位置から区切る
+        let (start, line) = line(s, line_index);
+        let offset = position.saturating_sub(start);
+        line[offset..]
+            .split_word_bound_indices()
+            .filter(|&(_, segment)| is_word(segment))
+            .map(|(index, segment)| {
+                let last = segment.char_indices().last().map_or(0, |(i, _)| i);
+                start + offset + index + last
+            })
+            .find(|&end| end > position)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_motion() {
+        let s = &Rope::from_str("let café = a.b;\n  日本語 end");
+
+        assert_eq!(next_word_start(s, 0), Some(4));
+        // 単語の途中から探す場合は、その単語の残りを飛ばす
+        assert_eq!(next_word_start(s, 5), Some(10));
+        assert_eq!(next_word_start(s, 13), Some(14));
+        assert_eq!(next_word_start(s, 14), Some(15));
+        // 改行をまたいで探す
+        assert_eq!(next_word_start(s, 15), Some(19));
+        assert_eq!(next_word_start(s, 29), None);
+
+        assert_eq!(previous_word_start(s, 19), Some(15));
+        assert_eq!(previous_word_start(s, 6), Some(4));
+        assert_eq!(previous_word_start(s, 4), Some(0));
+        assert_eq!(previous_word_start(s, 0), None);
+
+        // 単語の末尾は、最後の文字の先頭の位置
+        assert_eq!(next_word_end(s, 0), Some(2));
+        assert_eq!(next_word_end(s, 2), Some(7));
+        assert_eq!(next_word_end(s, 19), Some(22));
+        assert_eq!(next_word_end(s, 31), None);
+    }
+}
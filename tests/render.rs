@@ -0,0 +1,51 @@
+use edoc_core::{
+    buffer::{Buffer, Settings},
+    config::Config,
+    contents::{Contents, DisplayOptions},
+    screen,
+    status_bar::{StatusBar, StatusBarItem},
+};
+
+#[test]
+fn test_render_contents_and_status_bar() {
+    let mut contents = Contents::new(
+        "fn main() {\n    println!(\"hello\");\n}\n".into(),
+        16,
+        4,
+        0,
+        0,
+        0,
+        0,
+    );
+    let settings = Settings {
+        options: DisplayOptions::default(),
+        tab_width: None,
+        wrap: None,
+        config: Config::default(),
+    };
+    let buffer = Buffer::open(None, String::new(), &settings);
+    let mut status_bar = StatusBar::new(16, 1, 0, 4);
+    status_bar.add_item(StatusBarItem::new(
+        "file".to_string(),
+        "main.rs".to_string(),
+    ));
+
+    // 端末がなくても、ライブラリの外から表示した内容を見比べられる
+    let rendered = screen::render_to_string(16, 5, || {
+        contents.print()?;
+        status_bar.print(&buffer);
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(
+        rendered,
+        [
+            "1 fn main() {",
+            "2     println!(\"",
+            "  hello\");",
+            "3 }",
+            "main.rs",
+        ]
+        .join("\n")
+    );
+}